@@ -1,11 +1,14 @@
+use std::cmp::Ordering;
 use std::ops::Add;
 use std::fmt::{Debug, Formatter, Display};
 
-use ansi_term::Colour;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Moves are ordered row-major, (0, 0), (0, 1), ..., (2, 2), which is the order legal moves and best-move lists come
+// in.  The derived ordering relies on row being declared before column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Move {
     pub row: usize,
     pub column: usize,
@@ -35,6 +38,7 @@ impl Move {
         moves
     }
 
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         format!("({}, {})", self.row, self.column)
     }
@@ -62,7 +66,7 @@ impl Move {
 
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Board {
     columns: Vec<Vec<Square>>,
 }
@@ -105,6 +109,7 @@ impl Board {
         elements
     }
 
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
         let mut row_strings: Vec<String> = vec![
             "".to_string(),
@@ -127,29 +132,23 @@ impl Board {
 
     pub fn from_string(s: String) -> Result<Self, String> {
         let mut board = Board::empty();
-        let mut row_n = 0;
         let stripped_s = s.chars().filter(|c| !(c == &' ' || c == &'\t')).collect::<String>();
-        for row in stripped_s.split("\n") {
-            let mut col_n = 0;
-            for element in row.chars() {
+        for (row_n, row) in stripped_s.split('\n').enumerate() {
+            for (col_n, element) in row.chars().enumerate() {
                 match Square::from_char(element) {
                     Ok(square) => board.columns[col_n][row_n] = square,
                     Err(e) => return Err(e),
                 };
-                col_n += 1;
             }
-            row_n += 1;
         }
         return Ok(board)
     }
 
     pub fn sum(&self) -> u16 {
         let mut sum = 0;
-        let mut column_index = 0;
         for column in self.columns.iter() {
             let column_sum = Self::sum_column(column[0], column[1], column[2]);
             sum += column_sum;
-            column_index += 1;
         }
         return sum;
     }
@@ -168,11 +167,11 @@ impl Board {
         }
     }
 
-    pub fn to_string_with_square_highlighted(&self, row: usize, col: usize) -> String {
+    pub fn to_string_with_square_highlighted(&self, _row: usize, _col: usize) -> String {
         unimplemented!()
     }
     
-    pub fn new(x_bitboard: Bitboard, o_bitboard: Bitboard) -> Board {
+    pub fn new(_x_bitboard: Bitboard, _o_bitboard: Bitboard) -> Board {
         unimplemented!()
     }
 
@@ -207,42 +206,139 @@ impl Board {
         return new_board;
     }
 
+    pub fn column_threat(&self, die: Die, column_index: usize) -> u16 {
+        // The points the opponent would take by placing die opposite the column, multipliers included.
+        let column = &self.columns[column_index];
+        let remaining = |square: Square| if square == Square::Die(die) { Square::Empty } else { square };
+        return Self::sum_column(column[0], column[1], column[2])
+            - Self::sum_column(remaining(column[0]), remaining(column[1]), remaining(column[2]));
+    }
+
+    pub fn score_delta_if_placed(&self, die: Die, column_index: usize) -> u16 {
+        // The points placing die in the column would add, multipliers included.  Which empty square it goes in
+        // doesn't matter, so this assumes the first.
+        let column = &self.columns[column_index];
+        let mut placed = [column[0], column[1], column[2]];
+        if let Some(square) = placed.iter_mut().find(|square| **square == Square::Empty) {
+            *square = Square::Die(die);
+        }
+        return Self::sum_column(placed[0], placed[1], placed[2]) - Self::sum_column(column[0], column[1], column[2]);
+    }
+
+    pub fn eliminate_detailed(&self, die: Die, column_index: usize) -> (Board, EliminationResult) {
+        let new_board = self.eliminate(die, column_index);
+        let n_eliminated = new_board.get_n_empty_squares_in_column(column_index) - self.get_n_empty_squares_in_column(column_index);
+        // Only the one column changes, so there's no need to sum the whole board.
+        let points_lost = self.column_threat(die, column_index);
+        return (new_board, EliminationResult::new(die, column_index, n_eliminated, points_lost));
+    }
+
     pub fn get_empty_squares(&self) -> Vec<(usize, usize)> {
-        let mut rows = vec![
-            Vec::new(),
-            Vec::new(),
-            Vec::new(),
-        ];
-        for (col_n, column) in self.columns.iter().enumerate() {
-            for (row_n, square) in column.iter().enumerate() {
-                if *square == Square::Empty {
-                    rows[row_n].push((row_n, col_n));
+        // In row-major order, matching Move's ordering.
+        let mut empty_squares = Vec::new();
+        for row_n in 0..3 {
+            for (col_n, column) in self.columns.iter().enumerate() {
+                if column[row_n] == Square::Empty {
+                    empty_squares.push((row_n, col_n));
                 }
             }
         }
-        return rows.into_iter().flatten().collect();
+        return empty_squares;
     }
 
     pub fn get_n_empty_squares(&self) -> usize {
         self.get_empty_squares().len()
     }
 
+    pub fn get_heap_size(&self) -> usize {
+        // Bytes allocated for the columns, beyond size_of::<Board>().
+        self.columns.capacity() * std::mem::size_of::<Vec<Square>>()
+            + self.columns.iter().map(|column| column.capacity() * std::mem::size_of::<Square>()).sum::<usize>()
+    }
+
+    pub fn get_n_empty_squares_in_column(&self, column_index: usize) -> usize {
+        self.columns[column_index].iter().filter(|square| **square == Square::Empty).count()
+    }
+
+    pub fn get_column(&self, column_index: usize) -> Vec<Square> {
+        self.columns[column_index].clone()
+    }
+
+    pub fn to_key(&self) -> u32 {
+        // Packs the squares column by column, 3 bits each, with 0 for empty and the die value otherwise.
+        let mut key = 0;
+        for square in self.get_elements() {
+            let value = match square {
+                Square::Empty => 0,
+                Square::Die(die) => die.to_value() as u32,
+            };
+            key = (key << 3) | value;
+        }
+        return key;
+    }
+
+    pub fn with_rows_sorted(&self) -> Board {
+        // Only the dice in a column matter, not their rows, so this gives a canonical board with the empty squares last.
+        let mut new_board = self.clone();
+        for column in new_board.columns.iter_mut() {
+            column.sort_by(|a, b| b.cmp(a));
+        }
+        return new_board;
+    }
+
+    pub fn with_columns_permuted(&self, order: &[usize]) -> Board {
+        // The new board's ith column is this board's order[i]th column.
+        Board {
+            columns: order.iter().map(|column_index| self.columns[*column_index].clone()).collect(),
+        }
+    }
+
     pub fn get_empty_squares_up_to_row_symmetry(&self) -> Vec<(usize, usize)> {
+        // The first empty square in each column, in row-major order like get_empty_squares.
         let mut empty_squares = Vec::new();
         for (col_n, column) in self.columns.iter().enumerate() {
-            for (row_n, square) in column.iter().enumerate() {
-                if *square == Square::Empty {
-                    empty_squares.push((row_n, col_n));
-                    break;
-                }
+            if let Some(row_n) = column.iter().position(|square| *square == Square::Empty) {
+                empty_squares.push((row_n, col_n));
             }
         }
+        empty_squares.sort();
         return empty_squares;
     }
     
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EliminationResult {
+    die: Die,
+    column: usize,
+    n_eliminated: usize,
+    points_lost: u16,
+}
+
+impl EliminationResult {
+
+    pub fn new(die: Die, column: usize, n_eliminated: usize, points_lost: u16) -> Self {
+        EliminationResult { die, column, n_eliminated, points_lost }
+    }
+
+    pub fn get_die(&self) -> Die {
+        self.die
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+
+    pub fn get_n_eliminated(&self) -> usize {
+        self.n_eliminated
+    }
+
+    pub fn get_points_lost(&self) -> u16 {
+        self.points_lost
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Square {
     Empty,
     Die(Die),
@@ -285,6 +381,7 @@ impl Add<Square> for u16 {
 
 impl Square {
 
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match self {
             Self::Empty => "_".to_string(),
@@ -295,12 +392,12 @@ impl Square {
     pub fn from_char(c: char) -> Result<Self, String> {
         match c {
             '_' => Ok(Self::Empty),
-            c => Die::from_char(c).map(|x| Self::Die(x)),
+            c => Die::from_char(c).map(Self::Die),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Die {
     One,
     Two,
@@ -347,10 +444,22 @@ impl Die {
         vec![Die::One, Die::Two, Die::Three, Die::Four, Die::Five, Die::Six]
     }
 
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         self.to_value().to_string()
     }
 
+    pub fn to_unicode(&self) -> String {
+        match self {
+            Die::One => "⚀",
+            Die::Two => "⚁",
+            Die::Three => "⚂",
+            Die::Four => "⚃",
+            Die::Five => "⚄",
+            Die::Six => "⚅",
+        }.to_string()
+    }
+
     pub fn from_char(c: char) -> Result<Die, String> {
         match c {
             '1' => Ok(Die::One),
@@ -364,13 +473,48 @@ impl Die {
     }
 
     pub fn random() -> Die {
-        let mut rng = rand::thread_rng();
+        Die::random_from(&mut rand::thread_rng())
+    }
+
+    pub fn random_from(rng: &mut impl Rng) -> Die {
         let die_value: u8 = rng.gen_range(1..7);
         Die::new(die_value).unwrap()
     }
 
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DieDistribution([f32; 6]);
+
+impl Default for DieDistribution {
+    fn default() -> Self {
+        DieDistribution::uniform()
+    }
+}
+
+impl DieDistribution {
+
+    pub fn uniform() -> Self {
+        DieDistribution([1. / 6.; 6])
+    }
+
+    pub fn from_weights(weights: [f32; 6]) -> Result<Self, String> {
+        // Weights for One through Six, scaled to sum to 1.
+        if weights.iter().any(|weight| !weight.is_finite() || *weight < 0.) {
+            return Err(format!("Die weights must be non-negative: {:?}", weights));
+        }
+        let total = weights.iter().sum::<f32>();
+        if total <= 0. {
+            return Err("Die weights can't all be zero".to_string());
+        }
+        return Ok(DieDistribution(weights.map(|weight| weight / total)));
+    }
+
+    pub fn get_probability(&self, die: Die) -> f32 {
+        self.0[(die.to_value() - 1) as usize]
+    }
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Bitboard(u16);
 
@@ -405,7 +549,6 @@ impl Bitboard {
             return Err(format!("Binary string must be 9 characters long, got {}", binary.len()));
         }
         let mut bitboard = Bitboard::empty();
-        let mut index = 0;
         let mut i = 0;
         let mut j = 0;
         for c in binary.chars() {
@@ -455,7 +598,7 @@ impl Bitboard {
 }
 
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Outcome {
     Victory(Player),
     Draw,
@@ -464,6 +607,18 @@ pub enum Outcome {
 
 impl Outcome {
 
+    pub fn from_scores(player_1_score: u16, player_2_score: u16) -> Self {
+        // Whoever's ahead, as if the game had ended with these scores.
+        if player_1_score > player_2_score {
+            Outcome::Victory(Player::Player1)
+        } else if player_2_score > player_1_score {
+            Outcome::Victory(Player::Player2)
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match self {
             Outcome::Victory(player) => format!("{} wins", player.to_string()),
@@ -473,7 +628,7 @@ impl Outcome {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Player {
     Player1,
     Player2,
@@ -481,6 +636,7 @@ pub enum Player {
 
 impl Player {
 
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match self {
             Player::Player1 => "Player 1".to_string(),
@@ -488,6 +644,14 @@ impl Player {
         }
     }
 
+    pub fn from_string(s: &str) -> Result<Self, String> {
+        match s.trim() {
+            "1" => Ok(Player::Player1),
+            "2" => Ok(Player::Player2),
+            p => Err(format!("Invalid player, expected 1 or 2: {}", p)),
+        }
+    }
+
     pub fn opponent(&self) -> Self {
         match self {
             Player::Player1 => Player::Player2,
@@ -495,18 +659,38 @@ impl Player {
         }
     }
 
+    pub fn get_sign(&self) -> f32 {
+        // Evaluations are positive for Player 1, so multiplying by this turns one into the player's own perspective,
+        // where bigger is always better, and back again.
+        match self {
+            Player::Player1 => 1.,
+            Player::Player2 => -1.,
+        }
+    }
+
     pub fn compare_evaluation(&self, evaluation: f32, other_evaluation: f32) -> Comparison {
-        if evaluation == other_evaluation {
-            return Comparison::Equal;
+        match compare_totally(self.get_sign() * evaluation, self.get_sign() * other_evaluation) {
+            Ordering::Equal => Comparison::Equal,
+            Ordering::Greater => Comparison::Better,
+            Ordering::Less => Comparison::Worse,
         }
-        return match self {
-            Player::Player1 => {if evaluation > other_evaluation {Comparison::Better} else {Comparison::Worse}},
-            Player::Player2 => {if evaluation < other_evaluation {Comparison::Better} else {Comparison::Worse}},
+    }
+
+    pub fn order_best_first(&self, evaluation: f32, other_evaluation: f32) -> Ordering {
+        // For sorting evaluations so the best for the player comes first.
+        match self.compare_evaluation(evaluation, other_evaluation) {
+            Comparison::Better => Ordering::Less,
+            Comparison::Worse => Ordering::Greater,
+            Comparison::Equal => Ordering::Equal,
         }
     }
 
     pub fn get_random() -> Self {
-        if rand::random::<bool>() {
+        Player::random_from(&mut rand::thread_rng())
+    }
+
+    pub fn random_from(rng: &mut impl Rng) -> Self {
+        if rng.gen::<bool>() {
             Player::Player1
         } else {
             Player::Player2
@@ -522,6 +706,42 @@ pub enum Comparison {
     Equal,
 }
 
+// Relative to the size of the evaluations, how far apart two can be and still count as a tie.  The same evaluation
+// reached by adding up the rolls in a different order can come out a bit or two different.
+const TIE_TOLERANCE: f32 = 1e-6;
+
+pub fn compare_totally(evaluation: f32, other_evaluation: f32) -> Ordering {
+    // A total order, so a NaN can't leave a sort or a search inconsistent: it goes past every number at one end or the
+    // other, by its sign, rather than comparing as neither bigger nor smaller.  Zero and negative zero are the same.
+    (evaluation + 0.).total_cmp(&(other_evaluation + 0.))
+}
+
+pub fn max_keeping_nan(evaluation: f32, other_evaluation: f32) -> f32 {
+    // The bigger of the two, unless either is NaN, which f32::max would drop.  A NaN from a broken objective is kept
+    // all the way up to the result instead, where it's reported.
+    if evaluation.is_nan() || other_evaluation.is_nan() {
+        return f32::NAN;
+    }
+    return evaluation.max(other_evaluation);
+}
+
+pub fn are_tied(evaluation: f32, other_evaluation: f32) -> bool {
+    // Exact for infinities, which are only ever tied with themselves.
+    if evaluation == other_evaluation {
+        return true;
+    }
+    if !evaluation.is_finite() || !other_evaluation.is_finite() {
+        return false;
+    }
+    let scale = evaluation.abs().max(other_evaluation.abs()).max(1.);
+    return (evaluation - other_evaluation).abs() <= TIE_TOLERANCE * scale;
+}
+
+pub fn are_tied_within(evaluation: f32, other_evaluation: f32, tie_break_epsilon: f32) -> bool {
+    // Tied to within rounding or tie_break_epsilon, which is what makes two moves equally good.
+    return are_tied(evaluation, other_evaluation) || (evaluation - other_evaluation).abs() <= tie_break_epsilon;
+}
+
 #[cfg(test)]
 mod test_board_tests {
     use super::*;
@@ -533,6 +753,18 @@ mod test_board_tests {
         assert_eq!(die_1 + die_2, 9);
     }
 
+    #[test]
+    fn test_die_distribution() {
+        let uniform = DieDistribution::default();
+        assert!(Die::all().iter().all(|die| uniform.get_probability(*die) == 1. / 6.));
+
+        let loaded = DieDistribution::from_weights([1., 1., 1., 1., 1., 5.]).unwrap();
+        assert_eq!(loaded.get_probability(Die::Six), 0.5);
+        assert_eq!(loaded.get_probability(Die::One), 0.1);
+        assert!(DieDistribution::from_weights([0.; 6]).is_err());
+        assert!(DieDistribution::from_weights([1., 1., 1., 1., 1., -1.]).is_err());
+    }
+
     #[test]
     fn test_board_instantiates() {
         let b = Board::empty();
@@ -564,22 +796,28 @@ mod test_board_tests {
 
         let b = Board::from_string("256\n1_2\n626".to_string()).unwrap();
         assert_eq!(b.sum(), 42);
-        
+
+        // Placing a die adds what the whole board's sum goes up by, wherever in the column it goes.
+        let b = Board::from_string("5__\n5_2\n1__".to_string()).unwrap();
+        assert_eq!(b.score_delta_if_placed(Die::Five, 0), 0);
+        assert_eq!(b.score_delta_if_placed(Die::Two, 2), 6);
+        assert_eq!(b.score_delta_if_placed(Die::Two, 2), b.with_move_made(Die::Two, Move::new(2, 2)).unwrap().sum() - b.sum());
+        assert_eq!(b.score_delta_if_placed(Die::Four, 1), 4);
     }
 
     #[test]
     fn test_board_is_full() {
         let b = Board::empty();
-        assert_eq!(b.is_full(), false);
+        assert!(!b.is_full());
 
         let b = Board::from_string("5__\n__2\n___".to_string()).unwrap();
-        assert_eq!(b.is_full(), false);
+        assert!(!b.is_full());
 
         let b = Board::from_string("5__\n5_2\n1__".to_string()).unwrap();
-        assert_eq!(b.is_full(), false);
+        assert!(!b.is_full());
 
         let b = Board::from_string("412\n542\n162".to_string()).unwrap();
-        assert_eq!(b.is_full(), true);
+        assert!(b.is_full());
     }
 
     #[test]
@@ -597,6 +835,24 @@ mod test_board_tests {
         let eliminated_board = board.eliminate(Die::Two, 2);
 
         assert_eq!(eliminated_board, Board::from_string("5__\n___\n_3_".to_string()).unwrap());
+
+        let (eliminated_board, result) = board.eliminate_detailed(Die::Two, 2);
+        assert_eq!(eliminated_board, Board::from_string("5__\n___\n_3_".to_string()).unwrap());
+        assert_eq!(result, EliminationResult::new(Die::Two, 2, 2, 8));
+        assert_eq!(result.get_points_lost(), board.sum() - eliminated_board.sum());
+
+        let (non_eliminated_board, result) = board.eliminate_detailed(Die::Three, 2);
+        assert_eq!(non_eliminated_board, board);
+        assert_eq!(result.get_n_eliminated(), 0);
+        assert_eq!(result.get_points_lost(), 0);
+
+        assert_eq!(board.column_threat(Die::Two, 2), 8);
+        assert_eq!(board.column_threat(Die::Three, 2), 0);
+        assert_eq!(board.column_threat(Die::Three, 1), 3);
+        let board = Board::from_string("664\n64_\n6_4".to_string()).unwrap();
+        assert_eq!(board.column_threat(Die::Six, 0), 54);
+        assert_eq!(board.column_threat(Die::Four, 1), 4);
+        assert_eq!(board.column_threat(Die::Four, 2), 16);
     }
 
     #[test]
@@ -605,6 +861,34 @@ mod test_board_tests {
         assert_eq!(m, Move::new(1, 2));
     }
 
+    #[test]
+    fn test_player_from_string() {
+        assert_eq!(Player::from_string("1"), Ok(Player::Player1));
+        assert_eq!(Player::from_string(" 2\n"), Ok(Player::Player2));
+        assert!(Player::from_string("3").is_err());
+    }
+
+    #[test]
+    fn test_evaluations_tie_up_to_rounding() {
+        assert!(are_tied(0.1 + 0.2, 0.3));
+        assert!(are_tied(1000. + 1e-4, 1000.));
+        assert!(are_tied(f32::INFINITY, f32::INFINITY));
+        assert!(!are_tied(1., 1. + 1e-4));
+        assert!(!are_tied(f32::NEG_INFINITY, -1e30));
+        assert!(!are_tied(f32::NAN, f32::NAN));
+    }
+
+    #[test]
+    fn test_evaluations_compare_totally() {
+        assert_eq!(compare_totally(0., -0.), Ordering::Equal);
+        assert_eq!(compare_totally(f32::NAN, f32::INFINITY), Ordering::Greater);
+        assert_eq!(compare_totally(f32::NAN, f32::NAN), Ordering::Equal);
+        assert_eq!(Player::Player1.compare_evaluation(f32::NAN, 1.), Comparison::Better);
+        assert_eq!(Player::Player1.compare_evaluation(1., f32::NAN), Comparison::Worse);
+        assert_eq!(Player::Player2.compare_evaluation(-2., 1.), Comparison::Better);
+        assert_eq!(Player::Player2.compare_evaluation(0., -0.), Comparison::Equal);
+    }
+
     #[test]
     fn test_board_debug_format() {
         let b = Board::from_string("5__\n__2\n_32".to_string()).unwrap();
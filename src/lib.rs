@@ -0,0 +1,54 @@
+//! A solver for Knucklebones, the dice game from Cult of the Lamb.
+//!
+//! Positions are [`Node`]s, which can be read from KFEN strings, and a [`Solver`] finds the best moves in one under
+//! any [`SolverMode`].  The [`prelude`] has everything needed to set up a position and solve it.
+
+pub mod board;
+pub mod tree;
+pub mod solver;
+
+pub mod prelude {
+    // The types needed to set up a position, solve it and play the moves out.
+
+    /// A board, the dice on it and the moves that place them.
+    ///
+    /// ```
+    /// use knucklebones_solver::prelude::*;
+    ///
+    /// let board = Board::from_string("123\n45_\n6__".to_string()).unwrap();
+    /// let board = board.with_move_made(Die::Six, Move::new(1, 2)).unwrap();
+    /// assert_eq!(board.get_n_empty_squares(), 2);
+    /// ```
+    pub use crate::board::board::Board;
+    pub use crate::board::board::{Die, Move, Outcome, Player};
+
+    /// A position, waiting on either a roll or a move.
+    ///
+    /// ```
+    /// use knucklebones_solver::prelude::*;
+    ///
+    /// let node = Node::new_validated(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Three)).unwrap();
+    /// let node = node.with_move_made(Move::new(0, 0)).unwrap();
+    /// let node = node.with_roll(Die::Five).unwrap();
+    /// assert_eq!(node.get_node_type(), NodeType::Move(Player::Player2, Die::Five));
+    /// assert_eq!(Node::from_kfen(&node.to_kfen()).unwrap().to_kfen(), node.to_kfen());
+    /// ```
+    pub use crate::tree::tree::Node;
+    pub use crate::tree::tree::NodeType;
+
+    /// Solving a position.
+    ///
+    /// ```
+    /// use knucklebones_solver::prelude::*;
+    ///
+    /// // Player 2 to place a 6 with two squares left on their board.
+    /// let position = Node::from_kfen("1125545_6 / 435623_4_ 2 r6").unwrap();
+    /// let mut solver = Solver::from_root(position);
+    /// let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+    /// assert!(!best_moves.is_empty());
+    /// assert!(evaluation.is_expected_result());
+    /// assert!((-1. ..=1.).contains(&evaluation.get_evaluation()));
+    /// ```
+    pub use crate::solver::solver::Solver;
+    pub use crate::solver::solver::{Evaluation, SolverMode};
+}
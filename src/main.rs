@@ -1,18 +1,37 @@
-mod board;
-mod tree;
-mod solver;
-
-use std::io;
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use clap::{App, SubCommand, Arg, ArgMatches};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use crate::board::board::Player;
-use crate::tree::tree::Node;
-use crate::board::board::{Board, Move, Outcome, Die};
-use crate::solver::solver::{Solver, SolverMode, Evaluation, HeuristicDepthAndObjective};
-use crate::tree::tree::NodeType;
+use knucklebones_solver::board::board::Player;
+use knucklebones_solver::tree::tree::Node;
+use knucklebones_solver::board::board::{Board, Move, Outcome, Die, are_tied, compare_totally};
+use knucklebones_solver::solver::solver::{Evaluation, SearchProgress, Solver, SolverMode};
+use knucklebones_solver::solver::bench_positions::run_benchmark;
+use knucklebones_solver::solver::book::{OpeningBook, DEFAULT_BOOK_PLIES};
+use knucklebones_solver::solver::config::{CancellationToken, DepthSchedule, HeuristicFunction, RiskProfile, SearchObjective, SolverConfig, SolverConfigBuilder, SolverStrategy, DEFAULT_WIN_PROBABILITY_SCALE};
+use knucklebones_solver::solver::distribution::DEFAULT_HISTOGRAM_WIDTH;
+use knucklebones_solver::solver::error::SolverError;
+use knucklebones_solver::solver::heuristic::HeuristicWeights;
+use knucklebones_solver::solver::sweep::run_parameter_sweep;
+use knucklebones_solver::solver::tablebase::{EndgameTable, DEFAULT_MAX_EMPTY_SQUARES};
+use knucklebones_solver::tree::tree::{NodeType, PrettyPrintOptions, PvStep, Step};
+use knucklebones_solver::tree::history::GameHistory;
+
+const MAX_TREE_BYTES_WITHOUT_FORCE: usize = 1 << 30;
+const DEFAULT_SWEEP_GAMES: usize = 10;
+const DEFAULT_SWEEP_DEPTH: usize = 2;
+// How many moves "hint full" shows in play.
+const N_HINT_MOVES: usize = 3;
+// How deep play's --show-eval searches after each move, by default.  Shallow enough not to hold up the game.
+const DEFAULT_EVAL_DEPTH: usize = 2;
 
-const DEFAULT_DEPTH: usize = 4;
-const DEFAULT_MAX_DEPTH_TO_BRUTE_FORCE: usize = 1;
+// What Ctrl-C cancels while play's solver is searching.
+static CTRL_C_CANCELLATION_TOKEN: OnceLock<CancellationToken> = OnceLock::new();
 
 fn main() {
     let matches = App::new("Knucklebones (Cult of the Lamb) Solver")
@@ -22,7 +41,7 @@ fn main() {
 				.about("Solve Knucklebones Position")
 				.arg(
 					Arg::with_name("Next to Act Board")
-						.help("Board for the player who's next to act.")						
+						.help("Board for the player who's next to act, or the whole position as a single KFEN string.")						
 				).arg(
 					Arg::with_name("Next to Act Opponent's Board")
 						.help("Board for the player who's not next to act.")						
@@ -30,22 +49,124 @@ fn main() {
 					Arg::with_name("Roll")
 						.help("Latest Roll.")						
 				).arg(
+                    Arg::with_name("Player to Act")
+                        .help("Which player is next to act, 1 or 2.  Defaults to 1; KFEN positions already say.")
+                        .short('p')
+                        .long("player")
+                        .takes_value(true)
+                ).arg(
                     Arg::with_name("Full Tree")
                         .help("Print full tree.")
                         .short('t')
                         .long("tree")
+                ).arg(
+                    Arg::with_name("Unpruned")
+                        .help("With --tree, print every searched line rather than just the best ones.")
+                        .short('u')
+                        .long("unpruned")
                 ).arg(
                     Arg::with_name("Heuristic Depth")
                         .help("Depth of the heuristic search.")
                         .short('d')
                         .long("depth")
                         .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic")
+                        .help("Heuristic to score positions the search stops at: difference (the default), column-synergy or elimination-threat.")
+                        .long("heuristic")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic Weights")
+                        .help("JSON file of weights to add up heuristic terms with, e.g. {\"score_difference\": 1.0, \"empty_square_fill\": 3.5}.")
+                        .long("weights")
+                        .takes_value(true)
+                        .conflicts_with("Heuristic")
                 ).arg(
                     Arg::with_name("Max Depth to Brute Force")
                         .help("Max depth to brute force.")
                         .short('b')
                         .long("max-brute-force-depth")
                         .takes_value(true)
+                ).arg(
+                    Arg::with_name("Brute Force Depth")
+                        .help("How many moves ahead brute force looks before scoring unfinished games as they stand and marking the evaluation truncated.")
+                        .long("brute-force-depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Time Limit")
+                        .help("Search as deep as there's time for, e.g. \"2s\" or \"500ms\", instead of to a fixed depth.")
+                        .long("time-limit")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Adaptive")
+                        .help("Search as deep as an estimated budget of this many positions allows, instead of to a fixed depth.")
+                        .long("adaptive")
+                        .takes_value(true)
+                        .conflicts_with_all(&["Heuristic Depth", "Time Limit"])
+                ).arg(
+                    Arg::with_name("Schedule")
+                        .help("Search to a depth that depends on how many empty squares are left, from min empty squares:depth tiers with bf:max empty squares to brute force, e.g. 14:3,8:4,5:6,bf:4.")
+                        .long("schedule")
+                        .takes_value(true)
+                        .conflicts_with_all(&["Heuristic Depth", "Max Depth to Brute Force", "Time Limit", "Adaptive"])
+                ).arg(
+                    Arg::with_name("Confidence Threshold")
+                        .help("Also search a move less deep, and say the evaluation is low confidence if it moved by more than this or the best move changed.")
+                        .long("confidence-threshold")
+                        .takes_value(true)
+                        .conflicts_with("Time Limit")
+                ).arg(
+                    Arg::with_name("Verbose")
+                        .help("Print stats about the search and the search tree.")
+                        .short('v')
+                        .long("verbose")
+                ).arg(
+                    Arg::with_name("Progress")
+                        .help("Print a live status line while searching.")
+                        .long("progress")
+                ).arg(
+                    Arg::with_name("Win Probability")
+                        .help("Show heuristic evaluations as an estimated chance of winning.")
+                        .long("win-prob")
+                ).arg(
+                    Arg::with_name("Threads")
+                        .help("How many threads to search with, or 0 for one per core.  Only used when built with the parallel feature.")
+                        .long("threads")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Margin")
+                        .help("When brute forcing, prefer the move that wins (or loses) by the most from among those with the same expected result.")
+                        .long("margin")
+                ).arg(
+                    Arg::with_name("Lines")
+                        .help("Also print the best this many moves, each with the line expected to follow it.")
+                        .long("lines")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Objective")
+                        .help("What brute force maximizes: win-probability (the default) or margin, the expected final score difference.")
+                        .long("objective")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Draw Weight")
+                        .help("What brute force counts a draw as for whoever's to act, from -1 (a loss) through 0 (the default) to 1 (a win).")
+                        .long("draw-weight")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                ).arg(
+                    Arg::with_name("Risk")
+                        .help("How to weigh up the rolls after each move: expected-value (the default), pessimistic[:quantile] or worst-case.")
+                        .long("risk")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Distribution")
+                        .help("Also print how likely each final score difference is with best play, when close enough to the end to brute force.")
+                        .long("distribution")
+                ).arg(
+                    Arg::with_name("Cache")
+                        .help("Load brute force results from this file before searching, if it exists, and save them to it after.")
+                        .long("cache")
+                        .takes_value(true)
                 )
             )
         .subcommand(
@@ -57,12 +178,268 @@ fn main() {
                         .short('d')
                         .long("depth")
                         .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic")
+                        .help("Heuristic to score positions the search stops at: difference (the default), column-synergy or elimination-threat.")
+                        .long("heuristic")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic Weights")
+                        .help("JSON file of weights to add up heuristic terms with, e.g. {\"score_difference\": 1.0, \"empty_square_fill\": 3.5}.")
+                        .long("weights")
+                        .takes_value(true)
+                        .conflicts_with("Heuristic")
                 ).arg(
                     Arg::with_name("Max Depth to Brute Force")
                         .help("Max depth to brute force.")
                         .short('b')
                         .long("max-brute-force-depth")
                         .takes_value(true)
+                ).arg(
+                    Arg::with_name("Brute Force Depth")
+                        .help("How many moves ahead brute force looks before scoring unfinished games as they stand and marking the evaluation truncated.")
+                        .long("brute-force-depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Time Limit")
+                        .help("Search as deep as there's time for, e.g. \"2s\" or \"500ms\", instead of to a fixed depth.")
+                        .long("time-limit")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Adaptive")
+                        .help("Search as deep as an estimated budget of this many positions allows, instead of to a fixed depth.")
+                        .long("adaptive")
+                        .takes_value(true)
+                        .conflicts_with_all(&["Heuristic Depth", "Time Limit"])
+                ).arg(
+                    Arg::with_name("Schedule")
+                        .help("Search to a depth that depends on how many empty squares are left, from min empty squares:depth tiers with bf:max empty squares to brute force, e.g. 14:3,8:4,5:6,bf:4.")
+                        .long("schedule")
+                        .takes_value(true)
+                        .conflicts_with_all(&["Heuristic Depth", "Max Depth to Brute Force", "Time Limit", "Adaptive"])
+                ).arg(
+                    Arg::with_name("Win Probability")
+                        .help("Show heuristic evaluations as an estimated chance of winning.")
+                        .long("win-prob")
+                ).arg(
+                    Arg::with_name("Standing")
+                        .help("Show your expected standing before each of your rolls, averaged over what you could roll.")
+                        .long("standing")
+                ).arg(
+                    Arg::with_name("Confidence Threshold")
+                        .help("Mark the solver's moves with ?! when its evaluation moves by more than this from a move less deep, or its best move changes.")
+                        .long("confidence-threshold")
+                        .takes_value(true)
+                        .conflicts_with("Time Limit")
+                ).arg(
+                    Arg::with_name("Tablebase")
+                        .help("Endgame table to look positions up in, which endgames the game reaches are added to.")
+                        .long("tablebase")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Book")
+                        .help("Opening book to play the first moves from, as made by the book subcommand.")
+                        .long("book")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Threads")
+                        .help("How many threads to search with, or 0 for one per core.  Only used when built with the parallel feature.")
+                        .long("threads")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Margin")
+                        .help("When brute forcing, prefer the move that wins (or loses) by the most from among those with the same expected result.")
+                        .long("margin")
+                ).arg(
+                    Arg::with_name("Objective")
+                        .help("What brute force maximizes: win-probability (the default) or margin, the expected final score difference.")
+                        .long("objective")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Draw Weight")
+                        .help("What brute force counts a draw as for whoever's to act, from -1 (a loss) through 0 (the default) to 1 (a win).")
+                        .long("draw-weight")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                ).arg(
+                    Arg::with_name("Risk")
+                        .help("How to weigh up the rolls after each move: expected-value (the default), pessimistic[:quantile] or worst-case.")
+                        .long("risk")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Side")
+                        .help("Which side to play: first (or player1), second (or player2), or random (the default).")
+                        .long("side")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Show Evaluation")
+                        .help("After every move, print a quick evaluation of the position for you, and a list of them at the end.")
+                        .long("show-eval")
+                ).arg(
+                    Arg::with_name("Evaluation Depth")
+                        .help("Depth of --show-eval's search, 2 by default.")
+                        .long("eval-depth")
+                        .takes_value(true)
+                        .requires("Show Evaluation")
+                ).arg(
+                    Arg::with_name("Seed")
+                        .help("Seed for the side, the rolls and the solver's choice between equally good moves, so the same moves play the same game again.")
+                        .long("seed")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Position")
+                        .help("KFEN position to start from instead of empty boards, e.g. \"1125545_6 / 435623_4_ 2 r6\", which says whose turn it is.  With - for the roll, it starts by rolling.")
+                        .long("position")
+                        .takes_value(true)
+                )
+            )
+        .subcommand(
+            SubCommand::with_name("tablebase")
+                .about("Build an endgame table from the endgames a position leads to.")
+                .arg(
+					Arg::with_name("Next to Act Board")
+						.help("Board for the player who's next to act, or the whole position as a single KFEN string.")
+				).arg(
+					Arg::with_name("Next to Act Opponent's Board")
+						.help("Board for the player who's not next to act.")
+				).arg(
+					Arg::with_name("Roll")
+						.help("Latest Roll.")
+				).arg(
+                    Arg::with_name("Player to Act")
+                        .help("Which player is next to act, 1 or 2.  Defaults to 1; KFEN positions already say.")
+                        .short('p')
+                        .long("player")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Output")
+                        .help("File to save the table to.  If it's already a table, the new positions are added to it.")
+                        .short('o')
+                        .long("output")
+                        .takes_value(true)
+                        .required(true)
+                ).arg(
+                    Arg::with_name("Max Empty Squares")
+                        .help("Only add positions with at most this many empty squares between the two boards.")
+                        .short('k')
+                        .long("max-empty-squares")
+                        .takes_value(true)
+                )
+            )
+        .subcommand(
+            SubCommand::with_name("book")
+                .about("Build an opening book of the best moves in the first few moves of the game.")
+                .arg(
+                    Arg::with_name("Output")
+                        .help("File to save the book to.")
+                        .short('o')
+                        .long("output")
+                        .takes_value(true)
+                        .required(true)
+                ).arg(
+                    Arg::with_name("Plies")
+                        .help("How many moves into the game the book goes.")
+                        .long("plies")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic Depth")
+                        .help("Depth of the heuristic search.")
+                        .short('d')
+                        .long("depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic")
+                        .help("Heuristic to score positions the search stops at: difference (the default), column-synergy or elimination-threat.")
+                        .long("heuristic")
+                        .takes_value(true)
+                )
+            )
+        .subcommand(
+            SubCommand::with_name("sweep")
+                .about("Play the heuristic against itself with different empty square fills.")
+                .hide(true)
+                .arg(
+                    Arg::with_name("Values")
+                        .help("Comma separated empty square fills, e.g. \"3,3.5,4\".")
+                        .long("values")
+                        .takes_value(true)
+                        .required(true)
+                ).arg(
+                    Arg::with_name("Games")
+                        .help("Games to play between each pair of values.")
+                        .short('n')
+                        .long("games")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic Depth")
+                        .help("Depth of the heuristic search.")
+                        .short('d')
+                        .long("depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Seed")
+                        .help("Seed for the rolls, so the same sweep plays the same games.")
+                        .long("seed")
+                        .takes_value(true)
+                )
+            )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Solve the benchmark positions and print how long each took.")
+                .arg(
+                    Arg::with_name("Heuristic Depth")
+                        .help("Depth of the heuristic search.")
+                        .short('d')
+                        .long("depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Max Depth to Brute Force")
+                        .help("Max depth to brute force.")
+                        .short('b')
+                        .long("max-brute-force-depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic")
+                        .help("Heuristic to score positions the search stops at: difference (the default), column-synergy or elimination-threat.")
+                        .long("heuristic")
+                        .takes_value(true)
+                )
+            )
+        .subcommand(
+            SubCommand::with_name("analyze")
+                .about("Rank every legal move in a position.")
+                .arg(
+					Arg::with_name("Next to Act Board")
+						.help("Board for the player who's next to act, or the whole position as a single KFEN string.")
+				).arg(
+					Arg::with_name("Next to Act Opponent's Board")
+						.help("Board for the player who's not next to act.")
+				).arg(
+					Arg::with_name("Roll")
+						.help("Latest Roll.")
+				).arg(
+                    Arg::with_name("Player to Act")
+                        .help("Which player is next to act, 1 or 2.  Defaults to 1; KFEN positions already say.")
+                        .short('p')
+                        .long("player")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic Depth")
+                        .help("Depth of the heuristic search.")
+                        .short('d')
+                        .long("depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Max Depth to Brute Force")
+                        .help("Max depth to brute force.")
+                        .short('b')
+                        .long("max-brute-force-depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("Heuristic")
+                        .help("Heuristic to score positions the search stops at: difference (the default), column-synergy or elimination-threat.")
+                        .long("heuristic")
+                        .takes_value(true)
                 )
             )
         .subcommand(
@@ -70,7 +447,7 @@ fn main() {
                 .about("Print the game tree from a given position.")
                 .arg(
 					Arg::with_name("Next to Act Board")
-						.help("Board for the player who's next to act.")						
+						.help("Board for the player who's next to act, or the whole position as a single KFEN string.")						
 				).arg(
 					Arg::with_name("Next to Act Opponent's Board")
 						.help("Board for the player who's not next to act.")						
@@ -78,57 +455,274 @@ fn main() {
 					Arg::with_name("Roll")
 						.help("Latest Roll.")						
 				).arg(
+                    Arg::with_name("Player to Act")
+                        .help("Which player is next to act, 1 or 2.  Defaults to 1; KFEN positions already say.")
+                        .short('p')
+                        .long("player")
+                        .takes_value(true)
+                ).arg(
                     Arg::with_name("Heuristic Depth")
                         .help("Depth of the heuristic search.")
                         .short('d')
                         .long("depth")
                         .takes_value(true)
+                ).arg(
+                    Arg::with_name("Print Depth")
+                        .help("Only print this many levels of the tree.")
+                        .long("print-depth")
+                        .takes_value(true)
+                ).arg(
+                    Arg::with_name("No Boards")
+                        .help("Don't print the boards at each node.")
+                        .long("no-boards")
+                ).arg(
+                    Arg::with_name("Force")
+                        .help("Build the tree even if it's estimated to use a lot of memory.")
+                        .long("force")
                 )
         ).get_matches();
     
     if let Some(matches) = matches.subcommand_matches("solve") {
-        let (player_board, opponent_board, die) = match unpack_next_to_act_opponent_and_roll(matches) {
-            Ok((player_board, opponent_board, die)) => (player_board, opponent_board, die),
+        let game = match unpack_position(matches) {
+            Ok(game) => game,
             Err(e) => {
                 println!("{}", e);
                 return;
             }
         };
-        let depth = get_int_from_arg_or_else(matches.value_of("Heuristic Depth"), DEFAULT_DEPTH);
-        let max_depth_to_brute_force = get_int_from_arg_or_else(matches.value_of("Max Depth to Brute Force"), DEFAULT_MAX_DEPTH_TO_BRUTE_FORCE);
-        let mut game = Node::new(player_board, opponent_board, NodeType::Move(Player::Player1, die));
-        let mut solver = Solver::from_root(game.clone());
-        let (maybe_tree, evaluation) = solver
-            .get_evaluation_tree(SolverMode::Hybrid(max_depth_to_brute_force, (depth, |x| Solver::difference_heuristic(x, 3.5))))
-            .expect("Evaluation tree should be constructable.");
+        let config = match get_solver_config(matches) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let mut solver_mode = config.get_solver_mode();
+        let mut solver = Solver::from_root_with_config(game.clone(), config);
+        let objective_function = solver.get_objective_function(&solver_mode);
+        let maybe_cache_path = matches.value_of("Cache").map(Path::new);
+        if let Some(cache_path) = maybe_cache_path.filter(|cache_path| cache_path.exists()) {
+            if let Err(e) = solver.load_cache(cache_path) {
+                println!("{}", e);
+                return;
+            }
+        }
+        if matches.is_present("Progress") {
+            if let Err(e) = solver.solve_with_progress(solver_mode.clone(), |progress| print_search_progress(&progress, &game)) {
+                println!("{}", describe_solver_error(&e));
+                return;
+            }
+            println!();
+            // The timed search has already found how deep there's time for, so the tree is built that deep rather
+            // than searching again.
+            if let SolverMode::Timed((_, f)) = &solver_mode {
+                solver_mode = SolverMode::Heuristic((solver.get_search_stats().get_depth(), f.clone()));
+            }
+        }
+        let (maybe_tree, evaluation) = match solver.get_evaluation_tree(solver_mode.clone()) {
+            Ok(result) => result,
+            Err(e) => {
+                println!("{}", describe_solver_error(&e));
+                return;
+            }
+        };
+        // The tree only has the expected result, so the margin comes from searching again, which also picks between
+        // the moves it breaks ties for.  The tree's best moves are the best on average, so a risk profile needs the
+        // search as well, as does a cache, which only the search reads and writes.
+        let needs_search = (solver.get_config().get_break_ties_by_margin() && evaluation.is_expected_result())
+            || solver.get_config().get_risk_profile() != RiskProfile::ExpectedValue
+            || maybe_cache_path.is_some();
+        let (evaluation, maybe_searched_best_moves) = if needs_search {
+            match solver.get_best_moves_and_evaluation(solver_mode.clone()) {
+                Ok((best_moves, evaluation)) => (evaluation, Some(best_moves)),
+                Err(e) => {
+                    println!("{}", describe_solver_error(&e));
+                    return;
+                }
+            }
+        } else {
+            (evaluation, None)
+        };
+        if let Some(cache_path) = maybe_cache_path {
+            if let Err(e) = solver.save_cache(cache_path) {
+                println!("{}", e);
+            }
+        }
+        println!("Position: {}\n", game.to_kfen());
         println!("Player 1 Board:\n{}\n", game.get_player_1_board());
         println!("Player 2 Board:\n{}\n", game.get_player_2_board());
-        println!("Roll: {}", die.to_string());
-        println!("Evaluation: {}", evaluation.to_string());
+        println!("To Act: {}", game.get_active_player().to_string());
+        println!("Roll: {}", get_roll_string(&game));
+        println!("Evaluation (for {}): {}", game.get_active_player().to_string(), describe_evaluation(&evaluation.for_active_player(&game), matches));
+        if matches.is_present("Time Limit") || matches.is_present("Adaptive") {
+            println!("Depth Reached: {}", solver.get_search_stats().get_depth());
+        }
+        if let Some(winner) = evaluation.get_known_winner() {
+            println!("{} wins with best play.", winner.to_string());
+        }
+        if solver.get_config().get_confidence_threshold().is_some() {
+            // A second search, since the tree doesn't keep the shallower one.
+            match solver.get_best_moves_and_evaluation_with_confidence(solver_mode.clone()) {
+                Ok((_, confidence)) => println!(
+                    "Confidence: {} (moved {:+.1} for {} from a move less deep{})",
+                    if confidence.is_low_confidence() { "Low" } else { "High" },
+                    game.get_active_player().get_sign() * confidence.get_delta(),
+                    game.get_active_player().to_string(),
+                    if confidence.has_best_move_changed() { ", and the best move changed" } else { "" },
+                ),
+                Err(e) => println!("Confidence: {}", describe_solver_error(&e)),
+            }
+        }
         let evaluation_tree = maybe_tree.expect("Game should still be in progress.");
-        let best_moves = evaluation_tree.get_moves().expect("Guaranteed to be on a move node.");
+        let best_moves = maybe_searched_best_moves.unwrap_or_else(|| evaluation_tree.get_moves().expect("Guaranteed to be on a move node."));
         println!("Best Moves: {}", best_moves.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", "));
+        let principal_variation = evaluation_tree.get_principal_variation(&objective_function);
+        println!("Principal Variation: {}", PvStep::to_line(&principal_variation));
+        if matches.is_present("Distribution") {
+            match solver.get_outcome_distribution(solver_mode.clone()) {
+                Ok(distribution) => {
+                    let distribution = distribution.from_perspective(game.get_active_player());
+                    println!("Final Score Difference (for {}):\n{}\n{}", game.get_active_player().to_string(), distribution, distribution.to_histogram(DEFAULT_HISTOGRAM_WIDTH));
+                },
+                Err(e) => println!("Final Score Difference: {}", describe_solver_error(&e)),
+            }
+        }
+        if let Some(k) = matches.value_of("Lines") {
+            let k = match k.parse::<usize>() {
+                Ok(k) => k,
+                Err(_) => {
+                    println!("Invalid number of lines: {}", k);
+                    return;
+                }
+            };
+            match solver.get_top_k_lines(k, solver_mode, usize::MAX) {
+                Ok(lines) => {
+                    println!("Top Lines:");
+                    for (index, (m, evaluation, line)) in lines.iter().enumerate() {
+                        println!("{}. {} {}: {}", index + 1, m.to_string(), describe_evaluation(&evaluation.for_active_player(&game), matches), PvStep::to_line(line));
+                    }
+                },
+                Err(e) => println!("Top Lines: {}", describe_solver_error(&e)),
+            }
+        }
+        if matches.is_present("Verbose") {
+            println!("\nSearch Stats:\n{}", solver.get_search_stats());
+            println!("\nSearch Tree Stats:\n{}", solver.get_root().get_tree_stats());
+        }
         if matches.is_present("Full Tree") {
-            println!("\nOptimal Tree:\n{}", evaluation_tree.to_pretty_string(|x| Solver::difference_heuristic(x, 3.5)));
+            let mut tree = solver.get_root().clone();
+            if !matches.is_present("Unpruned") {
+                tree.prune_to_best_moves(&objective_function, solver.get_config().get_tie_break_epsilon());
+            }
+            println!("\nOptimal Tree:\n{}", tree.to_pretty_string(&objective_function));
         }
     } else if let Some(matches) = matches.subcommand_matches("play") {
-        let max_depth_to_brute_force = match matches.value_of("Max Depth to Brute Force") {
-            Some(depth) => depth.parse::<usize>().unwrap(),
-            None => DEFAULT_MAX_DEPTH_TO_BRUTE_FORCE
+        let cancellation_token = CTRL_C_CANCELLATION_TOKEN.get_or_init(CancellationToken::new);
+        // Without any search settings, the solver plays by the default depth schedule, so the early moves are quick and
+        // the endgame is exact.
+        let has_search_settings = ["Heuristic Depth", "Max Depth to Brute Force", "Time Limit", "Adaptive", "Schedule"].iter()
+            .any(|name| matches.is_present(name));
+        let config = match get_solver_config_builder(matches)
+            .map(|builder| if has_search_settings { builder } else { builder.strategy(SolverStrategy::Scheduled) })
+            .and_then(
+                |builder| match matches.value_of("Seed") {
+                    Some(seed) => seed.parse::<u64>().map(|seed| builder.seed(seed)).map_err(|_| format!("Invalid seed: {}", seed)),
+                    None => Ok(builder),
+                }
+            ).and_then(|builder| builder.cancellation_token(cancellation_token.clone()).build()) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let solver_mode = config.get_solver_mode();
+        let tablebase_path = matches.value_of("Tablebase").map(PathBuf::from);
+        let mut maybe_endgame_table = match &tablebase_path {
+            Some(path) => match load_endgame_table_or_new(path, DEFAULT_MAX_EMPTY_SQUARES) {
+                Ok(endgame_table) => Some(endgame_table),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            },
+            None => None,
+        };
+        let maybe_opening_book = match matches.value_of("Book") {
+            Some(path) => match OpeningBook::load(Path::new(path)) {
+                Ok(opening_book) => Some(opening_book),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            },
+            None => None,
+        };
+        let start = match matches.value_of("Position").map(Node::from_kfen).transpose() {
+            Ok(maybe_position) => maybe_position.unwrap_or_else(Node::empty),
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
         };
-        let heuristic_depth = match matches.value_of("Heuristic Depth") {
-            Some(depth) => depth.parse::<usize>().expect("Invalid depth!"),
-            None => DEFAULT_DEPTH
+        if start.is_game_over() {
+            println!("The game's already over in that position, so there's nothing to play.");
+            return;
+        }
+        // The side, the rolls and the solver's pick between tied moves all come from here, and ties come back in the
+        // same order every time, so a seed plays the same game again for the same moves.
+        let mut rng = match config.get_seed() {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
         };
-        let player = Player::get_random();
-        let mut rng = rand::thread_rng();
-        let mut game = Node::empty();
-        while !game.is_game_over() {
+        let maybe_eval_depth = match matches.is_present("Show Evaluation") {
+            true => match parse_or(matches.value_of("Evaluation Depth"), DEFAULT_EVAL_DEPTH, "evaluation depth") {
+                Ok(eval_depth) => Some(eval_depth),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            },
+            false => None,
+        };
+        let player = match parse_side(matches.value_of("Side").unwrap_or("random"), &mut rng) {
+            Ok(player) => player,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        // The position says whose turn it is, and the side which of the two is the person playing.
+        if start.get_active_player() == player {
+            println!("You're {}, so you move first.", player.to_string());
+        } else {
+            println!("You're {}, so the solver moves first.", player.to_string());
+        }
+        let mut history = GameHistory::new(start.clone());
+        // With --show-eval, each move's evaluation, by how many moves in it came, for the list at the end.
+        let mut evaluations: Vec<(usize, String)> = vec![];
+        // Kept between turns, following the game, so any tree it builds and its transposition table carry over to the
+        // next search.
+        let mut solver = Solver::from_root_with_config(start, config);
+        if let Some(endgame_table) = &maybe_endgame_table {
+            solver.set_endgame_table(Arc::new(endgame_table.clone()));
+        }
+        if let Some(opening_book) = maybe_opening_book {
+            solver.set_opening_book(Arc::new(opening_book));
+        }
+        while !history.get_current().is_game_over() {
+            let game = history.get_current().clone();
             match game.get_node_type() {
-                NodeType::Roll(_) => {
-                    let roll = Die::random();
-                    game = game.with_rolls(roll).expect("Roll is guaranteed to be legal.")
-                        .get_child_from_roll(roll).expect("Roll is guaranteed to be legal.").clone();
+                NodeType::Roll(p) => {
+                    if p == player && matches.is_present("Standing") {
+                        match solver.get_evaluation(solver_mode.clone()) {
+                            Ok(evaluation) => println!("Your expected standing before the roll: {}", describe_evaluation(&evaluation.from_perspective(player), matches)),
+                            Err(e) => println!("Couldn't evaluate the roll: {}", describe_solver_error(&e)),
+                        }
+                    }
+                    let die = Die::random_from(&mut rng);
+                    history.roll(die).expect("Roll is guaranteed to be legal.");
+                    solver.advance_by_roll(die).expect("Roll is guaranteed to be legal.");
                 },
                 NodeType::Move(p, roll) => {
                     if p == player {
@@ -136,12 +730,49 @@ fn main() {
                         let mut valid_move = false;
                         while !valid_move {
                             let mut input = String::new();
-                            println!("Enter move: ");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
+                            println!("Enter move (or \"hint\", \"hint full\" or \"undo\"): ");
+                            if io::stdin().read_line(&mut input).expect("Failed to read line") == 0 {
+                                println!("No more input, so the game ends here.");
+                                return;
+                            }
                             let input = input.trim();
-                            if let Ok(m) = Move::from_string(input) {
+                            if input == "hint" || input == "hint full" {
+                                // The solver's root is the current position, so this searches it without touching the
+                                // game, and the transposition table it fills is there for the solver's next move.
+                                cancel_on_ctrl_c(|| print_hint(&mut solver, solver_mode.clone(), input == "hint full", player, matches));
+                                cancellation_token.reset();
+                            } else if input == "undo" {
+                                if history.iter().any(|placed_move| placed_move.get_player() == player) {
+                                    // Undo the solver's reply as well so it's our move again.
+                                    while let Some(position) = history.undo() {
+                                        if position.get_active_player() == player {
+                                            break;
+                                        }
+                                    }
+                                    solver.reset_root(history.get_current().clone());
+                                    evaluations.retain(|(n_moves, _)| *n_moves <= history.get_n_moves());
+                                    println!("Took back your last move and everything since.");
+                                    valid_move = true;
+                                } else {
+                                    println!("Nothing to undo!");
+                                }
+                            } else if let Ok(m) = Move::from_string(input) {
                                 if game.is_legal_move(m) {
-                                    game = game.with_move_made(m).expect("Move is guaranteed to be valid");
+                                    history.push_move(m).expect("Move is guaranteed to be valid");
+                                    // The solver's roll comes straight after, so the root moves past both at once,
+                                    // unless the position before the roll is to be evaluated.
+                                    let mut steps = vec![Step::Move(roll, m)];
+                                    if let Some(eval_depth) = maybe_eval_depth.filter(|_| !history.get_current().is_game_over()) {
+                                        solver.advance_root(&steps).expect("Move is guaranteed to be legal.");
+                                        steps.clear();
+                                        evaluations.push((history.get_n_moves(), print_evaluation_after_move(&mut solver, eval_depth, player, matches)));
+                                    }
+                                    if !history.get_current().is_game_over() {
+                                        let die = Die::random_from(&mut rng);
+                                        history.roll(die).expect("Roll is guaranteed to be legal.");
+                                        steps.push(Step::Roll(die));
+                                    }
+                                    solver.advance_root(&steps).expect("Move and roll are guaranteed to be legal.");
                                     valid_move = true;
                                 } else {
                                     println!("Invalid move!");
@@ -151,18 +782,59 @@ fn main() {
                             }
                         }
                     } else {
-                        let mut solver = Solver::from_root(game.clone());
-                        let result = solver.get_best_moves_and_evaluation(
-                            SolverMode::Hybrid(max_depth_to_brute_force, (heuristic_depth, |x| Solver::difference_heuristic(x, 3.5)))
+                        if let (Some(endgame_table), Some(path)) = (maybe_endgame_table.as_mut(), &tablebase_path) {
+                            // The first time the game gets small enough, the rest of it is added to the table.
+                            if endgame_table.covers(&game) && endgame_table.probe(&game).is_none() {
+                                endgame_table.add_positions_from(std::slice::from_ref(&game), print_progress);
+                                println!();
+                                if let Err(e) = endgame_table.save(path) {
+                                    println!("{}", e);
+                                }
+                                solver.set_endgame_table(Arc::new(endgame_table.clone()));
+                            }
+                        }
+                        // With a confidence threshold, moves the solver isn't sure of are marked "?!".
+                        let result = cancel_on_ctrl_c(
+                            || match solver.get_config().get_confidence_threshold() {
+                                Some(_) => solver.get_best_moves_and_evaluation_with_confidence(solver_mode.clone())
+                                    .map(|(best_moves, confidence)| (best_moves, confidence.get_evaluation(), confidence.is_low_confidence())),
+                                None => solver.get_best_moves_and_evaluation(solver_mode.clone())
+                                    .map(|(best_moves, evaluation)| (best_moves, evaluation, false)),
+                            }
                         );
+                        cancellation_token.reset();
+                        // Cut short by Ctrl-C, the solver still has to move, so it looks a move ahead instead.
+                        let result = match result {
+                            Err(SolverError::Interrupted) => {
+                                println!("Search interrupted, so the solver plays the best move one move ahead.");
+                                let objective_function = solver.get_config().get_objective().clone();
+                                solver.get_best_moves_and_evaluation(SolverMode::Heuristic((1, objective_function)))
+                                    .map(|(best_moves, evaluation)| (best_moves, evaluation, false))
+                            },
+                            result => result,
+                        };
                         match result {
-                            Ok((best_moves, evaluation)) => {
+                            Ok((best_moves, evaluation, low_confidence)) => {
                                 let selected_move = best_moves.choose(&mut rng).unwrap();
-                                println!("Solver rolls a {} and plays {}.  Evaluation: {}", roll.to_string(), selected_move.to_string(), evaluation.from_perspective(player).to_string());
-                                game = game.with_move_made(*selected_move).expect("Move is guaranteed to be legal.");
+                                println!(
+                                    "Solver rolls a {} and plays {}{}.  Evaluation: {}",
+                                    roll.to_string(),
+                                    selected_move.to_string(),
+                                    if low_confidence { "?!" } else { "" },
+                                    describe_evaluation(&evaluation.from_perspective(player), matches),
+                                );
+                                if matches.is_present("Adaptive") {
+                                    println!("Searched to depth {}.", solver.get_search_stats().get_depth());
+                                }
+                                let effect = history.push_move(*selected_move).expect("Move is guaranteed to be legal.");
+                                solver.advance_by_move(*selected_move).expect("Move is guaranteed to be legal.");
+                                println!("{}", effect);
+                                if let Some(eval_depth) = maybe_eval_depth.filter(|_| !history.get_current().is_game_over()) {
+                                    evaluations.push((history.get_n_moves(), print_evaluation_after_move(&mut solver, eval_depth, player, matches)));
+                                }
                             },
                             Err(e) => {
-                                println!("Solver failed: {}", e);
+                                println!("Solver failed: {}", describe_solver_error(&e));
                                 break;
                             }
                         }
@@ -170,6 +842,7 @@ fn main() {
                 },
             }
         }
+        let game = history.get_current();
         let outcome = match game.get_outcome() {
             Outcome::Draw => "Draw",
             Outcome::Victory(p) => {
@@ -182,49 +855,534 @@ fn main() {
             Outcome::InProgress => panic!("Game is over, but outcome is in progress.")
         };
         println!(
-            "\nGame Over!\n\nFinal Board: {}\nPlayer: {}\nSolver: {}\nOutcome: {}\n",
+            "\nGame Over!\n\nFinal Board: {}\nOutcome: {}\n",
             game.to_string_from_perspective(player),
-            game.get_score(player),
-            game.get_score(player.opponent()),
             outcome,
         );
+        println!("Moves:");
+        for placed_move in history.iter() {
+            println!("{}", placed_move);
+        }
+        if maybe_eval_depth.is_some() {
+            // Each evaluation is from just after its move, so there's none after the last one, which ended the game.
+            println!("\nEvaluations (for you):");
+            for (n_moves, evaluation) in evaluations.iter() {
+                println!("{}: {}", history.iter().nth(n_moves - 1).expect("Evaluations are only kept for moves in the history."), evaluation);
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("tablebase") {
+        let game = match unpack_position(matches) {
+            Ok(game) => game,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let max_empty_squares = match matches.value_of("Max Empty Squares") {
+            Some(k) => match k.parse::<usize>() {
+                Ok(k) => k,
+                Err(_) => {
+                    println!("Invalid max empty squares: {}", k);
+                    return;
+                }
+            },
+            None => DEFAULT_MAX_EMPTY_SQUARES,
+        };
+        let path = Path::new(matches.value_of("Output").expect("Output is required."));
+        let mut endgame_table = match load_endgame_table_or_new(path, max_empty_squares) {
+            Ok(endgame_table) => endgame_table,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        if endgame_table.get_max_empty_squares() != max_empty_squares {
+            println!("{} holds positions with up to {} empty squares, not {}.", path.display(), endgame_table.get_max_empty_squares(), max_empty_squares);
+            return;
+        }
+        let n_added = endgame_table.add_positions_from(&[game], print_progress);
+        println!("\nAdded {} positions, for {} in all.", n_added, endgame_table.len());
+        if let Err(e) = endgame_table.save(path) {
+            println!("{}", e);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("book") {
+        let settings = parse_or(matches.value_of("Plies"), DEFAULT_BOOK_PLIES, "plies")
+            .and_then(|max_plies| Ok((max_plies, get_book_config(matches)?)));
+        let (max_plies, config) = match settings {
+            Ok(settings) => settings,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let opening_book = match OpeningBook::build(&config, max_plies, |done, total| print_progress_of("Searching openings", done, total)) {
+            Ok(opening_book) => opening_book,
+            Err(e) => {
+                println!("{}", describe_solver_error(&e));
+                return;
+            }
+        };
+        println!("\nSearched {} positions.", opening_book.len());
+        if let Err(e) = opening_book.save(Path::new(matches.value_of("Output").expect("Output is required."))) {
+            println!("{}", e);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("sweep") {
+        let values = matches.value_of("Values").expect("Values are required.").split(',')
+            .map(|value| value.trim().parse::<f32>().map_err(|_| format!("Invalid value: {}", value)))
+            .collect::<Result<Vec<f32>, String>>();
+        let settings = values.and_then(
+            |values| Ok((
+                values,
+                parse_or(matches.value_of("Games"), DEFAULT_SWEEP_GAMES, "games")?,
+                parse_or(matches.value_of("Heuristic Depth"), DEFAULT_SWEEP_DEPTH, "depth")?,
+                parse_or(matches.value_of("Seed"), 0, "seed")?,
+            ))
+        );
+        let (values, games, depth, seed) = match settings {
+            Ok(settings) => settings,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        if depth == 0 {
+            println!("The depth must be at least 1.");
+            return;
+        }
+        let results = match run_parameter_sweep(&values, games, depth, seed) {
+            Ok(results) => results,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        for result in results {
+            println!(
+                "{} vs {}: {} wins, {} draws, {} losses ({:.0}%)",
+                result.get_first_value(),
+                result.get_second_value(),
+                result.get_wins(),
+                result.get_draws(),
+                result.get_losses(),
+                100. * result.get_score(),
+            );
+        }
+    } else if let Some(matches) = matches.subcommand_matches("bench") {
+        let results = get_bench_config(matches).and_then(|config| run_benchmark(&config).map_err(|e| describe_solver_error(&e)));
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        println!("{:<20} {:>10} {:>10}  {:<24} Evaluation", "Position", "Nodes", "Time", "Best Moves");
+        for result in results.iter() {
+            println!(
+                "{:<20} {:>10} {:>9.3}s  {:<24} {}",
+                result.get_name(),
+                result.get_n_nodes(),
+                result.get_elapsed().as_secs_f64(),
+                result.get_best_moves().iter().map(|m| m.to_string()).collect::<Vec<String>>().join(", "),
+                result.get_evaluation(),
+            );
+        }
+        println!(
+            "{:<20} {:>10} {:>9.3}s",
+            "Total",
+            results.iter().map(|result| result.get_n_nodes()).sum::<usize>(),
+            results.iter().map(|result| result.get_elapsed()).sum::<Duration>().as_secs_f64(),
+        );
+    } else if let Some(matches) = matches.subcommand_matches("analyze") {
+        let game = match unpack_position(matches) {
+            Ok(game) => game,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let config = match get_analyze_config(matches) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let solver_mode = config.get_solver_mode();
+        let mut solver = Solver::from_root_with_config(game.clone(), config);
+        let ranked_moves = match solver.get_all_moves_ranked(solver_mode) {
+            Ok(ranked_moves) => ranked_moves,
+            Err(e) => {
+                println!("{}", describe_solver_error(&e));
+                return;
+            }
+        };
+        println!("Position: {}\n", game.to_kfen());
+        println!("Player 1 Board:\n{}\n", game.get_player_1_board());
+        println!("Player 2 Board:\n{}\n", game.get_player_2_board());
+        println!("To Act: {}", game.get_active_player().to_string());
+        println!("Roll: {}\n", get_roll_string(&game));
+        println!("{}", get_move_analysis(&game, &ranked_moves).join("\n"));
     } else if let Some(matches) = matches.subcommand_matches("tree") { 
-        let (player_board, opponent_board, die) = match unpack_next_to_act_opponent_and_roll(matches) {
-            Ok((x, y, z)) => (x, y, z),
+        let mut game = match unpack_position(matches) {
+            Ok(game) => game,
             Err(e) => {
                 println!("{}", e);
                 return;
             }
         };
-        let mut game = Node::new(player_board.clone(), opponent_board.clone(), NodeType::Move(Player::Player1, die));
-        match matches.value_of("Heuristic Depth") {
-            Some(depth_string) => {
-                let depth = depth_string.parse::<usize>().unwrap();
+        let maybe_depth = matches.value_of("Heuristic Depth").map(|depth_string| depth_string.parse::<usize>().unwrap());
+        // Checked before the tree's built, which can take a while.
+        let max_print_depth = match matches.value_of("Print Depth").map(|depth| parse_or(Some(depth), 0, "print depth")).transpose() {
+            Ok(max_print_depth) => max_print_depth,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        // The entire tree is only bounded by how long eliminations can stretch the game.
+        let estimated_depth = maybe_depth.unwrap_or_else(|| game.get_moves_left_bounds().1);
+        let estimated_bytes = game.estimated_tree_size(estimated_depth).saturating_mul(game.actual_memory_footprint());
+        if estimated_bytes > MAX_TREE_BYTES_WITHOUT_FORCE && !matches.is_present("Force") {
+            println!(
+                "Warning: the tree is estimated to use {} MB.  Pass --force to build it anyway.",
+                estimated_bytes / (1 << 20),
+            );
+            return;
+        }
+        match maybe_depth {
+            Some(depth) => {
                 game.build_n_moves_up_to_symmetry(depth);
             },
             None => {
                 game.build_entire_tree_up_to_symmetry();
             }
         }
+        let print_options = PrettyPrintOptions {
+            max_depth: max_print_depth,
+            show_boards: !matches.is_present("No Boards"),
+            ..PrettyPrintOptions::default()
+        };
         println!(
-            "Player Board: \n{}\n\nOpponent Board: \n{}\n\nRoll: {}\n\nTree:\n{}",
-            player_board.to_string(),
-            opponent_board.to_string(),
-            die.to_string(),
-            game.to_pretty_string(|x| Solver::difference_heuristic(x, 3.5)),
+            "To Act: {}\n\nPlayer Board: \n{}\n\nOpponent Board: \n{}\n\nRoll: {}\n\nStats:\n{}\n\nTree:\n{}",
+            game.get_active_player().to_string(),
+            game.get_player_board(game.get_active_player()).to_string(),
+            game.get_player_board(game.get_active_player().opponent()).to_string(),
+            get_roll_string(&game),
+            game.get_tree_stats(),
+            game.to_pretty_string_with(&print_options, SolverConfig::default().get_objective()),
         );
     } else {
         println!("Missing subcommand!");  
     }
 }
 
-fn get_int_from_arg_or_else(arg: Option<&str>, default: usize) -> usize {
-    match arg {
-        Some(arg) => arg.parse::<usize>().unwrap(),
-        None => default
+#[allow(clippy::needless_return)]
+fn get_solver_config(matches: &ArgMatches) -> Result<SolverConfig, String> {
+    return get_solver_config_builder(matches)?.build();
+}
+
+#[allow(clippy::needless_return)]
+fn get_solver_config_builder(matches: &ArgMatches) -> Result<SolverConfigBuilder, String> {
+    // The solver settings from the command line, with a time limit taking over from the depth settings.
+    let mut builder = SolverConfig::builder();
+    if let Some(depth) = matches.value_of("Heuristic Depth") {
+        builder = builder.heuristic_depth(depth.parse::<usize>().map_err(|_| format!("Invalid depth: {}", depth))?);
+    }
+    if let Some(depth) = matches.value_of("Max Depth to Brute Force") {
+        builder = builder.max_moves_left_to_brute_force(depth.parse::<usize>().map_err(|_| format!("Invalid max brute force depth: {}", depth))?);
+    }
+    if let Some(depth) = matches.value_of("Brute Force Depth") {
+        builder = builder.brute_force_depth(depth.parse::<usize>().map_err(|_| format!("Invalid brute force depth: {}", depth))?);
+    }
+    if let Some(heuristic) = matches.value_of("Heuristic") {
+        builder = builder.objective(HeuristicFunction::from_string(heuristic)?.to_objective());
+    }
+    if let Some(path) = matches.value_of("Heuristic Weights") {
+        builder = builder.objective(HeuristicWeights::load(Path::new(path))?.to_combined_heuristic().to_objective());
+    }
+    if let Some(schedule) = matches.value_of("Schedule") {
+        builder = builder.strategy(SolverStrategy::Scheduled).depth_schedule(DepthSchedule::from_string(schedule)?);
+    }
+    if let Some(max_nodes) = matches.value_of("Adaptive") {
+        builder = builder.strategy(SolverStrategy::Adaptive).node_budget(max_nodes.parse::<usize>().map_err(|_| format!("Invalid node budget: {}", max_nodes))?);
+    }
+    if let Some(time_limit) = matches.value_of("Time Limit") {
+        builder = builder.strategy(SolverStrategy::Timed).time_limit(parse_duration(time_limit)?);
+    }
+    if let Some(threshold) = matches.value_of("Confidence Threshold") {
+        builder = builder.confidence_threshold(threshold.parse::<f32>().map_err(|_| format!("Invalid confidence threshold: {}", threshold))?);
+    }
+    if let Some(n_threads) = matches.value_of("Threads") {
+        builder = builder.n_threads(n_threads.parse::<usize>().map_err(|_| format!("Invalid number of threads: {}", n_threads))?);
+    }
+    if matches.is_present("Margin") {
+        builder = builder.break_ties_by_margin(true);
+    }
+    if let Some(objective) = matches.value_of("Objective") {
+        builder = builder.search_objective(SearchObjective::from_string(objective)?);
+    }
+    if let Some(draw_weight) = matches.value_of("Draw Weight") {
+        builder = builder.draw_weight(draw_weight.parse::<f32>().map_err(|_| format!("Invalid draw weight: {}", draw_weight))?);
+    }
+    if let Some(risk_profile) = matches.value_of("Risk") {
+        builder = builder.risk_profile(RiskProfile::from_string(risk_profile)?);
+    }
+    return Ok(builder);
+}
+
+#[allow(clippy::needless_return)]
+fn cancel_on_ctrl_c<T>(search: impl FnOnce() -> T) -> T {
+    // While the search runs, Ctrl-C cancels it through CTRL_C_CANCELLATION_TOKEN rather than ending the game.  Before
+    // and after, it quits as usual.  Only Unix has the handler; elsewhere Ctrl-C always quits.
+    #[cfg(unix)]
+    {
+        extern "C" fn handle_ctrl_c(_: libc::c_int) {
+            if let Some(cancellation_token) = CTRL_C_CANCELLATION_TOKEN.get() {
+                cancellation_token.cancel();
+            }
+        }
+        // Safe since the handler only stores to an atomic.
+        unsafe {
+            libc::signal(libc::SIGINT, handle_ctrl_c as *const () as libc::sighandler_t);
+        }
+        let result = search();
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+        return result;
+    }
+    #[cfg(not(unix))]
+    return search();
+}
+
+#[allow(clippy::needless_return)]
+fn get_book_config(matches: &ArgMatches) -> Result<SolverConfig, String> {
+    // The same search play would use, other than the settings the book subcommand doesn't have.
+    let mut builder = SolverConfig::builder();
+    if let Some(depth) = matches.value_of("Heuristic Depth") {
+        builder = builder.heuristic_depth(depth.parse::<usize>().map_err(|_| format!("Invalid depth: {}", depth))?);
+    }
+    if let Some(heuristic) = matches.value_of("Heuristic") {
+        builder = builder.objective(HeuristicFunction::from_string(heuristic)?.to_objective());
+    }
+    return builder.build();
+}
+
+#[allow(clippy::needless_return)]
+fn get_bench_config(matches: &ArgMatches) -> Result<SolverConfig, String> {
+    // The default search, other than the settings bench has.
+    let mut builder = SolverConfig::builder();
+    if let Some(depth) = matches.value_of("Heuristic Depth") {
+        builder = builder.heuristic_depth(depth.parse::<usize>().map_err(|_| format!("Invalid depth: {}", depth))?);
+    }
+    if let Some(depth) = matches.value_of("Max Depth to Brute Force") {
+        builder = builder.max_moves_left_to_brute_force(depth.parse::<usize>().map_err(|_| format!("Invalid max brute force depth: {}", depth))?);
+    }
+    if let Some(heuristic) = matches.value_of("Heuristic") {
+        builder = builder.objective(HeuristicFunction::from_string(heuristic)?.to_objective());
+    }
+    return builder.build();
+}
+
+#[allow(clippy::needless_return)]
+fn get_analyze_config(matches: &ArgMatches) -> Result<SolverConfig, String> {
+    // analyze has the same search settings as bench.
+    return get_bench_config(matches);
+}
+
+#[allow(clippy::needless_return)]
+fn get_move_analysis(game: &Node, ranked_moves: &[(Move, Evaluation)]) -> Vec<String> {
+    // A line per move as get_all_moves_ranked orders them, with its evaluation for the player to act.  The moves the
+    // solver would choose between, those tied with the best, are starred, and a move that leads to the same position
+    // as one above it, up to the order of the dice in a column, says which.
+    let mut lines = vec![format!("{:>4}  {:<8} {:<20} Notes", "Rank", "Move", "Evaluation")];
+    let mut seen_positions: Vec<(u64, Move)> = vec![];
+    for (index, (m, evaluation)) in ranked_moves.iter().enumerate() {
+        let mut notes = vec![];
+        if let Some((_, best_evaluation)) = ranked_moves.first() {
+            if are_tied(evaluation.get_evaluation(), best_evaluation.get_evaluation()) {
+                notes.push("*".to_string());
+            }
+        }
+        let position_key = game.with_move_made(*m).expect("Ranked moves are legal.").symmetric_position_key();
+        match seen_positions.iter().find(|(key, _)| *key == position_key) {
+            Some((_, original)) => notes.push(format!("same as {}", original.to_string())),
+            None => seen_positions.push((position_key, *m)),
+        }
+        lines.push(format!("{:>4}  {:<8} {:<20} {}", index + 1, m.to_string(), evaluation.for_active_player(game).to_string(), notes.join(" ")).trim_end().to_string());
+    }
+    return lines;
+}
+
+fn print_hint(solver: &mut Solver, solver_mode: SolverMode, full: bool, player: Player, matches: &ArgMatches) {
+    // The moves the solver would choose between at its root, or with full its top three moves, with evaluations for
+    // player.
+    if full {
+        match solver.get_all_moves_ranked(solver_mode) {
+            Ok(ranked_moves) => {
+                println!("Hint:");
+                for (index, (m, evaluation)) in ranked_moves.iter().take(N_HINT_MOVES).enumerate() {
+                    println!("{}. {} {}", index + 1, m.to_string(), describe_evaluation(&evaluation.from_perspective(player), matches));
+                }
+            },
+            Err(e) => println!("Hint failed: {}", describe_solver_error(&e)),
+        }
+    } else {
+        match solver.get_best_moves_and_evaluation(solver_mode) {
+            Ok((best_moves, evaluation)) => println!(
+                "Hint: {}.  Evaluation: {}",
+                best_moves.iter().map(|m| m.to_string()).collect::<Vec<String>>().join(", "),
+                describe_evaluation(&evaluation.from_perspective(player), matches),
+            ),
+            Err(e) => println!("Hint failed: {}", describe_solver_error(&e)),
+        }
+    }
+}
+
+#[allow(clippy::needless_return)]
+fn print_evaluation_after_move(solver: &mut Solver, eval_depth: usize, player: Player, matches: &ArgMatches) -> String {
+    // A quick evaluation of the solver's root, just after a move, for player, which is printed and returned.  The
+    // search shares the solver's transposition table, so its next search picks up where this left off.
+    let objective_function = solver.get_config().get_objective().clone();
+    let description = match solver.get_evaluation(SolverMode::Heuristic((eval_depth, objective_function))) {
+        Ok(evaluation) => {
+            let evaluation = evaluation.from_perspective(player);
+            let standing = match compare_totally(evaluation.get_evaluation(), 0.) {
+                Ordering::Greater => "you're better",
+                Ordering::Less => "the solver's better",
+                Ordering::Equal => "it's even",
+            };
+            format!("{} ({})", describe_evaluation(&evaluation, matches), standing)
+        },
+        Err(e) => describe_solver_error(&e),
+    };
+    println!("Eval: {}", description);
+    return description;
+}
+
+fn parse_side(side: &str, rng: &mut StdRng) -> Result<Player, String> {
+    // Which player the person playing is, with random drawn from rng so a seeded game picks the same side.
+    match side.trim() {
+        "first" | "player1" => Ok(Player::Player1),
+        "second" | "player2" => Ok(Player::Player2),
+        "random" => Ok(Player::random_from(rng)),
+        side => Err(format!("Invalid side, expected first, second, player1, player2 or random: {}", side)),
+    }
+}
+
+fn parse_or<T: std::str::FromStr>(maybe_value: Option<&str>, default: T, name: &str) -> Result<T, String> {
+    match maybe_value {
+        Some(value) => value.parse::<T>().map_err(|_| format!("Invalid {}: {}", name, value)),
+        None => Ok(default),
     }
 }
 
+#[allow(clippy::needless_return)]
+fn load_endgame_table_or_new(path: &Path, max_empty_squares: usize) -> Result<EndgameTable, String> {
+    // The table saved at path, or an empty one if there isn't one yet.
+    if path.exists() {
+        return EndgameTable::load(path);
+    }
+    return Ok(EndgameTable::new(max_empty_squares));
+}
+
+fn print_progress(done: usize, total: usize) {
+    print_progress_of("Solving endgames", done, total);
+}
+
+fn print_progress_of(label: &str, done: usize, total: usize) {
+    // Overwrites the same line, and only every so often, since printing each of thousands of positions is slow.
+    if done == total || done.is_multiple_of(100) {
+        print!("\r{}: {}/{}", label, done, total);
+        io::stdout().flush().expect("Couldn't write to stdout.");
+    }
+}
+
+fn print_search_progress(progress: &SearchProgress, game: &Node) {
+    // Overwrites the same line, padded so a shorter line covers a longer one.
+    let best = match (progress.get_best_moves(), progress.get_evaluation()) {
+        (Some(best_moves), Some(evaluation)) => format!(
+            "best {} ({})",
+            best_moves.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", "),
+            evaluation.for_active_player(game).to_string(),
+        ),
+        _ => "no moves searched yet".to_string(),
+    };
+    print!(
+        "\rDepth {} | {} nodes | {:.1}s | {:<60}",
+        progress.get_depth(), progress.get_n_nodes(), progress.get_elapsed().as_secs_f32(), best,
+    );
+    io::stdout().flush().expect("Couldn't write to stdout.");
+}
+
+#[allow(clippy::needless_return)]
+fn describe_evaluation(evaluation: &Evaluation, matches: &ArgMatches) -> String {
+    if matches.is_present("Win Probability") {
+        return evaluation.to_win_probability_string(DEFAULT_WIN_PROBABILITY_SCALE);
+    }
+    return evaluation.to_string();
+}
+
+fn describe_solver_error(error: &SolverError) -> String {
+    match error {
+        SolverError::GameAlreadyOver => "The game's already over, so there's nothing to solve.".to_string(),
+        SolverError::WrongNodeType { .. } => "It's a roll, not a move, so there's nothing to solve until the die is rolled.".to_string(),
+        SolverError::DepthZero => "The depth must be at least 1.".to_string(),
+        SolverError::IllegalMove(m) => format!("{} isn't a legal move.", m.to_string()),
+        SolverError::InvalidMode(message) => message.clone(),
+        SolverError::Timeout => "Ran out of time before finishing a search.  Try a longer time limit.".to_string(),
+        SolverError::Interrupted => "The search was interrupted.".to_string(),
+        SolverError::NanEvaluation => "The heuristic scored a position as NaN, so there's no telling which move is best.".to_string(),
+        SolverError::Internal(message) => format!("Something went wrong in the solver: {}", message),
+    }
+}
+
+#[allow(clippy::needless_return)]
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    // A number of seconds, optionally fractional, or of milliseconds with an "ms" suffix, e.g. "2s", "1.5" or "500ms".
+    let duration = duration.trim();
+    let (value, seconds_per_unit) = match duration.strip_suffix("ms") {
+        Some(value) => (value, 0.001),
+        None => (duration.strip_suffix('s').unwrap_or(duration), 1.),
+    };
+    return match value.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0. => Ok(Duration::from_secs_f64(value * seconds_per_unit)),
+        _ => Err(format!("Invalid time limit, expected e.g. \"2s\" or \"500ms\": {}", duration)),
+    };
+}
+
+fn unpack_position(matches: &ArgMatches) -> Result<Node, String> {
+    // Either a single KFEN string, or the two boards and roll with --player (Player 1 by default) to act.
+    match (matches.value_of("Next to Act Board"), matches.value_of("Next to Act Opponent's Board"), matches.value_of("Roll")) {
+        (Some(kfen), None, None) => {
+            if matches.is_present("Player to Act") {
+                return Err("The player to act is already part of the KFEN string.".to_string());
+            }
+            Node::from_kfen(kfen)
+        },
+        _ => {
+            let player = match matches.value_of("Player to Act") {
+                Some(player) => Player::from_string(player)?,
+                None => Player::Player1,
+            };
+            let (player_board, opponent_board, die) = unpack_next_to_act_opponent_and_roll(matches)?;
+            match player {
+                Player::Player1 => Node::new_validated(player_board, opponent_board, NodeType::Move(player, die)),
+                Player::Player2 => Node::new_validated(opponent_board, player_board, NodeType::Move(player, die)),
+            }
+        },
+    }
+}
+
+fn get_roll_string(game: &Node) -> String {
+    match game.get_node_type() {
+        NodeType::Roll(_) => "-".to_string(),
+        NodeType::Move(_, die) => die.to_string(),
+    }
+}
+
+#[allow(clippy::needless_return)]
 fn unpack_next_to_act_opponent_and_roll(matches: &ArgMatches) -> Result<(Board, Board, Die), String> {
     match (matches.value_of("Next to Act Board"), matches.value_of("Next to Act Opponent's Board"), matches.value_of("Roll")) {
         (Some(player_board), Some(opponent_board), Some(roll)) => {
@@ -269,4 +1427,40 @@ fn unpack_next_to_act_opponent_and_roll(matches: &ArgMatches) -> Result<(Board,
 mod test_integration_tests {
     use super::*;
 
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration(" 500ms "), Ok(Duration::from_millis(500)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("2 minutes").is_err());
+    }
+
+    #[test]
+    fn test_parse_side() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(parse_side("first", &mut rng), Ok(Player::Player1));
+        assert_eq!(parse_side("player2", &mut rng), Ok(Player::Player2));
+        assert!(parse_side("middle", &mut rng).is_err());
+        let random_side = parse_side("random", &mut StdRng::seed_from_u64(0));
+        assert!(random_side.is_ok());
+        assert_eq!(parse_side("random", &mut StdRng::seed_from_u64(0)), random_side);
+    }
+
+    #[test]
+    fn test_get_move_analysis() {
+        let game = Node::from_kfen("_15_32___ / _41___36_ 2 r1").unwrap();
+        let analyze = || {
+            let config = SolverConfig::builder().heuristic_depth(2).build().unwrap();
+            let solver_mode = config.get_solver_mode();
+            get_move_analysis(&game, &Solver::from_root_with_config(game.clone(), config).get_all_moves_ranked(solver_mode).unwrap())
+        };
+        let lines = analyze();
+        assert_eq!(lines.len(), game.get_legal_moves().unwrap().len() + 1);
+        assert!(lines[1].starts_with("   1  (1, 1)") && lines[1].ends_with('*'));
+        assert!(lines[3].starts_with("   3  (2, 2)") && lines[3].ends_with("same as (1, 2)"));
+        assert!(lines[5].starts_with("   5  (1, 0)") && lines[5].ends_with("same as (0, 0)"));
+        assert_eq!(analyze(), lines);
+    }
 }
\ No newline at end of file
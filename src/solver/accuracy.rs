@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::board::board::{Die, Move, are_tied};
+use crate::solver::solver::Evaluation;
+use crate::tree::tree::{Node, NodeStatus};
+
+// The positions heuristic_accuracy_report is run on by default: everything with this few empty squares that follows
+// from random games with these seeds.
+pub const DEFAULT_ACCURACY_MAX_EMPTY_SQUARES: usize = 4;
+pub const DEFAULT_ACCURACY_SEEDS: [u64; 3] = [0, 1, 2];
+
+#[derive(Clone, Debug)]
+pub struct PositionAccuracy {
+    position: Node,
+    // The first of the moves the heuristic search thought best.
+    heuristic_move: Move,
+    // Every move brute force thought best.
+    best_moves: Vec<Move>,
+    heuristic_evaluation: Evaluation,
+    best_evaluation: Evaluation,
+    // What brute force made of the heuristic's move, from Player 1's perspective like the others.
+    heuristic_move_evaluation: Evaluation,
+    // Between the two evaluations of the position, each as Player 1's expected result.
+    absolute_error: f32,
+}
+
+impl PositionAccuracy {
+
+    pub fn new(
+        position: Node,
+        heuristic_move: Move,
+        best_moves: Vec<Move>,
+        heuristic_evaluation: Evaluation,
+        best_evaluation: Evaluation,
+        heuristic_move_evaluation: Evaluation,
+        absolute_error: f32,
+    ) -> Self {
+        PositionAccuracy { position, heuristic_move, best_moves, heuristic_evaluation, best_evaluation, heuristic_move_evaluation, absolute_error }
+    }
+
+    pub fn get_position(&self) -> &Node {
+        &self.position
+    }
+
+    pub fn get_heuristic_move(&self) -> Move {
+        self.heuristic_move
+    }
+
+    pub fn get_best_moves(&self) -> &Vec<Move> {
+        &self.best_moves
+    }
+
+    pub fn get_heuristic_evaluation(&self) -> Evaluation {
+        self.heuristic_evaluation
+    }
+
+    pub fn get_best_evaluation(&self) -> Evaluation {
+        self.best_evaluation
+    }
+
+    pub fn get_heuristic_move_evaluation(&self) -> Evaluation {
+        self.heuristic_move_evaluation
+    }
+
+    pub fn get_absolute_error(&self) -> f32 {
+        self.absolute_error
+    }
+
+    pub fn get_value_lost(&self) -> f32 {
+        // How much worse the heuristic's move is than the best one for the player who made it, never negative.
+        let player = self.position.get_active_player();
+        return (player.get_sign() * (self.best_evaluation.get_evaluation() - self.heuristic_move_evaluation.get_evaluation())).max(0.);
+    }
+
+    pub fn preserves_best_value(&self) -> bool {
+        // Whether the heuristic's move is as good as the best one, even if it isn't one of brute force's best moves.
+        are_tied(self.best_evaluation.get_evaluation(), self.heuristic_move_evaluation.get_evaluation())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HeuristicAccuracyReport {
+    positions: Vec<PositionAccuracy>,
+    // Positions that were finished, waiting on a roll or too deep to brute force.
+    n_skipped: usize,
+}
+
+impl Display for HeuristicAccuracyReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Positions: {} ({} skipped)\nBest Move Agreement: {:.1}%\nMean Value Lost: {:.4}\nMean Absolute Error: {:.4}",
+            self.get_n_positions(),
+            self.n_skipped,
+            self.get_move_agreement() * 100.,
+            self.get_mean_value_lost(),
+            self.get_mean_absolute_error(),
+        )
+    }
+}
+
+impl HeuristicAccuracyReport {
+
+    pub fn new(positions: Vec<PositionAccuracy>, n_skipped: usize) -> Self {
+        HeuristicAccuracyReport { positions, n_skipped }
+    }
+
+    pub fn get_positions(&self) -> &Vec<PositionAccuracy> {
+        &self.positions
+    }
+
+    pub fn get_n_positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn get_n_skipped(&self) -> usize {
+        self.n_skipped
+    }
+
+    pub fn get_move_agreement(&self) -> f32 {
+        // The share of positions where the heuristic's move was as good as the best, or 0 if there were none.
+        self.get_mean(|position| if position.preserves_best_value() { 1. } else { 0. })
+    }
+
+    pub fn get_mean_value_lost(&self) -> f32 {
+        self.get_mean(PositionAccuracy::get_value_lost)
+    }
+
+    pub fn get_mean_absolute_error(&self) -> f32 {
+        self.get_mean(PositionAccuracy::get_absolute_error)
+    }
+
+    fn get_mean(&self, f: impl Fn(&PositionAccuracy) -> f32) -> f32 {
+        if self.positions.is_empty() {
+            return 0.;
+        }
+        return self.positions.iter().map(f).sum::<f32>() / self.positions.len() as f32;
+    }
+}
+
+pub fn get_accuracy_positions(seeds: &[Node], max_empty_squares: usize) -> Vec<Node> {
+    // Every position to move in with max_empty_squares or fewer empty squares that can follow from the seeds, the
+    // seeds included, once each up to symmetry.  Lines aren't followed past an elimination, since squares reopened
+    // and refilled with other dice could keep turning up new positions for as long as the game goes on.
+    let mut seen = HashSet::new();
+    let mut positions = vec![];
+    let mut stack = seeds.iter()
+        .filter(|seed| seed.get_n_empty_squares() <= max_empty_squares)
+        .map(|seed| seed.clone_without_children())
+        .collect::<Vec<Node>>();
+    while let Some(node) = stack.pop() {
+        match node.status() {
+            NodeStatus::Terminal(_) => {},
+            NodeStatus::Roll(_) => stack.extend(
+                Die::all().into_iter().map(|die| node.with_roll(die).expect("Guaranteed to be a roll node."))
+            ),
+            NodeStatus::Move(_, _) => {
+                if !seen.insert(node.symmetric_position_key()) {
+                    continue;
+                }
+                stack.extend(
+                    node.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.").into_iter()
+                        .map(|m| node.with_move_made(m).expect("Won't error because we know the move is legal."))
+                        .filter(|child| child.get_n_empty_squares() < node.get_n_empty_squares())
+                );
+                positions.push(node);
+            },
+        }
+    }
+    return positions;
+}
+
+pub fn get_accuracy_seeds(seeds: &[u64], max_empty_squares: usize) -> Vec<Node> {
+    // For each seed, the first position to move in with max_empty_squares or fewer empty squares in a game of random
+    // moves and rolls.  A game that finishes before getting there doesn't give one.
+    let mut positions = vec![];
+    for seed in seeds {
+        let mut rng = StdRng::seed_from_u64(*seed);
+        let mut node = Node::empty();
+        loop {
+            node = match node.status() {
+                NodeStatus::Terminal(_) => break,
+                NodeStatus::Roll(_) => node.with_roll(Die::random_from(&mut rng)).expect("Guaranteed to be a roll node."),
+                NodeStatus::Move(_, _) if node.get_n_empty_squares() <= max_empty_squares => {
+                    positions.push(node);
+                    break;
+                },
+                NodeStatus::Move(_, _) => {
+                    let m = *node.get_legal_moves().expect("Guaranteed to be a move node.").choose(&mut rng).expect("A game that isn't over has a legal move.");
+                    node.with_move_made(m).expect("Won't error because we know the move is legal.")
+                },
+            };
+        }
+    }
+    return positions;
+}
+
+pub fn get_default_accuracy_positions() -> Vec<Node> {
+    get_accuracy_positions(
+        &get_accuracy_seeds(&DEFAULT_ACCURACY_SEEDS, DEFAULT_ACCURACY_MAX_EMPTY_SQUARES),
+        DEFAULT_ACCURACY_MAX_EMPTY_SQUARES,
+    )
+}
+
+#[cfg(test)]
+mod test_accuracy {
+    use super::*;
+
+    #[test]
+    fn test_accuracy_positions() {
+        let seeds = get_accuracy_seeds(&[0, 1], 3);
+        assert_eq!(seeds.len(), 2);
+        assert!(seeds.iter().all(|seed| seed.get_n_empty_squares() <= 3 && matches!(seed.status(), NodeStatus::Move(_, _))));
+        assert_eq!(get_accuracy_seeds(&[0, 1], 3).iter().map(|seed| seed.position_key()).collect::<Vec<u64>>(), seeds.iter().map(|seed| seed.position_key()).collect::<Vec<u64>>());
+
+        let positions = get_accuracy_positions(&seeds, 3);
+        assert!(positions.len() > seeds.len());
+        assert!(positions.iter().all(|position| position.get_n_empty_squares() <= 3 && matches!(position.status(), NodeStatus::Move(_, _))));
+        let keys = positions.iter().map(|position| position.symmetric_position_key()).collect::<HashSet<u64>>();
+        assert_eq!(keys.len(), positions.len());
+        assert!(seeds.iter().all(|seed| keys.contains(&seed.symmetric_position_key())));
+        assert!(get_accuracy_positions(&seeds, 0).is_empty());
+    }
+}
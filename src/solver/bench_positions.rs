@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+use crate::board::board::{Board, Die, Move, Player};
+use crate::solver::config::SolverConfig;
+use crate::solver::error::SolverError;
+use crate::solver::solver::{Evaluation, Solver};
+use crate::tree::tree::{Node, NodeType};
+
+pub fn opening() -> Node {
+    // Player 1's first move, with a 3 to place.
+    position("___\n___\n___", "___\n___\n___", NodeType::Move(Player::Player1, Die::Three))
+}
+
+pub fn elimination_midgame() -> Node {
+    // Player 2 can take out Player 1's pair of 6s, or let them be and play around the pair of 4s.
+    position("6_4\n6_4\n___", "_5_\n1__\n___", NodeType::Move(Player::Player2, Die::Six))
+}
+
+pub fn perf_test() -> Node {
+    // The position from the hybrid search's perf test, which brute forces anything within twelve moves of the end.
+    position("62_\n1_2\n62_", "356\n122\n62_", NodeType::Move(Player::Player2, Die::Six))
+}
+
+pub fn four_empty_endgame() -> Node {
+    position("361\n651\n_5_", "445\n1_3\n1_5", NodeType::Move(Player::Player1, Die::Two))
+}
+
+pub fn two_empty_endgame() -> Node {
+    // Player 2 can play for a draw or take a chance on winning.
+    position("112\n554\n5_6", "435\n623\n_4_", NodeType::Move(Player::Player2, Die::Six))
+}
+
+pub fn last_move_endgame() -> Node {
+    // Player 2 fills their board with their next move, so Player 1 has to put the 6 out of reach of it.
+    position("123\n45_\n6__", "111\n111\n12_", NodeType::Move(Player::Player1, Die::Six))
+}
+
+pub fn get_benchmark_positions() -> Vec<(&'static str, Node)> {
+    // Every benchmark position by name, from the start of the game to the end.
+    vec![
+        ("opening", opening()),
+        ("elimination-midgame", elimination_midgame()),
+        ("perf-test", perf_test()),
+        ("four-empty-endgame", four_empty_endgame()),
+        ("two-empty-endgame", two_empty_endgame()),
+        ("last-move-endgame", last_move_endgame()),
+    ]
+}
+
+fn position(player_1_board: &str, player_2_board: &str, node_type: NodeType) -> Node {
+    Node::new(
+        Board::from_string(player_1_board.to_string()).expect("Benchmark boards are valid."),
+        Board::from_string(player_2_board.to_string()).expect("Benchmark boards are valid."),
+        node_type,
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct BenchmarkResult {
+    name: String,
+    best_moves: Vec<Move>,
+    evaluation: Evaluation,
+    n_nodes: usize,
+    elapsed: Duration,
+}
+
+impl BenchmarkResult {
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_best_moves(&self) -> &Vec<Move> {
+        &self.best_moves
+    }
+
+    pub fn get_evaluation(&self) -> Evaluation {
+        self.evaluation
+    }
+
+    pub fn get_n_nodes(&self) -> usize {
+        self.n_nodes
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+pub fn run_benchmark(config: &SolverConfig) -> Result<Vec<BenchmarkResult>, SolverError> {
+    // Solves each benchmark position in the config's mode with a solver of its own, so none of them starts with
+    // another's transposition table.
+    let mut results = vec![];
+    for (name, position) in get_benchmark_positions() {
+        let mut solver = Solver::from_root_with_config(position, config.clone());
+        let start = Instant::now();
+        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(config.get_solver_mode())?;
+        results.push(
+            BenchmarkResult {
+                name: name.to_string(),
+                best_moves,
+                evaluation,
+                n_nodes: solver.get_search_stats().get_n_nodes(),
+                elapsed: start.elapsed(),
+            }
+        );
+    }
+    return Ok(results);
+}
+
+#[cfg(test)]
+mod test_bench_positions {
+    use std::collections::HashSet;
+
+    use crate::solver::config::SolverStrategy;
+
+    use super::*;
+
+    #[test]
+    fn test_benchmark_positions() {
+        let positions = get_benchmark_positions();
+        for (name, position) in positions.iter() {
+            let (player_1_board, player_2_board) = position.get_boards();
+            assert!(Node::new_validated(player_1_board, player_2_board, position.get_node_type()).is_ok(), "{} isn't a legal position.", name);
+            assert!(matches!(position.get_node_type(), NodeType::Move(_, _)));
+        }
+        let names = positions.iter().map(|(name, _)| *name).collect::<Vec<&str>>();
+        assert_eq!(names.iter().collect::<HashSet<&&str>>().len(), names.len());
+
+        let config = SolverConfig::builder().strategy(SolverStrategy::Heuristic).heuristic_depth(1).build().unwrap();
+        let results = run_benchmark(&config).unwrap();
+        assert_eq!(results.iter().map(|result| result.get_name()).collect::<Vec<&str>>(), names);
+        assert!(results.iter().all(|result| result.get_n_nodes() > 0 && !result.get_best_moves().is_empty()));
+    }
+}
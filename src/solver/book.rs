@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::board::board::Move;
+use crate::solver::config::SolverConfig;
+use crate::solver::error::SolverError;
+use crate::solver::solver::{Evaluation, EvaluationKind, Solver};
+use crate::tree::tree::{Node, NodeStatus};
+
+pub const DEFAULT_BOOK_PLIES: usize = 4;
+
+// Identifies the file format, followed by a version byte.
+const FILE_MAGIC: &[u8; 4] = b"KBOB";
+const FILE_VERSION: u8 = 1;
+// A u64 key, a byte for the kind of evaluation, an f32 evaluation and a byte of best columns.
+const ENTRY_BYTES: usize = 14;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BookEntry {
+    evaluation: Evaluation,
+    // Bit c is set if placing the die in column c of the canonical position is a best move.
+    best_columns: u8,
+}
+
+impl BookEntry {
+
+    pub fn get_evaluation(&self) -> Evaluation {
+        self.evaluation
+    }
+
+    pub fn get_best_moves(&self, node: &Node) -> Result<Vec<Move>, SolverError> {
+        // The best moves in node up to row symmetry, in the usual row-major order, as searching it would give.  The
+        // columns are put back in the node's order first.
+        let order = Self::get_canonical_node(node).get_canonical_column_order();
+        let moves = node.get_legal_moves_up_to_row_symmetry()?;
+        return Ok(
+            node.get_legal_moves()?.into_iter()
+                .filter(
+                    |m| moves.contains(m)
+                        && order.iter().enumerate().any(|(i, column)| *column == m.get_column() && self.best_columns & (1 << i) != 0)
+                ).collect()
+        );
+    }
+
+    fn get_canonical_node(node: &Node) -> Node {
+        // The dice sorted within columns, before the columns are ordered, so positions that only differ by row or
+        // column symmetry become the same position.
+        Node::new(node.get_player_1_board().with_rows_sorted(), node.get_player_2_board().with_rows_sorted(), node.get_node_type())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpeningBook {
+    max_plies: usize,
+    // Best moves for Move nodes by the key of their position up to row and column symmetry.
+    entries: HashMap<u64, BookEntry>,
+}
+
+impl OpeningBook {
+
+    pub fn new(max_plies: usize) -> Self {
+        OpeningBook { max_plies, entries: HashMap::new() }
+    }
+
+    pub fn build(config: &SolverConfig, max_plies: usize, mut progress: impl FnMut(usize, usize)) -> Result<Self, SolverError> {
+        // Searches every position in the first max_plies moves from the empty board with config, calling progress with
+        // how many are done out of how many there are after each one.  Each position is searched once up to
+        // symmetry, from its canonical form.
+        let mut book = Self::new(max_plies);
+        let positions = Self::get_opening_positions(max_plies);
+        for (i, position) in positions.iter().enumerate() {
+            // A fresh solver each time, so the result doesn't depend on what order the positions were searched in.
+            let (best_moves, evaluation) = Solver::from_root_with_config(position.clone(), config.clone())
+                .get_best_moves_and_evaluation(config.get_solver_mode())?;
+            let best_columns = best_moves.iter().fold(0, |columns, m| columns | (1 << m.get_column()));
+            book.entries.insert(position.position_key(), BookEntry { evaluation, best_columns });
+            progress(i + 1, positions.len());
+        }
+        return Ok(book);
+    }
+
+    fn get_opening_positions(max_plies: usize) -> Vec<Node> {
+        // The canonical form of every Move node fewer than max_plies moves in, a ply at a time so each is found at
+        // the fewest moves it takes to get there.
+        let mut seen = HashSet::new();
+        let mut positions = Vec::new();
+        let mut layer = vec![Node::empty()];
+        for _ in 0..max_plies {
+            let mut next_layer = Vec::new();
+            for roll_node in layer {
+                for (die, _) in roll_node.get_roll_probabilities() {
+                    let position = BookEntry::get_canonical_node(&roll_node.with_roll(die).expect("Guaranteed to be a roll node.")).canonicalize_columns();
+                    if position.is_terminal() || !seen.insert(position.position_key()) {
+                        continue;
+                    }
+                    for m in position.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.") {
+                        let child = position.with_move_made(m).expect("Won't error because we know the moves are legal.");
+                        if !child.is_terminal() {
+                            next_layer.push(child);
+                        }
+                    }
+                    positions.push(position);
+                }
+            }
+            layer = next_layer;
+        }
+        return positions;
+    }
+
+    pub fn probe(&self, node: &Node) -> Option<BookEntry> {
+        // None for anything the book doesn't have, including positions past its last ply, so they can be searched
+        // as usual.
+        if !matches!(node.status(), NodeStatus::Move(_, _)) {
+            return None;
+        }
+        return self.entries.get(&BookEntry::get_canonical_node(node).canonicalize_columns().position_key()).copied();
+    }
+
+    pub fn get_max_plies(&self) -> usize {
+        self.max_plies
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, self.to_bytes())
+            .map_err(|e| format!("Couldn't write the opening book to {}: {}", path.display(), e));
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Couldn't read the opening book from {}: {}", path.display(), e))?;
+        return Self::from_bytes(&bytes);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // The magic and version, the max plies and number of entries as little endian u64s, then the entries in key
+        // order so the same book always makes the same file.
+        let mut bytes = Vec::with_capacity(FILE_MAGIC.len() + 17 + self.entries.len() * ENTRY_BYTES);
+        bytes.extend_from_slice(FILE_MAGIC);
+        bytes.push(FILE_VERSION);
+        bytes.extend_from_slice(&(self.max_plies as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        let mut keys = self.entries.keys().copied().collect::<Vec<u64>>();
+        keys.sort();
+        for key in keys {
+            let entry = self.entries[&key];
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.push(if entry.evaluation.is_expected_result() { 1 } else { 0 });
+            bytes.extend_from_slice(&entry.evaluation.get_evaluation().to_le_bytes());
+            bytes.push(entry.best_columns);
+        }
+        return bytes;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let header_length = FILE_MAGIC.len() + 17;
+        if bytes.len() < header_length || &bytes[..FILE_MAGIC.len()] != FILE_MAGIC {
+            return Err("Not an opening book.".to_string());
+        }
+        if bytes[FILE_MAGIC.len()] != FILE_VERSION {
+            return Err(format!("Unsupported opening book version: {}", bytes[FILE_MAGIC.len()]));
+        }
+        let read_u64 = |start: usize| u64::from_le_bytes(bytes[start..start + 8].try_into().expect("Guaranteed to be 8 bytes."));
+        let max_plies = read_u64(FILE_MAGIC.len() + 1) as usize;
+        let n_entries = read_u64(FILE_MAGIC.len() + 9) as usize;
+        if n_entries.checked_mul(ENTRY_BYTES).and_then(|n| n.checked_add(header_length)) != Some(bytes.len()) {
+            return Err(format!("Opening book should have {} entries but is {} bytes long.", n_entries, bytes.len()));
+        }
+        let mut entries = HashMap::new();
+        for entry in bytes[header_length..].chunks_exact(ENTRY_BYTES) {
+            let key = u64::from_le_bytes(entry[..8].try_into().expect("Guaranteed to be 8 bytes."));
+            let value = f32::from_le_bytes(entry[9..13].try_into().expect("Guaranteed to be 4 bytes."));
+            let evaluation = match entry[8] {
+                0 => Evaluation::new(EvaluationKind::Heuristic, value)?,
+                1 => Evaluation::new(EvaluationKind::ExpectedResult, value)?,
+                kind => return Err(format!("Invalid kind of evaluation in the opening book: {}", kind)),
+            };
+            let best_columns = entry[13];
+            if best_columns == 0 || best_columns >= 1 << 3 || !value.is_finite() {
+                return Err(format!("Invalid opening book entry for position {}.", key));
+            }
+            entries.insert(key, BookEntry { evaluation, best_columns });
+        }
+        return Ok(OpeningBook { max_plies, entries });
+    }
+}
+
+#[cfg(test)]
+mod test_book {
+    use crate::board::board::Die;
+    use crate::solver::config::SolverStrategy;
+    use crate::tree::tree::NodeType;
+
+    use super::*;
+
+    fn get_config() -> SolverConfig {
+        SolverConfig::builder().strategy(SolverStrategy::Heuristic).heuristic_depth(2).build().unwrap()
+    }
+
+    fn get_positions_in(max_plies: usize) -> Vec<Node> {
+        // Every Move node in the first max_plies moves, without any symmetry reduction.
+        let mut positions = vec![];
+        let mut layer = vec![Node::empty()];
+        for _ in 0..max_plies {
+            let mut next_layer = vec![];
+            for roll_node in layer {
+                for (die, _) in roll_node.get_roll_probabilities() {
+                    let position = roll_node.with_roll(die).unwrap();
+                    for m in position.get_legal_moves().unwrap() {
+                        next_layer.push(position.with_move_made(m).unwrap());
+                    }
+                    positions.push(position);
+                }
+            }
+            layer = next_layer;
+        }
+        return positions;
+    }
+
+    #[test]
+    fn test_opening_book_matches_search() {
+        let mut n_calls = 0;
+        let book = OpeningBook::build(&get_config(), 2, |done, total| {
+            n_calls += 1;
+            assert_eq!(done, n_calls);
+            assert!(done <= total);
+        }).unwrap();
+        // Every column is the same on an empty board, so up to symmetry the first move only places a die, and there
+        // are six rolls of the empty board and six of each of the six boards after it.
+        assert_eq!(book.len(), 6 + 6 * 6);
+        assert_eq!(n_calls, 6 + 6 * 6);
+        assert_eq!(book.get_max_plies(), 2);
+
+        let positions = get_positions_in(2);
+        assert_eq!(positions.len(), 6 + 6 * 9 * 6);
+        for position in positions {
+            let entry = book.probe(&position).expect("The book has every position in its first two moves.");
+            let expected = Solver::from_root_with_config(position.clone(), get_config()).get_best_moves_and_evaluation(get_config().get_solver_mode()).unwrap();
+            assert_eq!((entry.get_best_moves(&position).unwrap(), entry.get_evaluation()), expected);
+        }
+
+        // Positions past the last ply, and Roll nodes, aren't in it.
+        let third_ply = Node::empty().apply_sequence(&[(Die::One, Move::new(0, 0)), (Die::Two, Move::new(0, 0))]).unwrap();
+        assert!(book.probe(&third_ply.with_roll(Die::Three).unwrap()).is_none());
+        assert!(book.probe(&Node::empty()).is_none());
+        assert!(book.probe(&Node::new(third_ply.get_player_1_board(), third_ply.get_player_2_board(), NodeType::Roll(third_ply.get_active_player()))).is_none());
+    }
+
+    #[test]
+    fn test_opening_book_saves_and_loads() {
+        let book = OpeningBook::build(&get_config(), 1, |_, _| {}).unwrap();
+        assert_eq!(OpeningBook::from_bytes(&book.to_bytes()).unwrap(), book);
+        let path = std::env::temp_dir().join(format!("knucklebones-opening-book-{}.bin", std::process::id()));
+        book.save(&path).unwrap();
+        assert_eq!(OpeningBook::load(&path).unwrap(), book);
+        fs::remove_file(&path).unwrap();
+
+        // Corrupted files are rejected rather than read as a different book.
+        assert!(OpeningBook::from_bytes(b"not a book").is_err());
+        let mut wrong_version = book.to_bytes();
+        wrong_version[FILE_MAGIC.len()] = FILE_VERSION + 1;
+        assert!(OpeningBook::from_bytes(&wrong_version).is_err());
+        let mut truncated = book.to_bytes();
+        truncated.pop();
+        assert!(OpeningBook::from_bytes(&truncated).is_err());
+        let mut bad_kind = book.to_bytes();
+        bad_kind[FILE_MAGIC.len() + 17 + 8] = 7;
+        assert!(OpeningBook::from_bytes(&bad_kind).is_err());
+        let mut no_best_moves = book.to_bytes();
+        *no_best_moves.last_mut().unwrap() = 0;
+        assert!(OpeningBook::from_bytes(&no_best_moves).is_err());
+        assert!(OpeningBook::load(&path).is_err());
+    }
+}
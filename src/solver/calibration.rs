@@ -0,0 +1,95 @@
+use crate::board::board::{Outcome, Player};
+use crate::solver::config::DEFAULT_EMPTY_SQUARE_FILL;
+use crate::solver::selfplay::{play_game, RollSource};
+use crate::solver::solver::{Evaluation, EvaluationKind};
+use crate::solver::sweep::get_sweep_config;
+
+// The range fit_win_probability_scale searches, as the steepness of the curve, 1 / scale.
+const MIN_STEEPNESS: f32 = 1e-3;
+const MAX_STEEPNESS: f32 = 10.;
+const FIT_ITERATIONS: usize = 100;
+
+pub fn get_self_play_samples(n_games: usize, depth: usize, empty_square_fill: f32, seed: u64) -> Result<Vec<(f32, f32)>, String> {
+    // Every heuristic evaluation difference_heuristic searched to depth makes over n_games games against itself, each
+    // with how the game turned out for Player 1: 1 for a win, 0.5 for a draw and 0 for a loss.  Certain wins and
+    // losses are left out, since there's nothing to estimate.
+    let config = get_sweep_config(empty_square_fill, depth)?;
+    let mut samples = vec![];
+    for game in 0..n_games {
+        let record = play_game(&config, &config, &mut RollSource::seeded(seed.wrapping_add(game as u64)))?;
+        let result = match record.get_outcome() {
+            Outcome::Victory(Player::Player1) => 1.,
+            Outcome::Victory(Player::Player2) => 0.,
+            _ => 0.5,
+        };
+        samples.extend(
+            record.get_moves().iter()
+                .map(|move_record| move_record.get_evaluation())
+                .filter(|evaluation| !evaluation.is_expected_result() && evaluation.get_known_winner().is_none())
+                .map(|evaluation| (evaluation.get_evaluation(), result))
+        );
+    }
+    return Ok(samples);
+}
+
+pub fn fit_win_probability_scale(samples: &[(f32, f32)]) -> Result<f32, String> {
+    // The scale for Evaluation::as_win_probability that best predicts the results, by maximum likelihood.  The log
+    // likelihood is concave in the steepness, so a ternary search finds its peak.
+    if samples.is_empty() {
+        return Err("Need at least one sample to fit the win probability scale.".to_string());
+    }
+    if samples.iter().any(|(evaluation, _)| evaluation.is_nan()) {
+        return Err("Can't fit the win probability scale to a NaN evaluation.".to_string());
+    }
+    let log_likelihood = |steepness: f32| samples.iter()
+        .map(
+            |(evaluation, result)| {
+                let probability = Evaluation::new(EvaluationKind::Heuristic, *evaluation).expect("Checked for NaN above.").as_win_probability(1. / steepness).clamp(1e-6, 1. - 1e-6);
+                result * probability.ln() + (1. - result) * (1. - probability).ln()
+            }
+        ).sum::<f32>();
+    let (mut low, mut high) = (MIN_STEEPNESS, MAX_STEEPNESS);
+    for _ in 0..FIT_ITERATIONS {
+        let lower_third = low + (high - low) / 3.;
+        let upper_third = high - (high - low) / 3.;
+        if log_likelihood(lower_third) < log_likelihood(upper_third) {
+            low = lower_third;
+        } else {
+            high = upper_third;
+        }
+    }
+    return Ok(2. / (low + high));
+}
+
+pub fn calibrate_win_probability_scale(n_games: usize, depth: usize, seed: u64) -> Result<f32, String> {
+    // How DEFAULT_WIN_PROBABILITY_SCALE was fit, with the default empty square fill.
+    return fit_win_probability_scale(&get_self_play_samples(n_games, depth, DEFAULT_EMPTY_SQUARE_FILL, seed)?);
+}
+
+#[cfg(test)]
+mod test_calibration {
+    use super::*;
+
+    #[test]
+    fn test_fit_win_probability_scale() {
+        // Samples drawn to match a known scale are fit back to it.
+        let scale = 8.;
+        let mut samples = vec![];
+        for evaluation in -40..=40 {
+            let probability = Evaluation::new(EvaluationKind::Heuristic, evaluation as f32).unwrap().as_win_probability(scale);
+            let n_wins = (probability * 100.).round() as usize;
+            samples.extend((0..100).map(|i| (evaluation as f32, if i < n_wins { 1. } else { 0. })));
+        }
+        assert!((fit_win_probability_scale(&samples).unwrap() - scale).abs() < 0.1);
+        assert!(fit_win_probability_scale(&[]).is_err());
+    }
+
+    #[test]
+    fn test_self_play_samples() {
+        let samples = get_self_play_samples(2, 1, 3.5, 3).unwrap();
+        assert!(!samples.is_empty());
+        assert!(samples.iter().all(|(evaluation, result)| evaluation.abs() < 1000. && [0., 0.5, 1.].contains(result)));
+        assert_eq!(get_self_play_samples(2, 1, 3.5, 3).unwrap(), samples);
+        assert!(calibrate_win_probability_scale(2, 1, 3).unwrap() > 0.);
+    }
+}
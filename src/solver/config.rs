@@ -0,0 +1,938 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::board::board::{DieDistribution, compare_totally};
+use crate::solver::solver::{Solver, SolverMode, SynergyWeights, ThreatParams};
+use crate::tree::tree::{ChanceExpansion, Objective};
+
+pub const DEFAULT_HEURISTIC_DEPTH: usize = 4;
+pub const DEFAULT_MAX_MOVES_LEFT_TO_BRUTE_FORCE: usize = 1;
+pub const DEFAULT_BRUTE_FORCE_DEPTH: usize = 6;
+pub const DEFAULT_NODE_BUDGET: usize = 100_000;
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(1);
+pub const DEFAULT_EMPTY_SQUARE_FILL: f32 = 3.5;
+pub const DEFAULT_COMPLETION_WEIGHT: f32 = 1.;
+pub const DEFAULT_EXPOSURE_WEIGHT: f32 = 0.5;
+pub const DEFAULT_THREAT_WEIGHT: f32 = 1.;
+pub const DEFAULT_PLAYOUTS_PER_MOVE: usize = 1000;
+pub const DEFAULT_MCTS_ITERATIONS: usize = 10_000;
+pub const DEFAULT_EXPLORATION_C: f32 = 1.4;
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+pub const DEFAULT_MAX_EXTENSIONS: usize = 2;
+// Fit ahead of time by calibrate_win_probability_scale(4000, 2, 0), which gives 28.3.
+pub const DEFAULT_WIN_PROBABILITY_SCALE: f32 = 28.3;
+// Chances of winning a move can throw away before it's an inaccuracy or a blunder.
+pub const DEFAULT_INACCURACY_THRESHOLD: f32 = 0.05;
+pub const DEFAULT_BLUNDER_THRESHOLD: f32 = 0.15;
+// How unlucky the rolls after a move are assumed to be under a pessimistic risk profile, unless it says otherwise.
+pub const DEFAULT_RISK_QUANTILE: f32 = 0.25;
+pub const DEFAULT_CACHE_MIN_DEPTH: usize = 2;
+// What brute force counts a draw as, between a loss at -1 and a win at 1.
+pub const DEFAULT_DRAW_WEIGHT: f32 = 0.;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SolverStrategy {
+    BruteForce,
+    Heuristic,
+    // Heuristic until few enough moves are left, then brute force.
+    Hybrid,
+    NodeBudget,
+    Timed,
+    // Hybrid, with the heuristic depth chosen to fit the node budget.
+    Adaptive,
+    // Heuristic to a depth that depends on how many empty squares are left, then brute force.
+    Scheduled,
+    // Random playouts after each move.
+    MonteCarlo,
+    Mcts,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SearchObjective {
+    // Brute force scores finished games as a win, draw or loss.
+    WinProbability,
+    // Brute force scores finished games by the final score difference, in the same points as the heuristics.
+    ExpectedMargin,
+}
+
+impl SearchObjective {
+
+    pub fn all() -> Vec<SearchObjective> {
+        vec![SearchObjective::WinProbability, SearchObjective::ExpectedMargin]
+    }
+
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        match self {
+            SearchObjective::WinProbability => "win-probability".to_string(),
+            SearchObjective::ExpectedMargin => "margin".to_string(),
+        }
+    }
+
+    pub fn from_string(s: &str) -> Result<Self, String> {
+        return Self::all().into_iter()
+            .find(|objective| objective.to_string() == s)
+            .ok_or(format!("Invalid objective, expected one of {}: {}", Self::all().iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", "), s));
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RiskProfile {
+    // The average over the rolls, as everywhere else in the search.
+    ExpectedValue,
+    // The value the rolls only have a quantile chance of falling below.
+    Pessimistic { quantile: f32 },
+    // The value after the worst roll.
+    WorstCase,
+}
+
+impl RiskProfile {
+
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        match self {
+            RiskProfile::ExpectedValue => "expected-value".to_string(),
+            RiskProfile::Pessimistic { quantile } => format!("pessimistic:{}", quantile),
+            RiskProfile::WorstCase => "worst-case".to_string(),
+        }
+    }
+
+    pub fn from_string(s: &str) -> Result<Self, String> {
+        // A pessimistic profile can be given its quantile after a colon, as in pessimistic:0.1.
+        match s.split_once(':') {
+            None if s == "expected-value" => Ok(RiskProfile::ExpectedValue),
+            None if s == "worst-case" => Ok(RiskProfile::WorstCase),
+            None if s == "pessimistic" => Ok(RiskProfile::Pessimistic { quantile: DEFAULT_RISK_QUANTILE }),
+            Some(("pessimistic", quantile)) => quantile.parse::<f32>()
+                .map(|quantile| RiskProfile::Pessimistic { quantile })
+                .map_err(|_| format!("Invalid quantile: {}", quantile)),
+            _ => Err(format!("Invalid risk profile, expected one of expected-value, pessimistic[:quantile], worst-case: {}", s)),
+        }
+    }
+
+    pub fn aggregate(&self, values: &[(f32, f32)]) -> f32 {
+        // Puts a value on the rolls after a move, given each roll's value for the player choosing the move and its
+        // probability.  Rolls that can't happen don't count towards the quantile or the worst case.
+        let mut possible_values = values.iter().filter(|(_, probability)| *probability > 0.).copied().collect::<Vec<(f32, f32)>>();
+        possible_values.sort_by(|(a, _), (b, _)| compare_totally(*a, *b));
+        let quantile = match self {
+            RiskProfile::ExpectedValue => return values.iter().map(|(value, probability)| value * probability).sum(),
+            RiskProfile::Pessimistic { quantile } => *quantile,
+            RiskProfile::WorstCase => 0.,
+        };
+        let total = possible_values.iter().map(|(_, probability)| probability).sum::<f32>();
+        let mut cumulative = 0.;
+        for (value, probability) in possible_values.iter() {
+            cumulative += probability;
+            if cumulative >= quantile * total - f32::EPSILON {
+                return *value;
+            }
+        }
+        return possible_values.last().map(|(value, _)| *value).unwrap_or(0.);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScheduledDepth {
+    Heuristic(usize),
+    BruteForce,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveOrdering {
+    // Every move in row-major order.
+    None,
+    // Moves best first by the config's heuristic on the positions they lead to.
+    Static,
+    // The root's moves best first by a shallow search, and the rest by the heuristic like Static.
+    ShallowSearch,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepthSchedule {
+    // Each (min empty squares, depth) tier covers the positions with at least that many empty squares that the tiers
+    // before it don't, most empty squares first.  Depths only go up as the board fills.
+    tiers: Vec<(usize, usize)>,
+    // Positions with no more empty squares than this are brute forced.  The last tier starts just above it, or at 0
+    // without it, so every position is covered.
+    max_empty_squares_to_brute_force: Option<usize>,
+}
+
+impl Default for DepthSchedule {
+    fn default() -> Self {
+        DepthSchedule::new(vec![(14, 3), (8, 4), (5, 6)], Some(4)).expect("The default schedule is valid.")
+    }
+}
+
+impl DepthSchedule {
+
+    pub fn new(tiers: Vec<(usize, usize)>, max_empty_squares_to_brute_force: Option<usize>) -> Result<Self, String> {
+        let schedule = DepthSchedule { tiers, max_empty_squares_to_brute_force };
+        let lowest_min_empty_squares = match schedule.tiers.last() {
+            Some((min_empty_squares, _)) => *min_empty_squares,
+            None => return Err("A depth schedule needs at least one depth.".to_string()),
+        };
+        if schedule.tiers.iter().any(|(_, depth)| *depth == 0) {
+            return Err(format!("Depths in a schedule must be at least 1: {}", schedule.to_string()));
+        }
+        if schedule.tiers.windows(2).any(|pair| pair[0].0 <= pair[1].0 || pair[0].1 > pair[1].1) {
+            return Err(format!("A schedule's empty squares must go down and its depths can't: {}", schedule.to_string()));
+        }
+        let expected_lowest = max_empty_squares_to_brute_force.map_or(0, |max_empty_squares| max_empty_squares + 1);
+        if lowest_min_empty_squares != expected_lowest {
+            return Err(format!("A schedule's last depth should start at {} empty squares to leave no gaps: {}", expected_lowest, schedule.to_string()));
+        }
+        return Ok(schedule);
+    }
+
+    pub fn get_tiers(&self) -> &Vec<(usize, usize)> {
+        &self.tiers
+    }
+
+    pub fn get_max_empty_squares_to_brute_force(&self) -> Option<usize> {
+        self.max_empty_squares_to_brute_force
+    }
+
+    pub fn get_depth(&self, n_empty_squares: usize) -> ScheduledDepth {
+        return self.tiers.iter()
+            .find(|(min_empty_squares, _)| n_empty_squares >= *min_empty_squares)
+            .map_or(ScheduledDepth::BruteForce, |(_, depth)| ScheduledDepth::Heuristic(*depth));
+    }
+
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        let mut tiers = self.tiers.iter().map(|(min_empty_squares, depth)| format!("{}:{}", min_empty_squares, depth)).collect::<Vec<String>>();
+        if let Some(max_empty_squares) = self.max_empty_squares_to_brute_force {
+            tiers.push(format!("bf:{}", max_empty_squares));
+        }
+        return tiers.join(",");
+    }
+
+    pub fn from_string(s: &str) -> Result<Self, String> {
+        // Comma separated min empty squares:depth tiers, most empty squares first, optionally ending with
+        // bf:max empty squares for where brute force takes over, as in 14:3,8:4,5:6,bf:4.
+        let invalid = || format!("Invalid depth schedule, expected tiers like 14:3,8:4,5:6,bf:4: {}", s);
+        let mut tiers = vec![];
+        let mut max_empty_squares_to_brute_force = None;
+        for (index, tier) in s.split(',').enumerate() {
+            let (left, right) = tier.trim().split_once(':').ok_or_else(invalid)?;
+            let right = right.parse::<usize>().map_err(|_| invalid())?;
+            if left == "bf" && index == s.split(',').count() - 1 {
+                max_empty_squares_to_brute_force = Some(right);
+            } else {
+                tiers.push((left.parse::<usize>().map_err(|_| invalid())?, right));
+            }
+        }
+        return DepthSchedule::new(tiers, max_empty_squares_to_brute_force);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    // Shared between clones, so whoever holds one can stop a search running with another.
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        // Searches stay cancelled until this is called, including ones started after the cancellation.
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HeuristicFunction {
+    Difference,
+    ColumnSynergy,
+    EliminationThreat,
+}
+
+impl HeuristicFunction {
+
+    pub fn all() -> Vec<HeuristicFunction> {
+        vec![HeuristicFunction::Difference, HeuristicFunction::ColumnSynergy, HeuristicFunction::EliminationThreat]
+    }
+
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        match self {
+            HeuristicFunction::Difference => "difference".to_string(),
+            HeuristicFunction::ColumnSynergy => "column-synergy".to_string(),
+            HeuristicFunction::EliminationThreat => "elimination-threat".to_string(),
+        }
+    }
+
+    pub fn from_string(s: &str) -> Result<Self, String> {
+        return Self::all().into_iter()
+            .find(|heuristic| heuristic.to_string() == s)
+            .ok_or(format!("Invalid heuristic, expected one of {}: {}", Self::all().iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", "), s));
+    }
+
+    pub fn to_objective(&self) -> Objective {
+        // With the default weights.
+        match self {
+            HeuristicFunction::Difference => Objective::ignoring_roll_with_scores(|x, scores| Solver::difference_heuristic_with_scores(x, scores, DEFAULT_EMPTY_SQUARE_FILL)),
+            HeuristicFunction::ColumnSynergy => {
+                let weights = SynergyWeights::default();
+                Objective::ignoring_roll(move |x| Solver::column_synergy_heuristic(x, &weights))
+            },
+            HeuristicFunction::EliminationThreat => {
+                let params = ThreatParams::default();
+                Objective::ignoring_roll(move |x| Solver::elimination_threat_heuristic(x, &params))
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SolverConfig {
+    strategy: SolverStrategy,
+    heuristic_depth: usize,
+    max_moves_left_to_brute_force: usize,
+    // Eliminations can reopen squares, so a game can always go on longer than the board suggests.  Brute force stops
+    // this many moves ahead, and its evaluation is tagged as truncated if any line hadn't finished by then.
+    brute_force_depth: usize,
+    objective: Objective,
+    // Moves whose evaluations are this close to the best are all counted as best, on top of the ones that only differ
+    // by rounding, which always are.
+    tie_break_epsilon: f32,
+    // Replaces the root's die distribution if set.
+    die_distribution: Option<DieDistribution>,
+    // 0 is one per core.  Only used when built with the parallel feature.
+    n_threads: usize,
+    // The most positions the transposition table holds, or no limit.
+    transposition_table_size: Option<usize>,
+    node_budget: usize,
+    time_limit: Duration,
+    playouts_per_move: usize,
+    mcts_iterations: usize,
+    exploration_c: f32,
+    // Monte Carlo and MCTS are reproducible if set.
+    seed: Option<u64>,
+    // The least time between progress reports.
+    progress_interval: Duration,
+    // Searches go a move further where they'd stop with an elimination of at least this many points hanging, at most
+    // max_extensions times down any line.  No extensions if not set.
+    extension_threshold: Option<u16>,
+    max_extensions: usize,
+    // Evaluations that move by more than this between the last two depths are low confidence, as are ones whose best
+    // move changes.  The command line only checks how confident the solver is if set.
+    confidence_threshold: Option<f32>,
+    // How much of the chance of winning a move has to lose, against the best move, to count as an inaccuracy or a
+    // blunder.
+    inaccuracy_threshold: f32,
+    blunder_threshold: f32,
+    // Brute force breaks ties between moves with the same expected result by the expected final score difference.
+    break_ties_by_margin: bool,
+    // What brute force maximizes.
+    search_objective: SearchObjective,
+    // What a draw is worth to brute force maximizing the chance of winning: 1 counts it as a win, and -1 as a loss.
+    draw_weight: f32,
+    // Which rolls searches and built trees average over at each chance node.
+    chance_expansion: ChanceExpansion,
+    // Searches that go move by move check this as they go, and stop once it's cancelled.
+    cancellation_token: Option<CancellationToken>,
+    // How the rolls after each of the root's moves are weighed up when picking one.  Deeper chance nodes are always
+    // averaged.
+    risk_profile: RiskProfile,
+    // Transposition table entries searched less deep than this aren't saved to a cache file, since they're the most
+    // numerous and the quickest to search again.
+    cache_min_depth: usize,
+    depth_schedule: DepthSchedule,
+    // The order searches try moves in, which only changes how soon they can stop searching the moves that can't be
+    // best.
+    move_ordering: MoveOrdering,
+    // Where searches stop at a Roll node under an objective that doesn't look at the roll, they score one roll for
+    // all of them.
+    merge_rolls: bool,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            strategy: SolverStrategy::Hybrid,
+            heuristic_depth: DEFAULT_HEURISTIC_DEPTH,
+            max_moves_left_to_brute_force: DEFAULT_MAX_MOVES_LEFT_TO_BRUTE_FORCE,
+            brute_force_depth: DEFAULT_BRUTE_FORCE_DEPTH,
+            objective: HeuristicFunction::Difference.to_objective(),
+            tie_break_epsilon: 0.,
+            die_distribution: None,
+            n_threads: 1,
+            transposition_table_size: None,
+            node_budget: DEFAULT_NODE_BUDGET,
+            time_limit: DEFAULT_TIME_LIMIT,
+            playouts_per_move: DEFAULT_PLAYOUTS_PER_MOVE,
+            mcts_iterations: DEFAULT_MCTS_ITERATIONS,
+            exploration_c: DEFAULT_EXPLORATION_C,
+            seed: None,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            extension_threshold: None,
+            max_extensions: DEFAULT_MAX_EXTENSIONS,
+            confidence_threshold: None,
+            inaccuracy_threshold: DEFAULT_INACCURACY_THRESHOLD,
+            blunder_threshold: DEFAULT_BLUNDER_THRESHOLD,
+            break_ties_by_margin: false,
+            search_objective: SearchObjective::WinProbability,
+            draw_weight: DEFAULT_DRAW_WEIGHT,
+            chance_expansion: ChanceExpansion::Full,
+            cancellation_token: None,
+            risk_profile: RiskProfile::ExpectedValue,
+            cache_min_depth: DEFAULT_CACHE_MIN_DEPTH,
+            depth_schedule: DepthSchedule::default(),
+            move_ordering: MoveOrdering::None,
+            merge_rolls: false,
+        }
+    }
+}
+
+impl SolverConfig {
+
+    pub fn builder() -> SolverConfigBuilder {
+        SolverConfigBuilder { config: SolverConfig::default() }
+    }
+
+    pub fn get_solver_mode(&self) -> SolverMode {
+        // The mode the solver's searches are run in.
+        let objective = self.objective.clone();
+        match self.strategy {
+            SolverStrategy::BruteForce => SolverMode::BruteForce,
+            SolverStrategy::Heuristic => SolverMode::Heuristic((self.heuristic_depth, objective)),
+            SolverStrategy::Hybrid => SolverMode::Hybrid(self.max_moves_left_to_brute_force, (self.heuristic_depth, objective)),
+            SolverStrategy::NodeBudget => SolverMode::NodeBudget((self.node_budget, objective)),
+            SolverStrategy::Timed => SolverMode::Timed((self.time_limit, objective)),
+            SolverStrategy::Adaptive => SolverMode::Adaptive(self.max_moves_left_to_brute_force, (self.node_budget, objective)),
+            SolverStrategy::Scheduled => SolverMode::Scheduled((self.depth_schedule.clone(), objective)),
+            SolverStrategy::MonteCarlo => SolverMode::MonteCarlo((self.playouts_per_move, self.seed)),
+            SolverStrategy::Mcts => SolverMode::Mcts((self.mcts_iterations, self.exploration_c, self.seed)),
+        }
+    }
+
+    pub fn get_strategy(&self) -> SolverStrategy {
+        self.strategy
+    }
+
+    pub fn get_heuristic_depth(&self) -> usize {
+        self.heuristic_depth
+    }
+
+    pub fn get_max_moves_left_to_brute_force(&self) -> usize {
+        self.max_moves_left_to_brute_force
+    }
+
+    pub fn get_brute_force_depth(&self) -> usize {
+        self.brute_force_depth
+    }
+
+    pub fn get_objective(&self) -> &Objective {
+        &self.objective
+    }
+
+    pub fn get_tie_break_epsilon(&self) -> f32 {
+        self.tie_break_epsilon
+    }
+
+    pub fn get_die_distribution(&self) -> Option<DieDistribution> {
+        self.die_distribution
+    }
+
+    pub fn get_n_threads(&self) -> usize {
+        self.n_threads
+    }
+
+    pub fn get_transposition_table_size(&self) -> Option<usize> {
+        self.transposition_table_size
+    }
+
+    pub fn get_node_budget(&self) -> usize {
+        self.node_budget
+    }
+
+    pub fn get_time_limit(&self) -> Duration {
+        self.time_limit
+    }
+
+    pub fn get_playouts_per_move(&self) -> usize {
+        self.playouts_per_move
+    }
+
+    pub fn get_mcts_iterations(&self) -> usize {
+        self.mcts_iterations
+    }
+
+    pub fn get_exploration_c(&self) -> f32 {
+        self.exploration_c
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn get_progress_interval(&self) -> Duration {
+        self.progress_interval
+    }
+
+    pub fn get_extension_threshold(&self) -> Option<u16> {
+        self.extension_threshold
+    }
+
+    pub fn get_max_extensions(&self) -> usize {
+        self.max_extensions
+    }
+
+    pub fn get_confidence_threshold(&self) -> Option<f32> {
+        self.confidence_threshold
+    }
+
+    pub fn get_inaccuracy_threshold(&self) -> f32 {
+        self.inaccuracy_threshold
+    }
+
+    pub fn get_blunder_threshold(&self) -> f32 {
+        self.blunder_threshold
+    }
+
+    pub fn get_break_ties_by_margin(&self) -> bool {
+        self.break_ties_by_margin
+    }
+
+    pub fn get_search_objective(&self) -> SearchObjective {
+        self.search_objective
+    }
+
+    pub fn get_draw_weight(&self) -> f32 {
+        self.draw_weight
+    }
+
+    pub fn get_chance_expansion(&self) -> ChanceExpansion {
+        self.chance_expansion
+    }
+
+    pub fn get_cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+
+    pub fn get_risk_profile(&self) -> RiskProfile {
+        self.risk_profile
+    }
+
+    pub fn get_cache_min_depth(&self) -> usize {
+        self.cache_min_depth
+    }
+
+    pub fn get_depth_schedule(&self) -> &DepthSchedule {
+        &self.depth_schedule
+    }
+
+    pub fn get_move_ordering(&self) -> MoveOrdering {
+        self.move_ordering
+    }
+
+    pub fn get_merge_rolls(&self) -> bool {
+        self.merge_rolls
+    }
+
+    pub fn with_cancellation_token(&self, cancellation_token: CancellationToken) -> Self {
+        // The same settings, checking cancellation_token instead of any token they had.
+        SolverConfig { cancellation_token: Some(cancellation_token), ..self.clone() }
+    }
+}
+
+pub struct SolverConfigBuilder {
+    config: SolverConfig,
+}
+
+impl SolverConfigBuilder {
+
+    pub fn strategy(mut self, strategy: SolverStrategy) -> Self {
+        self.config.strategy = strategy;
+        self
+    }
+
+    pub fn heuristic_depth(mut self, heuristic_depth: usize) -> Self {
+        self.config.heuristic_depth = heuristic_depth;
+        self
+    }
+
+    pub fn max_moves_left_to_brute_force(mut self, max_moves_left_to_brute_force: usize) -> Self {
+        self.config.max_moves_left_to_brute_force = max_moves_left_to_brute_force;
+        self
+    }
+
+    pub fn brute_force_depth(mut self, brute_force_depth: usize) -> Self {
+        self.config.brute_force_depth = brute_force_depth;
+        self
+    }
+
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.config.objective = objective;
+        self
+    }
+
+    pub fn tie_break_epsilon(mut self, tie_break_epsilon: f32) -> Self {
+        self.config.tie_break_epsilon = tie_break_epsilon;
+        self
+    }
+
+    pub fn die_distribution(mut self, die_distribution: DieDistribution) -> Self {
+        self.config.die_distribution = Some(die_distribution);
+        self
+    }
+
+    pub fn n_threads(mut self, n_threads: usize) -> Self {
+        self.config.n_threads = n_threads;
+        self
+    }
+
+    pub fn transposition_table_size(mut self, transposition_table_size: usize) -> Self {
+        self.config.transposition_table_size = Some(transposition_table_size);
+        self
+    }
+
+    pub fn node_budget(mut self, node_budget: usize) -> Self {
+        self.config.node_budget = node_budget;
+        self
+    }
+
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.config.time_limit = time_limit;
+        self
+    }
+
+    pub fn playouts_per_move(mut self, playouts_per_move: usize) -> Self {
+        self.config.playouts_per_move = playouts_per_move;
+        self
+    }
+
+    pub fn mcts_iterations(mut self, mcts_iterations: usize) -> Self {
+        self.config.mcts_iterations = mcts_iterations;
+        self
+    }
+
+    pub fn exploration_c(mut self, exploration_c: f32) -> Self {
+        self.config.exploration_c = exploration_c;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    pub fn progress_interval(mut self, progress_interval: Duration) -> Self {
+        self.config.progress_interval = progress_interval;
+        self
+    }
+
+    pub fn extension_threshold(mut self, extension_threshold: u16) -> Self {
+        self.config.extension_threshold = Some(extension_threshold);
+        self
+    }
+
+    pub fn max_extensions(mut self, max_extensions: usize) -> Self {
+        self.config.max_extensions = max_extensions;
+        self
+    }
+
+    pub fn confidence_threshold(mut self, confidence_threshold: f32) -> Self {
+        self.config.confidence_threshold = Some(confidence_threshold);
+        self
+    }
+
+    pub fn inaccuracy_threshold(mut self, inaccuracy_threshold: f32) -> Self {
+        self.config.inaccuracy_threshold = inaccuracy_threshold;
+        self
+    }
+
+    pub fn blunder_threshold(mut self, blunder_threshold: f32) -> Self {
+        self.config.blunder_threshold = blunder_threshold;
+        self
+    }
+
+    pub fn break_ties_by_margin(mut self, break_ties_by_margin: bool) -> Self {
+        self.config.break_ties_by_margin = break_ties_by_margin;
+        self
+    }
+
+    pub fn search_objective(mut self, search_objective: SearchObjective) -> Self {
+        self.config.search_objective = search_objective;
+        self
+    }
+
+    pub fn draw_weight(mut self, draw_weight: f32) -> Self {
+        self.config.draw_weight = draw_weight;
+        self
+    }
+
+    pub fn chance_expansion(mut self, chance_expansion: ChanceExpansion) -> Self {
+        self.config.chance_expansion = chance_expansion;
+        self
+    }
+
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.config.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    pub fn risk_profile(mut self, risk_profile: RiskProfile) -> Self {
+        self.config.risk_profile = risk_profile;
+        self
+    }
+
+    pub fn cache_min_depth(mut self, cache_min_depth: usize) -> Self {
+        self.config.cache_min_depth = cache_min_depth;
+        self
+    }
+
+    pub fn depth_schedule(mut self, depth_schedule: DepthSchedule) -> Self {
+        self.config.depth_schedule = depth_schedule;
+        self
+    }
+
+    pub fn move_ordering(mut self, move_ordering: MoveOrdering) -> Self {
+        self.config.move_ordering = move_ordering;
+        self
+    }
+
+    pub fn merge_rolls(mut self, merge_rolls: bool) -> Self {
+        self.config.merge_rolls = merge_rolls;
+        self
+    }
+
+    pub fn build(self) -> Result<SolverConfig, String> {
+        let config = self.config;
+        if config.heuristic_depth == 0 {
+            return Err("Heuristic depth must be at least 1.".to_string());
+        }
+        if config.brute_force_depth == 0 {
+            return Err("Brute force depth must be at least 1.".to_string());
+        }
+        if config.node_budget == 0 {
+            return Err("Node budget must be at least 1.".to_string());
+        }
+        if config.playouts_per_move == 0 {
+            return Err("Playouts per move must be at least 1.".to_string());
+        }
+        if config.mcts_iterations == 0 {
+            return Err("MCTS iterations must be at least 1.".to_string());
+        }
+        if config.exploration_c.is_nan() || config.exploration_c < 0. {
+            return Err(format!("Exploration constant must be a non-negative number: {}", config.exploration_c));
+        }
+        if config.tie_break_epsilon.is_nan() || config.tie_break_epsilon < 0. {
+            return Err(format!("Tie break epsilon must be a non-negative number: {}", config.tie_break_epsilon));
+        }
+        if let Some(confidence_threshold) = config.confidence_threshold {
+            if confidence_threshold.is_nan() || confidence_threshold < 0. {
+                return Err(format!("Confidence threshold must be a non-negative number: {}", confidence_threshold));
+            }
+        }
+        if !(0. ..=1.).contains(&config.inaccuracy_threshold) || !(config.inaccuracy_threshold..=1.).contains(&config.blunder_threshold) {
+            return Err(
+                format!(
+                    "Inaccuracy and blunder thresholds must be chances of winning, with the blunder threshold the bigger: {} and {}",
+                    config.inaccuracy_threshold,
+                    config.blunder_threshold,
+                )
+            );
+        }
+        if !(-1. ..=1.).contains(&config.draw_weight) {
+            return Err(format!("Draw weight must be between -1 and 1: {}", config.draw_weight));
+        }
+        if let ChanceExpansion::Sampled { n_rolls, .. } = config.chance_expansion {
+            if !(1..=6).contains(&n_rolls) {
+                return Err(format!("Sampled rolls must be between 1 and 6: {}", n_rolls));
+            }
+        }
+        if let RiskProfile::Pessimistic { quantile } = config.risk_profile {
+            if !(0. ..=1.).contains(&quantile) {
+                return Err(format!("Risk quantile must be between 0 and 1: {}", quantile));
+            }
+        }
+        return Ok(config);
+    }
+}
+
+#[cfg(test)]
+mod test_solver_config {
+    use crate::board::board::{Board, Die, Player};
+    use crate::tree::tree::{Node, NodeType};
+
+    use super::*;
+
+    #[test]
+    fn test_solver_config_defaults() {
+        let config = SolverConfig::default();
+        assert_eq!(config.get_strategy(), SolverStrategy::Hybrid);
+        assert_eq!(config.get_heuristic_depth(), DEFAULT_HEURISTIC_DEPTH);
+        assert_eq!(config.get_max_moves_left_to_brute_force(), DEFAULT_MAX_MOVES_LEFT_TO_BRUTE_FORCE);
+        assert_eq!(config.get_brute_force_depth(), DEFAULT_BRUTE_FORCE_DEPTH);
+        assert_eq!(config.get_tie_break_epsilon(), 0.);
+        assert_eq!(config.get_die_distribution(), None);
+        assert_eq!(config.get_n_threads(), 1);
+        assert_eq!(config.get_transposition_table_size(), None);
+        assert_eq!(config.get_progress_interval(), DEFAULT_PROGRESS_INTERVAL);
+        assert_eq!((config.get_extension_threshold(), config.get_max_extensions()), (None, DEFAULT_MAX_EXTENSIONS));
+        assert_eq!(config.get_confidence_threshold(), None);
+        assert_eq!((config.get_inaccuracy_threshold(), config.get_blunder_threshold()), (DEFAULT_INACCURACY_THRESHOLD, DEFAULT_BLUNDER_THRESHOLD));
+        assert!(!config.get_break_ties_by_margin());
+        assert_eq!(config.get_search_objective(), SearchObjective::WinProbability);
+        assert_eq!(config.get_chance_expansion(), ChanceExpansion::Full);
+        assert!(config.get_cancellation_token().is_none());
+        assert_eq!(config.get_move_ordering(), MoveOrdering::None);
+        assert!(!config.get_merge_rolls());
+        assert!(SolverConfig::builder().build().is_ok());
+
+        // The default is what the command line has always searched with.
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let mode = SolverMode::Hybrid(1, (4, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        assert_eq!(
+            Solver::from_root_with_config(root.clone(), config.clone()).get_best_moves_and_evaluation(config.get_solver_mode()).unwrap(),
+            Solver::from_root(root).get_best_moves_and_evaluation(mode).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_solver_config_builder() {
+        let config = SolverConfig::builder()
+            .strategy(SolverStrategy::Timed)
+            .time_limit(Duration::from_millis(10))
+            .n_threads(0)
+            .transposition_table_size(1000)
+            .build()
+            .unwrap();
+        assert!(matches!(config.get_solver_mode(), SolverMode::Timed((limit, _)) if limit == Duration::from_millis(10)));
+        assert_eq!(config.get_n_threads(), 0);
+        assert_eq!(config.get_transposition_table_size(), Some(1000));
+        let config = SolverConfig::builder().strategy(SolverStrategy::Heuristic).heuristic_depth(2).build().unwrap();
+        assert!(matches!(config.get_solver_mode(), SolverMode::Heuristic((2, _))));
+        let config = SolverConfig::builder().strategy(SolverStrategy::Adaptive).node_budget(5000).build().unwrap();
+        assert!(matches!(config.get_solver_mode(), SolverMode::Adaptive(DEFAULT_MAX_MOVES_LEFT_TO_BRUTE_FORCE, (5000, _))));
+        let config = SolverConfig::builder().strategy(SolverStrategy::MonteCarlo).playouts_per_move(10).seed(7).build().unwrap();
+        assert!(matches!(config.get_solver_mode(), SolverMode::MonteCarlo((10, Some(7)))));
+        let config = SolverConfig::builder().strategy(SolverStrategy::Mcts).mcts_iterations(10).exploration_c(2.).build().unwrap();
+        assert!(matches!(config.get_solver_mode(), SolverMode::Mcts((10, c, None)) if c == 2.));
+
+        assert!(SolverConfig::builder().heuristic_depth(0).build().is_err());
+        assert!(SolverConfig::builder().brute_force_depth(0).build().is_err());
+        assert_eq!(SolverConfig::builder().brute_force_depth(8).build().unwrap().get_brute_force_depth(), 8);
+        assert!(SolverConfig::builder().node_budget(0).build().is_err());
+        assert!(SolverConfig::builder().playouts_per_move(0).build().is_err());
+        assert!(SolverConfig::builder().mcts_iterations(0).build().is_err());
+        assert!(SolverConfig::builder().exploration_c(-1.).build().is_err());
+        assert!(SolverConfig::builder().tie_break_epsilon(-0.1).build().is_err());
+        assert!(SolverConfig::builder().tie_break_epsilon(f32::NAN).build().is_err());
+        assert!(SolverConfig::builder().confidence_threshold(-1.).build().is_err());
+        assert_eq!(SolverConfig::builder().confidence_threshold(2.5).build().unwrap().get_confidence_threshold(), Some(2.5));
+        assert!(SolverConfig::builder().inaccuracy_threshold(0.2).build().is_err());
+        assert!(SolverConfig::builder().blunder_threshold(1.5).build().is_err());
+        assert!(SolverConfig::builder().inaccuracy_threshold(f32::NAN).build().is_err());
+        assert!(SolverConfig::builder().inaccuracy_threshold(0.1).blunder_threshold(0.3).build().is_ok());
+        assert!(SolverConfig::builder().chance_expansion(ChanceExpansion::Sampled { n_rolls: 0, full_plies: 1, seed: 0 }).build().is_err());
+        assert!(SolverConfig::builder().chance_expansion(ChanceExpansion::Sampled { n_rolls: 7, full_plies: 1, seed: 0 }).build().is_err());
+        assert!(SolverConfig::builder().chance_expansion(ChanceExpansion::Sampled { n_rolls: 2, full_plies: 1, seed: 0 }).build().is_ok());
+
+        // The config shares the token rather than copying it.
+        let token = CancellationToken::new();
+        let config = SolverConfig::builder().cancellation_token(token.clone()).build().unwrap();
+        assert!(!config.get_cancellation_token().unwrap().is_cancelled());
+        token.cancel();
+        assert!(config.clone().get_cancellation_token().unwrap().is_cancelled());
+        token.reset();
+        assert!(!config.get_cancellation_token().unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn test_heuristic_function() {
+        for heuristic in HeuristicFunction::all() {
+            assert_eq!(HeuristicFunction::from_string(&heuristic.to_string()), Ok(heuristic));
+        }
+        assert!(HeuristicFunction::from_string("sum").is_err());
+
+        let player_1_board = Board::from_string("6__\n6__\n___".to_string()).unwrap();
+        let root = Node::new(player_1_board, Board::empty(), NodeType::Roll(Player::Player2));
+        assert_eq!(HeuristicFunction::Difference.to_objective().evaluate(&root), Solver::difference_heuristic(&root, DEFAULT_EMPTY_SQUARE_FILL));
+        assert_eq!(
+            HeuristicFunction::ColumnSynergy.to_objective().evaluate(&root),
+            Solver::column_synergy_heuristic(&root, &SynergyWeights::default()),
+        );
+        assert_eq!(
+            HeuristicFunction::EliminationThreat.to_objective().evaluate(&root),
+            Solver::elimination_threat_heuristic(&root, &ThreatParams::default()),
+        );
+    }
+
+    #[test]
+    fn test_search_objective() {
+        for objective in SearchObjective::all() {
+            assert_eq!(SearchObjective::from_string(&objective.to_string()), Ok(objective));
+        }
+        assert!(SearchObjective::from_string("score").is_err());
+    }
+
+    #[test]
+    fn test_risk_profile() {
+        for risk_profile in [RiskProfile::ExpectedValue, RiskProfile::Pessimistic { quantile: 0.1 }, RiskProfile::WorstCase] {
+            assert_eq!(RiskProfile::from_string(&risk_profile.to_string()), Ok(risk_profile));
+        }
+        assert_eq!(RiskProfile::from_string("pessimistic"), Ok(RiskProfile::Pessimistic { quantile: DEFAULT_RISK_QUANTILE }));
+        assert!(RiskProfile::from_string("pessimistic:lots").is_err());
+        assert!(RiskProfile::from_string("optimistic").is_err());
+        assert!(SolverConfig::builder().risk_profile(RiskProfile::Pessimistic { quantile: 1.5 }).build().is_err());
+        assert_eq!(SolverConfig::default().get_risk_profile(), RiskProfile::ExpectedValue);
+
+        // Rolls worth 1 through 6, each as likely, and an impossible one worth even less.
+        let values = (1..=6).map(|value| (value as f32, 1. / 6.)).chain([(-10., 0.)]).collect::<Vec<(f32, f32)>>();
+        assert!((RiskProfile::ExpectedValue.aggregate(&values) - 3.5).abs() < 1e-4);
+        assert_eq!(RiskProfile::WorstCase.aggregate(&values), 1.);
+        assert_eq!(RiskProfile::Pessimistic { quantile: 0.5 }.aggregate(&values), 3.);
+        assert_eq!(RiskProfile::Pessimistic { quantile: 0.2 }.aggregate(&values), 2.);
+        assert_eq!(RiskProfile::Pessimistic { quantile: 1. }.aggregate(&values), 6.);
+    }
+
+    #[test]
+    fn test_depth_schedule() {
+        let schedule = DepthSchedule::from_string("14:3,8:4,5:6,bf:4").unwrap();
+        assert_eq!(schedule, DepthSchedule::default());
+        assert_eq!(schedule.to_string(), "14:3,8:4,5:6,bf:4");
+        // Each tier starts at its min empty squares, and brute force takes over below the last.
+        for (n_empty_squares, expected) in [
+            (18, ScheduledDepth::Heuristic(3)),
+            (14, ScheduledDepth::Heuristic(3)),
+            (13, ScheduledDepth::Heuristic(4)),
+            (8, ScheduledDepth::Heuristic(4)),
+            (7, ScheduledDepth::Heuristic(6)),
+            (5, ScheduledDepth::Heuristic(6)),
+            (4, ScheduledDepth::BruteForce),
+            (0, ScheduledDepth::BruteForce),
+        ] {
+            assert_eq!(schedule.get_depth(n_empty_squares), expected);
+        }
+        let without_brute_force = DepthSchedule::from_string("10:2,0:5").unwrap();
+        assert_eq!(without_brute_force.get_max_empty_squares_to_brute_force(), None);
+        assert_eq!(without_brute_force.get_depth(0), ScheduledDepth::Heuristic(5));
+
+        // Thresholds that don't go down, depths that do, gaps and junk are all rejected.
+        for invalid in ["8:4,14:3,bf:7", "14:4,8:3,bf:7", "14:3,8:4,bf:4", "14:3,8:0,bf:7", "bf:4", "bf:4,5:3", "14-3", ""] {
+            assert!(DepthSchedule::from_string(invalid).is_err(), "{}", invalid);
+        }
+
+        let config = SolverConfig::builder().strategy(SolverStrategy::Scheduled).depth_schedule(without_brute_force.clone()).build().unwrap();
+        assert!(matches!(config.get_solver_mode(), SolverMode::Scheduled((schedule, _)) if schedule == without_brute_force));
+    }
+}
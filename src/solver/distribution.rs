@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::board::board::Player;
+
+// The widest bar to_histogram draws, for the likeliest score difference.
+pub const DEFAULT_HISTOGRAM_WIDTH: usize = 40;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutcomeDistribution {
+    // How likely each final score difference is, from Player 1's perspective like evaluations.  Only differences
+    // that can happen are kept.
+    probabilities: BTreeMap<i16, f32>,
+}
+
+impl Display for OutcomeDistribution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Win: {:.1}%\nDraw: {:.1}%\nLoss: {:.1}%\nExpected Margin: {:+.2}\nVariance: {:.2}",
+            self.get_win_probability() * 100.,
+            self.get_draw_probability() * 100.,
+            self.get_loss_probability() * 100.,
+            self.get_expected_margin(),
+            self.get_variance(),
+        )
+    }
+}
+
+impl OutcomeDistribution {
+
+    pub fn certain(score_difference: i16) -> Self {
+        OutcomeDistribution { probabilities: BTreeMap::from([(score_difference, 1.)]) }
+    }
+
+    pub fn mix(weighted: &[(OutcomeDistribution, f32)]) -> Self {
+        // Each distribution weighted by its share of the total weight.
+        let total = weighted.iter().map(|(_, weight)| weight).sum::<f32>();
+        let mut probabilities = BTreeMap::new();
+        for (distribution, weight) in weighted {
+            for (score_difference, probability) in &distribution.probabilities {
+                *probabilities.entry(*score_difference).or_insert(0.) += probability * weight / total;
+            }
+        }
+        return OutcomeDistribution { probabilities };
+    }
+
+    pub fn get_probabilities(&self) -> &BTreeMap<i16, f32> {
+        &self.probabilities
+    }
+
+    pub fn get_probability(&self, score_difference: i16) -> f32 {
+        self.probabilities.get(&score_difference).copied().unwrap_or(0.)
+    }
+
+    pub fn get_win_probability(&self) -> f32 {
+        self.probabilities.range(1..).map(|(_, probability)| probability).sum()
+    }
+
+    pub fn get_draw_probability(&self) -> f32 {
+        self.get_probability(0)
+    }
+
+    pub fn get_loss_probability(&self) -> f32 {
+        self.probabilities.range(..0).map(|(_, probability)| probability).sum()
+    }
+
+    pub fn get_expected_margin(&self) -> f32 {
+        self.probabilities.iter().map(|(score_difference, probability)| *score_difference as f32 * probability).sum()
+    }
+
+    pub fn get_variance(&self) -> f32 {
+        let mean = self.get_expected_margin();
+        self.probabilities.iter().map(|(score_difference, probability)| (*score_difference as f32 - mean).powi(2) * probability).sum()
+    }
+
+    pub fn from_perspective(&self, perspective: Player) -> Self {
+        // Player 2's score differences are Player 1's the other way round.
+        match perspective {
+            Player::Player1 => self.clone(),
+            Player::Player2 => OutcomeDistribution {
+                probabilities: self.probabilities.iter().map(|(score_difference, probability)| (-score_difference, *probability)).collect(),
+            },
+        }
+    }
+
+    pub fn to_histogram(&self, width: usize) -> String {
+        // A line per score difference, lowest first, with a bar as long as its probability against the likeliest.
+        let max_probability = self.probabilities.values().copied().fold(0., f32::max);
+        return self.probabilities.iter()
+            .map(
+                |(score_difference, probability)| format!(
+                    "{:>+4} {:>5.1}% {}",
+                    score_difference,
+                    probability * 100.,
+                    "#".repeat((probability / max_probability * width as f32).round() as usize),
+                )
+            ).collect::<Vec<String>>()
+            .join("\n");
+    }
+}
+
+#[cfg(test)]
+mod test_distribution {
+    use crate::board::board::are_tied;
+
+    use super::*;
+
+    #[test]
+    fn test_outcome_distribution() {
+        let distribution = OutcomeDistribution::mix(
+            &[(OutcomeDistribution::certain(-2), 1.), (OutcomeDistribution::certain(0), 1.), (OutcomeDistribution::certain(4), 2.)]
+        );
+        assert!(are_tied(distribution.get_probability(4), 0.5));
+        assert!(are_tied(distribution.get_probability(1), 0.));
+        assert!(are_tied(distribution.get_win_probability(), 0.5));
+        assert!(are_tied(distribution.get_draw_probability(), 0.25));
+        assert!(are_tied(distribution.get_loss_probability(), 0.25));
+        assert!(are_tied(distribution.get_expected_margin(), 1.5));
+        // (3.5^2 + 1.5^2) / 4 + 2.5^2 / 2
+        assert!(are_tied(distribution.get_variance(), 6.75));
+
+        let flipped = distribution.from_perspective(Player::Player2);
+        assert!(are_tied(flipped.get_loss_probability(), 0.5));
+        assert!(are_tied(flipped.get_expected_margin(), -1.5));
+        assert_eq!(distribution.from_perspective(Player::Player1), distribution);
+
+        let histogram = distribution.to_histogram(4);
+        assert_eq!(histogram, "  -2  25.0% ##\n  +0  25.0% ##\n  +4  50.0% ####");
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt::{Display, Formatter};
+
+use crate::board::board::Move;
+use crate::tree::tree::NodeType;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Move,
+    Roll,
+}
+
+impl From<NodeType> for NodeKind {
+    fn from(node_type: NodeType) -> Self {
+        match node_type {
+            NodeType::Move(_, _) => NodeKind::Move,
+            NodeType::Roll(_) => NodeKind::Roll,
+        }
+    }
+}
+
+impl Display for NodeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeKind::Move => write!(f, "move node"),
+            NodeKind::Roll => write!(f, "roll node"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolverError {
+    WrongNodeType { expected: NodeKind, found: NodeKind },
+    GameAlreadyOver,
+    DepthZero,
+    IllegalMove(Move),
+    // A mode that can't do what was asked, or whose settings don't make sense.
+    InvalidMode(String),
+    // A search with a deadline that didn't finish anything in time.
+    Timeout,
+    // A search that was stopped before it finished.
+    Interrupted,
+    // An objective that scored a position as NaN, which can't be compared with anything.
+    NanEvaluation,
+    // Something that should never happen, with whatever's known about it.
+    Internal(String),
+}
+
+impl Display for SolverError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverError::WrongNodeType { expected, found } => write!(f, "Expected a {}, but found a {}.", expected, found),
+            SolverError::GameAlreadyOver => write!(f, "The game is already over."),
+            SolverError::DepthZero => write!(f, "The search depth must be at least 1."),
+            SolverError::IllegalMove(m) => write!(f, "Illegal move: {}", m.to_string()),
+            SolverError::InvalidMode(message) => write!(f, "Invalid solver mode: {}", message),
+            SolverError::Timeout => write!(f, "Ran out of time before the search finished."),
+            SolverError::Interrupted => write!(f, "The search was interrupted."),
+            SolverError::NanEvaluation => write!(f, "The objective scored a position as NaN."),
+            SolverError::Internal(message) => write!(f, "Internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
+impl From<String> for SolverError {
+    // The remaining String errors are from APIs that haven't been converted, and only come up when something's wrong.
+    fn from(message: String) -> Self {
+        SolverError::Internal(message)
+    }
+}
+
+impl From<SolverError> for String {
+    fn from(error: SolverError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test_error {
+    use super::*;
+
+    #[test]
+    fn test_solver_error_display() {
+        assert_eq!(
+            SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }.to_string(),
+            "Expected a move node, but found a roll node.",
+        );
+        assert_eq!(String::from(SolverError::GameAlreadyOver), "The game is already over.");
+        assert_eq!(SolverError::from("Oops".to_string()), SolverError::Internal("Oops".to_string()));
+    }
+}
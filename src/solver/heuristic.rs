@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::solver::solver::{Solver, SynergyWeights, ThreatParams};
+use crate::tree::tree::{Node, Objective};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeuristicTerm {
+    ScoreDifference,
+    // Per point of empty square fill.
+    EmptySquareFill,
+    ColumnSynergy,
+    EliminationThreat,
+    ExpectedElimination,
+}
+
+impl HeuristicTerm {
+
+    pub fn all() -> Vec<HeuristicTerm> {
+        vec![
+            HeuristicTerm::ScoreDifference,
+            HeuristicTerm::EmptySquareFill,
+            HeuristicTerm::ColumnSynergy,
+            HeuristicTerm::EliminationThreat,
+            HeuristicTerm::ExpectedElimination,
+        ]
+    }
+
+    pub fn evaluate(&self, node: &Node) -> f32 {
+        // From Player 1's perspective, for a game in progress.
+        match self {
+            HeuristicTerm::ScoreDifference => node.get_score_difference() as f32,
+            HeuristicTerm::EmptySquareFill => Solver::empty_square_difference(node),
+            HeuristicTerm::ColumnSynergy => Solver::column_synergy(node, &SynergyWeights::default()),
+            HeuristicTerm::EliminationThreat => Solver::elimination_threat(node, &ThreatParams { weight: 1., ..ThreatParams::default() }),
+            HeuristicTerm::ExpectedElimination => Solver::expected_elimination_heuristic(node),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HeuristicWeights(BTreeMap<HeuristicTerm, f32>);
+
+impl HeuristicWeights {
+
+    pub fn new() -> Self {
+        HeuristicWeights(BTreeMap::new())
+    }
+
+    pub fn difference(empty_square_fill: f32) -> Self {
+        // The weights that make difference_heuristic.
+        Self::new().with(HeuristicTerm::ScoreDifference, 1.).with(HeuristicTerm::EmptySquareFill, empty_square_fill)
+    }
+
+    pub fn with(mut self, term: HeuristicTerm, weight: f32) -> Self {
+        self.0.insert(term, weight);
+        self
+    }
+
+    pub fn get(&self, term: HeuristicTerm) -> f32 {
+        self.0.get(&term).copied().unwrap_or(0.)
+    }
+
+    pub fn to_combined_heuristic(&self) -> CombinedHeuristic {
+        return self.0.iter()
+            .fold(CombinedHeuristic::builder(), |builder, (term, weight)| builder.named_term(*weight, *term))
+            .build();
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Guaranteed to serialize, since every key is a string.")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        // e.g. {"score_difference": 1.0, "empty_square_fill": 3.5}
+        let weights: Self = serde_json::from_str(json).map_err(|e| format!("Invalid heuristic weights: {}", e))?;
+        if let Some((term, weight)) = weights.0.iter().find(|(_, weight)| !weight.is_finite()) {
+            return Err(format!("Invalid heuristic weight for {:?}: {}", term, weight));
+        }
+        return Ok(weights);
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, self.to_json())
+            .map_err(|e| format!("Couldn't write the heuristic weights to {}: {}", path.display(), e));
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read the heuristic weights from {}: {}", path.display(), e))?;
+        return Self::from_json(&json);
+    }
+}
+
+type HeuristicFn = Box<dyn Fn(&Node) -> f32 + Send + Sync>;
+
+pub struct CombinedHeuristic {
+    terms: Vec<(f32, HeuristicFn)>,
+    // Which term each one is, if it's one of the named ones, so the weights can be saved.
+    names: Vec<Option<HeuristicTerm>>,
+}
+
+impl CombinedHeuristic {
+
+    pub fn builder() -> CombinedHeuristicBuilder {
+        CombinedHeuristicBuilder { heuristic: CombinedHeuristic { terms: vec![], names: vec![] } }
+    }
+
+    pub fn evaluate(&self, node: &Node) -> f32 {
+        // The weighted sum of the terms.
+        return self.terms.iter().map(|(weight, term)| weight * term(node)).sum();
+    }
+
+    pub fn get_weights(&self) -> Option<HeuristicWeights> {
+        // None if any of the terms is a closure, which can't be saved.
+        let mut weights = HeuristicWeights::new();
+        for ((weight, _), name) in self.terms.iter().zip(self.names.iter()) {
+            let term = (*name)?;
+            let total = weights.get(term) + weight;
+            weights = weights.with(term, total);
+        }
+        return Some(weights);
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn to_objective(self) -> Objective {
+        Objective::new(move |x| self.evaluate(x))
+    }
+}
+
+pub struct CombinedHeuristicBuilder {
+    heuristic: CombinedHeuristic,
+}
+
+impl CombinedHeuristicBuilder {
+
+    pub fn term(mut self, weight: f32, term: impl Fn(&Node) -> f32 + Send + Sync + 'static) -> Self {
+        self.heuristic.terms.push((weight, Box::new(term)));
+        self.heuristic.names.push(None);
+        self
+    }
+
+    pub fn named_term(mut self, weight: f32, term: HeuristicTerm) -> Self {
+        self.heuristic.terms.push((weight, Box::new(move |x| term.evaluate(x))));
+        self.heuristic.names.push(Some(term));
+        self
+    }
+
+    pub fn build(self) -> CombinedHeuristic {
+        self.heuristic
+    }
+}
+
+#[cfg(test)]
+mod test_heuristic {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+
+    use crate::board::board::{Board, Die, Player};
+    use crate::tree::tree::{NodeStatus, NodeType};
+
+    use super::*;
+
+    fn get_positions() -> Vec<Node> {
+        // Every position along a few random games, including the finished ones.
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut positions = vec![];
+        for _ in 0..5 {
+            let mut node = Node::new(Board::empty(), Board::empty(), NodeType::Roll(Player::Player1));
+            loop {
+                positions.push(node.clone());
+                node = match node.status() {
+                    NodeStatus::Terminal(_) => break,
+                    NodeStatus::Roll(_) => node.with_roll(Die::random_from(&mut rng)).unwrap(),
+                    NodeStatus::Move(_, _) => {
+                        let m = *node.get_legal_moves().unwrap().choose(&mut rng).unwrap();
+                        node.with_move_made(m).unwrap()
+                    },
+                };
+            }
+        }
+        return positions;
+    }
+
+    #[test]
+    fn test_combined_heuristic_matches_base_heuristics() {
+        let difference = HeuristicWeights::difference(3.5)
+            .with(HeuristicTerm::ColumnSynergy, 0.)
+            .with(HeuristicTerm::EliminationThreat, 0.)
+            .to_combined_heuristic();
+        let synergy = HeuristicWeights::difference(3.5).with(HeuristicTerm::ColumnSynergy, 1.).to_combined_heuristic();
+        let threat = HeuristicWeights::difference(3.5).with(HeuristicTerm::EliminationThreat, 1.).to_combined_heuristic();
+        let expected_elimination = HeuristicWeights::difference(3.5).with(HeuristicTerm::ExpectedElimination, 0.5).to_combined_heuristic();
+        let closures = CombinedHeuristic::builder()
+            .term(1., |x| Solver::column_synergy_heuristic(x, &SynergyWeights::default()))
+            .term(0., |x| Solver::elimination_threat(x, &ThreatParams::default()))
+            .build();
+        assert_eq!(closures.len(), 2);
+        assert_eq!(closures.get_weights(), None);
+        for position in get_positions() {
+            assert!((difference.evaluate(&position) - Solver::difference_heuristic(&position, 3.5)).abs() < 1e-4);
+            assert!((synergy.evaluate(&position) - Solver::column_synergy_heuristic(&position, &SynergyWeights::default())).abs() < 1e-4);
+            assert!((threat.evaluate(&position) - Solver::elimination_threat_heuristic(&position, &ThreatParams::default())).abs() < 1e-4);
+            let expected = Solver::difference_heuristic(&position, 3.5) + 0.5 * Solver::expected_elimination_heuristic(&position);
+            assert!((expected_elimination.evaluate(&position) - expected).abs() < 1e-4);
+            assert_eq!(closures.evaluate(&position), Solver::column_synergy_heuristic(&position, &SynergyWeights::default()));
+        }
+
+        let root = Node::new(Board::from_string("35_\n___\n___".to_string()).unwrap(), Board::empty(), NodeType::Roll(Player::Player2));
+        let objective = HeuristicWeights::difference(3.5).to_combined_heuristic().to_objective();
+        assert_eq!(objective.evaluate(&root), Solver::difference_heuristic(&root, 3.5));
+        assert!(CombinedHeuristic::builder().build().is_empty());
+    }
+
+    #[test]
+    fn test_heuristic_weights_round_trip() {
+        let weights = HeuristicWeights::difference(3.25)
+            .with(HeuristicTerm::ColumnSynergy, 0.5)
+            .with(HeuristicTerm::EliminationThreat, -0.75)
+            .with(HeuristicTerm::ExpectedElimination, 0.25);
+        assert_eq!(HeuristicWeights::from_json(&weights.to_json()), Ok(weights.clone()));
+        assert_eq!(weights.to_combined_heuristic().get_weights(), Some(weights.clone()));
+        assert_eq!(
+            HeuristicWeights::from_json(r#"{"score_difference": 1.0, "empty_square_fill": 3.25, "column_synergy": 0.5, "elimination_threat": -0.75, "expected_elimination": 0.25}"#),
+            Ok(weights.clone()),
+        );
+        let path = std::env::temp_dir().join(format!("knucklebones-heuristic-weights-{}.json", std::process::id()));
+        weights.save(&path).unwrap();
+        assert_eq!(HeuristicWeights::load(&path), Ok(weights));
+        fs::remove_file(&path).unwrap();
+
+        assert!(HeuristicWeights::from_json(r#"{"sum": 1.0}"#).is_err());
+        assert!(HeuristicWeights::from_json("[1.0]").is_err());
+        assert!(HeuristicWeights::load(&path).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::board::board::{Die, Move, Outcome};
+use crate::solver::config::DEFAULT_DRAW_WEIGHT;
+use crate::solver::error::{NodeKind, SolverError};
+use crate::solver::solver::Evaluation;
+use crate::tree::tree::{Node, NodeStatus};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MctsEdge {
+    Move(Move),
+    Roll(Die),
+}
+
+struct MctsNode {
+    node: Node,
+    children: Vec<(MctsEdge, usize)>,
+    // Moves up to row symmetry that haven't been expanded yet.  Always empty on roll nodes.
+    untried_moves: Vec<Move>,
+    visits: usize,
+    // The sum of the results backed up through here, from Player 1's perspective.
+    total_value: f32,
+}
+
+impl MctsNode {
+
+    fn new(node: Node) -> Self {
+        let untried_moves = match node.status() {
+            NodeStatus::Move(_, _) => node.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node."),
+            _ => vec![],
+        };
+        MctsNode { node, children: vec![], untried_moves, visits: 0, total_value: 0. }
+    }
+
+    fn get_mean_value(&self) -> f32 {
+        if self.visits == 0 {
+            return 0.;
+        }
+        return self.total_value / self.visits as f32;
+    }
+}
+
+pub struct MctsTree {
+    // The root is the first node, and children point into the arena by index.
+    nodes: Vec<MctsNode>,
+    exploration_c: f32,
+    rng: StdRng,
+}
+
+impl MctsTree {
+
+    pub fn new(root: &Node, exploration_c: f32, seed: Option<u64>) -> Result<Self, SolverError> {
+        match root.status() {
+            NodeStatus::Terminal(_) => return Err(SolverError::GameAlreadyOver),
+            NodeStatus::Roll(_) => return Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }),
+            NodeStatus::Move(_, _) => {},
+        }
+        if exploration_c.is_nan() || exploration_c < 0. {
+            return Err(SolverError::InvalidMode(format!("Exploration constant must be a non-negative number: {}", exploration_c)));
+        }
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        return Ok(MctsTree { nodes: vec![MctsNode::new(root.clone_without_children())], exploration_c, rng });
+    }
+
+    pub fn run(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            self.iterate();
+        }
+    }
+
+    fn iterate(&mut self) {
+        // Selects down the tree by UCB at move nodes and a uniform roll at roll nodes, expands the first node it
+        // hasn't seen, plays the game out from there and backs the result up the path.
+        let mut path = vec![0];
+        let mut current = 0;
+        let value = loop {
+            match self.nodes[current].node.status() {
+                NodeStatus::Terminal(outcome) => break Self::to_value(outcome),
+                NodeStatus::Move(_, _) => {
+                    if self.nodes[current].untried_moves.is_empty() {
+                        current = self.select_child(current);
+                        path.push(current);
+                        continue;
+                    }
+                    let i = self.rng.gen_range(0..self.nodes[current].untried_moves.len());
+                    let m = self.nodes[current].untried_moves.swap_remove(i);
+                    let child = self.nodes[current].node.with_move_made(m).expect("Won't error because we know the moves are legal.");
+                    current = self.add_child(current, MctsEdge::Move(m), child);
+                    path.push(current);
+                    break self.play_out(current);
+                },
+                NodeStatus::Roll(_) => {
+                    let die = Die::random_from(&mut self.rng);
+                    let existing = self.nodes[current].children.iter()
+                        .find(|(edge, _)| *edge == MctsEdge::Roll(die))
+                        .map(|(_, child)| *child);
+                    match existing {
+                        Some(child) => {
+                            current = child;
+                            path.push(current);
+                        },
+                        None => {
+                            let child = self.nodes[current].node.with_roll(die).expect("Guaranteed to be a roll node.");
+                            current = self.add_child(current, MctsEdge::Roll(die), child);
+                            path.push(current);
+                            break self.play_out(current);
+                        },
+                    }
+                },
+            }
+        };
+        for i in path {
+            self.nodes[i].visits += 1;
+            self.nodes[i].total_value += value;
+        }
+    }
+
+    fn select_child(&self, parent: usize) -> usize {
+        // UCB1 from the perspective of the player to move.  Every child has been visited once by the time the
+        // parent has no untried moves left.
+        let player = self.nodes[parent].node.get_active_player();
+        let log_parent_visits = (self.nodes[parent].visits as f32).ln();
+        let ucb = |child: usize| {
+            let child = &self.nodes[child];
+            let mean_value = player.get_sign() * child.get_mean_value();
+            mean_value + self.exploration_c * (log_parent_visits / child.visits as f32).sqrt()
+        };
+        return self.nodes[parent].children.iter()
+            .map(|(_, child)| *child)
+            .fold(None, |best: Option<(usize, f32)>, child| {
+                let value = ucb(child);
+                match best {
+                    Some((_, best_value)) if best_value >= value => best,
+                    _ => Some((child, value)),
+                }
+            })
+            .expect("Guaranteed to have expanded a child of a node with no untried moves.")
+            .0;
+    }
+
+    fn add_child(&mut self, parent: usize, edge: MctsEdge, node: Node) -> usize {
+        let child = self.nodes.len();
+        self.nodes.push(MctsNode::new(node));
+        self.nodes[parent].children.push((edge, child));
+        return child;
+    }
+
+    fn play_out(&mut self, from: usize) -> f32 {
+        let outcome = self.nodes[from].node.random_playout(&mut self.rng);
+        return Self::to_value(outcome);
+    }
+
+    fn to_value(outcome: Outcome) -> f32 {
+        return Evaluation::from_outcome(outcome, DEFAULT_DRAW_WEIGHT).expect("Guaranteed to be a finished game.").get_evaluation();
+    }
+
+    pub fn get_n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn get_root_value(&self) -> f32 {
+        // The mean result of every iteration, from Player 1's perspective.
+        self.nodes[0].get_mean_value()
+    }
+
+    pub fn get_root_visit_counts(&self) -> Vec<(Move, usize)> {
+        // Moves up to row symmetry in the order they were expanded.
+        self.get_root_children().map(|(m, child)| (m, child.visits)).collect()
+    }
+
+    pub fn get_root_move_values(&self) -> Vec<(Move, f32)> {
+        self.get_root_children().map(|(m, child)| (m, child.get_mean_value())).collect()
+    }
+
+    pub fn get_most_visited_moves(&self) -> Vec<Move> {
+        let visit_counts = self.get_root_visit_counts();
+        let most_visits = visit_counts.iter().map(|(_, visits)| *visits).max().unwrap_or(0);
+        return visit_counts.into_iter()
+            .filter(|(_, visits)| *visits == most_visits)
+            .map(|(m, _)| m)
+            .collect();
+    }
+
+    pub fn has_tried_every_root_move(&self) -> bool {
+        self.nodes[0].untried_moves.is_empty()
+    }
+
+    fn get_root_children(&self) -> impl Iterator<Item=(Move, &MctsNode)> {
+        self.nodes[0].children.iter().map(
+            |(edge, child)| match edge {
+                MctsEdge::Move(m) => (*m, &self.nodes[*child]),
+                MctsEdge::Roll(_) => panic!("The root is a move node."),
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_mcts {
+    use crate::board::board::{Board, Player};
+    use crate::tree::tree::NodeType;
+
+    use super::*;
+
+    #[test]
+    fn test_mcts_tree() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let mut tree = MctsTree::new(&root, 1.4, Some(3)).unwrap();
+        tree.run(2);
+        assert!(!tree.has_tried_every_root_move());
+        tree.run(98);
+        assert!(tree.has_tried_every_root_move());
+        assert_eq!(tree.get_root_visit_counts().iter().map(|(_, visits)| visits).sum::<usize>(), 100);
+        assert!(tree.get_root_value().abs() <= 1.);
+        assert!(tree.get_n_nodes() <= 101);
+
+        assert!(MctsTree::new(&root, -1., None).is_err());
+        let roll = Node::new(Board::empty(), Board::empty(), NodeType::Roll(Player::Player1));
+        assert!(MctsTree::new(&roll, 1.4, None).is_err());
+    }
+}
@@ -1 +1,26 @@
-pub mod solver;
\ No newline at end of file
+// The code here spells its returns out, and the solver module shares its directory's name.
+#[allow(clippy::module_inception, clippy::needless_return)]
+pub mod solver;
+#[allow(clippy::needless_return)]
+pub mod accuracy;
+#[allow(clippy::needless_return)]
+pub mod bench_positions;
+#[allow(clippy::needless_return)]
+pub mod book;
+#[allow(clippy::needless_return)]
+pub mod calibration;
+#[allow(clippy::needless_return)]
+pub mod config;
+#[allow(clippy::needless_return)]
+pub mod distribution;
+pub mod error;
+#[allow(clippy::needless_return)]
+pub mod heuristic;
+#[allow(clippy::needless_return)]
+pub mod mcts;
+#[allow(clippy::needless_return)]
+pub mod selfplay;
+#[allow(clippy::needless_return)]
+pub mod sweep;
+#[allow(clippy::needless_return)]
+pub mod tablebase;
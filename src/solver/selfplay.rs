@@ -0,0 +1,207 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::board::board::{Die, Outcome, Player};
+use crate::solver::config::SolverConfig;
+use crate::solver::error::SolverError;
+use crate::solver::solver::{Evaluation, Solver};
+use crate::tree::history::PlacedMove;
+use crate::tree::tree::{Node, NodeStatus};
+
+pub struct RollSource {
+    rng: StdRng,
+}
+
+impl RollSource {
+
+    pub fn seeded(seed: u64) -> Self {
+        // The same rolls every time for the same seed.
+        RollSource { rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn from_entropy() -> Self {
+        RollSource { rng: StdRng::from_entropy() }
+    }
+
+    pub fn roll(&mut self) -> Die {
+        Die::random_from(&mut self.rng)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MoveRecord {
+    placed_move: PlacedMove,
+    // What the mover's search made of the position, from Player 1's perspective.
+    evaluation: Evaluation,
+}
+
+impl MoveRecord {
+
+    pub fn get_placed_move(&self) -> PlacedMove {
+        self.placed_move
+    }
+
+    pub fn get_evaluation(&self) -> Evaluation {
+        self.evaluation
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    moves: Vec<MoveRecord>,
+    outcome: Outcome,
+    scores: (u16, u16),
+}
+
+impl GameRecord {
+
+    pub fn get_moves(&self) -> &Vec<MoveRecord> {
+        &self.moves
+    }
+
+    pub fn get_outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn get_scores(&self) -> (u16, u16) {
+        self.scores
+    }
+
+    pub fn get_n_moves(&self) -> usize {
+        self.moves.len()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MatchResult {
+    // Counted for the first config, whichever side it played.
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+
+impl MatchResult {
+
+    pub fn get_wins(&self) -> usize {
+        self.wins
+    }
+
+    pub fn get_draws(&self) -> usize {
+        self.draws
+    }
+
+    pub fn get_losses(&self) -> usize {
+        self.losses
+    }
+
+    pub fn get_n_games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn get_score(&self) -> f32 {
+        // The first config's share of the points, counting a draw as half a win.
+        if self.get_n_games() == 0 {
+            return 0.5;
+        }
+        return (self.wins as f32 + 0.5 * self.draws as f32) / self.get_n_games() as f32;
+    }
+}
+
+pub fn play_game(player_1_config: &SolverConfig, player_2_config: &SolverConfig, roll_source: &mut RollSource) -> Result<GameRecord, SolverError> {
+    // Each side searches with its own config and keeps its own solver for the whole game, so its transposition table
+    // carries over from move to move.  Of equally good moves, the first is played, so the game only depends on the
+    // rolls.
+    let mut node = Node::empty();
+    let mut solvers = (
+        Solver::from_root_with_config(Node::empty(), player_1_config.clone()),
+        Solver::from_root_with_config(Node::empty(), player_2_config.clone()),
+    );
+    let mut moves = vec![];
+    loop {
+        node = match node.status() {
+            NodeStatus::Terminal(outcome) => return Ok(GameRecord { moves, outcome, scores: node.get_scores() }),
+            NodeStatus::Roll(_) => {
+                let die = roll_source.roll();
+                solvers.0.advance_by_roll(die).expect("Guaranteed to be a roll node.");
+                solvers.1.advance_by_roll(die).expect("Guaranteed to be a roll node.");
+                node.with_roll(die).expect("Guaranteed to be a roll node.")
+            },
+            NodeStatus::Move(player, die) => {
+                let solver = match player {
+                    Player::Player1 => &mut solvers.0,
+                    Player::Player2 => &mut solvers.1,
+                };
+                let solver_mode = solver.get_config().get_solver_mode();
+                let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(solver_mode)?;
+                let m = *best_moves.first().expect("A game that isn't over has a best move.");
+                moves.push(MoveRecord { placed_move: PlacedMove::new(player, die, m), evaluation });
+                solvers.0.advance_by_move(m).expect("Won't error because we know the moves are legal.");
+                solvers.1.advance_by_move(m).expect("Won't error because we know the moves are legal.");
+                node.with_move_made(m).expect("Won't error because we know the moves are legal.")
+            },
+        };
+    }
+}
+
+pub fn play_match(n_games: usize, first_config: &SolverConfig, second_config: &SolverConfig, seed: u64) -> Result<MatchResult, SolverError> {
+    // Swaps who goes first each game.  Each two games in a row use the same rolls, so the luck of the dice mostly
+    // cancels out, and the same seed always plays the same games.
+    let mut result = MatchResult { wins: 0, draws: 0, losses: 0 };
+    for game in 0..n_games {
+        let mut roll_source = RollSource::seeded(seed.wrapping_add((game / 2) as u64));
+        let (first_config_player, record) = if game % 2 == 0 {
+            (Player::Player1, play_game(first_config, second_config, &mut roll_source)?)
+        } else {
+            (Player::Player2, play_game(second_config, first_config, &mut roll_source)?)
+        };
+        match record.get_outcome() {
+            Outcome::Victory(winner) if winner == first_config_player => result.wins += 1,
+            Outcome::Victory(_) => result.losses += 1,
+            _ => result.draws += 1,
+        }
+    }
+    return Ok(result);
+}
+
+#[cfg(test)]
+mod test_selfplay {
+    use crate::solver::config::SolverStrategy;
+
+    use super::*;
+
+    fn get_config(depth: usize) -> SolverConfig {
+        SolverConfig::builder().strategy(SolverStrategy::Heuristic).heuristic_depth(depth).build().unwrap()
+    }
+
+    #[test]
+    fn test_play_game() {
+        let record = play_game(&get_config(1), &get_config(2), &mut RollSource::seeded(3)).unwrap();
+        assert_eq!(play_game(&get_config(1), &get_config(2), &mut RollSource::seeded(3)).unwrap(), record);
+
+        // The moves replay to the recorded result, alternating between the players.
+        let mut node = Node::empty();
+        for (i, move_record) in record.get_moves().iter().enumerate() {
+            let placed_move = move_record.get_placed_move();
+            assert_eq!(placed_move.get_player(), if i % 2 == 0 { Player::Player1 } else { Player::Player2 });
+            node = node.with_roll(placed_move.get_die()).unwrap().with_move_made(placed_move.get_move()).unwrap();
+        }
+        assert!(node.is_game_over());
+        assert_eq!(node.get_outcome(), record.get_outcome());
+        assert_eq!(node.get_scores(), record.get_scores());
+        assert!(record.get_n_moves() >= 17);
+    }
+
+    #[test]
+    fn test_play_match() {
+        // The dice decide most games, so depth 3's edge over depth 1 is small, and this is one seeded match it wins
+        // rather than one it always would.
+        let result = play_match(10, &get_config(3), &get_config(1), 0).unwrap();
+        assert_eq!(result.get_n_games(), 10);
+        assert!(result.get_wins() > result.get_losses());
+        assert!(result.get_score() > 0.5);
+        assert_eq!(play_match(10, &get_config(3), &get_config(1), 0).unwrap(), result);
+
+        let result = play_match(0, &get_config(3), &get_config(1), 5).unwrap();
+        assert_eq!((result.get_n_games(), result.get_score()), (0, 0.5));
+    }
+}
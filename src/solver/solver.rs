@@ -1,238 +1,4510 @@
-use crate::board::board::{Board, Move, Outcome, Player};
-use crate::tree::tree::{Node, NodeType};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::board::board::{Board, Comparison, Die, DieDistribution, Move, Outcome, Player, Square, are_tied, are_tied_within, compare_totally, max_keeping_nan};
+use crate::solver::accuracy::{HeuristicAccuracyReport, PositionAccuracy};
+use crate::solver::book::OpeningBook;
+use crate::solver::config::{CancellationToken, DepthSchedule, MoveOrdering, RiskProfile, ScheduledDepth, SearchObjective, SolverConfig, DEFAULT_BRUTE_FORCE_DEPTH, DEFAULT_COMPLETION_WEIGHT, DEFAULT_DRAW_WEIGHT, DEFAULT_EMPTY_SQUARE_FILL, DEFAULT_EXPOSURE_WEIGHT, DEFAULT_THREAT_WEIGHT, DEFAULT_WIN_PROBABILITY_SCALE};
+use crate::solver::distribution::OutcomeDistribution;
+use crate::solver::error::{NodeKind, SolverError};
+use crate::solver::mcts::MctsTree;
+use crate::solver::tablebase::EndgameTable;
+use crate::tree::tree::{ChanceExpansion, Node, NodeStatus, NodeType, Objective, PvStep, Step};
+
+// What heuristics score a finished game as, well beyond any score difference, so a certain win is always
+// preferred to a big lead.
+pub const KNOWN_WIN_EVALUATION: f32 = 1000.;
+const KNOWN_WIN_TOLERANCE: f32 = 0.001;
+
+// Identifies the solver cache file format, followed by a version byte.
+const CACHE_FILE_MAGIC: &[u8; 4] = b"KBTT";
+const CACHE_FILE_VERSION: u8 = 4;
+// The die distribution's six probabilities as f32s, then the chance expansion: a byte that's 1 if it's sampled, and
+// the number of rolls as a byte, the full plies as a u64 and the seed as a u64, all 0 if it isn't.
+const CACHE_DIE_DISTRIBUTION_BYTES: usize = 24;
+const CACHE_CHANCE_EXPANSION_BYTES: usize = 18;
+const CACHE_FILE_HEADER_BYTES: usize = 5 + CACHE_DIE_DISTRIBUTION_BYTES + CACHE_CHANCE_EXPANSION_BYTES + 8;
+// A u64 key, an objective byte, a u64 depth, a byte of extensions used, an f32 evaluation, a byte counting the best
+// moves, which follow as a row byte and a column byte each, and a byte that's 1 if the entry's search was truncated.
+const CACHE_ENTRY_HEADER_BYTES: usize = 24;
+
+// How many positions a timed search pushes onto its stack between looking at the clock.
+const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
+// How many moves deep a search ordering its first moves by a shallow search looks at them.
+const SHALLOW_ORDERING_DEPTH: usize = 2;
+// How far below the best move so far, on top of the tie break epsilon, a move has to be sure to come before the rest
+// of the rolls after it are skipped, so rounding in the averages can't cost a tied move its place.
+const PRUNING_MARGIN: f32 = 1e-4;
 
 pub struct Solver {
     root: Node,
+    ordered_expansion: bool,
+    use_transposition_table: bool,
+    // Searched positions by position key.  They're kept when the root moves on, since later searches see many of the
+    // same positions, which assumes the die distribution stays the same.
+    transposition_table: HashMap<u64, TranspositionEntry>,
+    search_stats: SearchStats,
+    config: SolverConfig,
+    // Probed before searching the root.
+    endgame_table: Option<Arc<EndgameTable>>,
+    // Probed before anything else, in any mode.
+    opening_book: Option<Arc<OpeningBook>>,
 }
 
 impl Solver {
     pub fn from_root(root: Node) -> Self {
+        Self::from_root_with_config(root, SolverConfig::default())
+    }
+
+    pub fn from_root_with_config(mut root: Node, config: SolverConfig) -> Self {
+        if let Some(die_distribution) = config.get_die_distribution() {
+            root.set_die_distribution(die_distribution);
+        }
         Solver {
             root,
+            ordered_expansion: false,
+            use_transposition_table: true,
+            transposition_table: HashMap::new(),
+            search_stats: SearchStats::empty(),
+            config,
+            endgame_table: None,
+            opening_book: None,
+        }
+    }
+
+    pub fn set_ordered_expansion(&mut self, ordered_expansion: bool) {
+        // Whether to build and search Move node children best first by the objective.  This only changes the order the
+        // tree is searched in, not the result.
+        self.ordered_expansion = ordered_expansion;
+    }
+
+    pub fn set_use_transposition_table(&mut self, use_transposition_table: bool) {
+        // Whether searches reuse the evaluations of positions they've already searched, rather than searching them
+        // again when another move order gets there.
+        self.use_transposition_table = use_transposition_table;
+    }
+
+    pub fn set_endgame_table(&mut self, endgame_table: Arc<EndgameTable>) {
+        // Searches of positions in the table look them up instead.
+        self.endgame_table = Some(endgame_table);
+    }
+
+    pub fn set_opening_book(&mut self, opening_book: Arc<OpeningBook>) {
+        // Positions in the book are played from it straight away instead of being searched.
+        self.opening_book = Some(opening_book);
+    }
+
+    pub fn clear_transposition_table(&mut self) {
+        self.transposition_table.clear();
+    }
+
+    pub fn save_cache(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, self.cache_to_bytes())
+            .map_err(|e| format!("Couldn't write the solver cache to {}: {}", path.display(), e));
+    }
+
+    pub fn load_cache(&mut self, path: &Path) -> Result<(), String> {
+        // Adds the cached positions to the transposition table, as if this solver had searched them itself.
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Couldn't read the solver cache from {}: {}", path.display(), e))?;
+        for (key, entry) in self.cache_from_bytes(&bytes)? {
+            self.insert_entry(key, entry);
+        }
+        return Ok(());
+    }
+
+    fn cache_to_bytes(&self) -> Vec<u8> {
+        // The magic and version, the die distribution and chance expansion the entries were searched under, and the
+        // number of entries as a little endian u64, then each entry in key order: its key, objective, depth,
+        // extensions, evaluation, number of best moves and whether it was truncated, then the best moves' rows and
+        // columns.  Heuristic objectives only have keys for as long as the process lasts, so only
+        // the exact objectives' entries are kept, under the default draw weight, and only those searched at least the
+        // config's cache min depth.  Lower bounds only help the search that found them, so they're left out.
+        let min_depth = self.config.get_cache_min_depth();
+        let mut entries = self.transposition_table.iter()
+            .filter(|(_, entry)| entry.depth >= min_depth && !entry.is_lower_bound)
+            .filter_map(|(key, entry)| Self::to_cache_objective(entry.objective_key).map(|objective| (*key, objective, entry)))
+            .collect::<Vec<(u64, u8, &TranspositionEntry)>>();
+        entries.sort_by_key(|(key, _, _)| *key);
+        let mut bytes = Vec::with_capacity(CACHE_FILE_HEADER_BYTES + entries.len() * CACHE_ENTRY_HEADER_BYTES);
+        bytes.extend_from_slice(CACHE_FILE_MAGIC);
+        bytes.push(CACHE_FILE_VERSION);
+        bytes.extend_from_slice(&self.get_cache_die_distribution_bytes());
+        bytes.extend_from_slice(&self.get_cache_chance_expansion_bytes());
+        bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+        for (key, objective, entry) in entries {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.push(objective);
+            bytes.extend_from_slice(&(entry.depth as u64).to_le_bytes());
+            bytes.push(entry.extensions as u8);
+            bytes.extend_from_slice(&entry.evaluation.to_le_bytes());
+            bytes.push(entry.best_moves.len() as u8);
+            bytes.push(entry.is_truncated as u8);
+            for m in &entry.best_moves {
+                bytes.push(m.row as u8);
+                bytes.push(m.column as u8);
+            }
+        }
+        return bytes;
+    }
+
+    fn cache_from_bytes(&self, bytes: &[u8]) -> Result<Vec<(u64, TranspositionEntry)>, String> {
+        // Entries searched with other dice or other rolls expanded would be wrong for this solver, so they're refused.
+        let header_length = CACHE_FILE_HEADER_BYTES;
+        if bytes.len() < CACHE_FILE_MAGIC.len() + 1 || &bytes[..CACHE_FILE_MAGIC.len()] != CACHE_FILE_MAGIC {
+            return Err("Not a solver cache.".to_string());
+        }
+        if bytes[CACHE_FILE_MAGIC.len()] != CACHE_FILE_VERSION {
+            return Err(format!("Unsupported solver cache version: {}", bytes[CACHE_FILE_MAGIC.len()]));
+        }
+        if bytes.len() < header_length {
+            return Err("Solver cache ends part way through its header.".to_string());
+        }
+        let die_distribution_start = CACHE_FILE_MAGIC.len() + 1;
+        let chance_expansion_start = die_distribution_start + CACHE_DIE_DISTRIBUTION_BYTES;
+        if bytes[die_distribution_start..chance_expansion_start] != self.get_cache_die_distribution_bytes() {
+            return Err("Solver cache was searched with a different die distribution.".to_string());
+        }
+        if bytes[chance_expansion_start..chance_expansion_start + CACHE_CHANCE_EXPANSION_BYTES] != self.get_cache_chance_expansion_bytes() {
+            return Err("Solver cache was searched with a different chance expansion.".to_string());
+        }
+        let truncated = || "Solver cache ends part way through an entry.".to_string();
+        let read_u64 = |start: usize| bytes.get(start..start + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().expect("Guaranteed to be 8 bytes.")))
+            .ok_or_else(truncated);
+        let n_entries = read_u64(header_length - 8)? as usize;
+        let mut entries = Vec::with_capacity(n_entries.min((bytes.len() - header_length) / CACHE_ENTRY_HEADER_BYTES));
+        let mut start = header_length;
+        for _ in 0..n_entries {
+            let header = bytes.get(start..start + CACHE_ENTRY_HEADER_BYTES).ok_or_else(truncated)?;
+            let key = read_u64(start)?;
+            let objective_key = Self::from_cache_objective(header[8])?;
+            let depth = read_u64(start + 9)? as usize;
+            let extensions = header[17] as usize;
+            let evaluation = f32::from_le_bytes(header[18..22].try_into().expect("Guaranteed to be 4 bytes."));
+            let n_best_moves = header[22] as usize;
+            let is_truncated = match header[23] {
+                0 => false,
+                1 => true,
+                flag => return Err(format!("Invalid truncation flag in solver cache: {}", flag)),
+            };
+            start += CACHE_ENTRY_HEADER_BYTES;
+            let best_moves = bytes.get(start..start + 2 * n_best_moves).ok_or_else(truncated)?
+                .chunks_exact(2)
+                .map(|m| Move::new(m[0] as usize, m[1] as usize))
+                .collect::<Vec<Move>>();
+            start += 2 * n_best_moves;
+            entries.push((key, TranspositionEntry { objective_key, depth, extensions, evaluation, best_moves, is_truncated, is_lower_bound: false }));
+        }
+        if start != bytes.len() {
+            return Err(format!("Solver cache should have {} entries but has {} bytes left over.", n_entries, bytes.len() - start));
+        }
+        return Ok(entries);
+    }
+
+    fn get_cache_die_distribution_bytes(&self) -> Vec<u8> {
+        let die_distribution = self.root.get_die_distribution();
+        return Die::all().into_iter().flat_map(|die| die_distribution.get_probability(die).to_le_bytes()).collect();
+    }
+
+    fn get_cache_chance_expansion_bytes(&self) -> Vec<u8> {
+        let (is_sampled, n_rolls, full_plies, seed) = match self.config.get_chance_expansion() {
+            ChanceExpansion::Full => (0, 0, 0, 0),
+            ChanceExpansion::Sampled { n_rolls, full_plies, seed } => (1, n_rolls as u8, full_plies as u64, seed),
+        };
+        let mut bytes = vec![is_sampled, n_rolls];
+        bytes.extend_from_slice(&full_plies.to_le_bytes());
+        bytes.extend_from_slice(&seed.to_le_bytes());
+        return bytes;
+    }
+
+    fn to_cache_objective(objective_key: usize) -> Option<u8> {
+        if objective_key == Self::brute_force_objective().get_key() {
+            Some(0)
+        } else if objective_key == Self::margin_objective().get_key() {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    fn from_cache_objective(objective: u8) -> Result<usize, String> {
+        match objective {
+            0 => Ok(Self::brute_force_objective().get_key()),
+            1 => Ok(Self::margin_objective().get_key()),
+            _ => Err(format!("Unknown objective in solver cache: {}", objective)),
         }
     }
 
+    pub fn get_config(&self) -> &SolverConfig {
+        &self.config
+    }
+
     pub fn get_root(&self) -> &Node {
         &self.root
     }
 
-    pub fn get_evaluation_tree(&mut self, solver_mode: SolverMode) -> Result<(Option<Node>, Evaluation), String> {
+    pub fn advance_by_move(&mut self, m: Move) -> Result<(), String> {
+        // Moves the root to the position after m, keeping whatever of the tree was already built below it.
+        let next = match self.root.detach_child_by_move(m) {
+            Some(child) => child,
+            None => self.root.with_move_made(m)?,
+        };
+        self.set_root(next);
+        return Ok(());
+    }
+
+    pub fn advance_by_roll(&mut self, die: Die) -> Result<(), String> {
+        let next = match self.root.detach_child_by_roll(die) {
+            Some(child) => child,
+            None => self.root.with_roll(die)?,
+        };
+        self.set_root(next);
+        return Ok(());
+    }
+
+    pub fn advance_root(&mut self, path: &[Step]) -> Result<(), String> {
+        // Follows path from the root a roll or move at a time, e.g. the player's move and the roll after it, keeping
+        // the tree below and the transposition table for the next search.  The whole path is checked first, so the
+        // root is left alone if any step is illegal.
+        let mut node = self.root.clone_without_children();
+        for (index, step) in path.iter().enumerate() {
+            node = match (node.get_node_type(), step) {
+                (NodeType::Roll(_), Step::Roll(die)) => node.with_roll(*die),
+                (NodeType::Move(_, pending_die), Step::Move(die, m)) => {
+                    if pending_die != *die {
+                        return Err(format!("Step {}: Expected a roll of {}, got {}", index, pending_die.to_string(), die.to_string()));
+                    }
+                    node.with_move_made(*m)
+                },
+                (NodeType::Roll(_), Step::Move(_, _)) => Err(format!("Expected a roll, not a move: {}", node)),
+                (NodeType::Move(_, _), Step::Roll(_)) => Err(format!("Expected a move, not a roll: {}", node)),
+            }.map_err(|e| format!("Step {}: {}", index, e))?;
+        }
+        for step in path {
+            match step {
+                Step::Roll(die) => self.advance_by_roll(*die),
+                Step::Move(_, m) => self.advance_by_move(*m),
+            }.expect("Guaranteed to be legal, since the path was checked.");
+        }
+        return Ok(());
+    }
+
+    pub fn reset_root(&mut self, mut root: Node) {
+        // Jumps to another position, e.g. after an undo.  The transposition table is kept, since its entries are by
+        // position rather than by where they are in the tree.
+        if let Some(die_distribution) = self.config.get_die_distribution() {
+            root.set_die_distribution(die_distribution);
+        }
+        self.set_root(root);
+    }
+
+    fn set_root(&mut self, mut root: Node) {
+        // The cached evaluations were for the old tree's depth, and the new root's tree will be built deeper.
+        root.clear_evaluations();
+        self.root = root;
+    }
+
+    pub fn get_evaluation_tree(&mut self, solver_mode: SolverMode) -> Result<(Option<Node>, Evaluation), SolverError> {
+        // The search stats count the nodes and leaves of the tree that was built.
+        let start = Instant::now();
+        self.search_stats = SearchStats::empty();
+        self.check_can_search(&solver_mode)?;
+        let result = match solver_mode {
+            SolverMode::BruteForce => {
+                self.search_stats.depth = self.config.get_brute_force_depth();
+                self.get_evaluation_tree_brute_force()
+            },
+            SolverMode::Heuristic((depth, f)) => {
+                self.search_stats.depth = depth;
+                self.get_evaluation_tree_heuristic(depth, &f)
+            },
+            SolverMode::Hybrid(max_moves_left_before_brute_force, (depth, f)) => {
+                self.search_stats.depth = if self.should_brute_force(max_moves_left_before_brute_force) { self.config.get_brute_force_depth() } else { depth };
+                self.get_evaluation_tree_hybrid(max_moves_left_before_brute_force, depth, &f)
+            },
+            SolverMode::NodeBudget((max_nodes, f)) => {
+                let result = self.get_evaluation_tree_node_budget(max_nodes, &f);
+                // Each move down the tree is a Move node and then a Roll node.
+                self.search_stats.depth = self.root.get_tree_stats().get_max_depth() / 2;
+                result
+            },
+            SolverMode::Adaptive(max_moves_left_before_brute_force, (max_nodes, f)) => {
+                let depth = self.get_adaptive_depth(max_nodes);
+                return self.get_evaluation_tree(SolverMode::Hybrid(max_moves_left_before_brute_force, (depth, f)));
+            },
+            SolverMode::Scheduled((schedule, f)) => return self.get_evaluation_tree(self.get_scheduled_mode(&schedule, f)),
+            SolverMode::Timed((budget, f)) => {
+                // The search decides how deep there's time for, then the tree is built that deep.
+                self.search_timed(budget, &f, &mut SearchControl::unlimited())?;
+                let depth = self.search_stats.depth;
+                self.get_evaluation_tree_heuristic(depth, &f)
+            },
+            SolverMode::MonteCarlo(_) => Err(SolverError::InvalidMode("Monte Carlo plays games out rather than building an evaluation tree.".to_string())),
+            SolverMode::Mcts(_) => Err(SolverError::InvalidMode("MCTS builds its own tree rather than an evaluation tree.".to_string())),
+        };
+        let tree_stats = self.root.get_tree_stats();
+        self.search_stats.n_nodes = tree_stats.get_n_nodes();
+        self.search_stats.n_leaves = tree_stats.get_n_leaves();
+        self.search_stats.elapsed = start.elapsed();
+        return result;
+    }
+
+    pub fn get_best_moves_and_evaluation(&mut self, solver_mode: SolverMode) -> Result<(Vec<Move>, Evaluation), SolverError> {
+        // Searches without building the tree, except on a node budget, which is a budget on the tree's size.
+        return self.search_with_control(solver_mode, &mut SearchControl::unlimited());
+    }
+
+    pub fn solve_with_progress(&mut self, solver_mode: SolverMode, mut callback: impl FnMut(SearchProgress)) -> Result<(Vec<Move>, Evaluation), SolverError> {
+        // The same search as get_best_moves_and_evaluation, calling back with how it's going at most once every
+        // progress interval, both part way through a search and after each depth a timed search finishes, and once
+        // more at the end.  Monte Carlo, MCTS and node budget searches only call back at the end.
+        let interval = self.config.get_progress_interval();
+        let mut control = SearchControl { deadline: None, progress: Some(ProgressReporter::new(&mut callback, interval)), on_depth: None };
+        let result = self.search_with_control(solver_mode, &mut control)?;
+        let n_nodes = self.search_stats.n_nodes;
+        // Keeps the depth it was last searching, which a timed search may not have finished, so the depths never go
+        // back down.
+        let progress = control.progress.as_mut().expect("Guaranteed to have been given a callback.");
+        progress.best = Some(result.clone());
+        progress.report(n_nodes, None);
+        return Ok(result);
+    }
+
+    pub fn get_best_moves_and_evaluation_with_confidence(&mut self, solver_mode: SolverMode) -> Result<(Vec<Move>, EvaluationWithConfidence), SolverError> {
+        // Searches a move less deep first, as iterative deepening would, so the transposition table carries that
+        // search's work over into the full one, and compares the two.  A search one move deep is compared with the
+        // objective at the root instead, which has no best move to change.  Only modes that search to a depth can
+        // be compared this way.
+        let start = Instant::now();
+        self.search_stats = SearchStats::empty();
+        self.check_can_search(&solver_mode)?;
+        let (depth, objective_function) = self.get_fixed_depth_and_objective(&solver_mode)
+            .ok_or(SolverError::InvalidMode("Only modes that search to a depth can say how confident they are.".to_string()))?;
+        let previous = if depth > 1 {
+            Some(self.search_to_depth(depth - 1, &objective_function, &mut SearchControl::unlimited())?)
+        } else {
+            None
+        };
+        let (best_moves, evaluation) = self.search_to_depth(depth, &objective_function, &mut SearchControl::unlimited())?;
+        self.search_stats.elapsed = start.elapsed();
+        let (delta, best_move_changed) = match previous {
+            Some((previous_best_moves, previous_evaluation)) => (
+                evaluation.get_evaluation() - previous_evaluation.get_evaluation(),
+                !best_moves.iter().any(|m| previous_best_moves.contains(m)),
+            ),
+            None => (evaluation.get_evaluation() - objective_function.evaluate(&self.root), false),
+        };
+        let low_confidence = best_move_changed
+            || self.config.get_confidence_threshold().is_some_and(|threshold| delta.abs() > threshold);
+        return Ok((best_moves, EvaluationWithConfidence { evaluation, delta, best_move_changed, low_confidence }));
+    }
+
+    fn get_fixed_depth_and_objective(&self, solver_mode: &SolverMode) -> Option<(usize, Objective)> {
+        // How deep a mode searches the root and with what, for the modes that decide that before searching.
         match solver_mode {
-            SolverMode::BruteForce => self.get_evaluation_tree_brute_force(),
-            SolverMode::Heuristic((depth, f)) => 
-                self.get_evaluation_tree_heuristic(depth, f),
-            SolverMode::Hybrid(max_moves_left_before_brute_force, (depth, f)) => 
-                self.get_evaluation_tree_hybrid(max_moves_left_before_brute_force, depth, f),
+            SolverMode::BruteForce => Some((self.config.get_brute_force_depth(), self.exact_objective())),
+            SolverMode::Heuristic((depth, f)) => Some((*depth, f.clone())),
+            SolverMode::Hybrid(max_moves_left_before_brute_force, (depth, f)) => {
+                if self.should_brute_force(*max_moves_left_before_brute_force) {
+                    Some((self.config.get_brute_force_depth(), self.exact_objective()))
+                } else {
+                    Some((*depth, f.clone()))
+                }
+            },
+            SolverMode::Adaptive(max_moves_left_before_brute_force, (max_nodes, f)) => {
+                if self.should_brute_force(*max_moves_left_before_brute_force) {
+                    Some((self.config.get_brute_force_depth(), self.exact_objective()))
+                } else {
+                    Some((self.get_adaptive_depth(*max_nodes), f.clone()))
+                }
+            },
+            SolverMode::Scheduled((schedule, f)) => self.get_fixed_depth_and_objective(&self.get_scheduled_mode(schedule, f.clone())),
+            _ => None,
         }
     }
 
-    pub fn get_best_moves_and_evaluation(&mut self, solver_mode: SolverMode) -> Result<(Vec<Move>, Evaluation), String> {
-        self.get_evaluation_tree(solver_mode).map(
-            |(maybe_tree, evaluation)|
-            (Self::get_best_moves_from_evaluation_tree(maybe_tree).expect("Guaranteed to be a Move Node"), evaluation)
-        )
+    pub fn solve_streaming(mut self, solver_mode: SolverMode) -> (Receiver<SearchEvent>, SolveHandle) {
+        // Searches on a thread of its own, sending how it's going back over the channel: progress at most once every
+        // progress interval, each depth as it's finished, and the result at the end, after which the channel closes.
+        // Modes that search to a depth get there one depth at a time, as a timed search does, so the transposition
+        // table carries each depth's work over into the next.  Cancelling through the handle, or the config's
+        // cancellation token if it has one, finishes with the deepest search done so far, or Interrupted if there
+        // isn't one.  Joining the handle gives the solver back, transposition table and all.
+        let cancellation_token = self.config.get_cancellation_token().cloned().unwrap_or_default();
+        self.config = self.config.with_cancellation_token(cancellation_token.clone());
+        let (sender, receiver) = mpsc::channel();
+        let thread = std::thread::spawn(
+            move || {
+                let progress_sender = sender.clone();
+                let depth_sender = sender.clone();
+                // A closed channel only means nobody's listening any more, which doesn't stop the search.
+                let mut on_progress = |progress: SearchProgress| {
+                    let _ = progress_sender.send(SearchEvent::Progress { n_nodes: progress.get_n_nodes(), elapsed: progress.get_elapsed() });
+                };
+                let mut on_depth = |depth: usize, (best_moves, evaluation): &(Vec<Move>, Evaluation)| {
+                    let _ = depth_sender.send(SearchEvent::DepthCompleted { depth, best_moves: Self::in_canonical_order(best_moves.clone()), evaluation: *evaluation });
+                };
+                let interval = self.config.get_progress_interval();
+                let mut control = SearchControl {
+                    deadline: None,
+                    progress: Some(ProgressReporter::new(&mut on_progress, interval)),
+                    on_depth: Some(&mut on_depth),
+                };
+                let result = self.search_deepening(solver_mode, &mut control);
+                let _ = sender.send(SearchEvent::Finished { result });
+                self
+            }
+        );
+        return (receiver, SolveHandle { cancellation_token, thread });
+    }
+
+    fn search_deepening(&mut self, solver_mode: SolverMode, control: &mut SearchControl) -> Result<(Vec<Move>, Evaluation), SolverError> {
+        // As search_with_control, but one depth at a time for the modes that search to a fixed depth.
+        let (depth, objective_function) = match self.get_fixed_depth_and_objective(&solver_mode) {
+            Some(depth_and_objective) => depth_and_objective,
+            None => return self.search_with_control(solver_mode, control),
+        };
+        let start = Instant::now();
+        self.search_stats = SearchStats::empty();
+        self.check_can_search(&solver_mode)?;
+        if let Some(result) = self.probe_opening_book()? {
+            return Ok(result);
+        }
+        let mut result = None;
+        for next_depth in 1..=depth {
+            match self.search_to_depth(next_depth, &objective_function, control) {
+                Ok(deeper_result) => {
+                    control.finish_depth(next_depth, self.search_stats.n_nodes, &deeper_result);
+                    result = Some((next_depth, deeper_result));
+                },
+                Err(SolverError::Interrupted) if result.is_some() => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let (depth_reached, (best_moves, evaluation)) = result.expect("Guaranteed to have searched at least one depth.");
+        self.search_stats.depth = depth_reached;
+        self.search_stats.elapsed = start.elapsed();
+        return Ok((Self::in_canonical_order(best_moves), evaluation));
+    }
+
+    fn probe_opening_book(&mut self) -> Result<Option<(Vec<Move>, Evaluation)>, SolverError> {
+        // The book only knows the best moves by expected value.
+        let opening_book = self.opening_book.as_ref().filter(|_| self.config.get_risk_profile() == RiskProfile::ExpectedValue);
+        match opening_book.and_then(|opening_book| opening_book.probe(&self.root)) {
+            Some(entry) => {
+                self.search_stats.n_hits += 1;
+                return Ok(Some((Self::in_canonical_order(entry.get_best_moves(&self.root)?), entry.get_evaluation())));
+            },
+            None => return Ok(None),
+        }
+    }
+
+    fn search_with_control(&mut self, solver_mode: SolverMode, control: &mut SearchControl) -> Result<(Vec<Move>, Evaluation), SolverError> {
+        let start = Instant::now();
+        self.search_stats = SearchStats::empty();
+        self.check_can_search(&solver_mode)?;
+        if let Some(result) = self.probe_opening_book()? {
+            return Ok(result);
+        }
+        let result = match solver_mode {
+            SolverMode::BruteForce => self.search_to_depth(self.config.get_brute_force_depth(), &self.exact_objective(), control),
+            SolverMode::Heuristic((depth, f)) => self.search_to_depth(depth, &f, control),
+            SolverMode::Hybrid(max_moves_left_before_brute_force, (depth, f)) => {
+                if self.should_brute_force(max_moves_left_before_brute_force) {
+                    self.search_to_depth(self.config.get_brute_force_depth(), &self.exact_objective(), control)
+                } else {
+                    self.search_to_depth(depth, &f, control)
+                }
+            },
+            SolverMode::NodeBudget(_) => self.get_evaluation_tree(solver_mode).map(
+                |(maybe_tree, evaluation)|
+                (Self::get_best_moves_from_evaluation_tree(maybe_tree).expect("Guaranteed to be a Move Node"), evaluation)
+            ),
+            SolverMode::Adaptive(max_moves_left_before_brute_force, (max_nodes, f)) => {
+                let depth = self.get_adaptive_depth(max_nodes);
+                self.search_with_control(SolverMode::Hybrid(max_moves_left_before_brute_force, (depth, f)), control)
+            },
+            SolverMode::Scheduled((schedule, f)) => self.search_with_control(self.get_scheduled_mode(&schedule, f), control),
+            SolverMode::Timed((budget, f)) => self.search_timed(budget, &f, control),
+            SolverMode::MonteCarlo((playouts_per_move, seed)) => self.search_monte_carlo(playouts_per_move, seed),
+            SolverMode::Mcts((iterations, exploration_c, seed)) => self.search_mcts(iterations, exploration_c, seed),
+        };
+        self.search_stats.elapsed = start.elapsed();
+        return result.map(|(best_moves, evaluation)| (Self::in_canonical_order(best_moves), evaluation));
+    }
+
+    fn in_canonical_order(mut best_moves: Vec<Move>) -> Vec<Move> {
+        // Tied moves come back row-major whichever order the mode found them in, so the same position always gives
+        // the same list.
+        best_moves.sort();
+        best_moves.dedup();
+        return best_moves;
+    }
+
+    fn check_can_search(&self, solver_mode: &SolverMode) -> Result<(), SolverError> {
+        // The errors every mode has in common, so each of them can assume a game in progress on a Move node.
+        match self.root.status() {
+            NodeStatus::Terminal(_) => return Err(SolverError::GameAlreadyOver),
+            NodeStatus::Roll(_) => return Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }),
+            NodeStatus::Move(_, _) => {},
+        }
+        match solver_mode {
+            SolverMode::Heuristic((0, _)) | SolverMode::Hybrid(_, (0, _)) => return Err(SolverError::DepthZero),
+            SolverMode::MonteCarlo((0, _)) => return Err(SolverError::InvalidMode("Monte Carlo needs at least one playout per move.".to_string())),
+            SolverMode::Mcts((0, _, _)) => return Err(SolverError::InvalidMode("MCTS needs at least one iteration.".to_string())),
+            SolverMode::Adaptive(_, (0, _)) => return Err(SolverError::InvalidMode("Adaptive depth needs a node budget of at least one.".to_string())),
+            _ => return Ok(()),
+        }
+    }
+
+    fn search_to_depth(&mut self, depth: usize, objective_function: &Objective, control: &mut SearchControl) -> Result<(Vec<Move>, Evaluation), SolverError> {
+        control.start_depth(depth);
+        // Without a deadline, the only way the search doesn't finish is being cancelled.
+        let result = self.search(depth, objective_function, control)
+            .and_then(|maybe_result| maybe_result.ok_or(SolverError::Interrupted));
+        self.search_stats.depth = depth;
+        if self.breaks_ties_by_margin(objective_function) {
+            return result.map(|(best_moves, evaluation)| self.break_ties_by_margin(best_moves, evaluation, depth));
+        }
+        return result;
+    }
+
+    fn search_timed(&mut self, budget: Duration, objective_function: &Objective, control: &mut SearchControl) -> Result<(Vec<Move>, Evaluation), SolverError> {
+        // Iterative deepening: searches one move deeper each time until the budget runs out, keeping the deepest
+        // search that finished.  The first move is always searched in full so there's something to play, and there's
+        // no point going past the longest the game could last.  The transposition table carries the shallower searches'
+        // work over into the deeper ones.  Being cancelled stops it like the deadline passing, unless it's before the
+        // first move's search is done.
+        let deadline = Instant::now() + budget;
+        let mut result = self.search_to_depth(1, objective_function, control)?;
+        control.finish_depth(1, self.search_stats.n_nodes, &result);
+        control.deadline = Some(deadline);
+        let (_, max_moves_left) = self.root.get_moves_left_bounds();
+        for depth in 2..=max_moves_left {
+            if Instant::now() >= deadline || self.is_cancelled() {
+                break;
+            }
+            control.start_depth(depth);
+            match self.search(depth, objective_function, control)? {
+                Some(deeper_result) => {
+                    result = deeper_result;
+                    self.search_stats.depth = depth;
+                    control.finish_depth(depth, self.search_stats.n_nodes, &result);
+                },
+                None => break,
+            }
+        }
+        control.deadline = None;
+        return Ok(result);
+    }
+
+    fn search(&mut self, depth: usize, objective_function: &Objective, control: &mut SearchControl) -> Result<Option<(Vec<Move>, Evaluation)>, SolverError> {
+        // The expectimax value of the root searched depth moves ahead, and the moves that get it, the same as building
+        // the tree up to symmetry and evaluating it would give.  Positions are generated as they're needed and dropped
+        // once they've been scored, and the search keeps its own stack, so neither memory nor the call stack grow with
+        // the size of the tree.  None if the deadline passes or the search is cancelled first.
+        if self.is_cancelled() {
+            return Ok(None);
+        }
+        let n_truncated = self.search_stats.n_truncated;
+        let root = self.root.clone_without_children();
+        self.search_stats.n_nodes += 1;
+        if root.is_terminal() || depth == 0 {
+            self.count_leaf(&root, objective_function);
+            return Ok(Some((vec![], Self::to_evaluation(objective_function, objective_function.evaluate(&root))?)));
+        }
+        // The endgame table and the transposition table only know the best moves by expected value.
+        let is_risk_neutral = self.config.get_risk_profile() == RiskProfile::ExpectedValue;
+        if is_risk_neutral {
+            if let Some(result) = self.probe_endgame_table(&root, objective_function)? {
+                return Ok(Some(result));
+            }
+        }
+        let maybe_result = match self.look_up(&root, depth, 0, objective_function, None).filter(|_| is_risk_neutral) {
+            Some(entry) => Some((entry.evaluation, entry.best_moves.clone())),
+            None if is_risk_neutral => self.search_root(&root, depth, objective_function, control),
+            None => self.search_root_with_risk_profile(&root, depth, objective_function, control),
+        };
+        let (evaluation, root_best_moves) = match maybe_result {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+        // The search works from the perspective of whoever's to act, so this is the one place its value is turned back
+        // into Player 1's.  Moves skipped by row symmetry are as good as the move they're equivalent to.
+        let evaluation = root.get_active_player().get_sign() * evaluation;
+        let best_moves = Self::with_row_symmetric_moves(&root, root_best_moves)?;
+        let evaluation = self.mark_if_truncated(Self::to_evaluation(objective_function, evaluation)?, n_truncated);
+        return Ok(Some((best_moves, self.mark_if_sampled(evaluation, depth))));
+    }
+
+    fn with_row_symmetric_moves(root: &Node, root_best_moves: Vec<Move>) -> Result<Vec<Move>, SolverError> {
+        // Every legal move at the root equivalent to one of the best moves up to row symmetry.
+        let best_children = root_best_moves.into_iter()
+            .map(|m| root.with_move_made(m).expect("Won't error because we know the moves are legal."))
+            .collect::<Vec<Node>>();
+        let best_moves = root.get_legal_moves()?.into_iter()
+            .filter(
+                |m| {
+                    let child = root.with_move_made(*m).expect("Won't error because we know the moves are legal.");
+                    best_children.iter().any(|best_child| best_child.equals_up_to_children(&child))
+                }
+            ).collect::<Vec<Move>>();
+        return Ok(best_moves);
+    }
+
+    fn probe_endgame_table(&mut self, root: &Node, objective_function: &Objective) -> Result<Option<(Vec<Move>, Evaluation)>, SolverError> {
+        // A position in the table has already been brute forced, which is worth more than any search it would get,
+        // unless brute force is after the margin or weighing draws differently, which the table doesn't have.
+        if Self::is_exact_objective(objective_function) && objective_function.get_key() != Self::brute_force_objective().get_key() {
+            return Ok(None);
+        }
+        match self.endgame_table.as_ref().and_then(|endgame_table| endgame_table.probe(root)) {
+            Some(entry) => {
+                self.search_stats.n_hits += 1;
+                let evaluation = Evaluation::new(EvaluationKind::ExpectedResult, entry.get_evaluation()).map_err(SolverError::Internal)?;
+                let evaluation = if entry.is_truncated() { evaluation.truncated() } else { evaluation };
+                return Ok(Some((entry.get_best_moves(root)?, evaluation)));
+            },
+            None => return Ok(None),
+        }
+    }
+
+    fn search_root(&mut self, root: &Node, depth: usize, objective_function: &Objective, control: &mut SearchControl) -> Option<(f32, Vec<Move>)> {
+        #[cfg(feature = "parallel")]
+        if self.config.get_n_threads() != 1 {
+            return self.search_root_in_parallel(root, depth, objective_function, control);
+        }
+        return self.search_from(root, depth, 0, objective_function, control);
+    }
+
+    fn search_root_with_risk_profile(&mut self, root: &Node, depth: usize, objective_function: &Objective, control: &mut SearchControl) -> Option<(f32, Vec<Move>)> {
+        // The root's best moves when the rolls after each of them are weighed up by the config's risk profile rather
+        // than averaged, along with the expected value of the first, for the player to act.  Each roll's position is
+        // searched as usual, so everything past them is still averaged.
+        let risk_profile = self.config.get_risk_profile();
+        let moves = root.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.");
+        let mut expected_evaluations = Vec::new();
+        let mut risk_evaluations = Vec::new();
+        for m in moves.iter() {
+            let child = root.with_move_made(*m).expect("Won't error because we know the moves are legal.");
+            self.search_stats.n_nodes += 1;
+            // For the player to act at the root, whatever the roll.
+            let mut roll_evaluations = Vec::new();
+            if child.is_terminal() {
+                self.search_stats.n_leaves += 1;
+                roll_evaluations.push((-Self::evaluate_for_active_player(objective_function, &child), 1.));
+            } else {
+                let (child_depth, child_extensions) = self.get_child_depth(&child, depth, 0);
+                for (die, share) in self.get_roll_shares(&child) {
+                    let grandchild = child.with_roll(die).expect("Won't error because the game isn't over.");
+                    self.search_stats.n_nodes += 1;
+                    let evaluation = if child_depth == 0 {
+                        self.count_leaf(&grandchild, objective_function);
+                        Self::evaluate_for_active_player(objective_function, &grandchild)
+                    } else {
+                        match self.look_up(&grandchild, child_depth, child_extensions, objective_function, None) {
+                            Some(entry) => entry.evaluation,
+                            None => self.search_from(&grandchild, child_depth, child_extensions, objective_function, control)?.0,
+                        }
+                    };
+                    roll_evaluations.push((-evaluation, share));
+                }
+            }
+            expected_evaluations.push(RiskProfile::ExpectedValue.aggregate(&roll_evaluations));
+            risk_evaluations.push(risk_profile.aggregate(&roll_evaluations));
+        }
+        let (_, best_moves) = Self::get_best_evaluation_and_moves(moves.clone(), &risk_evaluations, self.config.get_tie_break_epsilon());
+        let first_best_index = moves.iter().position(|m| best_moves.first() == Some(m)).expect("Guaranteed to be a best move.");
+        return Some((expected_evaluations[first_best_index], best_moves));
     }
 
-    fn get_evaluation_tree_brute_force(&mut self) -> Result<(Option<Node>, Evaluation), String> {
-        self.root.build_entire_tree_up_to_symmetry();
-        return self.root.get_evaluation_tree(
-            |x| Evaluation::from_outcome(
-                x.get_outcome()
-            ).expect("Outcome will be known at all leaf nodes.")
-            .get_evaluation(),
-        ).map(
-            |(maybe_tree, evaluation)|
-            (maybe_tree, Evaluation::new(evaluation))
+    #[cfg(feature = "parallel")]
+    fn search_root_in_parallel(&mut self, root: &Node, depth: usize, objective_function: &Objective, control: &mut SearchControl) -> Option<(f32, Vec<Move>)> {
+        // Each root move's position is searched on its own thread with its own transposition table, which is merged
+        // into this one afterwards.  Brute force is only ever this close to the end of the game, where there are too
+        // few moves to keep the threads busy, so it's split on the roll after each move as well.  The results are
+        // taken in move and roll order once they're all in, and averaged over the rolls just as the serial search
+        // does, so they come out the same however the threads finish.  The objective is shared between the threads,
+        // so it has to be Sync, which fn pointers always are.  The threads don't report progress, since the callback
+        // needn't be.
+        use rayon::prelude::*;
+
+        let moves = root.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.");
+        let children = moves.iter()
+            .map(|m| root.with_move_made(*m).expect("Won't error because we know the moves are legal."))
+            .collect::<Vec<Node>>();
+        if children.iter().any(|child| child.is_terminal()) {
+            // A game-ending move fills the last square, so it's the only legal move and there's nothing to split up.
+            return self.search_from(root, depth, 0, objective_function, control);
+        }
+        let split_rolls = Self::is_exact_objective(objective_function);
+        let n_truncated = self.search_stats.n_truncated;
+        // Each child's depth, and whether it's split on its rolls, along with the positions to search for it, with
+        // their evaluations, and whether they were truncated, if they're already known.
+        let mut plans = Vec::new();
+        let mut tasks = Vec::new();
+        for (index, child) in children.iter().enumerate() {
+            let (child_depth, child_extensions) = self.get_child_depth(child, depth, 0);
+            let is_split = split_rolls && child_depth > 0;
+            let positions = if is_split {
+                self.get_roll_shares(child).into_iter()
+                    .map(|(die, _)| child.with_roll(die).expect("Won't error because the game isn't over."))
+                    .collect()
+            } else {
+                vec![child.clone()]
+            };
+            for position in positions {
+                let known_evaluation = self.look_up(&position, child_depth, child_extensions, objective_function, None).map(|entry| (entry.evaluation, entry.is_truncated));
+                // Each worker has the root as its own, so it counts plies from it and samples the same rolls.
+                tasks.push((index, root.clone_without_children(), position, child_depth, child_extensions, known_evaluation));
+            }
+            plans.push((child_depth, child_extensions, is_split));
+        }
+        let use_transposition_table = self.use_transposition_table;
+        let deadline = control.deadline;
+        let config = self.config.clone();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(self.config.get_n_threads()).build()
+            .expect("Couldn't start the search threads.");
+        let searched = pool.install(
+            || tasks.into_par_iter()
+                .map(
+                    |(index, worker_root, position, position_depth, position_extensions, known_evaluation)| {
+                        if known_evaluation.is_some() {
+                            return (index, known_evaluation, None);
+                        }
+                        let mut worker = Solver::from_root_with_config(worker_root, config.clone());
+                        worker.set_use_transposition_table(use_transposition_table);
+                        let maybe_evaluation = worker.search_from(&position, position_depth, position_extensions, objective_function, &mut SearchControl::until(deadline))
+                            .map(|(evaluation, _)| (evaluation, worker.search_stats.n_truncated > 0));
+                        (index, maybe_evaluation, Some(worker))
+                    }
+                ).collect::<Vec<(usize, Option<(f32, bool)>, Option<Solver>)>>()
         );
+
+        let mut evaluations_by_child = vec![Vec::new(); children.len()];
+        for (index, maybe_evaluation, maybe_worker) in searched {
+            if let Some(worker) = maybe_worker {
+                self.search_stats.n_nodes += worker.search_stats.n_nodes + 1;
+                self.search_stats.n_hits += worker.search_stats.n_hits;
+                self.search_stats.n_misses += worker.search_stats.n_misses;
+                self.search_stats.n_leaves += worker.search_stats.n_leaves;
+                self.search_stats.n_cutoffs += worker.search_stats.n_cutoffs;
+                self.search_stats.n_pruned += worker.search_stats.n_pruned;
+                self.search_stats.n_merged_rolls += worker.search_stats.n_merged_rolls;
+                self.search_stats.n_truncated += worker.search_stats.n_truncated;
+                for (key, entry) in worker.transposition_table {
+                    self.insert_entry(key, entry);
+                }
+            }
+            evaluations_by_child[index].push(maybe_evaluation);
+        }
+        let mut evaluations = Vec::new();
+        for ((child, (child_depth, child_extensions, is_split)), child_evaluations) in children.iter().zip(plans).zip(evaluations_by_child) {
+            let evaluation = if is_split {
+                let mut average_evaluation = 0.;
+                let mut is_truncated = false;
+                for (maybe_evaluation, (_, share)) in child_evaluations.into_iter().zip(self.get_roll_shares(child)) {
+                    let (evaluation, is_roll_truncated) = maybe_evaluation?;
+                    average_evaluation += evaluation * share;
+                    is_truncated |= is_roll_truncated;
+                }
+                self.search_stats.n_nodes += 1;
+                self.store(child, TranspositionEntry::new(objective_function, child_depth, child_extensions, average_evaluation, vec![], is_truncated));
+                average_evaluation
+            } else {
+                child_evaluations[0]?.0
+            };
+            // Each child's evaluation is for the opponent, who acts next.
+            evaluations.push(-evaluation);
+        }
+
+        let (best_evaluation, best_moves) = Self::get_best_evaluation_and_moves(moves, &evaluations, self.config.get_tie_break_epsilon());
+        let is_truncated = self.search_stats.n_truncated > n_truncated;
+        self.store(root, TranspositionEntry::new(objective_function, depth, 0, best_evaluation, best_moves.clone(), is_truncated));
+        return Some((best_evaluation, best_moves));
+    }
+
+    fn search_from(&mut self, root: &Node, depth: usize, extensions: usize, objective_function: &Objective, control: &mut SearchControl) -> Option<(f32, Vec<Move>)> {
+        // The root's evaluation for the player to act and its best moves up to row symmetry, with extensions already
+        // used on the way to it.  Every position finished before the deadline stays in the transposition table even if
+        // the root doesn't get finished.  With move ordering, the rolls after a move stop being searched once it's sure
+        // to fall short of the best move before it, which is why the likeliest best moves go first.
+        let move_ordering = self.get_move_ordering(objective_function);
+        let mut root_frame = self.new_search_frame(root.clone(), root.get_scores(), depth, extensions, objective_function, move_ordering, None);
+        if move_ordering == MoveOrdering::ShallowSearch && depth > SHALLOW_ORDERING_DEPTH {
+            self.order_by_shallow_search(&mut root_frame, objective_function, control)?;
+        }
+        let mut stack = vec![root_frame];
+        // The evaluation of the frame just finished, for the one below it on the stack to take in.
+        let mut child_evaluation: Option<f32> = None;
+        let mut n_frames_pushed: usize = 0;
+        loop {
+            let frame = stack.last_mut().expect("Guaranteed to be a frame on the stack until the root's finished.");
+            let next_frame = match frame {
+                SearchFrame::Move { node, scores, depth, extensions, moves, evaluations, .. } => {
+                    // A move hands the turn to the opponent, so the child's evaluation is negated.
+                    if let Some(evaluation) = child_evaluation.take() {
+                        evaluations.push(-evaluation);
+                    }
+                    match moves.get(evaluations.len()) {
+                        None => None,
+                        Some(next_move) => {
+                            let (child, effect) = node.with_move_made_detailed(*next_move).expect("Won't error because we know the moves are legal.");
+                            let child_scores = effect.get_scores_after(*scores);
+                            if child.is_terminal() {
+                                // Scored by the objective and compared with the other moves like any of them.
+                                self.search_stats.n_nodes += 1;
+                                self.search_stats.n_leaves += 1;
+                                evaluations.push(-Self::evaluate_for_active_player_with_scores(objective_function, &child, child_scores));
+                                None
+                            } else {
+                                let (child_depth, child_extensions) = self.get_child_depth(&child, *depth, *extensions);
+                                // The most the opponent can make of the child with this move still in the running, as
+                                // evaluations are for the player to act.
+                                let bound = Some(self.config.get_tie_break_epsilon() + PRUNING_MARGIN - evaluations.iter().copied().fold(f32::NEG_INFINITY, f32::max))
+                                    .filter(|_| move_ordering != MoveOrdering::None && !evaluations.is_empty());
+                                Some((child, child_scores, child_depth, child_extensions, bound))
+                            }
+                        },
+                    }
+                },
+                SearchFrame::Roll { node, scores, depth, extensions, rolls, merged_into, evaluations, index, average_evaluation, bound, is_pruned, .. } => {
+                    if let Some(evaluation) = child_evaluation.take() {
+                        evaluations.push(evaluation);
+                        *average_evaluation += evaluation * rolls[*index].1;
+                        *index += 1;
+                        // Evaluations of the win probability objectives are never below -1, so this is the least the
+                        // average can come to.
+                        let lowest_evaluation = *average_evaluation - rolls[*index..].iter().map(|(_, share)| share).sum::<f32>();
+                        if *index < rolls.len() && bound.is_some_and(|bound| lowest_evaluation > bound) {
+                            self.search_stats.n_pruned += 1;
+                            *average_evaluation = lowest_evaluation;
+                            *index = rolls.len();
+                            *is_pruned = true;
+                        }
+                    }
+                    match rolls.get(*index) {
+                        None => None,
+                        Some((die, _)) => {
+                            if let Some(earlier_index) = merged_into[*index] {
+                                // Searched the same as an earlier roll, so it takes that roll's evaluation, and the
+                                // average comes out exactly as if it had been searched.
+                                self.search_stats.n_merged_rolls += 1;
+                                child_evaluation = Some(evaluations[earlier_index]);
+                                continue;
+                            }
+                            let child = node.with_roll(*die).expect("Won't error because the game isn't over.");
+                            if *depth == 0 {
+                                self.search_stats.n_nodes += 1;
+                                self.count_leaf(&child, objective_function);
+                                child_evaluation = Some(Self::evaluate_for_active_player_with_scores(objective_function, &child, *scores));
+                                continue;
+                            }
+                            // A roll doesn't change the boards, so the scores stay the same.
+                            Some((child, *scores, *depth, *extensions, None))
+                        },
+                    }
+                },
+            };
+            match next_frame {
+                Some((child, child_scores, child_depth, child_extensions, bound)) => {
+                    match self.look_up(&child, child_depth, child_extensions, objective_function, bound) {
+                        Some(entry) => child_evaluation = Some(entry.evaluation),
+                        None => {
+                            self.search_stats.n_nodes += 1;
+                            n_frames_pushed += 1;
+                            if n_frames_pushed.is_multiple_of(DEADLINE_CHECK_INTERVAL) {
+                                if control.is_past_deadline() || self.is_cancelled() {
+                                    return None;
+                                }
+                                if let Some(progress) = control.progress.as_mut().filter(|progress| progress.is_due()) {
+                                    let best_so_far = Self::get_best_so_far(&stack[0], objective_function, self.config.get_tie_break_epsilon())
+                                        .map(|(best_moves, evaluation)| (best_moves, self.mark_if_sampled(evaluation, depth)));
+                                    progress.report(self.search_stats.n_nodes, best_so_far);
+                                }
+                            }
+                            if child_depth == 0 {
+                                // A Roll node with no moves left to search, so its rolls are scored as they are.
+                                self.search_stats.n_cutoffs += 1;
+                            }
+                            let frame = self.new_search_frame(child, child_scores, child_depth, child_extensions, objective_function, move_ordering, bound);
+                            stack.push(frame);
+                        },
+                    }
+                },
+                None => {
+                    let frame = stack.pop().expect("Guaranteed to be a frame on the stack.");
+                    let (node, depth, extensions, evaluation, best_moves, is_pruned, n_truncated) = match frame {
+                        SearchFrame::Move { node, depth, extensions, moves, evaluations, n_truncated, .. } => {
+                            let (evaluation, mut best_moves) = Self::get_best_evaluation_and_moves(moves, &evaluations, self.config.get_tie_break_epsilon());
+                            // Row-major, whatever order the moves were searched in.
+                            best_moves.sort();
+                            (node, depth, extensions, evaluation, best_moves, false, n_truncated)
+                        },
+                        SearchFrame::Roll { node, depth, extensions, average_evaluation, is_pruned, n_truncated, .. } => (node, depth, extensions, average_evaluation, vec![], is_pruned, n_truncated),
+                    };
+                    let is_truncated = self.search_stats.n_truncated > n_truncated;
+                    if stack.is_empty() {
+                        self.store(&node, TranspositionEntry::new(objective_function, depth, extensions, evaluation, best_moves.clone(), is_truncated));
+                        return Some((evaluation, best_moves));
+                    }
+                    // A pruned position's evaluation is only a bound, and only stands in for searches that would be
+                    // pruned by it too.
+                    let entry = TranspositionEntry::new(objective_function, depth, extensions, evaluation, best_moves, is_truncated);
+                    self.store(&node, if is_pruned { entry.lower_bound() } else { entry });
+                    child_evaluation = Some(evaluation);
+                },
+            }
+        }
+    }
+
+    fn count_leaf(&mut self, node: &Node, objective_function: &Objective) {
+        // A game brute force's depth ran out on before it finished is scored as it stands, which is only a guess at
+        // how it ends.
+        self.search_stats.n_leaves += 1;
+        if !node.is_terminal() && Self::is_exact_objective(objective_function) {
+            self.search_stats.n_truncated += 1;
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.config.get_cancellation_token().is_some_and(|cancellation_token| cancellation_token.is_cancelled())
+    }
+
+    fn get_child_depth(&self, child: &Node, depth: usize, extensions: usize) -> (usize, usize) {
+        // The depth to search the Roll node a move leads to, and the extensions used on the way.  Where the search
+        // would stop with the player about to roll threatening an elimination of at least the extension threshold,
+        // it's searched one more move, so the leaf isn't scored with the elimination still to come.
+        let is_extended = depth == 1
+            && extensions < self.config.get_max_extensions()
+            && self.config.get_extension_threshold().is_some_and(|threshold| Self::get_max_elimination_threat(child) >= threshold);
+        if is_extended {
+            return (1, extensions + 1);
+        }
+        return (depth - 1, extensions);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new_search_frame(&mut self, node: Node, scores: (u16, u16), depth: usize, extensions: usize, objective_function: &Objective, move_ordering: MoveOrdering, bound: Option<f32>) -> SearchFrame {
+        match node.get_node_type() {
+            NodeType::Move(_, _) => SearchFrame::Move {
+                moves: self.get_ordered_moves(&node, objective_function, move_ordering),
+                node,
+                scores,
+                depth,
+                extensions,
+                evaluations: Vec::new(),
+                n_truncated: self.search_stats.n_truncated,
+            },
+            NodeType::Roll(_) => {
+                let rolls = self.get_roll_shares(&node);
+                SearchFrame::Roll {
+                    merged_into: self.get_merged_rolls(&rolls, depth, objective_function),
+                    rolls,
+                    node,
+                    scores,
+                    depth,
+                    extensions,
+                    evaluations: Vec::new(),
+                    index: 0,
+                    average_evaluation: 0.,
+                    bound,
+                    is_pruned: false,
+                    n_truncated: self.search_stats.n_truncated,
+                }
+            },
+        }
+    }
+
+    fn get_merged_rolls(&self, rolls: &[(Die, f32)], depth: usize, objective_function: &Objective) -> Vec<Option<usize>> {
+        // For each of the rolls at a node, the earlier roll it's scored along with, if the config merges rolls.  Only
+        // where the search stops under an objective that doesn't look at the die do the rolls' positions score the
+        // same, since each roll places a different die; there, the first roll is scored for all of them.
+        if !self.config.get_merge_rolls() || depth > 0 || !objective_function.ignores_roll() {
+            return vec![None; rolls.len()];
+        }
+        return (0..rolls.len()).map(|index| if index == 0 { None } else { Some(0) }).collect();
+    }
+
+    fn get_move_ordering(&self, objective_function: &Objective) -> MoveOrdering {
+        // Ordering only pays off through the cutoffs, which need to know how low an evaluation can go, so it's only
+        // for the objectives scoring wins, draws and losses.
+        if Self::is_win_probability_objective(objective_function) {
+            return self.config.get_move_ordering();
+        }
+        return MoveOrdering::None;
+    }
+
+    fn get_ordered_moves(&self, node: &Node, objective_function: &Objective, move_ordering: MoveOrdering) -> Vec<Move> {
+        // The moves up to row symmetry, best first for the player to act by the config's heuristic unless they're
+        // unordered, with equally good moves in row-major order.  Unordered moves still go best first by the search's
+        // objective with ordered expansion, as the children of a built tree would.
+        let moves = node.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.");
+        let ordering_objective = match move_ordering {
+            MoveOrdering::None if self.ordered_expansion => objective_function,
+            MoveOrdering::None => return moves,
+            MoveOrdering::Static | MoveOrdering::ShallowSearch => self.config.get_objective(),
+        };
+        let player = node.get_active_player();
+        let mut scored_moves = moves.into_iter()
+            .map(|m| (ordering_objective.evaluate(&node.with_move_made(m).expect("Won't error because we know the move is legal.")), m))
+            .collect::<Vec<(f32, Move)>>();
+        scored_moves.sort_by(|(a, _), (b, _)| player.order_best_first(*a, *b));
+        return scored_moves.into_iter().map(|(_, m)| m).collect();
+    }
+
+    fn order_by_shallow_search(&mut self, frame: &mut SearchFrame, objective_function: &Objective, control: &mut SearchControl) -> Option<()> {
+        // Puts the moves of the frame a search starts from best first by searching each of them SHALLOW_ORDERING_DEPTH
+        // moves deep, with equally good moves left in the order the heuristic put them.  None if the deadline passes
+        // or the search is cancelled first.
+        if let SearchFrame::Move { node, extensions, moves, .. } = frame {
+            let mut scored_moves = Vec::new();
+            for m in moves.iter() {
+                let child = node.with_move_made(*m).expect("Won't error because we know the moves are legal.");
+                self.search_stats.n_nodes += 1;
+                let evaluation = if child.is_terminal() {
+                    self.search_stats.n_leaves += 1;
+                    Self::evaluate_for_active_player(objective_function, &child)
+                } else {
+                    let (child_depth, child_extensions) = self.get_child_depth(&child, SHALLOW_ORDERING_DEPTH, *extensions);
+                    self.search_from(&child, child_depth, child_extensions, objective_function, control)?.0
+                };
+                // For the player to act, who moves before the child's evaluation is for.
+                scored_moves.push((-evaluation, *m));
+            }
+            scored_moves.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+            *moves = scored_moves.into_iter().map(|(_, m)| m).collect();
+        }
+        return Some(());
+    }
+
+    fn get_roll_shares(&self, node: &Node) -> Vec<(Die, f32)> {
+        // The rolls the config's chance expansion has the search average over at node, counting plies from the root.
+        let dice = self.config.get_chance_expansion().get_dice(node, node.get_ply().saturating_sub(self.root.get_ply()));
+        return SearchFrame::get_roll_shares(node, &dice);
+    }
+
+    fn mark_if_sampled(&self, evaluation: Evaluation, depth: usize) -> Evaluation {
+        // Tags the evaluation of a search depth moves deep as sampled if it would have left out any rolls, including
+        // on lines it could have been extended down.
+        let max_extensions = if self.config.get_extension_threshold().is_some() { self.config.get_max_extensions() } else { 0 };
+        if self.config.get_chance_expansion().samples_within(depth + max_extensions) {
+            return evaluation.sampled();
+        }
+        return evaluation;
+    }
+
+    fn mark_if_truncated(&self, evaluation: Evaluation, n_truncated: usize) -> Evaluation {
+        // Tags the evaluation as truncated if brute force's depth ran out on any game since the search's count of
+        // them was n_truncated.
+        if self.search_stats.n_truncated > n_truncated {
+            return evaluation.truncated();
+        }
+        return evaluation;
+    }
+
+    pub fn get_max_elimination_threat(node: &Node) -> u16 {
+        // The most points the player about to roll could take from the other board with their next die, whatever
+        // they roll.
+        if node.is_terminal() {
+            return 0;
+        }
+        let player = node.get_active_player();
+        let board = node.get_player_board(player);
+        let opponent_board = node.get_player_board(player.opponent());
+        return (0..3)
+            .filter(|column| board.get_n_empty_squares_in_column(*column) > 0)
+            .flat_map(|column| Die::all().into_iter().map(move |die| (die, column)))
+            .map(|(die, column)| opponent_board.column_threat(die, column))
+            .max()
+            .unwrap_or(0);
     }
 
-    fn get_evaluation_tree_heuristic(&mut self, depth: usize, objective_function: fn(&Node) -> f32) -> Result<(Option<Node>, Evaluation), String> {
-        self.root.build_n_moves_up_to_symmetry(depth);
-        self.root.get_evaluation_tree(objective_function)
+    fn get_best_so_far(root_frame: &SearchFrame, objective_function: &Objective, tie_break_epsilon: f32) -> Option<(Vec<Move>, Evaluation)> {
+        // The best of the root's moves searched so far, up to row symmetry, or None before the first is done.
+        match root_frame {
+            SearchFrame::Move { node, moves, evaluations, .. } if !evaluations.is_empty() => {
+                let (evaluation, best_moves) = Self::get_best_evaluation_and_moves(moves[..evaluations.len()].to_vec(), evaluations, tie_break_epsilon);
+                return Some((best_moves, Self::to_evaluation(objective_function, node.get_active_player().get_sign() * evaluation).ok()?));
+            },
+            _ => return None,
+        }
+    }
+
+    fn get_best_evaluation_and_moves(moves: Vec<Move>, evaluations: &[f32], tie_break_epsilon: f32) -> (f32, Vec<Move>) {
+        // The biggest of the evaluations, which are for the player choosing between the moves, and every move whose
+        // evaluation is tied with it, to within rounding or tie_break_epsilon.
+        let best_evaluation = evaluations.iter().copied().fold(f32::NEG_INFINITY, max_keeping_nan);
+        let best_moves = moves.into_iter().zip(evaluations)
+            .filter(|(_, evaluation)| are_tied_within(**evaluation, best_evaluation, tie_break_epsilon))
+            .map(|(m, _)| m)
+            .collect();
+        return (best_evaluation, best_moves);
+    }
+
+    fn breaks_ties_by_margin(&self, objective_function: &Objective) -> bool {
+        // Only brute force has ties between results that mean the same thing, since a win is a win however big.  Nor
+        // are there exact ties to break once the rolls are sampled.
+        return self.config.get_break_ties_by_margin()
+            && Self::is_win_probability_objective(objective_function)
+            && !self.config.get_chance_expansion().samples_within(self.config.get_brute_force_depth());
+    }
+
+    fn break_ties_by_margin(&self, best_moves: Vec<Move>, evaluation: Evaluation, depth: usize) -> (Vec<Move>, Evaluation) {
+        // Of the moves tied for the best expected result, the ones with the biggest expected margin for the player to
+        // act.  The result itself is left as the search found it.
+        let mut memo = HashMap::new();
+        let margins = best_moves.iter()
+            .map(|m| -Self::get_result_and_margin(&self.root.with_move_made(*m).expect("Won't error because we know the moves are legal."), depth - 1, self.get_draw_value(), &mut memo).1)
+            .collect::<Vec<f32>>();
+        let best_margin = margins.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let best_moves = best_moves.into_iter().zip(margins)
+            .filter(|(_, margin)| are_tied(*margin, best_margin))
+            .map(|(m, _)| m)
+            .collect();
+        let margin = self.root.get_active_player().get_sign() * best_margin;
+        return (best_moves, Self::with_margin(evaluation, margin));
+    }
+
+    fn rank_ties_by_margin(&self, ranked_moves: Vec<(Move, Evaluation)>, depth: usize) -> Vec<(Move, Evaluation)> {
+        // Each move gets its expected margin, and moves tied on expected result are put in order of it, best first.
+        let player = self.root.get_active_player();
+        let mut memo = HashMap::new();
+        let mut with_margins = ranked_moves.into_iter()
             .map(
-                |(maybe_tree, evaluation)|
-                (maybe_tree, Evaluation::new(evaluation))
-            )
+                |(m, evaluation)| {
+                    let child = self.root.with_move_made(m).expect("Won't error because we know the moves are legal.");
+                    // From the perspective of the opponent, who acts next, back to Player 1's.
+                    let margin = child.get_active_player().get_sign() * Self::get_result_and_margin(&child, depth - 1, self.get_draw_value(), &mut memo).1;
+                    (m, Self::with_margin(evaluation, margin))
+                }
+            ).collect::<Vec<(Move, Evaluation)>>();
+        let mut start = 0;
+        while start < with_margins.len() {
+            let result = with_margins[start].1.get_evaluation();
+            let end = start + with_margins[start..].iter().take_while(|(_, evaluation)| are_tied(evaluation.get_evaluation(), result)).count();
+            with_margins[start..end].sort_by(
+                |(_, a), (_, b)| player.order_best_first(
+                    a.get_margin().expect("Guaranteed to have a margin."),
+                    b.get_margin().expect("Guaranteed to have a margin."),
+                )
+            );
+            start = end;
+        }
+        return with_margins;
     }
 
-    fn get_evaluation_tree_hybrid(&mut self, max_moves_left_before_brute_force: usize, depth: usize, objective_function: fn(&Node) -> f32) -> Result<(Option<Node>, Evaluation), String> {
-        if self.root.get_moves_left_ignoring_elimination() <= max_moves_left_before_brute_force {
-            self.get_evaluation_tree_brute_force()
+    fn get_result_and_margin(node: &Node, depth: usize, draw_weight: f32, memo: &mut HashMap<(u64, usize), (f32, f32)>) -> (f32, f32) {
+        // The expected result brute force gives the node searched depth moves ahead, and the expected score difference
+        // when each player picks the move with the biggest margin from among the ones with the best result, both for
+        // the player to act.  Like brute force, unfinished games are scored as they stand once the depth runs out.
+        let sign = node.get_active_player().get_sign();
+        if node.is_terminal() || (depth == 0 && matches!(node.get_node_type(), NodeType::Move(_, _))) {
+            let result = Evaluation::from_outcome(node.get_outcome_by_score(), draw_weight).expect("Outcome by score is never in progress.").get_evaluation();
+            return (sign * result, sign * node.get_score_difference() as f32);
+        }
+        if let Some(result_and_margin) = memo.get(&(node.position_key(), depth)) {
+            return *result_and_margin;
+        }
+        let result_and_margin = match node.get_node_type() {
+            NodeType::Roll(_) => SearchFrame::get_roll_shares(node, &Die::all()).into_iter()
+                .map(
+                    |(die, share)| {
+                        let child = node.with_roll(die).expect("Won't error because the game isn't over.");
+                        let (result, margin) = Self::get_result_and_margin(&child, depth, draw_weight, memo);
+                        (result * share, margin * share)
+                    }
+                ).fold((0., 0.), |(result, margin), (child_result, child_margin)| (result + child_result, margin + child_margin)),
+            NodeType::Move(_, _) => {
+                // A move hands the turn to the opponent, so the child's values are negated.
+                let mut best = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+                for m in node.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.") {
+                    let child = node.with_move_made(m).expect("Won't error because we know the moves are legal.");
+                    let (child_result, child_margin) = Self::get_result_and_margin(&child, depth - 1, draw_weight, memo);
+                    let (result, margin) = (-child_result, -child_margin);
+                    if (are_tied(result, best.0) && margin > best.1) || (!are_tied(result, best.0) && result > best.0) {
+                        best = (result, margin);
+                    }
+                }
+                best
+            },
+        };
+        memo.insert((node.position_key(), depth), result_and_margin);
+        return result_and_margin;
+    }
+
+    pub fn solve_exact_memoized(&mut self) -> Result<(Vec<Move>, Evaluation, MemoizedSolveStats), SolverError> {
+        // The same best moves and evaluation as brute force, found by recursing over positions with both boards'
+        // rows sorted and their columns put in canonical order, so every line of play that reaches the same late-game
+        // position up to symmetry shares one evaluation of it.  Every roll is always expanded, since the sampled rolls
+        // depend on the order of the columns.  Neither the transposition table nor the endgame table is used.
+        self.search_stats = SearchStats::empty();
+        self.check_can_search(&SolverMode::BruteForce)?;
+        if self.config.get_chance_expansion().samples_within(self.config.get_brute_force_depth()) {
+            return Err(SolverError::InvalidMode("The memoized solver expands every roll, so it can't sample them.".to_string()));
+        }
+        let start = Instant::now();
+        let objective_function = self.exact_objective();
+        let root = self.root.clone_without_children();
+        let mut memo = HashMap::new();
+        let mut stats = MemoizedSolveStats { n_unique_states: 0, n_expansions: 0 };
+        let moves = root.get_legal_moves_up_to_row_symmetry()?;
+        let mut is_truncated = false;
+        let evaluations = moves.iter()
+            .map(
+                |m| {
+                    let child = root.with_move_made(*m).expect("Won't error because we know the moves are legal.");
+                    let (evaluation, is_child_truncated) = self.get_memoized_child_evaluation(&child, self.config.get_brute_force_depth(), 0, &objective_function, &mut memo, &mut stats);
+                    is_truncated |= is_child_truncated;
+                    -evaluation
+                }
+            )
+            .collect::<Vec<f32>>();
+        let (evaluation, root_best_moves) = Self::get_best_evaluation_and_moves(moves, &evaluations, self.config.get_tie_break_epsilon());
+        stats.n_unique_states = memo.len();
+        let evaluation = Self::to_evaluation(&objective_function, root.get_active_player().get_sign() * evaluation)?;
+        let evaluation = if is_truncated { evaluation.truncated() } else { evaluation };
+        let best_moves = Self::in_canonical_order(Self::with_row_symmetric_moves(&root, root_best_moves)?);
+        let (best_moves, evaluation) = if self.breaks_ties_by_margin(&objective_function) {
+            self.break_ties_by_margin(best_moves, evaluation, self.config.get_brute_force_depth())
         } else {
-            self.get_evaluation_tree_heuristic(depth, objective_function)
+            (best_moves, evaluation)
+        };
+        self.search_stats.depth = self.config.get_brute_force_depth();
+        self.search_stats.n_nodes = stats.n_expansions;
+        self.search_stats.elapsed = start.elapsed();
+        return Ok((best_moves, evaluation, stats));
+    }
+
+    fn get_memoized_child_evaluation(
+        &self,
+        child: &Node,
+        depth: usize,
+        extensions: usize,
+        objective_function: &Objective,
+        memo: &mut HashMap<(u64, usize, usize), (f32, bool)>,
+        stats: &mut MemoizedSolveStats,
+    ) -> (f32, bool) {
+        // The evaluation of the node a move leads to, for the player to act there, as brute force would search it
+        // from a Move node with depth moves left, and whether the depth ran out on any game before it finished.
+        if child.is_terminal() {
+            return (Self::evaluate_for_active_player(objective_function, child), false);
+        }
+        let (child_depth, child_extensions) = self.get_child_depth(child, depth, extensions);
+        return self.get_memoized_evaluation(child, child_depth, child_extensions, objective_function, memo, stats);
+    }
+
+    fn get_memoized_evaluation(
+        &self,
+        node: &Node,
+        depth: usize,
+        extensions: usize,
+        objective_function: &Objective,
+        memo: &mut HashMap<(u64, usize, usize), (f32, bool)>,
+        stats: &mut MemoizedSolveStats,
+    ) -> (f32, bool) {
+        // The evaluation of an unfinished node for the player to act, with depth moves left to search, and whether
+        // it was truncated.  Symmetric
+        // positions take their maximums over the same evaluations and their averages in the same die order, so
+        // sharing one evaluation between them gives exactly what brute force would.
+        stats.n_expansions += 1;
+        let key = (node.canonicalize_columns().symmetric_position_key(), depth, extensions);
+        if let Some(evaluation_and_truncation) = memo.get(&key) {
+            return *evaluation_and_truncation;
+        }
+        let evaluation_and_truncation = match node.get_node_type() {
+            NodeType::Roll(_) => {
+                let mut average_evaluation = 0.;
+                let mut is_truncated = false;
+                for (die, share) in self.get_roll_shares(node) {
+                    let child = node.with_roll(die).expect("Won't error because the game isn't over.");
+                    // Rolls follow moves, so the game's still going when the depth runs out here.
+                    let (evaluation, is_child_truncated) = if depth == 0 {
+                        (Self::evaluate_for_active_player(objective_function, &child), true)
+                    } else {
+                        self.get_memoized_evaluation(&child, depth, extensions, objective_function, memo, stats)
+                    };
+                    average_evaluation += evaluation * share;
+                    is_truncated |= is_child_truncated;
+                }
+                (average_evaluation, is_truncated)
+            },
+            NodeType::Move(_, _) => node.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.").into_iter()
+                // A move hands the turn to the opponent, so the child's evaluation is negated.
+                .map(
+                    |m| {
+                        let child = node.with_move_made(m).expect("Won't error because we know the moves are legal.");
+                        let (evaluation, is_truncated) = self.get_memoized_child_evaluation(&child, depth, extensions, objective_function, memo, stats);
+                        (-evaluation, is_truncated)
+                    }
+                )
+                .fold((f32::NEG_INFINITY, false), |(best, is_truncated), (evaluation, is_child_truncated)| (max_keeping_nan(best, evaluation), is_truncated || is_child_truncated)),
+        };
+        memo.insert(key, evaluation_and_truncation);
+        return evaluation_and_truncation;
+    }
+
+    fn look_up(&mut self, node: &Node, depth: usize, extensions: usize, objective_function: &Objective, bound: Option<f32>) -> Option<&TranspositionEntry> {
+        // An entry searched as deep, under the same objective, stands in for searching the node again, as long as it's
+        // exact or enough to tell the evaluation is above bound.
+        if !self.use_transposition_table {
+            return None;
+        }
+        match self.transposition_table.get(&node.position_key()) {
+            Some(entry) if entry.covers(objective_function, depth, extensions, bound) => {
+                self.search_stats.n_hits += 1;
+                // The hit stands in for a search, along with any games its depth ran out on.
+                if entry.is_truncated {
+                    self.search_stats.n_truncated += 1;
+                }
+                Some(entry)
+            },
+            _ => {
+                self.search_stats.n_misses += 1;
+                None
+            },
+        }
+    }
+
+    fn store(&mut self, node: &Node, entry: TranspositionEntry) {
+        if !self.use_transposition_table {
+            return;
+        }
+        self.insert_entry(node.position_key(), entry);
+    }
+
+    fn insert_entry(&mut self, key: u64, entry: TranspositionEntry) {
+        // An exact search of the same position is worth more than a newer lower bound.  Once the table's full, only the
+        // positions already in it are updated.
+        match self.transposition_table.get(&key) {
+            Some(existing) if existing.is_worth_more_than(&entry) => {},
+            None if self.config.get_transposition_table_size().is_some_and(|size| self.transposition_table.len() >= size) => {},
+            _ => {
+                self.transposition_table.insert(key, entry);
+            },
+        }
+    }
+
+    pub fn heuristic_accuracy_report(heuristic: &Objective, depth: usize, positions: &[Node]) -> Result<HeuristicAccuracyReport, SolverError> {
+        // How a heuristic searched to depth does against brute force on each position where brute force can see to
+        // the end of the game, barring eliminations: whether its move gives up any of the brute-force value, and how
+        // far off its evaluation is once both are read as Player 1's expected result.  Each position gets fresh
+        // solvers, so neither search borrows from the other's transposition table.
+        let mut position_accuracies = vec![];
+        let mut n_skipped = 0;
+        for position in positions {
+            if !matches!(position.status(), NodeStatus::Move(_, _)) || position.get_moves_left_ignoring_elimination() > DEFAULT_BRUTE_FORCE_DEPTH {
+                n_skipped += 1;
+                continue;
+            }
+            let ranked_moves = Solver::from_root(position.clone_without_children()).get_all_moves_ranked(SolverMode::BruteForce)?;
+            let (_, best_evaluation) = *ranked_moves.first().expect("A game that isn't over has a legal move.");
+            let best_moves = ranked_moves.iter()
+                .filter(|(_, evaluation)| are_tied(evaluation.get_evaluation(), best_evaluation.get_evaluation()))
+                .map(|(m, _)| *m)
+                .collect();
+            let (heuristic_moves, heuristic_evaluation) = Solver::from_root(position.clone_without_children())
+                .get_best_moves_and_evaluation(SolverMode::Heuristic((depth, heuristic.clone())))?;
+            let heuristic_move = *heuristic_moves.first().expect("A game that isn't over has a best move.");
+            let (_, heuristic_move_evaluation) = *ranked_moves.iter()
+                .find(|(m, _)| *m == heuristic_move)
+                .ok_or(SolverError::IllegalMove(heuristic_move))?;
+            let as_expected_result = |evaluation: Evaluation| 2. * evaluation.as_win_probability(DEFAULT_WIN_PROBABILITY_SCALE) - 1.;
+            position_accuracies.push(
+                PositionAccuracy::new(
+                    position.clone_without_children(),
+                    heuristic_move,
+                    best_moves,
+                    heuristic_evaluation,
+                    best_evaluation,
+                    heuristic_move_evaluation,
+                    (as_expected_result(heuristic_evaluation) - as_expected_result(best_evaluation)).abs(),
+                )
+            );
+        }
+        return Ok(HeuristicAccuracyReport::new(position_accuracies, n_skipped));
+    }
+
+    pub fn get_search_stats(&self) -> SearchStats {
+        // What the last search took, from any of the calls that search or build the tree.
+        self.search_stats
+    }
+
+    pub fn get_all_moves_ranked(&mut self, solver_mode: SolverMode) -> Result<Vec<(Move, Evaluation)>, SolverError> {
+        // Every legal move with its evaluation, best first for the player to act, with ties in row-major order.  The
+        // moves are searched with one transposition table, so positions they have in common are only searched once.
+        let start = Instant::now();
+        let result = self.rank_all_moves(solver_mode);
+        self.search_stats.elapsed = start.elapsed();
+        return result;
+    }
+
+    pub fn evaluate_played_move(&mut self, position: &Node, played_move: Move, solver_mode: SolverMode) -> Result<MoveAssessment, SolverError> {
+        // How much played_move cost the player who made it at position, from one ranking of every move.  The position
+        // is ranked as the root, sharing the transposition table, and the root is put back afterwards.
+        let player = position.get_active_player();
+        let mut position = position.clone_without_children();
+        if let Some(die_distribution) = self.config.get_die_distribution() {
+            position.set_die_distribution(die_distribution);
+        }
+        let root = std::mem::replace(&mut self.root, position);
+        let result = self.get_all_moves_ranked(solver_mode);
+        self.root = root;
+        let ranked_moves = result?;
+        let (_, best_evaluation) = *ranked_moves.first().ok_or(SolverError::Internal("There are no legal moves to rank.".to_string()))?;
+        let (_, played_evaluation) = *ranked_moves.iter().find(|(m, _)| *m == played_move).ok_or(SolverError::IllegalMove(played_move))?;
+        return Ok(MoveAssessment::new(player, played_evaluation, best_evaluation, &self.config));
+    }
+
+    fn rank_all_moves(&mut self, solver_mode: SolverMode) -> Result<Vec<(Move, Evaluation)>, SolverError> {
+        self.search_stats = SearchStats::empty();
+        self.check_can_search(&solver_mode)?;
+        let objective_function = self.get_objective_function(&solver_mode);
+        let depth = match solver_mode {
+            SolverMode::BruteForce => self.config.get_brute_force_depth(),
+            SolverMode::Heuristic((depth, _)) => depth,
+            SolverMode::Hybrid(max_moves_left_before_brute_force, (depth, _)) => {
+                if self.should_brute_force(max_moves_left_before_brute_force) {
+                    self.config.get_brute_force_depth()
+                } else {
+                    depth
+                }
+            },
+            SolverMode::Adaptive(max_moves_left_before_brute_force, (max_nodes, f)) => {
+                let depth = self.get_adaptive_depth(max_nodes);
+                return self.rank_all_moves(SolverMode::Hybrid(max_moves_left_before_brute_force, (depth, f)));
+            },
+            SolverMode::Scheduled((schedule, f)) => return self.rank_all_moves(self.get_scheduled_mode(&schedule, f)),
+            SolverMode::NodeBudget(_) => {
+                self.get_evaluation_tree(solver_mode)?;
+                return self.root.get_move_evaluations(&objective_function)?.into_iter()
+                    .map(|(m, evaluation)| Ok((m, Self::to_evaluation(&objective_function, evaluation)?)))
+                    .collect();
+            },
+            SolverMode::MonteCarlo((playouts_per_move, seed)) => {
+                let move_evaluations = self.get_monte_carlo_move_evaluations(playouts_per_move, seed)?;
+                return Ok(
+                    Self::rank_legal_moves(&self.root, &move_evaluations).into_iter()
+                        .map(|(m, evaluation)| (m, Self::to_expected_result(evaluation)))
+                        .collect()
+                );
+            },
+            SolverMode::Mcts((iterations, exploration_c, seed)) => {
+                // Ranks the moves by their mean playout results, which needs every move tried at least once.
+                let tree = self.run_mcts(iterations, exploration_c, seed)?;
+                if !tree.has_tried_every_root_move() {
+                    return Err(SolverError::InvalidMode(format!("{} iterations are too few to try every move.", iterations)));
+                }
+                return Ok(
+                    Self::rank_legal_moves(&self.root, &tree.get_root_move_values()).into_iter()
+                        .map(|(m, evaluation)| (m, Self::to_expected_result(evaluation)))
+                        .collect()
+                );
+            },
+            SolverMode::Timed((budget, f)) => {
+                // Ranks the moves as deep as there was time to search the best one.
+                self.search_timed(budget, &f, &mut SearchControl::unlimited())?;
+                self.search_stats.depth
+            },
+        };
+        let ranked_moves = self.rank_moves(depth.max(1), &objective_function)?;
+        if self.breaks_ties_by_margin(&objective_function) {
+            return Ok(self.rank_ties_by_margin(ranked_moves, depth.max(1)));
+        }
+        return Ok(ranked_moves);
+    }
+
+    pub fn get_evaluation_by_roll(&mut self, solver_mode: SolverMode) -> Result<Vec<(Die, Evaluation, Vec<Move>)>, SolverError> {
+        // For a root waiting on a roll, each die it could roll with the evaluation and best moves after it, in die
+        // order.  The rolls are searched one after another as the root, so they share the transposition table.
+        match self.root.status() {
+            NodeStatus::Terminal(_) => return Err(SolverError::GameAlreadyOver),
+            NodeStatus::Move(_, _) => return Err(SolverError::WrongNodeType { expected: NodeKind::Roll, found: NodeKind::Move }),
+            NodeStatus::Roll(_) => {},
+        }
+        let roll_root = std::mem::replace(&mut self.root, Node::empty());
+        let mut evaluations = Vec::new();
+        let mut result = Ok(());
+        for (die, _) in roll_root.get_roll_probabilities() {
+            self.root = roll_root.with_roll(die).expect("Guaranteed to be a roll node.");
+            match self.get_best_moves_and_evaluation(solver_mode.clone()) {
+                Ok((best_moves, evaluation)) => evaluations.push((die, evaluation, best_moves)),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                },
+            }
+        }
+        self.root = roll_root;
+        return result.map(|_| evaluations);
+    }
+
+    fn rank_moves(&mut self, depth: usize, objective_function: &Objective) -> Result<Vec<(Move, Evaluation)>, SolverError> {
+        let root = self.root.clone_without_children();
+        let mut move_evaluations = Vec::new();
+        // The positions after the moves whose searches brute force's depth ran out on a game in.
+        let mut truncated_keys = Vec::new();
+        for m in root.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.") {
+            let child = root.with_move_made(m).expect("Won't error because we know the moves are legal.");
+            let n_truncated = self.search_stats.n_truncated;
+            self.search_stats.n_nodes += 1;
+            let evaluation = if child.is_terminal() {
+                self.search_stats.n_leaves += 1;
+                objective_function.evaluate(&child)
+            } else {
+                let (child_depth, child_extensions) = self.get_child_depth(&child, depth, 0);
+                let evaluation = match self.look_up(&child, child_depth, child_extensions, objective_function, None) {
+                    Some(entry) => entry.evaluation,
+                    None => self.search_from(&child, child_depth, child_extensions, objective_function, &mut SearchControl::unlimited())
+                        .expect("Guaranteed to finish without a deadline.").0,
+                };
+                // From the perspective of the opponent, who acts next, back to Player 1's.
+                child.get_active_player().get_sign() * evaluation
+            };
+            move_evaluations.push((m, evaluation));
+            if self.search_stats.n_truncated > n_truncated {
+                truncated_keys.push(child.symmetric_position_key());
+            }
+        }
+        self.search_stats.depth = depth;
+        // Moves skipped by row symmetry are truncated if the move they're equivalent to is.
+        return Self::rank_legal_moves(&root, &move_evaluations).into_iter()
+            .map(
+                |(m, evaluation)| {
+                    let evaluation = self.mark_if_sampled(Self::to_evaluation(objective_function, evaluation)?, depth);
+                    let key = root.with_move_made(m).expect("Won't error because we know the moves are legal.").symmetric_position_key();
+                    match truncated_keys.contains(&key) {
+                        true => Ok((m, evaluation.truncated())),
+                        false => Ok((m, evaluation)),
+                    }
+                }
+            ).collect();
+    }
+
+    fn rank_legal_moves(root: &Node, move_evaluations: &[(Move, f32)]) -> Vec<(Move, f32)> {
+        // Every legal move, best first, given the evaluations of the moves up to row symmetry.  Moves skipped by row
+        // symmetry get the value of the move they're equivalent to.
+        let player = root.get_active_player();
+        let evaluations_by_key = move_evaluations.iter()
+            .map(|(m, evaluation)| (root.with_move_made(*m).expect("Won't error because we know the moves are legal.").symmetric_position_key(), *evaluation))
+            .collect::<Vec<(u64, f32)>>();
+        let mut ranked_moves = root.get_legal_moves().expect("Guaranteed to be a move node.").into_iter()
+            .map(
+                |m| {
+                    let key = root.with_move_made(m).expect("Won't error because we know the moves are legal.").symmetric_position_key();
+                    let (_, evaluation) = evaluations_by_key.iter().find(|(child_key, _)| *child_key == key)
+                        .expect("Guaranteed to be equivalent to one of the moves up to row symmetry.");
+                    (m, *evaluation)
+                }
+            ).collect::<Vec<(Move, f32)>>();
+        ranked_moves.sort_by(|(_, a), (_, b)| player.order_best_first(*a, *b));
+        return ranked_moves;
+    }
+
+    fn get_monte_carlo_move_evaluations(&mut self, playouts_per_move: usize, seed: Option<u64>) -> Result<Vec<(Move, f32)>, SolverError> {
+        // The mean result of playouts_per_move random games after each move up to row symmetry, with random rolls and
+        // random legal replies.  Rolls are fair whatever the root's die distribution.  The moves are played out in
+        // order from one generator, so a seed gives the same results every time.
+        let root = self.root.clone_without_children();
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut move_evaluations = Vec::new();
+        for m in root.get_legal_moves_up_to_row_symmetry()? {
+            let child = root.with_move_made(m).expect("Won't error because we know the moves are legal.");
+            let total = (0..playouts_per_move)
+                .map(
+                    |_| Evaluation::from_outcome(child.random_playout(&mut rng), DEFAULT_DRAW_WEIGHT)
+                        .expect("Guaranteed to be a finished game.")
+                        .get_evaluation()
+                ).sum::<f32>();
+            self.search_stats.n_nodes += playouts_per_move;
+            self.search_stats.n_leaves += playouts_per_move;
+            move_evaluations.push((m, total / playouts_per_move as f32));
+        }
+        return Ok(move_evaluations);
+    }
+
+    pub fn get_mcts_visit_counts(&mut self, iterations: usize, exploration_c: f32, seed: Option<u64>) -> Result<Vec<(Move, usize)>, SolverError> {
+        // How often MCTS visited each move up to row symmetry, for seeing why it picked what it did.
+        self.check_can_search(&SolverMode::Mcts((iterations, exploration_c, seed)))?;
+        return Ok(self.run_mcts(iterations, exploration_c, seed)?.get_root_visit_counts());
+    }
+
+    fn run_mcts(&mut self, iterations: usize, exploration_c: f32, seed: Option<u64>) -> Result<MctsTree, SolverError> {
+        let mut tree = MctsTree::new(&self.root, exploration_c, seed)?;
+        tree.run(iterations);
+        self.search_stats.n_nodes += tree.get_n_nodes();
+        // Each iteration plays one game out.
+        self.search_stats.n_leaves += iterations;
+        return Ok(tree);
+    }
+
+    fn search_mcts(&mut self, iterations: usize, exploration_c: f32, seed: Option<u64>) -> Result<(Vec<Move>, Evaluation), SolverError> {
+        let tree = self.run_mcts(iterations, exploration_c, seed)?;
+        let most_visited_moves = tree.get_most_visited_moves();
+        let best_moves = self.root.get_legal_moves_up_to_row_symmetry()?.into_iter()
+            .filter(|m| most_visited_moves.contains(m))
+            .collect();
+        return Ok((best_moves, Self::to_expected_result(tree.get_root_value())));
+    }
+
+    fn search_monte_carlo(&mut self, playouts_per_move: usize, seed: Option<u64>) -> Result<(Vec<Move>, Evaluation), SolverError> {
+        let move_evaluations = self.get_monte_carlo_move_evaluations(playouts_per_move, seed)?;
+        let sign = self.root.get_active_player().get_sign();
+        let (moves, evaluations): (Vec<Move>, Vec<f32>) = move_evaluations.into_iter().map(|(m, evaluation)| (m, sign * evaluation)).unzip();
+        let (evaluation, best_moves) = Self::get_best_evaluation_and_moves(moves, &evaluations, self.config.get_tie_break_epsilon());
+        return Ok((best_moves, Self::to_expected_result(sign * evaluation)));
+    }
+
+    pub fn get_outcome_distribution(&mut self, solver_mode: SolverMode) -> Result<OutcomeDistribution, SolverError> {
+        // How likely each final score difference is with best play, which only brute force can say, since a
+        // heuristic's leaves don't know how the game ends.  Lines brute force stops before the end of, which takes
+        // eliminations, count the score difference as it stands there.
+        self.check_can_search(&solver_mode)?;
+        let brute_forces = match &solver_mode {
+            SolverMode::BruteForce => true,
+            SolverMode::Hybrid(max_moves_left_before_brute_force, _) | SolverMode::Adaptive(max_moves_left_before_brute_force, _) => {
+                self.should_brute_force(*max_moves_left_before_brute_force)
+            },
+            SolverMode::Scheduled((schedule, _)) => schedule.get_depth(self.root.get_n_empty_squares()) == ScheduledDepth::BruteForce,
+            _ => false,
+        };
+        if !brute_forces {
+            return Err(SolverError::InvalidMode("Only brute force knows how the game ends, so the position has to be close enough to the end to brute force.".to_string()));
+        }
+        let objective_function = self.exact_objective();
+        self.build_n_moves(self.config.get_brute_force_depth(), &objective_function);
+        return Ok(self.get_node_outcome_distribution(&self.root, &objective_function));
+    }
+
+    fn get_node_outcome_distribution(&self, node: &Node, objective_function: &Objective) -> OutcomeDistribution {
+        // Chance nodes mix their rolls' distributions.  Move nodes take the best move's, which with margin tie breaks
+        // is the one among those tied for best expected to win by the most.
+        if node.is_leaf() {
+            return OutcomeDistribution::certain(node.get_score_difference());
+        }
+        match node.get_node_type() {
+            NodeType::Roll(_) => {
+                let weighted = node.get_children_with_rolls().expect("Guaranteed to be a roll node.").into_iter()
+                    .map(|(die, child)| (self.get_node_outcome_distribution(child, objective_function), node.get_die_distribution().get_probability(die)))
+                    .collect::<Vec<(OutcomeDistribution, f32)>>();
+                return OutcomeDistribution::mix(&weighted);
+            },
+            NodeType::Move(player, _) => {
+                let evaluations = node.get_children().iter().map(|child| child.get_evaluation(objective_function)).collect::<Vec<f32>>();
+                let best_evaluation = evaluations.iter().copied()
+                    .max_by(|a, b| compare_totally(player.get_sign() * a, player.get_sign() * b))
+                    .expect("Move node isn't a leaf, so it has children.");
+                let mut best_children = node.get_children().iter().zip(evaluations)
+                    .filter(|(_, evaluation)| are_tied(*evaluation, best_evaluation))
+                    .map(|(child, _)| child);
+                if !self.config.get_break_ties_by_margin() {
+                    let best_child = best_children.next().expect("The best evaluation comes from one of the children.");
+                    return self.get_node_outcome_distribution(best_child, objective_function);
+                }
+                return best_children
+                    .map(|child| self.get_node_outcome_distribution(child, objective_function))
+                    .reduce(
+                        |best, distribution| match compare_totally(player.get_sign() * distribution.get_expected_margin(), player.get_sign() * best.get_expected_margin()) {
+                            Ordering::Greater => distribution,
+                            _ => best,
+                        }
+                    ).expect("The best evaluation comes from one of the children.");
+            },
+        }
+    }
+
+    pub fn get_principal_variation(&mut self, solver_mode: SolverMode, max_plies: usize) -> Result<Vec<PvStep>, SolverError> {
+        // The expected continuation from the evaluation tree, for at most max_plies moves.
+        let objective_function = self.get_objective_function(&solver_mode);
+        self.get_evaluation_tree(solver_mode).map(
+            |(maybe_tree, _)|
+            maybe_tree.map_or(vec![], |tree| tree.get_principal_variation(&objective_function).into_iter().take(max_plies).collect())
+        )
+    }
+
+    pub fn get_top_k_lines(&mut self, k: usize, solver_mode: SolverMode, max_plies: usize) -> Result<Vec<(Move, Evaluation, Vec<PvStep>)>, SolverError> {
+        // The k best moves as get_all_moves_ranked orders them, best first with ties in row-major order, each with the
+        // line the evaluation tree expects to follow it, for at most max_plies moves counting the move itself.  All of
+        // them if there are fewer than k.  The line's first step has the evaluation of playing its move, which for the
+        // best move is the same as get_principal_variation's.
+        let ranked_moves = self.get_all_moves_ranked(solver_mode.clone())?;
+        let objective_function = self.get_objective_function(&solver_mode);
+        // The evaluation tree only keeps the best moves, so the lines come from the whole tree built for it.
+        self.get_evaluation_tree(solver_mode)?;
+        let root = self.root.clone_without_children();
+        let (player, die) = match root.get_node_type() {
+            NodeType::Move(player, die) => (player, die),
+            NodeType::Roll(_) => return Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }),
+        };
+        let mut lines = Vec::new();
+        for (m, evaluation) in ranked_moves.into_iter().take(k) {
+            let position = root.with_move_made(m).expect("Won't error because we know the moves are legal.");
+            // The tree may only have one of the moves equivalent up to row symmetry.
+            let child = self.root.get_children().iter()
+                .find(|child| child.symmetric_position_key() == position.symmetric_position_key())
+                .ok_or(SolverError::Internal(format!("The evaluation tree has no child for {}.", m.to_string())))?;
+            let mut line = vec![PvStep::new(player, die, m, child.get_evaluation(&objective_function))];
+            line.extend(Self::follow_line(position, child.get_principal_variation(&objective_function)));
+            line.truncate(max_plies);
+            lines.push((m, evaluation, line));
+        }
+        return Ok(lines);
+    }
+
+    fn follow_line(mut position: Node, principal_variation: Vec<PvStep>) -> Vec<PvStep> {
+        // The line from a position equivalent to the one the principal variation was found from, up to row symmetry.
+        // Every empty square in a column is as good as any other, so each move goes in the first empty square of its
+        // column.
+        let mut line = Vec::new();
+        for step in principal_variation {
+            if let NodeType::Roll(_) = position.get_node_type() {
+                position = position.with_roll(step.get_die()).expect("Won't error because the line goes on.");
+            }
+            let column = step.get_move().get_column();
+            let m = position.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.").into_iter()
+                .find(|m| m.get_column() == column)
+                .expect("Guaranteed to have an empty square in the column, as the equivalent position did.");
+            let followed_step = PvStep::new(step.get_player(), step.get_die(), m, step.get_evaluation());
+            line.push(if step.is_roll_assumed() { followed_step.with_roll_assumed() } else { followed_step });
+            position = position.with_move_made(m).expect("Won't error because we know the move is legal.");
+        }
+        return line;
+    }
+
+    pub fn get_objective_function(&self, solver_mode: &SolverMode) -> Objective {
+        // The function used to score the leaves of the evaluation tree in this mode.
+        match solver_mode {
+            SolverMode::BruteForce => self.exact_objective(),
+            SolverMode::Heuristic((_, f)) => f.clone(),
+            SolverMode::NodeBudget((_, f)) => f.clone(),
+            SolverMode::Timed((_, f)) => f.clone(),
+            // Playouts are scored by how they end, like brute force.
+            SolverMode::MonteCarlo(_) | SolverMode::Mcts(_) => Self::brute_force_objective(),
+            SolverMode::Hybrid(max_moves_left_before_brute_force, (_, f)) | SolverMode::Adaptive(max_moves_left_before_brute_force, (_, f)) => {
+                if self.should_brute_force(*max_moves_left_before_brute_force) {
+                    self.exact_objective()
+                } else {
+                    f.clone()
+                }
+            },
+            SolverMode::Scheduled((schedule, f)) => self.get_objective_function(&self.get_scheduled_mode(schedule, f.clone())),
+        }
+    }
+
+    fn brute_force_objective() -> Objective {
+        Self::win_probability_objective(DEFAULT_DRAW_WEIGHT)
+    }
+
+    fn win_probability_objective(draw_weight: f32) -> Objective {
+        // The same objective every time for each draw weight, so evaluations cached under it are found again.  A draw
+        // weight of -0 is the same as 0, which the default weight negates to with Player 2 to act.
+        let draw_weight = if draw_weight == 0. { 0. } else { draw_weight };
+        let mut objectives = Self::win_probability_objectives().lock().expect("Nothing panics while holding the lock.");
+        objectives.entry(draw_weight.to_bits()).or_insert_with(
+            || Objective::ignoring_roll_with_scores(
+                move |_, (player_1_score, player_2_score)| Evaluation::from_outcome(
+                    Outcome::from_scores(player_1_score, player_2_score), draw_weight
+                ).expect("Outcome by score is never in progress.")
+                .get_evaluation()
+            ).exact()
+        ).clone()
+    }
+
+    fn win_probability_objectives() -> &'static Mutex<HashMap<u32, Objective>> {
+        // By the bits of the draw weight.
+        static WIN_PROBABILITY_OBJECTIVES: OnceLock<Mutex<HashMap<u32, Objective>>> = OnceLock::new();
+        WIN_PROBABILITY_OBJECTIVES.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    fn is_win_probability_objective(objective_function: &Objective) -> bool {
+        // Under any draw weight.
+        let key = objective_function.get_key();
+        return Self::win_probability_objectives().lock().expect("Nothing panics while holding the lock.").values().any(|objective| objective.get_key() == key);
+    }
+
+    fn margin_objective() -> Objective {
+        // Brute force's objective when it's maximizing the expected margin, kept the same way.
+        static MARGIN_OBJECTIVE: OnceLock<Objective> = OnceLock::new();
+        MARGIN_OBJECTIVE.get_or_init(|| Objective::ignoring_roll_with_scores(|_, (player_1_score, player_2_score)| player_1_score as f32 - player_2_score as f32).exact()).clone()
+    }
+
+    fn exact_objective(&self) -> Objective {
+        // What brute force scores its leaves with under the config's objective.
+        match self.config.get_search_objective() {
+            SearchObjective::WinProbability => Self::win_probability_objective(self.get_draw_value()),
+            SearchObjective::ExpectedMargin => Self::margin_objective(),
+        }
+    }
+
+    fn get_draw_value(&self) -> f32 {
+        // What a draw is worth to Player 1.  The config's draw weight is for whoever's to act at the root, and their
+        // opponent plays against it, so the search stays zero sum.
+        self.root.get_active_player().get_sign() * self.config.get_draw_weight()
+    }
+
+    fn is_exact_objective(objective_function: &Objective) -> bool {
+        let key = objective_function.get_key();
+        return Self::is_win_probability_objective(objective_function) || key == Self::margin_objective().get_key();
+    }
+
+    fn evaluate_for_active_player(objective_function: &Objective, node: &Node) -> f32 {
+        // Objectives score positions for Player 1, and the search for whoever's to act.
+        node.get_active_player().get_sign() * objective_function.evaluate(node)
+    }
+
+    fn evaluate_for_active_player_with_scores(objective_function: &Objective, node: &Node, scores: (u16, u16)) -> f32 {
+        // With the scores the search carried down to node.
+        node.get_active_player().get_sign() * objective_function.evaluate_with_scores(node, scores)
+    }
+
+    fn to_evaluation(objective_function: &Objective, evaluation: f32) -> Result<Evaluation, SolverError> {
+        // Tags a value with the kind of objective that produced it.  A NaN means a broken objective, which the searches
+        // pass up rather than compare, so it's found out here instead of from moves picked by comparisons that don't
+        // mean anything.
+        let kind = if Self::is_win_probability_objective(objective_function) {
+            EvaluationKind::ExpectedResult
+        } else if objective_function.get_key() == Self::margin_objective().get_key() {
+            EvaluationKind::ExpectedMargin
+        } else {
+            EvaluationKind::Heuristic
+        };
+        Evaluation::new(kind, evaluation).map_err(|_| SolverError::NanEvaluation)
+    }
+
+    fn to_expected_result(evaluation: f32) -> Evaluation {
+        // For the averages of playouts' results, which can't be NaN.
+        Evaluation::new(EvaluationKind::ExpectedResult, evaluation).expect("Averages of results are never NaN.")
+    }
+
+    fn with_margin(evaluation: Evaluation, margin: f32) -> Evaluation {
+        // An expected result from the search along with the margin worked out to break ties, which is an average of
+        // score differences and so can't be NaN.  The margin was searched as deep, so it's truncated if the result is.
+        let with_margin = Evaluation::new_with_margin(evaluation.get_evaluation(), margin).expect("Margins are never NaN.");
+        if evaluation.is_truncated() {
+            return with_margin.truncated();
+        }
+        return with_margin;
+    }
+
+    fn get_evaluation_tree_brute_force(&mut self) -> Result<(Option<Node>, Evaluation), SolverError> {
+        let objective_function = self.exact_objective();
+        self.build_n_moves(self.config.get_brute_force_depth(), &objective_function);
+        self.search_stats.n_truncated = Self::count_unfinished_leaves(&self.root);
+        let (maybe_tree, evaluation) = self.root.get_evaluation_tree(&objective_function)?;
+        let evaluation = self.mark_if_truncated(Self::to_evaluation(&objective_function, evaluation)?, 0);
+        return Ok((maybe_tree, self.mark_if_sampled(evaluation, self.config.get_brute_force_depth())));
+    }
+
+    fn count_unfinished_leaves(node: &Node) -> usize {
+        // The leaves of a built tree the game isn't over at.
+        if node.is_leaf() {
+            return if node.is_terminal() { 0 } else { 1 };
+        }
+        return node.get_children().iter().map(Self::count_unfinished_leaves).sum();
+    }
+
+    fn build_n_moves(&mut self, n: usize, objective_function: &Objective) {
+        // The tree has the same rolls at each chance node as the search would average over.
+        let chance_expansion = self.config.get_chance_expansion();
+        if self.ordered_expansion {
+            self.root.build_n_moves_ordered_with(n, objective_function, &chance_expansion);
+        } else {
+            self.root.build_n_moves_up_to_symmetry_with(n, &chance_expansion);
+        }
+    }
+
+    fn get_evaluation_tree_heuristic(&mut self, depth: usize, objective_function: &Objective) -> Result<(Option<Node>, Evaluation), SolverError> {
+        self.build_n_moves(depth, objective_function);
+        let (maybe_tree, evaluation) = self.root.get_evaluation_tree(objective_function)?;
+        return Ok((maybe_tree, self.mark_if_sampled(Self::to_evaluation(objective_function, evaluation)?, depth)));
+    }
+
+    fn get_evaluation_tree_node_budget(&mut self, max_nodes: usize, objective_function: &Objective) -> Result<(Option<Node>, Evaluation), SolverError> {
+        // Always searches at least one move so there's something to play, even if that's over budget.
+        if self.root.is_leaf() {
+            self.root.build_n_moves_up_to_symmetry(1);
+        }
+        self.root.build_with_node_budget(max_nodes);
+        let (maybe_tree, evaluation) = self.root.get_evaluation_tree(objective_function)?;
+        return Ok((maybe_tree, Self::to_evaluation(objective_function, evaluation)?));
+    }
+
+    fn get_evaluation_tree_hybrid(&mut self, max_moves_left_before_brute_force: usize, depth: usize, objective_function: &Objective) -> Result<(Option<Node>, Evaluation), SolverError> {
+        if self.should_brute_force(max_moves_left_before_brute_force) {
+            self.get_evaluation_tree_brute_force()
+        } else {
+            self.get_evaluation_tree_heuristic(depth, objective_function)
+        }
+    }
+
+    pub fn get_scheduled_mode(&self, schedule: &DepthSchedule, objective_function: Objective) -> SolverMode {
+        // What the schedule says to do with the root's empty squares.
+        match schedule.get_depth(self.root.get_n_empty_squares()) {
+            ScheduledDepth::Heuristic(depth) => SolverMode::Heuristic((depth, objective_function)),
+            ScheduledDepth::BruteForce => SolverMode::BruteForce,
+        }
+    }
+
+    pub fn get_adaptive_depth(&self, max_nodes: usize) -> usize {
+        // The deepest heuristic search of the root estimate_n_nodes expects to fit in max_nodes, searching at least
+        // one move so there's something to play, and no deeper than the game could last.
+        let (_, max_moves_left) = self.root.get_moves_left_bounds();
+        return (2..=max_moves_left)
+            .take_while(|depth| Self::estimate_n_nodes(&self.root, *depth) <= max_nodes as f64)
+            .last()
+            .unwrap_or(1);
+    }
+
+    pub fn estimate_n_nodes(node: &Node, depth: usize) -> f64 {
+        // Roughly how many positions searching node depth moves ahead visits, ignoring eliminations and the
+        // transposition table.  Up to row symmetry a player has one move per column with room, and each move is
+        // followed by six rolls.  Each move is assumed to go in the roomiest column, which keeps the most moves open.
+        let mut room = [Player::Player1, Player::Player2].map(
+            |player| {
+                let board = node.get_player_board(player);
+                [0, 1, 2].map(|column_index| board.get_n_empty_squares_in_column(column_index))
+            }
+        );
+        let mut player = node.get_active_player();
+        let mut width = 1.;
+        let mut n_nodes = 1.;
+        for _ in 0..depth {
+            let columns = &mut room[if player == Player::Player1 { 0 } else { 1 }];
+            // A board filling up would end the game, but eliminations can reopen it, so there's always a move counted.
+            let n_moves = columns.iter().filter(|n_empty| **n_empty > 0).count().max(1);
+            width *= (n_moves * Die::all().len()) as f64;
+            n_nodes += width;
+            let roomiest = columns.iter_mut().max().expect("Guaranteed to be three columns.");
+            *roomiest = roomiest.saturating_sub(1);
+            player = player.opponent();
+        }
+        return n_nodes;
+    }
+
+    pub fn perft(&self, depth: usize, up_to_symmetry: bool) -> usize {
+        // How many positions the root leads to depth moves ahead, with all six rolls at every chance node, including
+        // the ones after the last move, and finished games counted where they finish.  That's the number of leaves
+        // building the tree depth moves deep gives, without building it.  Up to symmetry only counts one of the moves
+        // that are the same up to row symmetry, like the trees the solver builds.
+        return Self::count_perft_leaves(&self.root, depth, up_to_symmetry);
+    }
+
+    pub fn perft_full(&self, depth: usize) -> usize {
+        self.perft(depth, false)
+    }
+
+    pub fn perft_up_to_symmetry(&self, depth: usize) -> usize {
+        self.perft(depth, true)
+    }
+
+    pub fn divide(&self, depth: usize, up_to_symmetry: bool) -> Result<Vec<(Move, usize)>, SolverError> {
+        // Perft broken down by the root's moves, which add up to perft of the root.
+        self.check_can_search(&SolverMode::BruteForce)?;
+        if depth == 0 {
+            return Err(SolverError::DepthZero);
+        }
+        let legal_moves = if up_to_symmetry { self.root.get_legal_moves_up_to_row_symmetry()? } else { self.root.get_legal_moves()? };
+        return Ok(
+            legal_moves.into_iter()
+                .map(
+                    |m| {
+                        let child = self.root.with_move_made(m).expect("Won't error because we know the move is legal.");
+                        (m, Self::count_perft_leaves(&child, depth - 1, up_to_symmetry))
+                    }
+                ).collect()
+        );
+    }
+
+    fn count_perft_leaves(root: &Node, depth: usize, up_to_symmetry: bool) -> usize {
+        // Keeps its own stack, like the search, since only the positions still to be counted need keeping.
+        let mut n_leaves = 0;
+        let mut stack = vec![(root.clone_without_children(), depth)];
+        while let Some((node, depth)) = stack.pop() {
+            match node.status() {
+                NodeStatus::Terminal(_) => n_leaves += 1,
+                NodeStatus::Roll(_) => stack.extend(
+                    Die::all().into_iter().map(|die| (node.with_roll(die).expect("Guaranteed to be a roll node."), depth))
+                ),
+                NodeStatus::Move(_, _) if depth == 0 => n_leaves += 1,
+                NodeStatus::Move(_, _) => {
+                    let legal_moves = if up_to_symmetry { node.get_legal_moves_up_to_row_symmetry() } else { node.get_legal_moves() };
+                    stack.extend(
+                        legal_moves.expect("Guaranteed to be a move node.").into_iter()
+                            .map(|m| (node.with_move_made(m).expect("Won't error because we know the move is legal."), depth - 1))
+                    );
+                },
+            }
+        }
+        return n_leaves;
+    }
+
+    fn should_brute_force(&self, max_moves_left_before_brute_force: usize) -> bool {
+        // Use the max bound, since eliminations can make the game much longer than the board suggests.
+        let (_, max_moves_left) = self.root.get_moves_left_bounds();
+        return max_moves_left <= max_moves_left_before_brute_force;
+    }
+
+    fn get_best_moves_from_evaluation_tree(maybe_tree: Option<Node>) -> Result<Vec<Move>, SolverError> {
+        match maybe_tree {
+            Some(tree) => {
+                match tree.get_node_type() {
+                    NodeType::Roll(_) => Err(SolverError::Internal(format!("Roll node can't be the root of an evaluation tree: {}", tree))),
+                    NodeType::Move(_, _) => {
+                        let mut best_moves = tree.get_moves().expect("Guaranteed to be a move node.");
+                        best_moves.sort();
+                        Ok(best_moves)
+                    },
+                }
+            },
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn get_evaluation(&mut self, solver_mode: SolverMode) -> Result<Evaluation, SolverError> {
+        // A root waiting on a roll has no best move, but it's still worth the average over the rolls, weighted by the
+        // die distribution.
+        if let NodeStatus::Roll(_) = self.root.status() {
+            return self.get_expected_evaluation(solver_mode);
+        }
+        self.get_best_moves_and_evaluation(solver_mode).map(|(_, evaluation)| evaluation)
+    }
+
+    fn get_expected_evaluation(&mut self, solver_mode: SolverMode) -> Result<Evaluation, SolverError> {
+        let evaluations = self.get_evaluation_by_roll(solver_mode)?;
+        let first_evaluation = evaluations.first().map(|(_, evaluation, _)| *evaluation).expect("Guaranteed to have a roll for every die.");
+        if evaluations.iter().any(|(_, evaluation, _)| evaluation.is_expected_result() != first_evaluation.is_expected_result()) {
+            return Err(SolverError::Internal("The rolls were evaluated with different kinds of evaluation.".to_string()));
+        }
+        let expectation = evaluations.iter().zip(self.root.get_roll_probabilities())
+            .map(|((_, evaluation, _), (_, probability))| probability * evaluation.get_evaluation())
+            .sum::<f32>();
+        let expected_evaluation = first_evaluation.with_evaluation(expectation).map_err(SolverError::Internal)?;
+        if evaluations.iter().any(|(_, evaluation, _)| evaluation.is_truncated()) {
+            return Ok(expected_evaluation.truncated());
+        }
+        return Ok(expected_evaluation);
+    }
+
+    pub fn difference_heuristic(node: &Node, empty_square_fill: f32) -> f32 {
+        // Heuristic == Difference in current score, attributing empty_square_fill to squares that will get played assuming no eliminations.
+        return Self::difference_heuristic_with_scores(node, node.get_scores(), empty_square_fill);
+    }
+
+    pub fn difference_heuristic_with_scores(node: &Node, scores: (u16, u16), empty_square_fill: f32) -> f32 {
+        // difference_heuristic with both players' scores already worked out.
+        let (player_1_score, player_2_score) = scores;
+        return (player_1_score as f32 - player_2_score as f32) + Self::empty_square_difference(node) * empty_square_fill;
+    }
+
+    pub fn empty_square_difference(node: &Node) -> f32 {
+        // How many more squares Player 1 will get to play than Player 2 assuming no eliminations, which
+        // difference_heuristic scores empty_square_fill each.
+        // Worked out for the player to act, who gets the next square, and then turned into Player 1's perspective.
+        let player = node.get_active_player();
+        let empty_squares = node.get_player_board(player).get_n_empty_squares() as f32;
+        let opponent_empty_squares = node.get_player_board(player.opponent()).get_n_empty_squares() as f32;
+        return player.get_sign() * (opponent_empty_squares - empty_squares + 1.);
+    }
+
+    pub fn column_synergy_heuristic(node: &Node, weights: &SynergyWeights) -> f32 {
+        // difference_heuristic with column_synergy added on.
+        return Self::difference_heuristic(node, weights.empty_square_fill) + Self::column_synergy(node, weights);
+    }
+
+    pub fn column_synergy(node: &Node, weights: &SynergyWeights) -> f32 {
+        // What each side's columns promise, from Player 1's perspective: the expected bonus from matching a die
+        // already in one of them with the next roll, less the expected loss to the opponent matching one with theirs.
+        if node.is_terminal() {
+            return 0.;
+        }
+        let player_1_board = node.get_player_1_board();
+        let player_2_board = node.get_player_2_board();
+        let die_distribution = node.get_die_distribution();
+        return Self::get_column_synergy(&player_1_board, &player_2_board, &die_distribution, weights)
+            - Self::get_column_synergy(&player_2_board, &player_1_board, &die_distribution, weights);
+    }
+
+    pub fn elimination_threat_heuristic(node: &Node, params: &ThreatParams) -> f32 {
+        // difference_heuristic with elimination_threat added on.
+        return Self::difference_heuristic(node, params.empty_square_fill) + Self::elimination_threat(node, params);
+    }
+
+    pub fn elimination_threat(node: &Node, params: &ThreatParams) -> f32 {
+        // The points each side expects to lose to the other's next die, from Player 1's perspective: for each column,
+        // the chance of the other side rolling a die in it that they have room to play opposite, times what it
+        // would take.  Each column counts on its own, though only one die gets played.
+        if node.is_terminal() {
+            return 0.;
+        }
+        let player_1_board = node.get_player_1_board();
+        let player_2_board = node.get_player_2_board();
+        let die_distribution = node.get_die_distribution();
+        return params.weight * (
+            Self::get_expected_threat(&player_2_board, &player_1_board, &die_distribution)
+                - Self::get_expected_threat(&player_1_board, &player_2_board, &die_distribution)
+        );
+    }
+
+    pub fn expected_elimination_heuristic(node: &Node) -> f32 {
+        // The points each side expects to take with their next die, from Player 1's perspective: for each roll, its
+        // chance times the most it could take from any column they have room to play it in.  Unlike
+        // elimination_threat, only the best column counts, since only one die gets played.  The player to act on a
+        // Move node already knows their die.
+        if node.is_terminal() {
+            return 0.;
+        }
+        let player_1_board = node.get_player_1_board();
+        let player_2_board = node.get_player_2_board();
+        let die_distribution = node.get_die_distribution();
+        let get_roll_probabilities = |player: Player| match node.get_node_type() {
+            NodeType::Move(active_player, die) if active_player == player => vec![(die, 1.)],
+            _ => Die::all().into_iter().map(|die| (die, die_distribution.get_probability(die))).collect(),
+        };
+        return Self::get_expected_elimination(&player_1_board, &player_2_board, &get_roll_probabilities(Player::Player1))
+            - Self::get_expected_elimination(&player_2_board, &player_1_board, &get_roll_probabilities(Player::Player2));
+    }
+
+    pub fn opponent_reply_heuristic(node: &Node, inner: impl Fn(&Node) -> f32) -> f32 {
+        // inner after the player to act makes the move inner likes best for them, averaged over their rolls if they've
+        // still to roll.  That's the player the search's last move was against, so a shallow search gets to see their
+        // reply at its leaves without the tree going any deeper.  The replies are made as they're needed rather than
+        // stored.
+        match node.status() {
+            NodeStatus::Terminal(_) => inner(node),
+            NodeStatus::Roll(_) => node.get_roll_probabilities().into_iter()
+                .map(|(die, probability)| probability * Self::get_greedy_reply_evaluation(&node.with_roll(die).expect("Guaranteed to be a roll node."), &inner))
+                .sum(),
+            NodeStatus::Move(_, _) => Self::get_greedy_reply_evaluation(node, &inner),
+        }
+    }
+
+    fn get_greedy_reply_evaluation(node: &Node, inner: &impl Fn(&Node) -> f32) -> f32 {
+        // inner after the move it scores best for the player to act, from Player 1's perspective like inner.
+        let player = node.get_active_player();
+        return node.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.").into_iter()
+            .map(|m| inner(&node.with_move_made(m).expect("Won't error because we know the move is legal.")))
+            .max_by(|a, b| player.order_best_first(*b, *a))
+            .expect("A game that isn't over has a legal move.");
+    }
+
+    fn get_expected_elimination(board: &Board, opponent_board: &Board, roll_probabilities: &[(Die, f32)]) -> f32 {
+        // What board's player expects to take from opponent_board with their next die.
+        return roll_probabilities.iter()
+            .map(
+                |(die, probability)| probability * (0..3)
+                    .filter(|column| board.get_n_empty_squares_in_column(*column) > 0)
+                    .map(|column| opponent_board.column_threat(*die, column))
+                    .max()
+                    .unwrap_or(0) as f32
+            ).sum();
+    }
+
+    fn get_expected_threat(board: &Board, opponent_board: &Board, die_distribution: &DieDistribution) -> f32 {
+        // What the opponent expects to take from board.
+        return (0..3)
+            .filter(|column| opponent_board.get_n_empty_squares_in_column(*column) > 0)
+            .map(
+                |column| Die::all().into_iter()
+                    .map(|die| die_distribution.get_probability(die) * board.column_threat(die, column) as f32)
+                    .sum::<f32>()
+            ).sum();
+    }
+
+    fn get_column_synergy(board: &Board, opponent_board: &Board, die_distribution: &DieDistribution, weights: &SynergyWeights) -> f32 {
+        // Only one die gets placed a turn, so each roll counts the column it does the most in.
+        let sum = board.sum();
+        return Die::all().into_iter().map(
+            |die| {
+                let completion_bonus = (0..3).filter_map(
+                    |column| board.get_column(column).iter().position(|square| *square == Square::Empty).map(
+                        |row| board.with_move_made(die, Move::new(row, column)).expect("Guaranteed to be an empty square.").sum() - sum - die.to_value()
+                    )
+                ).max().unwrap_or(0);
+                let exposure = (0..3)
+                    .filter(|column| opponent_board.get_n_empty_squares_in_column(*column) > 0)
+                    .map(|column| board.column_threat(die, column))
+                    .max()
+                    .unwrap_or(0);
+                die_distribution.get_probability(die) * (weights.completion * completion_bonus as f32 - weights.exposure * exposure as f32)
+            }
+        ).sum();
+    }
+
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ThreatParams {
+    // How much of the expected loss to eliminations to count.
+    pub weight: f32,
+    pub empty_square_fill: f32,
+}
+
+impl Default for ThreatParams {
+    fn default() -> Self {
+        ThreatParams { weight: DEFAULT_THREAT_WEIGHT, empty_square_fill: DEFAULT_EMPTY_SQUARE_FILL }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SynergyWeights {
+    // How much of the expected bonus from doubling or tripling a column to count.
+    pub completion: f32,
+    // How much of the expected loss to eliminations to count.  The opponent won't always want to spend a die on one.
+    pub exposure: f32,
+    pub empty_square_fill: f32,
+}
+
+impl Default for SynergyWeights {
+    fn default() -> Self {
+        SynergyWeights {
+            completion: DEFAULT_COMPLETION_WEIGHT,
+            exposure: DEFAULT_EXPOSURE_WEIGHT,
+            empty_square_fill: DEFAULT_EMPTY_SQUARE_FILL,
+        }
+    }
+}
+
+struct TranspositionEntry {
+    objective_key: usize,
+    // The moves left to search below the position, and the extensions already used on the way to it, which leave
+    // fewer for the search below.
+    depth: usize,
+    extensions: usize,
+    // For the player to act, like everything else in the search.
+    evaluation: f32,
+    // Up to row symmetry, and empty for Roll nodes.
+    best_moves: Vec<Move>,
+    // Whether brute force's depth ran out before the end of the game anywhere in the search it stands for.
+    is_truncated: bool,
+    // Whether the rolls stopped being searched once the evaluation was sure to be above the bound it was searched
+    // with, so it's only the least the evaluation could be.
+    is_lower_bound: bool,
+}
+
+impl TranspositionEntry {
+
+    fn new(objective_function: &Objective, depth: usize, extensions: usize, evaluation: f32, best_moves: Vec<Move>, is_truncated: bool) -> Self {
+        TranspositionEntry { objective_key: objective_function.get_key(), depth, extensions, evaluation, best_moves, is_truncated, is_lower_bound: false }
+    }
+
+    fn lower_bound(self) -> Self {
+        TranspositionEntry { is_lower_bound: true, ..self }
+    }
+
+    fn covers(&self, objective_function: &Objective, depth: usize, extensions: usize, bound: Option<f32>) -> bool {
+        // Whether the entry can stand in for a search to depth with extensions already used, which only needs to know
+        // whether the evaluation is above bound if there is one.  A lower bound only does if it's above it too.  A
+        // deeper search sees further than the one it would stand in for, and eliminations make the games it sees
+        // differ, so it can only stand in for a search of the same depth without changing the result.
+        self.objective_key == objective_function.get_key()
+            && self.depth == depth
+            && self.extensions == extensions
+            && (!self.is_lower_bound || bound.is_some_and(|bound| self.evaluation > bound))
+    }
+
+    fn is_worth_more_than(&self, other: &TranspositionEntry) -> bool {
+        // Whether the entry is exact and the other only a lower bound from a search of the same depth, which is all
+        // that stops a newer entry taking over.
+        self.objective_key == other.objective_key
+            && (self.depth, self.extensions) == (other.depth, other.extensions)
+            && !self.is_lower_bound
+            && other.is_lower_bound
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MemoizedSolveStats {
+    n_unique_states: usize,
+    n_expansions: usize,
+}
+
+impl Display for MemoizedSolveStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unique States: {}\nExpansions: {}", self.n_unique_states, self.n_expansions)
+    }
+}
+
+impl MemoizedSolveStats {
+
+    pub fn get_n_unique_states(&self) -> usize {
+        // Canonical positions evaluated, counting the same position with a different number of moves left separately.
+        self.n_unique_states
+    }
+
+    pub fn get_n_expansions(&self) -> usize {
+        // Positions the solver asked for the evaluation of, whether it had already evaluated them or not.
+        self.n_expansions
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SearchStats {
+    n_nodes: usize,
+    n_leaves: usize,
+    n_hits: usize,
+    n_misses: usize,
+    n_cutoffs: usize,
+    n_pruned: usize,
+    n_merged_rolls: usize,
+    n_truncated: usize,
+    depth: usize,
+    elapsed: Duration,
+}
+
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Nodes: {}\nLeaves: {}\nDepth: {}\nTransposition Hits: {} (Misses: {})\nCutoffs: {}\nPruned: {}\nMerged Rolls: {}\nTruncated: {}\nTime: {:.3}s",
+            self.n_nodes, self.n_leaves, self.depth, self.n_hits, self.n_misses, self.n_cutoffs, self.n_pruned, self.n_merged_rolls, self.n_truncated, self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+impl SearchStats {
+
+    fn empty() -> Self {
+        SearchStats {
+            n_nodes: 0,
+            n_leaves: 0,
+            n_hits: 0,
+            n_misses: 0,
+            n_cutoffs: 0,
+            n_pruned: 0,
+            n_merged_rolls: 0,
+            n_truncated: 0,
+            depth: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn get_n_nodes(&self) -> usize {
+        // Positions searched or scored, not counting ones found in the transposition table.
+        self.n_nodes
+    }
+
+    pub fn get_n_leaves(&self) -> usize {
+        // Positions scored by the objective, or games played out, rather than searched any further.
+        self.n_leaves
+    }
+
+    pub fn get_n_cutoffs(&self) -> usize {
+        // Roll nodes the depth limit stopped the search at.
+        self.n_cutoffs
+    }
+
+    pub fn get_n_pruned(&self) -> usize {
+        // Roll nodes whose rolls were cut short because the move before them couldn't be best.
+        self.n_pruned
+    }
+
+    pub fn get_n_merged_rolls(&self) -> usize {
+        // Rolls scored along with another roll at the same position rather than on their own.
+        self.n_merged_rolls
+    }
+
+    pub fn get_n_truncated(&self) -> usize {
+        // Unfinished games brute force scored as they stood because its depth ran out, counting each transposition
+        // table hit on a search that had any as one more.
+        self.n_truncated
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn get_n_hits(&self) -> usize {
+        self.n_hits
+    }
+
+    pub fn get_n_misses(&self) -> usize {
+        self.n_misses
+    }
+
+    pub fn get_depth(&self) -> usize {
+        // How many moves ahead the result was searched, which for a timed search is the deepest search that finished.
+        self.depth
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchProgress {
+    // The depth being searched.
+    depth: usize,
+    n_nodes: usize,
+    elapsed: Duration,
+    // From the deepest search that's finished, or else the moves searched so far at the root.
+    best: Option<(Vec<Move>, Evaluation)>,
+}
+
+impl SearchProgress {
+
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn get_n_nodes(&self) -> usize {
+        self.n_nodes
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn get_best_moves(&self) -> Option<&Vec<Move>> {
+        self.best.as_ref().map(|(best_moves, _)| best_moves)
+    }
+
+    pub fn get_evaluation(&self) -> Option<Evaluation> {
+        self.best.as_ref().map(|(_, evaluation)| *evaluation)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EvaluationWithConfidence {
+    evaluation: Evaluation,
+    // How much the evaluation moved from the search a move less deep, from Player 1's perspective.
+    delta: f32,
+    // Whether none of the best moves were best a move less deep.
+    best_move_changed: bool,
+    // Whether the best move changed or the delta is over the config's confidence threshold.
+    low_confidence: bool,
+}
+
+impl EvaluationWithConfidence {
+
+    pub fn get_evaluation(&self) -> Evaluation {
+        self.evaluation
+    }
+
+    pub fn get_delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn has_best_move_changed(&self) -> bool {
+        self.best_move_changed
+    }
+
+    pub fn is_low_confidence(&self) -> bool {
+        self.low_confidence
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveClassification {
+    // Tied for best, to within the tie break epsilon.
+    Best,
+    Fine,
+    Inaccuracy,
+    Blunder,
+}
+
+impl MoveClassification {
+
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        match self {
+            MoveClassification::Best => "best".to_string(),
+            MoveClassification::Fine => "fine".to_string(),
+            MoveClassification::Inaccuracy => "inaccuracy".to_string(),
+            MoveClassification::Blunder => "blunder".to_string(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MoveAssessment {
+    played_evaluation: Evaluation,
+    best_evaluation: Evaluation,
+    // How much worse the played move is than the best for the player who made it, so never negative.
+    delta: f32,
+    classification: MoveClassification,
+}
+
+impl MoveAssessment {
+
+    fn new(player: Player, played_evaluation: Evaluation, best_evaluation: Evaluation, config: &SolverConfig) -> Self {
+        // The thresholds are on the chance of winning lost, so they mean the same for heuristic evaluations and
+        // expected results.
+        let delta = player.get_sign() * (best_evaluation.get_evaluation() - played_evaluation.get_evaluation());
+        let win_probability_lost = player.get_sign() * (
+            best_evaluation.as_win_probability(DEFAULT_WIN_PROBABILITY_SCALE) - played_evaluation.as_win_probability(DEFAULT_WIN_PROBABILITY_SCALE)
+        );
+        let classification = if are_tied(best_evaluation.get_evaluation(), played_evaluation.get_evaluation()) || delta <= config.get_tie_break_epsilon() {
+            MoveClassification::Best
+        } else if win_probability_lost >= config.get_blunder_threshold() {
+            MoveClassification::Blunder
+        } else if win_probability_lost >= config.get_inaccuracy_threshold() {
+            MoveClassification::Inaccuracy
+        } else {
+            MoveClassification::Fine
+        };
+        return MoveAssessment { played_evaluation, best_evaluation, delta: delta.max(0.), classification };
+    }
+
+    pub fn get_played_evaluation(&self) -> Evaluation {
+        self.played_evaluation
+    }
+
+    pub fn get_best_evaluation(&self) -> Evaluation {
+        self.best_evaluation
+    }
+
+    pub fn get_delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn get_classification(&self) -> MoveClassification {
+        self.classification
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchEvent {
+    // Player 1's evaluation, as everywhere else.
+    DepthCompleted { depth: usize, best_moves: Vec<Move>, evaluation: Evaluation },
+    Progress { n_nodes: usize, elapsed: Duration },
+    Finished { result: Result<(Vec<Move>, Evaluation), SolverError> },
+}
+
+pub struct SolveHandle {
+    cancellation_token: CancellationToken,
+    thread: JoinHandle<Solver>,
+}
+
+impl SolveHandle {
+
+    pub fn cancel(&self) {
+        self.cancellation_token.cancel();
+    }
+
+    pub fn join(self) -> Solver {
+        // Waits for the search to finish, which it has once Finished is sent.
+        self.thread.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
+
+struct SearchControl<'a> {
+    // Searches give up once this passes.
+    deadline: Option<Instant>,
+    progress: Option<ProgressReporter<'a>>,
+    on_depth: Option<DepthCallback<'a>>,
+}
+
+// Called with each depth an iterative deepening search finishes, and what it found.
+type DepthCallback<'a> = &'a mut dyn FnMut(usize, &(Vec<Move>, Evaluation));
+
+impl SearchControl<'_> {
+
+    fn unlimited() -> Self {
+        SearchControl { deadline: None, progress: None, on_depth: None }
+    }
+
+    #[cfg(feature = "parallel")]
+    fn until(deadline: Option<Instant>) -> Self {
+        SearchControl { deadline, progress: None, on_depth: None }
+    }
+
+    fn is_past_deadline(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn start_depth(&mut self, depth: usize) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress.depth = depth;
+        }
+    }
+
+    fn finish_depth(&mut self, depth: usize, n_nodes: usize, result: &(Vec<Move>, Evaluation)) {
+        if let Some(on_depth) = self.on_depth.as_mut() {
+            on_depth(depth, result);
+        }
+        if let Some(progress) = self.progress.as_mut() {
+            progress.best = Some(result.clone());
+            if progress.is_due() {
+                progress.report(n_nodes, None);
+            }
+        }
+    }
+}
+
+struct ProgressReporter<'a> {
+    callback: &'a mut dyn FnMut(SearchProgress),
+    interval: Duration,
+    start: Instant,
+    last_report: Option<Instant>,
+    depth: usize,
+    best: Option<(Vec<Move>, Evaluation)>,
+}
+
+impl<'a> ProgressReporter<'a> {
+
+    fn new(callback: &'a mut dyn FnMut(SearchProgress), interval: Duration) -> Self {
+        ProgressReporter { callback, interval, start: Instant::now(), last_report: None, depth: 0, best: None }
+    }
+
+    fn is_due(&self) -> bool {
+        self.last_report.is_none_or(|last_report| last_report.elapsed() >= self.interval)
+    }
+
+    fn report(&mut self, n_nodes: usize, best_so_far: Option<(Vec<Move>, Evaluation)>) {
+        self.last_report = Some(Instant::now());
+        (self.callback)(
+            SearchProgress { depth: self.depth, n_nodes, elapsed: self.start.elapsed(), best: self.best.clone().or(best_so_far) }
+        );
+    }
+}
+
+enum SearchFrame {
+    // A position part way through Solver::search, with depth moves left to search below it.
+    Move {
+        node: Node,
+        // Both players' scores, carried down from the root a move at a time rather than summed at every position.
+        scores: (u16, u16),
+        depth: usize,
+        // How many times the search has been extended on the way here.
+        extensions: usize,
+        moves: Vec<Move>,
+        // The evaluations of the moves searched so far, for the player to act, in the same order.
+        evaluations: Vec<f32>,
+        // The search's count of truncated leaves when the frame was pushed, so any more by the time it's popped were
+        // below it.
+        n_truncated: usize,
+    },
+    Roll {
+        node: Node,
+        scores: (u16, u16),
+        depth: usize,
+        extensions: usize,
+        // Each roll with its share of the average.
+        rolls: Vec<(Die, f32)>,
+        // For each roll, an earlier one whose position is searched the same, whose evaluation it takes instead.
+        merged_into: Vec<Option<usize>>,
+        // The evaluations of the rolls searched so far, in the same order.
+        evaluations: Vec<f32>,
+        index: usize,
+        average_evaluation: f32,
+        // The move that led here can't be best if the evaluation comes out above this, so the rest of the rolls are
+        // skipped once it's sure to.
+        bound: Option<f32>,
+        // Whether the rolls were cut short, leaving the least the evaluation could have been.
+        is_pruned: bool,
+        n_truncated: usize,
+    },
+}
+
+impl SearchFrame {
+
+    fn get_roll_shares(node: &Node, dice: &[Die]) -> Vec<(Die, f32)> {
+        // Each of dice with its share of the average, which add up to one.
+        let probabilities = node.get_roll_probabilities().into_iter()
+            .filter(|(die, _)| dice.contains(die))
+            .collect::<Vec<(Die, f32)>>();
+        let total = probabilities.iter().map(|(_, probability)| probability).sum::<f32>();
+        return probabilities.into_iter().map(|(die, probability)| (die, probability / total)).collect();
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Evaluation {
+    kind: EvaluationKind,
+    // Never NaN, since every way of making an evaluation turns it away, so the order below is total.
+    evaluation: f32,
+    // The expected final score difference alongside an expected result, which breaks ties between moves with the
+    // same result.
+    margin: Option<f32>,
+    // Whether it only approximates its kind, from a search that only averaged over some of the rolls at deeper chance
+    // nodes.
+    is_sampled: bool,
+    // Whether brute force's depth ran out before the end of the game on some line it searched, which it then scored
+    // as the game stood, so it only approximates its kind too.
+    is_truncated: bool,
+}
+
+// In the order evaluations of different kinds sort in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EvaluationKind {
+    // The expected result under brute force, from 1 for a Player 1 win through 0 for a draw to -1 for a Player 2 win.
+    ExpectedResult,
+    // The expected final score difference under brute force, when that's what it's maximizing.
+    ExpectedMargin,
+    // The objective's score, in whatever units it uses.
+    Heuristic,
+}
+
+impl PartialEq for Evaluation {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Evaluation {}
+
+impl PartialOrd for Evaluation {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Evaluation {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Values of different kinds aren't on the same scale, so the kinds are just kept apart, in EvaluationKind's
+        // order, and compare_for refuses to compare them.  Sampled and truncated values are on the same scale as the
+        // kind they approximate, and only come before an exact value when the two are the same.  Margins only come into
+        // it when the results are the same, and an expected result without one comes first.
+        self.get_kind().cmp(&other.get_kind())
+            .then_with(|| compare_totally(self.get_evaluation(), other.get_evaluation()))
+            .then_with(
+                || match (self.get_margin(), other.get_margin()) {
+                    (Some(margin), Some(other_margin)) => compare_totally(margin, other_margin),
+                    (margin, other_margin) => margin.is_some().cmp(&other_margin.is_some()),
+                }
+            ).then_with(|| other.is_sampled().cmp(&self.is_sampled()))
+            .then_with(|| other.is_truncated().cmp(&self.is_truncated()))
+    }
+}
+
+impl Display for Evaluation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.to_string(), f)
+    }
+}
+
+impl Evaluation {
+
+    pub fn new(kind: EvaluationKind, evaluation: f32) -> Result<Self, String> {
+        // NaN has no place in the order, so it's turned away here rather than left to surface in a comparison.  Every
+        // other way of making an evaluation goes through here.
+        if evaluation.is_nan() {
+            return Err(format!("Cannot make a {:?} evaluation from NaN.", kind));
+        }
+        return Ok(Evaluation { kind, evaluation, margin: None, is_sampled: false, is_truncated: false });
+    }
+
+    pub fn new_with_margin(expected_result: f32, margin: f32) -> Result<Self, String> {
+        // An expected result along with the expected final score difference.
+        if margin.is_nan() {
+            return Err("Cannot make an expected margin from NaN.".to_string());
+        }
+        return Self::new(EvaluationKind::ExpectedResult, expected_result)
+            .map(|evaluation| Evaluation { margin: Some(margin), ..evaluation });
+    }
+
+    pub fn get_kind(&self) -> EvaluationKind {
+        self.kind
+    }
+
+    pub fn get_evaluation(&self) -> f32 {
+        self.evaluation
+    }
+
+    pub fn get_margin(&self) -> Option<f32> {
+        // The expected final score difference, from the same perspective as the evaluation, if it was worked out.
+        match self.kind {
+            EvaluationKind::ExpectedMargin => Some(self.evaluation),
+            _ => self.margin,
+        }
+    }
+
+    pub fn is_expected_result(&self) -> bool {
+        self.kind == EvaluationKind::ExpectedResult
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.is_sampled
+    }
+
+    pub fn sampled(&self) -> Self {
+        // The same value, tagged as only approximate.  A margin alongside an expected result is dropped.
+        Evaluation { margin: None, is_sampled: true, ..*self }
+    }
+
+    pub fn unsampled(&self) -> Self {
+        // The kind of evaluation a sampled one approximates, with the same value.
+        Evaluation { is_sampled: false, ..*self }
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.is_truncated
+    }
+
+    pub fn truncated(&self) -> Self {
+        // The same value, tagged as cut off by brute force's depth before the end of the game.
+        Evaluation { is_truncated: true, ..*self }
+    }
+
+    pub fn with_evaluation(&self, evaluation: f32) -> Result<Self, String> {
+        // The same kind of evaluation with a different value, and the same margin if it has one.
+        return Self::new(self.kind, evaluation).map(|with_evaluation| Evaluation { evaluation: with_evaluation.evaluation, ..*self });
+    }
+
+    pub fn compare_for(&self, other: &Evaluation, player: Player) -> Result<Comparison, String> {
+        // Whether this is better or worse than other for player.
+        if !self.is_same_kind(other) {
+            return Err(format!("Cannot compare evaluations of different kinds: {} and {}", self, other));
+        }
+        return Ok(player.compare_evaluation(self.get_evaluation(), other.get_evaluation()));
+    }
+
+    pub fn cmp_for(&self, other: &Evaluation, player: Player) -> Ordering {
+        // As cmp, but greater is better for player.
+        self.from_perspective(player).cmp(&other.from_perspective(player))
+    }
+
+    pub fn better_for(player: Player) -> impl Fn(&Evaluation, &Evaluation) -> Ordering {
+        // For max_by, to find the best evaluation for player, and min_by, the worst.
+        move |evaluation, other| evaluation.cmp_for(other, player)
+    }
+
+    fn is_same_kind(&self, other: &Evaluation) -> bool {
+        // Whether the two are on the same scale, which an expected result is whether it has a margin or not, and a
+        // sampled evaluation is whether it's sampled or not.
+        self.get_kind() == other.get_kind()
+    }
+
+    pub fn from_perspective(&self, perspective: Player) -> Self {
+        // Turning the sign round can't make a NaN.
+        let sign = perspective.get_sign();
+        Evaluation { evaluation: sign * self.evaluation, margin: self.margin.map(|margin| sign * margin), ..*self }
+    }
+
+    pub fn for_active_player(&self, node: &Node) -> Self {
+        // Positive is good for whoever's next to act at the node, rather than for Player 1.
+        return self.from_perspective(node.get_active_player());
+    }
+
+    #[allow(clippy::inherent_to_string_shadow_display)]
+    pub fn to_string(&self) -> String {
+        // Expected results are shown as the share of the points expected, counting a draw as half a win.
+        if self.is_sampled {
+            return format!("{} (sampled)", self.unsampled());
+        }
+        if self.is_truncated {
+            return format!("{} (truncated)", Evaluation { is_truncated: false, ..*self });
+        }
+        let evaluation = self.evaluation;
+        match (self.kind, self.margin) {
+            (EvaluationKind::ExpectedResult, None) => format!(
+                "{}W {:.0}%",
+                if evaluation >= 0. { "+" } else { "-" },
+                (evaluation + 1.) * 50.,
+            ),
+            (EvaluationKind::ExpectedResult, Some(margin)) => format!(
+                "{}, {}Δ{:.1} expected",
+                Evaluation { margin: None, ..*self }.to_string(),
+                if margin >= 0. { "+" } else { "-" },
+                margin.abs(),
+            ),
+            (EvaluationKind::ExpectedMargin, _) => format!("{}Δ{:.1} expected", if evaluation >= 0. { "+" } else { "-" }, evaluation.abs()),
+            (EvaluationKind::Heuristic, _) => format!("{:+.1} (heuristic)", evaluation),
+        }
+    }
+
+    pub fn as_win_probability(&self, scale: f32) -> f32 {
+        // Player 1's estimated chance of winning, counting a draw as half a win like expected results do.  Heuristic
+        // evaluations go through a logistic curve, on which a lead of scale is worth about 73%, except certain wins,
+        // which are certain.  Expected margins are in the same points as the heuristic, so they go through it too.
+        let evaluation = self.evaluation;
+        match self.kind {
+            EvaluationKind::ExpectedResult => return (evaluation + 1.) / 2.,
+            EvaluationKind::ExpectedMargin => return 1. / (1. + (-evaluation / scale).exp()),
+            EvaluationKind::Heuristic => match self.get_known_winner() {
+                Some(Player::Player1) => return 1.,
+                Some(Player::Player2) => return 0.,
+                None => return 1. / (1. + (-evaluation / scale).exp()),
+            },
+        }
+    }
+
+    pub fn to_win_probability_string(&self, scale: f32) -> String {
+        // Expected results are already shown as a share of the points, so they're left as they are.
+        if self.is_sampled {
+            return format!("{} (sampled)", self.unsampled().to_win_probability_string(scale));
+        }
+        match self.kind {
+            EvaluationKind::ExpectedResult => self.to_string(),
+            EvaluationKind::ExpectedMargin => format!("{:.0}% to win ({})", self.as_win_probability(scale) * 100., self),
+            EvaluationKind::Heuristic => format!("{:.0}% to win ({:+.1} heuristic)", self.as_win_probability(scale) * 100., self.evaluation),
+        }
+    }
+
+    pub fn get_known_winner(&self) -> Option<Player> {
+        // Only heuristic evaluations can tell a certain win from a likely one, since brute force scores unfinished
+        // games by who's ahead, and not even they can once the rolls are sampled, since the rest might not win.
+        // Averaging over rolls can land a hair either side of the exact value.
+        if self.kind != EvaluationKind::Heuristic || self.is_sampled {
+            return None;
+        }
+        if self.evaluation >= KNOWN_WIN_EVALUATION - KNOWN_WIN_TOLERANCE {
+            Some(Player::Player1)
+        } else if self.evaluation <= -KNOWN_WIN_EVALUATION + KNOWN_WIN_TOLERANCE {
+            Some(Player::Player2)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_outcome(outcome: Outcome, draw_weight: f32) -> Result<Self, String> {
+        // A draw is worth draw_weight, from a loss at -1 to a win at 1.
+        match outcome {
+            Outcome::Victory(Player::Player1) => Self::new(EvaluationKind::ExpectedResult, 1.0),
+            Outcome::Victory(Player::Player2) => Self::new(EvaluationKind::ExpectedResult, -1.0),
+            Outcome::Draw => Self::new(EvaluationKind::ExpectedResult, draw_weight),
+            Outcome::InProgress => Err("Cannot convert InProgress to Evaluation".to_string()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum SolverMode {
+    BruteForce,
+    Heuristic(HeuristicDepthAndObjective),
+    Hybrid(BruteForceMaxMovesRemaining, HeuristicDepthAndObjective),
+    NodeBudget(NodeBudgetAndObjective),
+    Timed(TimeBudgetAndObjective),
+    // Heuristic as deep as an estimated node budget allows, until few enough moves are left to brute force.
+    Adaptive(BruteForceMaxMovesRemaining, NodeBudgetAndObjective),
+    // Heuristic to the schedule's depth for the root's empty squares, or brute force once there are few enough.
+    Scheduled(DepthScheduleAndObjective),
+    MonteCarlo(PlayoutsPerMoveAndSeed),
+    Mcts(IterationsExplorationAndSeed),
+}
+
+pub type HeuristicDepthAndObjective = (usize, Objective);
+pub type NodeBudgetAndObjective = (usize, Objective);
+pub type TimeBudgetAndObjective = (Duration, Objective);
+pub type DepthScheduleAndObjective = (DepthSchedule, Objective);
+pub type PlayoutsPerMoveAndSeed = (usize, Option<u64>);
+pub type IterationsExplorationAndSeed = (usize, f32, Option<u64>);
+pub type BruteForceMaxMovesRemaining = usize;
+
+#[cfg(test)]
+mod test_solver {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    use crate::tree::tree::NodeType;
+    use crate::solver::accuracy::{get_accuracy_positions, get_accuracy_seeds};
+    use crate::solver::config::{CancellationToken, HeuristicFunction, SolverConfig, SolverStrategy};
+
+    use super::*;
+
+    #[test]
+    fn test_solver_solves_endgame_situations() {
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let mut solver = Solver::from_root(root);
+        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert_eq!(best_moves, vec![Move::new(1, 1)]);
+        assert_eq!(evaluation, Evaluation::new(EvaluationKind::ExpectedResult, 1.0).unwrap());
+
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("255\n1_2\n652".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+        let mut solver = Solver::from_root(root);
+        // There's no move to make until the die is rolled, but there's still an expected result.
+        assert_eq!(
+            solver.get_best_moves_and_evaluation(SolverMode::BruteForce),
+            Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }),
+        );
+        let evaluation = solver.get_evaluation(SolverMode::BruteForce).unwrap();
+        assert!(evaluation.is_expected_result());
+        assert!(evaluation <= Evaluation::new(EvaluationKind::ExpectedResult, 1.0).unwrap());
+        assert!(evaluation >= Evaluation::new(EvaluationKind::ExpectedResult, -1.0).unwrap());
+
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap(); // 40 before move.
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap(); // 24 before move.
+        // Player 2 has two moves:
+        // (2, 2) => 42, after which Player 1 has one move, (2, 2):
+        // 1 => 43 => Player 1 wins
+        // 2 => 46, eliminating a 2 => 46 vs. 40 => Player 1 wins
+        // 3 => 43 => Player 1 wins
+        // 4 => 44 => Player 1 wins
+        // 5 => 45 => Player 1 wins
+        // 6 => 46, eliminating both 6's => 46 vs. 18 => Player 1 wins
+        // (1, 1) => 30, eliminating Player 1's 6 in the middle column, which reopens a square and keeps the game going.
+        // So (2, 2) loses outright and Player 2 should prefer (1, 1).
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let mut solver = Solver::from_root(root.clone());
+        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert_eq!(best_moves, vec![Move::new(1, 1)]);
+        assert!(evaluation < Evaluation::new(EvaluationKind::ExpectedResult, 1.0).unwrap());
+        assert!(evaluation > Evaluation::new(EvaluationKind::ExpectedResult, -1.0).unwrap());
+        // The game after (1, 1) can outlast brute force's depth, but Player 1's win after (2, 2) is certain.
+        assert!(evaluation.is_truncated());
+        let ranked_moves = Solver::from_root(root).get_all_moves_ranked(SolverMode::BruteForce).unwrap();
+        assert_eq!(
+            *ranked_moves.iter().find(|(m, _)| *m == Move::new(2, 2)).unwrap(),
+            (Move::new(2, 2), Evaluation::new(EvaluationKind::ExpectedResult, 1.0).unwrap()),
+        );
+    }
+
+    #[test]
+    fn test_solver_gets_principal_variation() {
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let mut solver = Solver::from_root(root);
+        let principal_variation = solver.get_principal_variation(SolverMode::BruteForce, 10).unwrap();
+        assert_eq!(principal_variation, vec![PvStep::new(Player::Player1, Die::Six, Move::new(1, 1), 1.0)]);
+
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let mode = SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        let (best_moves, evaluation) = Solver::from_root(root.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+        let principal_variation = Solver::from_root(root.clone()).get_principal_variation(mode.clone(), 10).unwrap();
+        assert_eq!(principal_variation.len(), 2);
+        assert!(best_moves.contains(&principal_variation[0].get_move()));
+        assert_eq!(Evaluation::new(EvaluationKind::Heuristic, principal_variation[0].get_evaluation()).unwrap(), evaluation);
+        assert_eq!(Solver::from_root(root).get_principal_variation(mode, 1).unwrap(), principal_variation[..1].to_vec());
+
+        // Player 1 has one square up to row symmetry, and then Player 2 has one.  Player 2 can take back the one or
+        // the three, but the game ends either way.
+        let player_1_board = Board::from_string("11_\n22_\n333".to_string()).unwrap();
+        let player_2_board = Board::from_string("444\n555\n66_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::One));
+        let principal_variation = Solver::from_root(root).get_principal_variation(SolverMode::BruteForce, 10).unwrap();
+        assert_eq!(principal_variation.iter().map(|step| step.get_move()).collect::<Vec<Move>>(), vec![Move::new(0, 2), Move::new(2, 2)]);
+        assert_eq!(principal_variation.iter().map(|step| step.is_roll_assumed()).collect::<Vec<bool>>(), vec![false, true]);
+        assert_eq!(
+            PvStep::to_line(&principal_variation),
+            format!("P1 1→(0, 2) | P2 r?{}→(2, 2)", principal_variation[1].get_die().to_string()),
+        );
+    }
+
+    #[test]
+    fn test_solver_solves_heuristically() {
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let mut solver = Solver::from_root(root);
+        let result = solver
+            .get_best_moves_and_evaluation(
+                SolverMode::Heuristic((1, Objective::new(|x| Solver::difference_heuristic(x, 3.5)))),
+            ).unwrap();
+        assert_eq!(
+            result,
+            (
+                vec![Move::new(0, 0), Move::new(0, 1), Move::new(0, 2)],
+                Evaluation::new(EvaluationKind::Heuristic, 6.0).unwrap()
+            )
+        );
+
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap(); // 40 before move.
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap(); // 24 before move.
+        assert_eq!(player_1_board.sum(), 40);
+        assert_eq!(player_2_board.sum(), 24);
+        // Player 2 has two moves:
+        // (1, 1) => 30, and the game goes on.
+        // (2, 2) => 42, leaving Player 1 one move, (1, 1), which ends the game.
+        // Based on rolls, that means the score after (2, 2) is:
+        // 1 => 43 => Player 1 wins and diff = 1
+        // 2 => 46 + 2 (due to elimination of the 2) => Player 1 wins and diff = 6
+        // 3 => 43 => Player 1 wins.  diff = 1
+        // 4 => 44 => Player 1 wins.  Diff = 2.
+        // 5 => 45 => Player 1 wins.  Diff = 3.
+        // 6 => 46 + 24 (due to elimination of 2 6's) => Player 1 wins.  Diff = 28
+        // So (2, 2) is a certain loss, however close, and Player 2 has to keep the game going with (1, 1).
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let mut solver = Solver::from_root(root);
+        let mode = SolverMode::Heuristic((5, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+        assert_eq!(best_moves, vec![Move::new(1, 1)]);
+        assert_eq!(evaluation.get_known_winner(), None);
+        solver.get_evaluation_tree(mode.clone()).unwrap();
+        let move_evaluations = solver.get_root().get_move_evaluations(&Objective::new(|x| Solver::difference_heuristic(x, 3.5))).unwrap();
+        assert_eq!(move_evaluations[1].0, Move::new(2, 2));
+        assert_eq!(Evaluation::new(EvaluationKind::Heuristic, move_evaluations[1].1).unwrap().get_known_winner(), Some(Player::Player1));
+
+        // Player 1 has a forced win in 2:  whatever they play, Player 2's last die ends the game and can't eliminate enough to catch up.
+        let player_1_board = Board::from_string("66_\n66_\n66_".to_string()).unwrap();
+        let player_2_board = Board::from_string("111\n111\n11_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let mut solver = Solver::from_root(root);
+        let (_, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
+        assert!((evaluation.get_evaluation() - KNOWN_WIN_EVALUATION).abs() < 0.001);
+        assert_eq!(evaluation.get_known_winner(), Some(Player::Player1));
+
+        // The same for any objective, which is only asked about games in progress.
+        for mode in [
+            SolverMode::Heuristic((2, Objective::new(|x| -x.get_score_difference() as f32))),
+            SolverMode::NodeBudget((500, Objective::with_scores(|_, _| 0.))),
+        ] {
+            let (_, evaluation) = solver.get_best_moves_and_evaluation(mode).unwrap();
+            assert_eq!(evaluation.get_known_winner(), Some(Player::Player1));
+        }
+    }
+
+    #[test]
+    fn test_solver_mirrors_player_2_to_act() {
+        let active_players_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let opponents_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let as_player_1 = Node::from_active_player_boards(Player::Player1, active_players_board.clone(), opponents_board.clone(), Die::Six);
+        let as_player_2 = Node::from_active_player_boards(Player::Player2, active_players_board, opponents_board, Die::Six);
+        assert_eq!(as_player_2.get_player_2_board(), as_player_1.get_player_1_board());
+        assert_eq!(as_player_2.get_active_player(), Player::Player2);
+
+        let modes = [
+            SolverMode::BruteForce,
+            SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5)))),
+        ];
+        for mode in modes {
+            let (player_1_moves, player_1_evaluation) = Solver::from_root(as_player_1.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+            let (player_2_moves, player_2_evaluation) = Solver::from_root(as_player_2.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+            assert_eq!(player_2_moves, player_1_moves);
+            assert!((player_2_evaluation.get_evaluation() + player_1_evaluation.get_evaluation()).abs() < 0.0001);
+            assert!(
+                (player_2_evaluation.for_active_player(&as_player_2).get_evaluation()
+                    - player_1_evaluation.for_active_player(&as_player_1).get_evaluation()).abs() < 0.0001
+            );
+        }
+    }
+
+    #[test]
+    fn test_solver_negates_mirrored_evaluations() {
+        // Swapping the boards and the player to act is the same game from the other seat, so every evaluation is
+        // exactly negated, not just to within rounding.
+        let opening = Node::empty().apply_sequence(&[(Die::Three, Move::new(2, 1)), (Die::Three, Move::new(0, 1))]).unwrap().with_roll(Die::Five).unwrap();
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        assert_eq!(endgame.mirrored().get_player_1_board(), endgame.get_player_2_board());
+        assert_eq!(endgame.mirrored().get_active_player(), Player::Player1);
+        assert!(endgame.mirrored().mirrored().equals_up_to_children(&endgame));
+
+        let synergy_weights = SynergyWeights::default();
+        let threat_params = ThreatParams::default();
+        let objectives = [
+            Objective::new(|x| Solver::difference_heuristic(x, 3.5)),
+            Objective::new(move |x| Solver::column_synergy_heuristic(x, &synergy_weights)),
+            Objective::new(move |x| Solver::elimination_threat_heuristic(x, &threat_params)),
+        ];
+        for objective in objectives.iter() {
+            for position in [&opening, &endgame] {
+                assert_eq!(objective.evaluate(&position.mirrored()), -objective.evaluate(position));
+            }
+        }
+
+        let heuristic_modes = objectives.into_iter().map(|objective| SolverMode::Heuristic((2, objective))).collect::<Vec<SolverMode>>();
+        let mut endgame_modes = heuristic_modes.clone();
+        endgame_modes.push(SolverMode::BruteForce);
+        // Brute forcing the opening would take too long.
+        for (position, modes) in [(opening, heuristic_modes), (endgame, endgame_modes)] {
+            for mode in modes.iter() {
+                let (moves, evaluation) = Solver::from_root(position.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+                let (mirrored_moves, mirrored_evaluation) = Solver::from_root(position.mirrored()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+                assert_eq!(mirrored_moves, moves);
+                assert_eq!(mirrored_evaluation, evaluation.with_evaluation(-evaluation.get_evaluation()).unwrap());
+                assert_eq!(mirrored_evaluation.for_active_player(&position.mirrored()), evaluation.for_active_player(&position));
+
+                let ranked = Solver::from_root(position.clone()).get_all_moves_ranked(mode.clone()).unwrap();
+                let mirrored_ranked = Solver::from_root(position.mirrored()).get_all_moves_ranked(mode.clone()).unwrap();
+                assert_eq!(
+                    mirrored_ranked,
+                    ranked.into_iter().map(|(m, evaluation)| (m, evaluation.with_evaluation(-evaluation.get_evaluation()).unwrap())).collect::<Vec<(Move, Evaluation)>>()
+                );
+            }
+            let objective = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+            let mut tree = position.clone();
+            tree.build_n_moves_up_to_symmetry(2);
+            let mut mirrored_tree = position.mirrored();
+            mirrored_tree.build_n_moves_up_to_symmetry(2);
+            assert_eq!(mirrored_tree.get_evaluation(&objective), -tree.get_evaluation(&objective));
+        }
+    }
+
+    #[test]
+    fn test_solver_searches_like_evaluation_tree() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let mut weighted_opening = opening.clone();
+        weighted_opening.set_die_distribution(DieDistribution::from_weights([1., 1., 1., 1., 1., 5.]).unwrap());
+        let cases = vec![
+            (opening.clone(), SolverMode::Heuristic((1, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (opening, SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (weighted_opening, SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame.clone(), SolverMode::Heuristic((5, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame, SolverMode::BruteForce),
+        ];
+        for (root, mode) in cases {
+            let (maybe_tree, evaluation) = Solver::from_root(root.clone()).get_evaluation_tree(mode.clone()).unwrap();
+            let best_moves = Solver::get_best_moves_from_evaluation_tree(maybe_tree).unwrap();
+            let mut solver = Solver::from_root(root);
+            // The transposition table can stand deeper searches in for shallower ones, which the tree never does.
+            solver.set_use_transposition_table(false);
+            assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), (best_moves, evaluation));
+            // Nothing was built to get there.
+            assert!(solver.get_root().is_leaf());
+        }
+        assert!(Solver::from_root(Node::empty()).get_best_moves_and_evaluation(SolverMode::BruteForce).is_err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_solver_searches_in_parallel() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let mut weighted_opening = opening.clone();
+        weighted_opening.set_die_distribution(DieDistribution::from_weights([1., 1., 1., 1., 1., 5.]).unwrap());
+        let cases = vec![
+            (opening.clone(), SolverMode::Heuristic((1, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (opening, SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (weighted_opening, SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame.clone(), SolverMode::Heuristic((5, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame, SolverMode::BruteForce),
+        ];
+        for (root, mode) in cases {
+            let mut serial_solver = Solver::from_root(root.clone());
+            serial_solver.set_use_transposition_table(false);
+            let serial_result = serial_solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+            for n_threads in [0, 2, 3] {
+                let config = SolverConfig::builder().n_threads(n_threads).build().unwrap();
+                let mut solver = Solver::from_root_with_config(root.clone(), config);
+                solver.set_use_transposition_table(false);
+                assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), serial_result);
+            }
+            // With the transposition table, the threads don't see each other's entries, but still always come to the
+            // same answer.
+            let mut solver = Solver::from_root_with_config(root.clone(), SolverConfig::builder().n_threads(2).build().unwrap());
+            let parallel_result = solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+            let mut solver = Solver::from_root_with_config(root, SolverConfig::builder().n_threads(3).build().unwrap());
+            assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), parallel_result);
+        }
+    }
+
+    #[test]
+    fn test_solver_solves_exact_memoized() {
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let mut weighted_endgame = endgame.clone();
+        weighted_endgame.set_die_distribution(DieDistribution::from_weights([1., 1., 1., 1., 1., 5.]).unwrap());
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let winning_endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let player_1_board = Board::from_string("64_\n5_4\n634".to_string()).unwrap();
+        let player_2_board = Board::from_string("_11\n211\n211".to_string()).unwrap();
+        let tied_endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let configs = [
+            SolverConfig::default(),
+            SolverConfig::builder().break_ties_by_margin(true).build().unwrap(),
+            SolverConfig::builder().search_objective(SearchObjective::ExpectedMargin).build().unwrap(),
+        ];
+        for root in [endgame.clone(), endgame.mirrored(), weighted_endgame, winning_endgame, tied_endgame] {
+            for config in configs.clone() {
+                let brute_force_result = Solver::from_root_with_config(root.clone(), config.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+                let (_, tree_evaluation) = Solver::from_root_with_config(root.clone(), config.clone()).get_evaluation_tree(SolverMode::BruteForce).unwrap();
+                let (best_moves, evaluation, stats) = Solver::from_root_with_config(root.clone(), config).solve_exact_memoized().unwrap();
+                assert_eq!((best_moves, evaluation), brute_force_result);
+                assert_eq!(evaluation.get_evaluation(), tree_evaluation.get_evaluation());
+                assert!(stats.get_n_unique_states() <= stats.get_n_expansions());
+            }
+        }
+
+        // The endgame reaches the same positions, up to symmetry, by different lines.
+        let (_, _, stats) = Solver::from_root(endgame.clone()).solve_exact_memoized().unwrap();
+        assert!(stats.get_n_unique_states() < stats.get_n_expansions());
+
+        let sampled_config = SolverConfig::builder()
+            .chance_expansion(ChanceExpansion::Sampled { n_rolls: 2, full_plies: 0, seed: 7 })
+            .build()
+            .unwrap();
+        assert!(matches!(Solver::from_root_with_config(endgame, sampled_config).solve_exact_memoized(), Err(SolverError::InvalidMode(_))));
+        let mut solver = Solver::from_root(Node::new(Board::empty(), Board::empty(), NodeType::Roll(Player::Player1)));
+        assert_eq!(
+            solver.solve_exact_memoized().map(|_| ()),
+            Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }),
+        );
+    }
+
+    #[test]
+    fn test_solver_memoized_matches_brute_force() {
+        // Endgames from a couple of empty squares up to where eliminations keep the game going past brute force's
+        // depth, whose best moves and evaluations come out exactly the same, with the transposition table or without.
+        let endgames = [
+            "1125545_6 / 435623_4_ 2 r6",
+            "111_22_33 / 44455_666 1 r6",
+            "361651_5_ / 4451_31_5 1 r2",
+            "64_5_4634 / _11211211 1 r4",
+            "661142_62 / 2561_262_ 2 r6",
+            "125_3_2_4 / 26_1435_6 1 r3",
+        ];
+        for kfen in endgames {
+            let endgame = Node::from_kfen(kfen).unwrap();
+            let (best_moves, evaluation, _) = Solver::from_root(endgame.clone()).solve_exact_memoized().unwrap();
+            for use_transposition_table in [true, false] {
+                let mut solver = Solver::from_root(endgame.clone());
+                solver.set_use_transposition_table(use_transposition_table);
+                assert_eq!(solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), (best_moves.clone(), evaluation));
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_solver_brute_forces_in_parallel() {
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let player_1_board = Board::from_string("25_\n21_\n3__".to_string()).unwrap();
+        let player_2_board = Board::from_string("_66\n134\n123".to_string()).unwrap();
+        let longer_endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let mut weighted_endgame = longer_endgame.clone();
+        weighted_endgame.set_die_distribution(DieDistribution::from_weights([1., 1., 1., 1., 1., 5.]).unwrap());
+        for root in [endgame.clone(), endgame.mirrored(), longer_endgame, weighted_endgame] {
+            // Split on the rolls as well as the moves, the average over the rolls is still the serial search's.
+            let mut serial_solver = Solver::from_root(root.clone());
+            serial_solver.set_use_transposition_table(false);
+            let serial_result = serial_solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+            let serial_ranking = serial_solver.get_all_moves_ranked(SolverMode::BruteForce).unwrap();
+            for n_threads in [0, 2, 3] {
+                let config = SolverConfig::builder().n_threads(n_threads).build().unwrap();
+                let mut solver = Solver::from_root_with_config(root.clone(), config);
+                solver.set_use_transposition_table(false);
+                assert_eq!(solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), serial_result);
+                assert_eq!(solver.get_all_moves_ranked(SolverMode::BruteForce).unwrap(), serial_ranking);
+            }
+
+            // With the transposition table, however many threads there are, they're given the same positions to
+            // search, so they always agree with each other, if not always to the last bit with the serial search.
+            let results = [0, 2, 3].map(
+                |n_threads| Solver::from_root_with_config(root.clone(), SolverConfig::builder().n_threads(n_threads).build().unwrap())
+                    .get_best_moves_and_evaluation(SolverMode::BruteForce)
+                    .unwrap()
+            );
+            assert_eq!(results[0], results[1]);
+            assert_eq!(results[1], results[2]);
+            assert_eq!(results[1].0, serial_result.0);
+        }
+
+        // The threads sample the same rolls as the serial search, including the ones it's split on.
+        let sampled_config = |n_threads| SolverConfig::builder()
+            .n_threads(n_threads)
+            .chance_expansion(ChanceExpansion::Sampled { n_rolls: 2, full_plies: 0, seed: 7 })
+            .build()
+            .unwrap();
+        let mut serial_solver = Solver::from_root_with_config(endgame.clone(), sampled_config(1));
+        serial_solver.set_use_transposition_table(false);
+        let serial_result = serial_solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert!(serial_result.1.is_sampled());
+        for n_threads in [0, 2, 3] {
+            let mut solver = Solver::from_root_with_config(endgame.clone(), sampled_config(n_threads));
+            solver.set_use_transposition_table(false);
+            assert_eq!(solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), serial_result);
+        }
+    }
+
+    #[test]
+    fn test_solver_uses_transposition_table() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let cases = vec![
+            (opening, SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame.clone(), SolverMode::BruteForce),
+        ];
+        for (root, mode) in cases {
+            let mut solver = Solver::from_root(root.clone());
+            solver.set_use_transposition_table(false);
+            let expected = solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+            let stats_without_table = solver.get_search_stats();
+            assert_eq!((stats_without_table.get_n_hits(), stats_without_table.get_n_misses()), (0, 0));
+
+            let mut solver = Solver::from_root(root);
+            let result = solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+            assert_eq!(result, expected);
+            let stats = solver.get_search_stats();
+            assert!(stats.get_n_hits() > 0);
+            assert!(stats.get_n_nodes() < stats_without_table.get_n_nodes());
+
+            // The second time round the root itself is in the table.
+            assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), result);
+            assert_eq!(solver.get_search_stats().get_n_nodes(), 1);
+            assert_eq!(solver.get_search_stats().get_n_hits(), 1);
+            solver.clear_transposition_table();
+            assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), result);
+            let counts = |stats: SearchStats| (stats.get_n_nodes(), stats.get_n_leaves(), stats.get_n_hits(), stats.get_n_misses(), stats.get_n_cutoffs(), stats.get_depth());
+            assert_eq!(counts(solver.get_search_stats()), counts(stats));
+        }
+
+        // Entries only stand in for searches of the same depth, so neither a deeper nor a shallower one changes the
+        // result.
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let mut solver = Solver::from_root(endgame.clone());
+        solver.get_best_moves_and_evaluation(SolverMode::Heuristic((5, objective_function.clone()))).unwrap();
+        assert_eq!(
+            solver.get_best_moves_and_evaluation(SolverMode::Heuristic((3, objective_function.clone()))).unwrap(),
+            Solver::from_root(endgame.clone()).get_best_moves_and_evaluation(SolverMode::Heuristic((3, objective_function.clone()))).unwrap(),
+        );
+        let mut solver = Solver::from_root(endgame);
+        solver.get_best_moves_and_evaluation(SolverMode::Heuristic((1, objective_function.clone()))).unwrap();
+        let shallow_stats = solver.get_search_stats();
+        solver.get_best_moves_and_evaluation(SolverMode::Heuristic((2, objective_function.clone()))).unwrap();
+        assert!(solver.get_search_stats().get_n_nodes() > shallow_stats.get_n_nodes());
+    }
+
+    #[test]
+    fn test_solver_transposition_table_keeps_evaluations() {
+        // The table only saves searching, so every evaluation comes out exactly the same without it, including with
+        // pruning, which leaves lower bounds in it, and with extensions, which search the same position deeper
+        // depending on the way there.
+        let midgame = Node::from_kfen("_15_32___ / _41___36_ 2 r1").unwrap();
+        let endgame = Node::from_kfen("361651_5_ / 4451_31_5 1 r2").unwrap();
+        let ordered = SolverConfig::builder().move_ordering(MoveOrdering::Static).build().unwrap();
+        let extended = SolverConfig::builder().extension_threshold(6).max_extensions(2).build().unwrap();
+        let cases = [
+            (midgame.clone(), SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5)))), extended.clone()),
+            (midgame, SolverMode::Heuristic((2, Solver::brute_force_objective())), ordered.clone()),
+            (endgame.clone(), SolverMode::BruteForce, ordered.clone()),
+            (endgame, SolverMode::BruteForce, extended),
+            (Node::from_kfen("661142_62 / 2561_262_ 2 r6").unwrap(), SolverMode::BruteForce, ordered),
+        ];
+        for (position, mode, config) in cases {
+            let solve = |use_transposition_table| {
+                let mut solver = Solver::from_root_with_config(position.clone(), config.clone());
+                solver.set_use_transposition_table(use_transposition_table);
+                (solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), solver.get_all_moves_ranked(mode.clone()).unwrap(), solver.get_search_stats())
+            };
+            let (result, ranked_moves, stats) = solve(true);
+            let (expected, expected_ranked_moves, stats_without_table) = solve(false);
+            assert_eq!(result, expected);
+            assert_eq!(ranked_moves, expected_ranked_moves);
+            assert!(stats.get_n_hits() > 0);
+            assert!(stats.get_n_nodes() < stats_without_table.get_n_nodes());
+        }
+    }
+
+    #[test]
+    fn test_solver_counts_search_stats() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let mut solver = Solver::from_root(opening.clone());
+        solver.get_best_moves_and_evaluation(SolverMode::Heuristic((3, objective_function.clone()))).unwrap();
+        let stats = solver.get_search_stats();
+        assert_eq!(stats.get_depth(), 3);
+        assert!(stats.get_n_leaves() > 0 && stats.get_n_leaves() <= stats.get_n_nodes());
+        assert!(stats.get_n_cutoffs() > 0 && stats.get_n_cutoffs() < stats.get_n_leaves());
+        assert!(stats.get_n_hits() > 0);
+        assert!(stats.get_elapsed() > Duration::ZERO);
+
+        // One move ahead, every move is followed by a roll the search stops at, and each roll's position is a leaf.
+        let mut solver = Solver::from_root(opening.clone());
+        solver.set_use_transposition_table(false);
+        solver.get_best_moves_and_evaluation(SolverMode::Heuristic((1, objective_function.clone()))).unwrap();
+        let n_moves = opening.get_legal_moves_up_to_row_symmetry().unwrap().len();
+        assert_eq!(solver.get_search_stats().get_n_cutoffs(), n_moves);
+        assert_eq!(solver.get_search_stats().get_n_leaves(), n_moves * 6);
+        assert_eq!(solver.get_search_stats().get_n_nodes(), 1 + n_moves + n_moves * 6);
+
+        // Building the tree counts the tree.
+        let mut solver = Solver::from_root(opening.clone());
+        solver.get_evaluation_tree(SolverMode::Heuristic((2, objective_function.clone()))).unwrap();
+        let tree_stats = solver.get_root().get_tree_stats();
+        let stats = solver.get_search_stats();
+        assert_eq!((stats.get_n_nodes(), stats.get_n_leaves(), stats.get_depth()), (tree_stats.get_n_nodes(), tree_stats.get_n_leaves(), 2));
+        solver.get_evaluation_tree(SolverMode::NodeBudget((500, objective_function))).unwrap();
+        assert!(solver.get_search_stats().get_depth() >= 1);
+
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let mut solver = Solver::from_root(Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six)));
+        solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        let stats = solver.get_search_stats();
+        assert_eq!(stats.get_depth(), DEFAULT_BRUTE_FORCE_DEPTH);
+        assert!(stats.get_n_leaves() > 0 && stats.get_n_leaves() <= stats.get_n_nodes());
+        assert!(stats.to_string().starts_with(&format!("Nodes: {}\nLeaves: {}", stats.get_n_nodes(), stats.get_n_leaves())));
+    }
+
+    #[test]
+    fn test_solver_solves_timed() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let player_1_board = Board::from_string("661\n142\n6_3".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n152\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        // Without the transposition table the earlier, shallower searches can't feed into the last one, so it's the
+        // same as searching that deep straight away.
+        let mut solver = Solver::from_root(endgame.clone());
+        solver.set_use_transposition_table(false);
+        let result = solver.get_best_moves_and_evaluation(SolverMode::Timed((Duration::from_secs(60), objective_function.clone()))).unwrap();
+        // With plenty of time it searches as far as the game could possibly go.
+        let depth = solver.get_search_stats().get_depth();
+        assert_eq!(depth, endgame.get_moves_left_bounds().1);
+        let mut fixed_depth_solver = Solver::from_root(endgame.clone());
+        fixed_depth_solver.set_use_transposition_table(false);
+        assert_eq!(fixed_depth_solver.get_best_moves_and_evaluation(SolverMode::Heuristic((depth, objective_function.clone()))).unwrap(), result);
+        let (_, evaluation) = solver.get_evaluation_tree(SolverMode::Timed((Duration::from_secs(60), objective_function.clone()))).unwrap();
+        assert_eq!(evaluation, result.1);
+
+        // Out of time, it still finishes the first move.
+        let root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Three));
+        let mut solver = Solver::from_root(root.clone());
+        let (best_moves, _) = solver.get_best_moves_and_evaluation(SolverMode::Timed((Duration::ZERO, objective_function.clone()))).unwrap();
+        assert_eq!(solver.get_search_stats().get_depth(), 1);
+        assert!(!best_moves.is_empty());
+        assert!(best_moves.iter().all(|m| root.get_legal_moves().unwrap().contains(m)));
+
+        // A search that's cut off part way is dropped rather than holding things up.
+        let start = Instant::now();
+        solver.get_best_moves_and_evaluation(SolverMode::Timed((Duration::from_millis(50), objective_function.clone()))).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(solver.get_search_stats().get_depth() < root.get_moves_left_bounds().1);
+    }
+
+    #[test]
+    fn test_expected_elimination_heuristic() {
+        // Player 2's tripled five is worth 45 points, and Player 1 has room in that column, so it's worth 45 / 6 to
+        // Player 1.  Player 2 can only take Player 1's 2, since there's no room opposite the 1.
+        let player_1_board = Board::from_string("1_2\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("5__\n5__\n5__".to_string()).unwrap();
+        let root = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
+        assert!((Solver::expected_elimination_heuristic(&root) - (45. / 6. - 2. / 6.)).abs() < 1e-4);
+        assert!((Solver::expected_elimination_heuristic(&root.mirrored()) + (45. / 6. - 2. / 6.)).abs() < 1e-4);
+
+        // A Player 1 who's already rolled the five takes it all, and one who's rolled anything else takes nothing.
+        let rolled_five = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Five));
+        assert!((Solver::expected_elimination_heuristic(&rolled_five) - (45. - 2. / 6.)).abs() < 1e-4);
+        let rolled_four = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Four));
+        assert!((Solver::expected_elimination_heuristic(&rolled_four) + 2. / 6.).abs() < 1e-4);
+
+        // Fives three times as likely as anything else.
+        let mut weighted = root.clone();
+        weighted.set_die_distribution(DieDistribution::from_weights([1., 1., 1., 1., 3., 1.]).unwrap());
+        assert!((Solver::expected_elimination_heuristic(&weighted) - (45. * 3. / 8. - 2. / 8.)).abs() < 1e-4);
+
+        // With no room opposite the fives, there's nothing to take.
+        let player_1_board = Board::from_string("1_2\n4__\n3__".to_string()).unwrap();
+        let blocked = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+        assert!((Solver::expected_elimination_heuristic(&blocked) + 2. / 6.).abs() < 1e-4);
+        let finished = Node::new(Board::from_string("111\n222\n333".to_string()).unwrap(), Board::empty(), NodeType::Roll(Player::Player2));
+        assert_eq!(Solver::expected_elimination_heuristic(&finished), 0.);
+    }
+
+    #[test]
+    fn test_heuristic_accuracy_report() {
+        let positions = get_accuracy_positions(&get_accuracy_seeds(&[1], 3), 3);
+        let with_roll_pending = Node::new(positions[0].get_player_1_board(), positions[0].get_player_2_board(), NodeType::Roll(positions[0].get_active_player()));
+
+        // Brute force's own objective searched as deep as brute force agrees with it everywhere.
+        let report = Solver::heuristic_accuracy_report(&Solver::brute_force_objective(), DEFAULT_BRUTE_FORCE_DEPTH, &[positions.clone(), vec![with_roll_pending]].concat()).unwrap();
+        assert_eq!(report.get_n_positions(), positions.len());
+        assert_eq!(report.get_n_skipped(), 1);
+        assert_eq!(report.get_move_agreement(), 1.);
+        assert_eq!(report.get_mean_value_lost(), 0.);
+        assert!(report.get_mean_absolute_error() < 1e-4);
+        for position in report.get_positions() {
+            assert!(position.get_best_moves().contains(&position.get_heuristic_move()));
+            assert!(position.get_heuristic_evaluation().is_expected_result() && position.get_best_evaluation().is_expected_result());
+        }
+
+        let report = Solver::heuristic_accuracy_report(&Objective::new(|_| 0.), 1, &positions).unwrap();
+        assert_eq!(report.get_n_positions(), positions.len());
+        assert!((0. ..=1.).contains(&report.get_move_agreement()));
+        assert!(report.get_positions().iter().all(|position| position.preserves_best_value() || position.get_value_lost() > 0.));
+        assert!(report.get_mean_absolute_error() > 0.);
+    }
+
+    #[test]
+    fn test_solver_weighs_rolls_by_risk_profile() {
+        // Playing the Five in Player 2's first column wins unless Player 1 rolls a Five and takes it straight back,
+        // while the middle column can't be won or lost outright on the next roll.
+        let player_1_board = Board::from_string("315\n452\n1_5".to_string()).unwrap();
+        let player_2_board = Board::from_string("544\n_34\n__1".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        let ranked_moves = Solver::from_root(root.clone()).get_all_moves_ranked(SolverMode::BruteForce).unwrap();
+        assert_eq!(ranked_moves[0], (Move::new(1, 0), Evaluation::new(EvaluationKind::ExpectedResult, -0.5).unwrap()));
+        let (_, safe_evaluation) = *ranked_moves.iter().find(|(m, _)| *m == Move::new(2, 1)).unwrap();
+
+        let solve = |risk_profile: RiskProfile| {
+            let config = SolverConfig::builder().strategy(SolverStrategy::BruteForce).risk_profile(risk_profile).build().unwrap();
+            Solver::from_root_with_config(root.clone(), config).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap()
+        };
+        // The other moves' lines run past brute force's depth, so picking between them is truncated.
+        assert_eq!(solve(RiskProfile::ExpectedValue), (vec![Move::new(1, 0)], Evaluation::new(EvaluationKind::ExpectedResult, -0.5).unwrap().truncated()));
+        // The evaluation is still the expected result of the move picked.
+        let (best_moves, evaluation) = solve(RiskProfile::WorstCase);
+        assert_eq!(best_moves, vec![Move::new(2, 1)]);
+        assert!((evaluation.get_evaluation() - safe_evaluation.get_evaluation()).abs() < 1e-4);
+        // Only one roll in six goes wrong, so the median roll still favours the first column.
+        assert_eq!(solve(RiskProfile::Pessimistic { quantile: 0.5 }).0, vec![Move::new(1, 0)]);
+        assert_eq!(solve(RiskProfile::Pessimistic { quantile: 0.1 }).0, vec![Move::new(2, 1)]);
+    }
+
+    #[test]
+    fn test_solver_perft() {
+        // From the empty board, each move is one of 6 rolls and then one of 9 squares, or one of 3 columns up to row
+        // symmetry, and every position counted has its roll.  The third move has a square fewer unless the second
+        // knocked out the first, which 3 of its 54 ways do: 54 * (3 * 54 + 51 * 48) * 6 in all.  Up to row symmetry,
+        // a column with one die has one move as much as an empty one.
+        let solver = Solver::from_root(Node::empty());
+        assert_eq!((0..=2).map(|depth| solver.perft_full(depth)).collect::<Vec<usize>>(), vec![6, 324, 17_496]);
+        assert_eq!((0..=3).map(|depth| solver.perft_up_to_symmetry(depth)).collect::<Vec<usize>>(), vec![6, 108, 1944, 34_992]);
+        assert_eq!(solver.perft_full(3), 845_640);
+
+        // The trees the solver builds have as many leaves, here and from a position with eliminations to come.
+        let position = Node::from_kfen("1125545_6 / 435623_4_ 2 r6").unwrap();
+        for (root, depth) in [(Node::empty(), 2), (position.clone(), 3)] {
+            let mut full = root.clone();
+            full.build_n_moves(depth);
+            assert_eq!(full.get_tree_stats().get_n_leaves(), Solver::from_root(root.clone()).perft_full(depth));
+            let mut up_to_symmetry = root.clone();
+            up_to_symmetry.build_n_moves_up_to_symmetry(depth);
+            assert_eq!(up_to_symmetry.get_tree_stats().get_n_leaves(), Solver::from_root(root).perft_up_to_symmetry(depth));
+        }
+
+        let solver = Solver::from_root(position);
+        for up_to_symmetry in [false, true] {
+            let divided = solver.divide(3, up_to_symmetry).unwrap();
+            assert_eq!(divided.iter().map(|(m, _)| *m).collect::<Vec<Move>>(), vec![Move::new(2, 0), Move::new(2, 2)]);
+            assert_eq!(divided.iter().map(|(_, n_leaves)| n_leaves).sum::<usize>(), solver.perft(3, up_to_symmetry));
+        }
+        assert_eq!(solver.divide(0, false), Err(SolverError::DepthZero));
+        assert!(matches!(Solver::from_root(Node::empty()).divide(1, false), Err(SolverError::WrongNodeType { .. })));
+    }
+
+    #[test]
+    fn test_solver_orders_moves() {
+        // Ordering the moves only changes how much gets searched.
+        let midgame = Node::from_kfen("_15_32___ / _41___36_ 2 r1").unwrap();
+        let endgame = Node::from_kfen("361651_5_ / 4451_31_5 1 r2").unwrap();
+        for (root, mode) in [(midgame.clone(), SolverMode::Heuristic((4, Solver::brute_force_objective()))), (endgame, SolverMode::BruteForce)] {
+            let search = |move_ordering| {
+                let mut solver = Solver::from_root_with_config(root.clone(), SolverConfig::builder().move_ordering(move_ordering).build().unwrap());
+                (solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), solver.get_search_stats())
+            };
+            let (result, stats) = search(MoveOrdering::None);
+            assert_eq!(stats.get_n_pruned(), 0);
+            for move_ordering in [MoveOrdering::Static, MoveOrdering::ShallowSearch] {
+                let (ordered_result, ordered_stats) = search(move_ordering);
+                assert_eq!(ordered_result, result);
+                assert!(ordered_stats.get_n_pruned() > 0);
+                assert!(ordered_stats.get_n_nodes() < stats.get_n_nodes());
+            }
+        }
+
+        // Heuristics have no bounds to prune with, so they're searched the same either way.
+        let mode = SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        let mut solver = Solver::from_root(midgame.clone());
+        let result = solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+        let mut ordered_solver = Solver::from_root_with_config(midgame, SolverConfig::builder().move_ordering(MoveOrdering::ShallowSearch).build().unwrap());
+        assert_eq!(ordered_solver.get_best_moves_and_evaluation(mode).unwrap(), result);
+        assert_eq!(ordered_solver.get_search_stats().get_n_nodes(), solver.get_search_stats().get_n_nodes());
+    }
+
+    #[test]
+    fn test_solver_merges_rolls() {
+        // The difference heuristic and brute force's objectives only look at the boards, so where the search stops,
+        // one roll is scored for all six, and the evaluations and best moves come out exactly the same.
+        let opening = Node::from_kfen("3________ / _________ 2 r5").unwrap();
+        let endgame = Node::from_kfen("361651_5_ / 4451_31_5 1 r2").unwrap();
+        let heuristic_mode = SolverMode::Heuristic((3, HeuristicFunction::Difference.to_objective()));
+        for (root, mode) in [(opening.clone(), heuristic_mode), (endgame, SolverMode::BruteForce)] {
+            let mut solver = Solver::from_root(root.clone());
+            let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+            let mut merging_solver = Solver::from_root_with_config(root, SolverConfig::builder().merge_rolls(true).build().unwrap());
+            let (merged_best_moves, merged_evaluation) = merging_solver.get_best_moves_and_evaluation(mode).unwrap();
+            assert_eq!(merged_best_moves, best_moves);
+            assert_eq!(merged_evaluation, evaluation);
+            assert_eq!(merged_evaluation.get_evaluation().to_bits(), evaluation.get_evaluation().to_bits());
+            assert_eq!(solver.get_search_stats().get_n_merged_rolls(), 0);
+            let merged_stats = merging_solver.get_search_stats();
+            assert_eq!(merged_stats.get_n_merged_rolls(), 5 * merged_stats.get_n_cutoffs());
+            assert!(merged_stats.get_n_nodes() < solver.get_search_stats().get_n_nodes());
+        }
+
+        // Nothing's merged for an objective that might look at the roll.
+        let mode = SolverMode::Heuristic((2, Objective::new(Solver::expected_elimination_heuristic)));
+        let mut merging_solver = Solver::from_root_with_config(opening, SolverConfig::builder().merge_rolls(true).build().unwrap());
+        merging_solver.get_best_moves_and_evaluation(mode).unwrap();
+        assert_eq!(merging_solver.get_search_stats().get_n_merged_rolls(), 0);
+    }
+
+    #[test]
+    fn test_opponent_reply_heuristic() {
+        // A move's reply is the last move a search one move deeper would make, so scoring the leaves with the reply
+        // inner likes best matches the deeper search, to within rounding.
+        let inner = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let reply_inner = inner.clone();
+        let reply = Objective::new(move |x| Solver::opponent_reply_heuristic(x, |y| reply_inner.evaluate(y)));
+        for root in [Node::from_kfen("4665_2__2 / 2__345_55 2 r4").unwrap(), Node::from_kfen("____42___ / 2_____5__ 2 r3").unwrap()] {
+            for depth in [1, 2] {
+                let search = |depth, objective_function: &Objective| {
+                    Solver::from_root(root.clone()).get_best_moves_and_evaluation(SolverMode::Heuristic((depth, objective_function.clone()))).unwrap()
+                };
+                let (best_moves, evaluation) = search(depth, &reply);
+                let (deeper_best_moves, deeper_evaluation) = search(depth + 1, &inner);
+                assert_eq!(best_moves, deeper_best_moves);
+                assert!(are_tied(evaluation.get_evaluation(), deeper_evaluation.get_evaluation()));
+            }
+        }
+
+        // Waiting on a roll, it's the average of the replies to each roll, and a finished game is left to inner.
+        let roll_node = Node::from_kfen("____42___ / 2_____5__ 1 -").unwrap();
+        let expected = Die::all().into_iter()
+            .map(|die| Solver::opponent_reply_heuristic(&roll_node.with_roll(die).unwrap(), |x| inner.evaluate(x)) / 6.)
+            .sum::<f32>();
+        assert!(are_tied(Solver::opponent_reply_heuristic(&roll_node, |x| inner.evaluate(x)), expected));
+        let finished = Node::from_kfen("123456123 / 1_1_1_1_1 2 -").unwrap();
+        assert_eq!(Solver::opponent_reply_heuristic(&finished, |x| inner.evaluate(x)), inner.evaluate(&finished));
+    }
+
+    #[test]
+    fn test_solver_weighs_draws() {
+        // Player 2 to place a Six can make it a third each to win, draw or lose, or take a 38% chance of winning at
+        // the cost of a 54% chance of losing and only a 7% chance of drawing.  Counting a draw as worth less than
+        // about -0.61 makes the gamble the better bet.
+        let root = Node::from_kfen("1125545_6 / 435623_4_ 2 r6").unwrap();
+        let solve = |draw_weight: f32| {
+            let config = SolverConfig::builder().draw_weight(draw_weight).build().unwrap();
+            Solver::from_root_with_config(root.clone(), config).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap()
+        };
+        let (best_moves, evaluation) = solve(DEFAULT_DRAW_WEIGHT);
+        assert_eq!(best_moves, vec![Move::new(2, 0)]);
+        assert!(are_tied(evaluation.get_evaluation(), 0.));
+        assert_eq!(solve(-0.5).0, vec![Move::new(2, 0)]);
+        assert_eq!(solve(-0.7).0, vec![Move::new(2, 2)]);
+        assert_eq!(solve(-1.).0, vec![Move::new(2, 2)]);
+        // Counting draws as wins, the draw's worth a third of a point more to Player 2.
+        let (best_moves, evaluation) = solve(1.);
+        assert_eq!(best_moves, vec![Move::new(2, 0)]);
+        assert!(are_tied(evaluation.get_evaluation(), -1. / 3.));
+
+        assert!(SolverConfig::builder().draw_weight(1.5).build().is_err());
+    }
+
+    #[test]
+    fn test_solver_scheduled_depth() {
+        // 5 empty squares are searched a move deep, and 4 brute forced.
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let mode = SolverMode::Scheduled((DepthSchedule::from_string("5:1,bf:4").unwrap(), objective_function.clone()));
+        let heuristic_root = Node::new(
+            Board::from_string("61_\n142\n62_".to_string()).unwrap(),
+            Board::from_string("256\n1__\n62_".to_string()).unwrap(),
+            NodeType::Move(Player::Player1, Die::Three),
+        );
+        let brute_force_root = Node::new(
+            Board::from_string("61_\n142\n62_".to_string()).unwrap(),
+            Board::from_string("256\n1__\n625".to_string()).unwrap(),
+            NodeType::Move(Player::Player1, Die::Three),
+        );
+        assert_eq!(heuristic_root.get_n_empty_squares(), 5);
+        assert_eq!(brute_force_root.get_n_empty_squares(), 4);
+
+        let mut solver = Solver::from_root(heuristic_root.clone());
+        assert_eq!(
+            solver.get_best_moves_and_evaluation(mode.clone()).unwrap(),
+            Solver::from_root(heuristic_root).get_best_moves_and_evaluation(SolverMode::Heuristic((1, objective_function))).unwrap(),
+        );
+        assert_eq!(solver.get_search_stats().get_depth(), 1);
+
+        let (best_moves, evaluation) = Solver::from_root(brute_force_root.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+        assert!(evaluation.is_expected_result());
+        assert_eq!((best_moves, evaluation), Solver::from_root(brute_force_root.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap());
+        let (_, tree_evaluation) = Solver::from_root(brute_force_root.clone()).get_evaluation_tree(mode).unwrap();
+        assert_eq!(tree_evaluation, Solver::from_root(brute_force_root).get_evaluation_tree(SolverMode::BruteForce).unwrap().1);
+    }
+
+    #[test]
+    fn test_solver_outcome_distribution() {
+        // Player 1's One goes in the last column either way, for 36 points against Player 2's 30 before their roll,
+        // which fills their board.  A Two or a Five knocks out Player 1's matching dice, and a One makes three of a
+        // kind: 1 (36 - 35), -4 (28 - 32), 3 (36 - 33), 2 (36 - 34), -4 (31 - 35) and 0 (36 - 36).
+        let player_1_board = Board::from_string("24_\n24_\n56_".to_string()).unwrap();
+        let player_2_board = Board::from_string("142\n154\n_65".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::One));
+        let distribution = Solver::from_root(root.clone()).get_outcome_distribution(SolverMode::BruteForce).unwrap();
+        assert_eq!(distribution.get_probabilities().keys().copied().collect::<Vec<i16>>(), vec![-4, 0, 1, 2, 3]);
+        assert!(are_tied(distribution.get_probability(-4), 2. / 6.));
+        assert!(are_tied(distribution.get_probability(3), 1. / 6.));
+        assert!(are_tied(distribution.get_win_probability(), 0.5));
+        assert!(are_tied(distribution.get_draw_probability(), 1. / 6.));
+        assert!(are_tied(distribution.get_loss_probability(), 1. / 3.));
+        assert!(are_tied(distribution.get_expected_margin(), -1. / 3.));
+        assert!(are_tied(distribution.get_variance(), 68. / 9.));
+
+        // The same chances as the evaluation, which counts a draw as half a win.
+        let (_, evaluation) = Solver::from_root(root.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert!(are_tied(evaluation.get_evaluation(), distribution.get_win_probability() - distribution.get_loss_probability()));
+
+        let mode = SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        assert!(matches!(Solver::from_root(root).get_outcome_distribution(mode), Err(SolverError::InvalidMode(_))));
+    }
+
+    #[test]
+    fn test_solver_saves_and_loads_cache() {
+        let player_1_board = Board::from_string("61_\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1__\n62_".to_string()).unwrap();
+        let root = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Three));
+        let path = std::env::temp_dir().join(format!("knucklebones-solver-cache-{}.bin", std::process::id()));
+        let mut solver = Solver::from_root(root.clone());
+        let expected = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        // Whether the root ran into brute force's depth is saved along with it.
+        assert!(expected.1.is_truncated());
+        solver.save_cache(&path).unwrap();
+
+        // A fresh solver finds the root in the cache rather than searching it, whoever's to act.
+        let mut reloaded = Solver::from_root(root.clone());
+        reloaded.load_cache(&path).unwrap();
+        assert_eq!(reloaded.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), expected);
+        assert_eq!(reloaded.get_search_stats().get_n_hits(), 1);
+        assert_eq!(reloaded.get_search_stats().get_n_misses(), 0);
+        let player_2_root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Three));
+        let mut player_2_solver = Solver::from_root(player_2_root.clone());
+        let player_2_expected = player_2_solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        player_2_solver.save_cache(&path).unwrap();
+        let mut player_2_reloaded = Solver::from_root(player_2_root);
+        player_2_reloaded.load_cache(&path).unwrap();
+        assert_eq!(player_2_reloaded.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), player_2_expected);
+        assert_eq!(player_2_reloaded.get_search_stats().get_n_hits(), 1);
+
+        // Heuristic entries and shallow ones are left out.
+        let mut heuristic = Solver::from_root(root.clone());
+        heuristic.get_best_moves_and_evaluation(SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
+        assert_eq!(heuristic.cache_to_bytes().len(), CACHE_FILE_HEADER_BYTES);
+        let config = SolverConfig::builder().cache_min_depth(DEFAULT_BRUTE_FORCE_DEPTH + 1).build().unwrap();
+        let mut shallow = Solver::from_root_with_config(root.clone(), config);
+        shallow.load_cache(&path).unwrap();
+        assert_eq!(shallow.cache_to_bytes().len(), CACHE_FILE_HEADER_BYTES);
+
+        // Entries searched with other dice, or other rolls expanded, are refused, and the same settings are fine.
+        let weighted = SolverConfig::builder().die_distribution(DieDistribution::from_weights([1., 1., 1., 1., 1., 5.]).unwrap()).build().unwrap();
+        let sampled = SolverConfig::builder().chance_expansion(ChanceExpansion::Sampled { n_rolls: 2, full_plies: 1, seed: 3 }).build().unwrap();
+        for config in [weighted, sampled] {
+            let mut mismatched = Solver::from_root_with_config(root.clone(), config.clone());
+            assert!(mismatched.load_cache(&path).is_err());
+            assert_eq!(mismatched.cache_to_bytes().len(), CACHE_FILE_HEADER_BYTES);
+            let mut matched = Solver::from_root_with_config(root.clone(), config.clone());
+            let expected = matched.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+            matched.save_cache(&path).unwrap();
+            let mut reloaded = Solver::from_root_with_config(root.clone(), config);
+            reloaded.load_cache(&path).unwrap();
+            assert_eq!(reloaded.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), expected);
+            assert_eq!(reloaded.get_search_stats().get_n_hits(), 1);
+            assert!(Solver::from_root(root.clone()).load_cache(&path).is_err());
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let bytes = solver.cache_to_bytes();
+        assert!(bytes.len() > CACHE_FILE_HEADER_BYTES);
+        assert!(solver.cache_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(solver.cache_from_bytes(b"KBET\x01").is_err());
+    }
+
+    #[test]
+    fn test_solver_solves_streaming() {
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1__\n62_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Three));
+        let objective = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let expected = Solver::from_root(root.clone()).get_best_moves_and_evaluation(SolverMode::Heuristic((3, objective.clone()))).unwrap();
+
+        // Each depth in turn, with progress in between, and the same result as searching straight to the last one.
+        let (receiver, handle) = Solver::from_root(root.clone()).solve_streaming(SolverMode::Heuristic((3, objective.clone())));
+        let events = receiver.iter().collect::<Vec<SearchEvent>>();
+        let solver = handle.join();
+        let depths = events.iter()
+            .filter_map(|event| if let SearchEvent::DepthCompleted { depth, .. } = event { Some(*depth) } else { None })
+            .collect::<Vec<usize>>();
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert!(matches!(events.last(), Some(SearchEvent::Finished { result }) if *result == Ok(expected.clone())));
+        assert_eq!(events.iter().filter(|event| matches!(event, SearchEvent::Finished { .. })).count(), 1);
+        assert!(matches!(&events[events.len() - 2], SearchEvent::DepthCompleted { depth: 3, best_moves, evaluation } if (best_moves.clone(), *evaluation) == expected));
+        let progress = events.iter()
+            .filter_map(|event| if let SearchEvent::Progress { n_nodes, .. } = event { Some(*n_nodes) } else { None })
+            .collect::<Vec<usize>>();
+        assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(solver.get_search_stats().get_depth(), 3);
+
+        // Cancelled before it starts, there's no depth to fall back on.
+        let cancellation_token = CancellationToken::new();
+        cancellation_token.cancel();
+        let config = SolverConfig::builder().cancellation_token(cancellation_token).build().unwrap();
+        let (receiver, handle) = Solver::from_root_with_config(root, config).solve_streaming(SolverMode::Heuristic((3, objective)));
+        handle.cancel();
+        assert_eq!(receiver.iter().last(), Some(SearchEvent::Finished { result: Err(SolverError::Interrupted) }));
+        handle.join();
+    }
+
+    #[test]
+    fn test_solver_cancels_search() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Three));
+        let cancellation_token = CancellationToken::new();
+        let config = SolverConfig::builder().cancellation_token(cancellation_token.clone()).build().unwrap();
+        let cancel_soon = || {
+            let cancellation_token = cancellation_token.clone();
+            std::thread::spawn(
+                move || {
+                    std::thread::sleep(Duration::from_millis(50));
+                    cancellation_token.cancel();
+                }
+            )
+        };
+
+        // Far too deep to finish, so it's only the cancellation that stops it.
+        let canceller = cancel_soon();
+        let start = Instant::now();
+        let mut solver = Solver::from_root_with_config(root.clone(), config.clone());
+        let result = solver.get_best_moves_and_evaluation(SolverMode::Heuristic((10, objective_function.clone())));
+        canceller.join().unwrap();
+        assert_eq!(result, Err(SolverError::Interrupted));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // It stays cancelled until it's reset.
+        assert_eq!(solver.get_best_moves_and_evaluation(SolverMode::Heuristic((1, objective_function.clone()))), Err(SolverError::Interrupted));
+
+        // Iterative deepening keeps the deepest search that finished.
+        cancellation_token.reset();
+        let canceller = cancel_soon();
+        let start = Instant::now();
+        let mut solver = Solver::from_root_with_config(root.clone(), config.clone());
+        let (best_moves, _) = solver.get_best_moves_and_evaluation(SolverMode::Timed((Duration::from_secs(60), objective_function.clone()))).unwrap();
+        canceller.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(!best_moves.is_empty());
+        assert!(solver.get_search_stats().get_depth() < root.get_moves_left_bounds().1);
+
+        cancellation_token.reset();
+        let mut solver = Solver::from_root_with_config(root.clone(), config);
+        assert_eq!(
+            solver.get_best_moves_and_evaluation(SolverMode::Heuristic((2, objective_function.clone()))),
+            Solver::from_root(root).get_best_moves_and_evaluation(SolverMode::Heuristic((2, objective_function))),
+        );
+    }
+
+    #[test]
+    fn test_solver_extends_on_pending_eliminations() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let root = Node::from_kfen("_362_31_6 / 5_2___322 2 r4").unwrap();
+        let search = |config: SolverConfig, depth: usize| {
+            let mut solver = Solver::from_root_with_config(root.clone(), config);
+            solver.set_use_transposition_table(false);
+            let result = solver.get_best_moves_and_evaluation(SolverMode::Heuristic((depth, objective_function.clone()))).unwrap();
+            (result, solver.get_search_stats().get_n_nodes())
+        };
+        // Two moves ahead, the search stops with eliminations still to come and plays into them, which searching
+        // deeper or extending at them both avoid.
+        let ((plain_moves, _), plain_n_nodes) = search(SolverConfig::default(), 2);
+        let config = SolverConfig::builder().extension_threshold(10).build().unwrap();
+        let ((extended_moves, _), extended_n_nodes) = search(config, 2);
+        let ((deep_moves, _), _) = search(SolverConfig::default(), 4);
+        assert_eq!(plain_moves, vec![Move::new(1, 2)]);
+        assert_eq!(extended_moves, vec![Move::new(0, 1)]);
+        assert_eq!(extended_moves, deep_moves);
+        assert!(extended_n_nodes > plain_n_nodes);
+
+        // No extensions means no change, and more of them can only search more.
+        let config = SolverConfig::builder().extension_threshold(10).max_extensions(0).build().unwrap();
+        assert_eq!(search(config, 2), search(SolverConfig::default(), 2));
+        let config = SolverConfig::builder().extension_threshold(10).max_extensions(4).build().unwrap();
+        assert!(search(config, 2).1 >= extended_n_nodes);
+        let config = SolverConfig::builder().extension_threshold(u16::MAX).build().unwrap();
+        assert_eq!(search(config, 2), search(SolverConfig::default(), 2));
+
+        // Player 1 is about to roll.  Player 2's doubled 2s are worth the most, but Player 1's last column is full, so
+        // the most they could take is the 5.
+        let root = Node::from_kfen("_362_31_6 / 5_2_3_3_2 1 -").unwrap();
+        assert_eq!(Solver::get_max_elimination_threat(&root), 5);
+    }
+
+    #[test]
+    fn test_solver_solves_with_progress() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let config = SolverConfig::builder().progress_interval(Duration::ZERO).build().unwrap();
+        let player_1_board = Board::from_string("661\n142\n6_3".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n152\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        for (root, solver_mode) in [
+            (endgame, SolverMode::Timed((Duration::from_secs(60), objective_function.clone()))),
+            (opening, SolverMode::Heuristic((4, objective_function.clone()))),
+        ] {
+            let mut solver = Solver::from_root_with_config(root.clone(), config.clone());
+            let mut reports = vec![];
+            let result = solver.solve_with_progress(solver_mode.clone(), |progress| reports.push(progress)).unwrap();
+            assert_eq!(Solver::from_root(root).get_best_moves_and_evaluation(solver_mode).unwrap(), result);
+
+            // It ticks part way through the searches as well as when they finish.
+            assert!(reports.len() > 2);
+            assert!(reports.windows(2).all(|pair| pair[0].get_depth() <= pair[1].get_depth()));
+            assert!(reports.windows(2).all(|pair| pair[0].get_n_nodes() <= pair[1].get_n_nodes()));
+            assert!(reports.windows(2).all(|pair| pair[0].get_elapsed() <= pair[1].get_elapsed()));
+            let last = reports.last().unwrap();
+            assert_eq!(last.get_depth(), solver.get_search_stats().get_depth());
+            assert_eq!(last.get_n_nodes(), solver.get_search_stats().get_n_nodes());
+            assert_eq!((last.get_best_moves(), last.get_evaluation()), (Some(&result.0), Some(result.1)));
         }
+
+        // The rate limit holds the ticks back, but the last one always comes.
+        let mut solver = Solver::from_root(Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Three)));
+        let mut n_reports = 0;
+        solver.solve_with_progress(SolverMode::Heuristic((1, objective_function.clone())), |_| n_reports += 1).unwrap();
+        assert_eq!(n_reports, 1);
+        let mut solver = Solver::from_root(Node::new(Board::empty(), Board::empty(), NodeType::Roll(Player::Player1)));
+        assert!(solver.solve_with_progress(SolverMode::BruteForce, |_| panic!("Shouldn't report a search that can't start.")).is_err());
     }
 
-    fn get_best_moves_from_evaluation_tree(maybe_tree: Option<Node>) -> Result<Vec<Move>, String> {
-        match maybe_tree {
-            Some(tree) => {
-                match tree.get_node_type() {
-                    NodeType::Roll(_) => Err("Roll node can't be the root of an evaluation tree.".to_string()),
-                    NodeType::Move(_, _) => Ok(tree.get_moves().expect("Guaranteed to be a move node.")),
+    #[test]
+    fn test_solver_gets_top_k_lines() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let cases = vec![
+            (opening, SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame, SolverMode::BruteForce),
+        ];
+        for (root, mode) in cases {
+            // More lines than there are moves gives every move, in the order they're ranked.
+            let lines = Solver::from_root(root.clone()).get_top_k_lines(100, mode.clone(), 10).unwrap();
+            let ranked_moves = Solver::from_root(root.clone()).get_all_moves_ranked(mode.clone()).unwrap();
+            assert_eq!(lines.iter().map(|(m, evaluation, _)| (*m, *evaluation)).collect::<Vec<(Move, Evaluation)>>(), ranked_moves);
+            assert_eq!(lines[0].2, Solver::from_root(root.clone()).get_principal_variation(mode.clone(), 10).unwrap());
+            for (m, _, line) in &lines {
+                // Each line starts with its move and can be played out from the root.
+                assert_eq!(line[0].get_move(), *m);
+                let mut position = root.clone();
+                for step in line {
+                    if let NodeType::Roll(_) = position.get_node_type() {
+                        position = position.with_roll(step.get_die()).unwrap();
+                    }
+                    position = position.with_move_made(step.get_move()).unwrap();
                 }
-            },
-            None => Ok(vec![]),
+            }
+
+            let lines = Solver::from_root(root.clone()).get_top_k_lines(2, mode.clone(), 1).unwrap();
+            assert_eq!(lines.len(), 2);
+            assert_eq!(lines.iter().map(|(m, _, _)| *m).collect::<Vec<Move>>(), ranked_moves[..2].iter().map(|(m, _)| *m).collect::<Vec<Move>>());
+            assert!(lines.iter().all(|(_, _, line)| line.len() == 1));
         }
     }
 
-    pub fn get_evaluation(&mut self, solver_mode: SolverMode) -> Result<Evaluation, String> {
-        self.get_best_moves_and_evaluation(solver_mode).map(|(_, evaluation)| evaluation)
+    #[test]
+    fn test_solver_orders_expansion() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let cases = vec![
+            (opening, SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame, SolverMode::BruteForce),
+        ];
+        for (root, mode) in cases {
+            let mut solver = Solver::from_root(root.clone());
+            let result = solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+            let mut ordered_solver = Solver::from_root(root.clone());
+            ordered_solver.set_ordered_expansion(true);
+            assert_eq!(ordered_solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), result);
+        }
+
+        // The search goes through the moves in the same order as the children of an ordered tree.
+        let objective = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        // A Three doubles the one in the first column, which row-major order would come to second.
+        let mut opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Three));
+        let mut ordered_solver = Solver::from_root(opening.clone());
+        ordered_solver.set_ordered_expansion(true);
+        let moves = ordered_solver.get_ordered_moves(&opening, &objective, MoveOrdering::None);
+        assert_eq!(moves, vec![Move::new(1, 0), Move::new(0, 2), Move::new(1, 1)]);
+        let keys = moves.iter().map(|m| opening.with_move_made(*m).unwrap().symmetric_position_key()).collect::<Vec<u64>>();
+        opening.generate_children_ordered(|x| objective.evaluate(x));
+        assert_eq!(keys, opening.get_children().iter().map(|child| child.symmetric_position_key()).collect::<Vec<u64>>());
     }
 
-    pub fn difference_heuristic(node: &Node, empty_square_fill: f32) -> f32 {
-        // Heuristic == Difference in current score, attributing empty_square_fill to squares that will get played assuming no eliminations.
-        let difference = node.get_score_difference();
-        if node.is_game_over() {
-            return difference as f32;
-        };
-        let mut player_1_empty_squares = node.get_player_1_board().get_n_empty_squares() as f32;
-        let mut player_2_empty_squares = node.get_player_2_board().get_n_empty_squares() as f32;
-        let finishing_first = if player_1_empty_squares > player_2_empty_squares {
-            Player::Player2
-        } else if player_1_empty_squares < player_2_empty_squares {
-            Player::Player1
-        } else {
-            node.get_active_player()
-        };
-        let finishing_first_bonus = if node.get_active_player() == finishing_first {
-            1.0
-        } else {
-            -1.0
-        };
-        let empty_square_raw_difference = match finishing_first {
-            Player::Player1 => (player_2_empty_squares - player_1_empty_squares + finishing_first_bonus),
-            Player::Player2 => -(player_1_empty_squares - player_2_empty_squares + finishing_first_bonus),
-        };
-        let empty_square_heuristic = empty_square_raw_difference * empty_square_fill;
-        return (difference as f32) + empty_square_heuristic;
+    #[test]
+    fn test_solver_breaks_ties_by_margin() {
+        // Either four wins whatever Player 2 rolls for their last square, but tripling the right hand column wins by more
+        // than doubling the middle one.
+        let player_1_board = Board::from_string("64_\n5_4\n634".to_string()).unwrap();
+        let player_2_board = Board::from_string("_11\n211\n211".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let (best_moves, evaluation) = Solver::from_root(endgame.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert_eq!(best_moves, vec![Move::new(0, 2), Move::new(1, 1)]);
+        assert_eq!(evaluation, Evaluation::new(EvaluationKind::ExpectedResult, 1.).unwrap());
+
+        // The result is the same, with the margin alongside it.
+        let config = SolverConfig::builder().break_ties_by_margin(true).build().unwrap();
+        let mut solver = Solver::from_root_with_config(endgame.clone(), config.clone());
+        let (margin_best_moves, margin_evaluation) = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert_eq!(margin_best_moves, vec![Move::new(0, 2)]);
+        assert_eq!(margin_evaluation.get_evaluation(), evaluation.get_evaluation());
+        let margin = margin_evaluation.get_margin().unwrap();
+        assert!((margin - 109. / 3.).abs() < 1e-4);
+        assert_eq!(margin_evaluation.to_string(), "+W 100%, +Δ36.3 expected");
+        assert_eq!(margin_evaluation.from_perspective(Player::Player2).to_string(), "-W 0%, -Δ36.3 expected");
+
+        let ranked_moves = solver.get_all_moves_ranked(SolverMode::BruteForce).unwrap();
+        assert_eq!(ranked_moves.iter().map(|(m, _)| *m).collect::<Vec<Move>>(), vec![Move::new(0, 2), Move::new(1, 1)]);
+        assert_eq!(ranked_moves[0].1, margin_evaluation);
+        assert!(ranked_moves[0].1 > ranked_moves[1].1);
+        assert!((ranked_moves[0].1.get_margin().unwrap() - ranked_moves[1].1.get_margin().unwrap() - 8.).abs() < 1e-4);
+
+        // Heuristic searches don't have ties of that kind.
+        let mode = SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        assert_eq!(
+            Solver::from_root_with_config(endgame.clone(), config).get_best_moves_and_evaluation(mode.clone()).unwrap(),
+            Solver::from_root(endgame).get_best_moves_and_evaluation(mode).unwrap(),
+        );
     }
 
-}
+    #[test]
+    fn test_solver_maximizes_expected_margin() {
+        // Player 1 is behind either way.  Filling the top row's middle square gives them the best chance of
+        // catching up, but taking one of Player 2's twos off the bottom left loses by the least on average.
+        let player_1_board = Board::from_string("1_2\n132\n_12".to_string()).unwrap();
+        let player_2_board = Board::from_string("2__\n566\n224".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Two));
+        let (best_moves, evaluation) = Solver::from_root(endgame.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert_eq!(best_moves, vec![Move::new(0, 1)]);
+        assert!(evaluation.is_expected_result());
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
-pub struct Evaluation(f32);
+        let config = SolverConfig::builder().search_objective(SearchObjective::ExpectedMargin).build().unwrap();
+        let mut solver = Solver::from_root_with_config(endgame.clone(), config.clone());
+        let (margin_best_moves, margin_evaluation) = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert_eq!(margin_best_moves, vec![Move::new(2, 0)]);
+        assert!(margin_evaluation.get_kind() == EvaluationKind::ExpectedMargin && margin_evaluation.get_evaluation() < 0.);
+        assert_eq!(margin_evaluation.get_margin(), Some(margin_evaluation.get_evaluation()));
 
-impl Evaluation {
+        // Each objective ranks the other's move below its own.
+        let position = |ranked_moves: &Vec<(Move, Evaluation)>, m: Move| ranked_moves.iter().position(|(ranked_move, _)| *ranked_move == m).unwrap();
+        let ranked_by_margin = solver.get_all_moves_ranked(SolverMode::BruteForce).unwrap();
+        assert_eq!(ranked_by_margin[0], (Move::new(2, 0), margin_evaluation));
+        assert!(position(&ranked_by_margin, Move::new(2, 0)) < position(&ranked_by_margin, Move::new(0, 1)));
+        let ranked_by_result = Solver::from_root(endgame.clone()).get_all_moves_ranked(SolverMode::BruteForce).unwrap();
+        assert!(position(&ranked_by_result, Move::new(0, 1)) < position(&ranked_by_result, Move::new(2, 0)));
+
+        // The tree compares moves the same way the search does.
+        let (maybe_tree, tree_evaluation) = Solver::from_root_with_config(endgame.clone(), config.clone()).get_evaluation_tree(SolverMode::BruteForce).unwrap();
+        let mut untabled_solver = Solver::from_root_with_config(endgame.clone(), config.clone());
+        untabled_solver.set_use_transposition_table(false);
+        assert_eq!(
+            untabled_solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(),
+            (Solver::get_best_moves_from_evaluation_tree(maybe_tree).unwrap(), tree_evaluation),
+        );
 
-    pub fn new(evaluation: f32) -> Self {
-        Evaluation(evaluation)
+        // Margins aren't expected results, so they aren't compared with them or looked up in an endgame table.
+        assert!(margin_evaluation.compare_for(&evaluation, Player::Player1).is_err());
+        let reply = endgame.with_move_made(Move::new(0, 1)).unwrap().with_roll(Die::Six).unwrap();
+        let endgame_table = EndgameTable::build(std::slice::from_ref(&reply), 4, |_, _| {});
+        assert!(endgame_table.probe(&reply).is_some());
+        let mut solver = Solver::from_root_with_config(reply.clone(), config.clone());
+        solver.set_endgame_table(Arc::new(endgame_table));
+        assert_eq!(
+            solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(),
+            Solver::from_root_with_config(reply, config).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(),
+        );
     }
 
-    pub fn get_evaluation(&self) -> f32 {
-        self.0
+    #[test]
+    fn test_solver_samples_chance_nodes() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let player_1_board = Board::from_string("111\n22_\n33_".to_string()).unwrap();
+        let player_2_board = Board::from_string("444\n55_\n666".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let cases = vec![
+            (opening, SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame, SolverMode::BruteForce),
+        ];
+        let sampled_config = |n_rolls| SolverConfig::builder()
+            .chance_expansion(ChanceExpansion::Sampled { n_rolls, full_plies: 1, seed: 7 })
+            .build()
+            .unwrap();
+        for (root, mode) in cases {
+            // Sampling every roll is the exact search.
+            let mut exact_solver = Solver::from_root(root.clone());
+            let exact_result = exact_solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+            assert_eq!(Solver::from_root_with_config(root.clone(), sampled_config(6)).get_best_moves_and_evaluation(mode.clone()).unwrap(), exact_result);
+            assert_eq!(
+                Solver::from_root_with_config(root.clone(), sampled_config(6)).get_evaluation_tree(mode.clone()).unwrap(),
+                Solver::from_root(root.clone()).get_evaluation_tree(mode.clone()).unwrap(),
+            );
+
+            // Two rolls from each chance node past the first move is the same approximation every time, and the tree
+            // is built with the same rolls the search averages over.
+            let mut sampled_solver = Solver::from_root_with_config(root.clone(), sampled_config(2));
+            let sampled_result = sampled_solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+            assert!(sampled_solver.get_search_stats().get_n_nodes() < exact_solver.get_search_stats().get_n_nodes());
+            assert_eq!(Solver::from_root_with_config(root.clone(), sampled_config(2)).get_best_moves_and_evaluation(mode.clone()).unwrap(), sampled_result);
+            let (maybe_tree, tree_evaluation) = Solver::from_root_with_config(root.clone(), sampled_config(2)).get_evaluation_tree(mode.clone()).unwrap();
+            let mut untabled_solver = Solver::from_root_with_config(root.clone(), sampled_config(2));
+            untabled_solver.set_use_transposition_table(false);
+            assert_eq!(
+                untabled_solver.get_best_moves_and_evaluation(mode.clone()).unwrap(),
+                (Solver::get_best_moves_from_evaluation_tree(maybe_tree).unwrap(), tree_evaluation),
+            );
+
+            // It's tagged as sampled, and isn't far off.
+            let (_, evaluation) = sampled_result;
+            assert!(evaluation.is_sampled() && !exact_result.1.is_sampled());
+            assert_eq!(evaluation.unsampled().is_expected_result(), exact_result.1.is_expected_result());
+            assert!(evaluation.compare_for(&exact_result.1, Player::Player1).is_ok());
+            let tolerance = if evaluation.is_expected_result() { 0.5 } else { 10. };
+            assert!((evaluation.get_evaluation() - exact_result.1.get_evaluation()).abs() < tolerance, "{} vs {}", evaluation, exact_result.1);
+            assert!(Solver::from_root_with_config(root, sampled_config(2)).get_all_moves_ranked(mode).unwrap().iter().all(|(_, evaluation)| evaluation.is_sampled()));
+        }
     }
 
-    pub fn from_perspective(&self, perspective: Player) -> Self {
-        match perspective {
-            Player::Player1 => Evaluation(self.0),
-            Player::Player2 => Evaluation(-self.0),
+    #[test]
+    fn test_solver_breaks_ties_canonically() {
+        // Mirror image columns, so the left and right ones always tie when searched to a depth.
+        let player_1_board = Board::from_string("_3_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("_3_\n___\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Two));
+        for depth in 1..=3 {
+            let mode = SolverMode::Heuristic((depth, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+            let expected = vec![Move::new(0, 0), Move::new(0, 2), Move::new(1, 1)];
+            // Whether the transposition table has the position from the last solve or not.
+            let mut solver = Solver::from_root(opening.clone());
+            for _ in 0..5 {
+                assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap().0, expected);
+                assert_eq!(Solver::from_root(opening.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap().0, expected);
+            }
+        }
+        // Modes that don't look at every move alike still give the same list every time, in the same order.
+        let modes = vec![
+            SolverMode::NodeBudget((1000, Objective::new(|x| Solver::difference_heuristic(x, 3.5)))),
+            SolverMode::MonteCarlo((10, Some(3))),
+            SolverMode::Mcts((200, 1.4, Some(3))),
+        ];
+        for mode in modes {
+            let expected = Solver::from_root(opening.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap().0;
+            assert!(!expected.is_empty());
+            assert!(expected.windows(2).all(|pair| pair[0] < pair[1]));
+            for _ in 0..5 {
+                assert_eq!(Solver::from_root(opening.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap().0, expected);
+            }
         }
     }
 
-    pub fn to_string(&self) -> String {
-        format!("{:.2}", self.0)
+    #[test]
+    fn test_solver_advances_root() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let mode = SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        let mut solver = Solver::from_root(root.clone());
+        solver.get_evaluation_tree(mode.clone()).unwrap();
+        solver.advance_by_move(Move::new(0, 2)).unwrap();
+        solver.advance_by_roll(Die::Two).unwrap();
+        let n_reused_nodes = solver.get_root().count_nodes();
+        assert!(n_reused_nodes > 1);
+
+        let position = root.apply_sequence(&[(Die::Four, Move::new(0, 2))]).unwrap().with_roll(Die::Two).unwrap();
+        let mut fresh_solver = Solver::from_root(position.clone());
+        let expected = fresh_solver.get_evaluation_tree(mode.clone()).unwrap();
+        assert!(solver.get_root().equals_up_to_children(&position));
+        assert_eq!(solver.get_evaluation_tree(mode.clone()).unwrap(), expected);
+        assert_eq!(solver.get_root(), fresh_solver.get_root());
+        assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap().1, expected.1);
+
+        // Positions that weren't built are constructed instead.
+        let mut solver = Solver::from_root(root.clone());
+        solver.advance_by_move(Move::new(0, 2)).unwrap();
+        solver.advance_by_roll(Die::Two).unwrap();
+        assert_eq!(solver.get_root(), &position);
+        assert!(solver.advance_by_roll(Die::Two).is_err());
+        assert!(Solver::from_root(Node::empty()).advance_by_move(Move::new(0, 0)).is_err());
     }
 
-    fn from_outcome(outcome: Outcome) -> Result<Self, String> {
-        match outcome {
-            Outcome::Victory(Player::Player1) => Ok(Evaluation::new(1.0)),
-            Outcome::Victory(Player::Player2) => Ok(Evaluation::new(-1.0)),
-            Outcome::Draw => Ok(Evaluation::new(0.0)),
-            Outcome::InProgress => Err("Cannot convert InProgress to Evaluation".to_string()),
+    #[test]
+    fn test_solver_estimates_confidence() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+
+        // A forced win is worth the same however deep it's searched.
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        for solver_mode in [SolverMode::BruteForce, SolverMode::Heuristic((2, objective_function.clone()))] {
+            let (best_moves, confidence) = Solver::from_root(endgame.clone()).get_best_moves_and_evaluation_with_confidence(solver_mode).unwrap();
+            assert_eq!(best_moves, vec![Move::new(1, 1)]);
+            assert_eq!(confidence.get_delta(), 0.);
+            assert!(!confidence.has_best_move_changed());
+            assert!(!confidence.is_low_confidence());
+        }
+
+        // Pairing the 6s in the left column looks best a move ahead, but leaves them to be eliminated.
+        let tactical = Node::from_kfen("3_1651_5_ / 445163__5 1 r6").unwrap();
+        let mut solver = Solver::from_root(tactical.clone());
+        solver.set_use_transposition_table(false);
+        let (best_moves, confidence) = solver.get_best_moves_and_evaluation_with_confidence(SolverMode::Heuristic((2, objective_function.clone()))).unwrap();
+        let (shallow_best_moves, shallow_evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Heuristic((1, objective_function.clone()))).unwrap();
+        assert_eq!(shallow_best_moves, vec![Move::new(2, 0)]);
+        assert_eq!(
+            solver.get_best_moves_and_evaluation(SolverMode::Heuristic((2, objective_function.clone()))).unwrap(),
+            (best_moves.clone(), confidence.get_evaluation()),
+        );
+        assert!(!best_moves.contains(&Move::new(2, 0)));
+        assert!(confidence.has_best_move_changed());
+        assert!(confidence.is_low_confidence());
+        assert!((confidence.get_delta() - (confidence.get_evaluation().get_evaluation() - shallow_evaluation.get_evaluation())).abs() < 1e-4);
+
+        // Otherwise it's the size of the delta that counts, if there's a threshold.
+        let opening = Node::from_kfen("35_______ / __4__4___ 1 r4").unwrap();
+        let solver_mode = SolverMode::Heuristic((2, objective_function.clone()));
+        let (_, confidence) = Solver::from_root(opening.clone()).get_best_moves_and_evaluation_with_confidence(solver_mode.clone()).unwrap();
+        assert!(!confidence.has_best_move_changed());
+        assert!(confidence.get_delta().abs() > 0.5);
+        assert!(!confidence.is_low_confidence());
+        for (threshold, low_confidence) in [(0.5, true), (1000., false)] {
+            let config = SolverConfig::builder().confidence_threshold(threshold).build().unwrap();
+            let mut solver = Solver::from_root_with_config(opening.clone(), config);
+            assert_eq!(solver.get_best_moves_and_evaluation_with_confidence(solver_mode.clone()).unwrap().1.is_low_confidence(), low_confidence);
         }
+
+        // A move ahead is compared with the root itself.
+        let (_, confidence) = Solver::from_root(opening.clone()).get_best_moves_and_evaluation_with_confidence(SolverMode::Heuristic((1, objective_function.clone()))).unwrap();
+        assert!((confidence.get_delta() - (confidence.get_evaluation().get_evaluation() - objective_function.evaluate(&opening))).abs() < 1e-4);
+        assert!(!confidence.has_best_move_changed());
+
+        assert!(matches!(
+            Solver::from_root(opening).get_best_moves_and_evaluation_with_confidence(SolverMode::MonteCarlo((10, Some(1)))),
+            Err(SolverError::InvalidMode(_)),
+        ));
     }
-}
 
-#[derive(Copy, Clone)]
-pub enum SolverMode {
-    BruteForce,
-    Heuristic(HeuristicDepthAndObjective),
-    Hybrid(BruteForceMaxMovesRemaining, HeuristicDepthAndObjective),
-}
+    #[test]
+    fn test_solver_advances_root_along_path() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let mode = SolverMode::Heuristic((3, objective_function.clone()));
+        let path = [Step::Move(Die::Four, Move::new(0, 2)), Step::Roll(Die::Two)];
+        let position = root.apply_sequence(&[(Die::Four, Move::new(0, 2))]).unwrap().with_roll(Die::Two).unwrap();
 
-pub type HeuristicDepthAndObjective = (usize, fn(&Node) -> f32);
-pub type BruteForceMaxMovesRemaining = usize;
+        // Advancing then solving is the same as starting over.
+        let mut fresh_solver = Solver::from_root(position.clone());
+        let expected = fresh_solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+        let mut solver = Solver::from_root(root.clone());
+        solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+        solver.advance_root(&path).unwrap();
+        assert_eq!(solver.get_root(), &position);
+        assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), expected);
 
-#[cfg(test)]
-mod test_solver {
-    use crate::{tree::tree::NodeType, board::board::Die};
+        // A search after advancing that's a move shallower finds what the search before left in it.
+        let shallower_mode = SolverMode::Heuristic((2, objective_function));
+        let mut fresh_solver = Solver::from_root(position.clone());
+        let expected = fresh_solver.get_best_moves_and_evaluation(shallower_mode.clone()).unwrap();
+        let mut solver = Solver::from_root(root.clone());
+        solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+        solver.advance_root(&path).unwrap();
+        assert_eq!(solver.get_best_moves_and_evaluation(shallower_mode).unwrap(), expected);
+        assert!(solver.get_search_stats().get_n_nodes() < fresh_solver.get_search_stats().get_n_nodes());
 
-    use super::*;
+        // An illegal step anywhere leaves the root where it was.
+        let mut solver = Solver::from_root(root.clone());
+        assert!(solver.advance_root(&[Step::Move(Die::Four, Move::new(0, 2)), Step::Move(Die::Two, Move::new(0, 0))]).is_err());
+        assert!(solver.advance_root(&[Step::Move(Die::Two, Move::new(0, 2))]).is_err());
+        assert!(solver.advance_root(&[Step::Move(Die::Four, Move::new(0, 0))]).is_err());
+        assert_eq!(solver.get_root(), &root);
+        solver.advance_root(&[]).unwrap();
+        assert_eq!(solver.get_root(), &root);
+
+        // Resetting the root goes anywhere, keeping the table.
+        solver.get_best_moves_and_evaluation(mode.clone()).unwrap();
+        solver.reset_root(position.clone());
+        assert_eq!(solver.get_root(), &position);
+        solver.get_best_moves_and_evaluation(mode).unwrap();
+        assert!(solver.get_search_stats().get_n_hits() > fresh_solver.get_search_stats().get_n_hits());
+    }
 
     #[test]
-    fn test_solver_solves_endgame_situations() {
-        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
-        let mut solver = Solver::from_root(root);
-        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
-        assert_eq!(best_moves, vec![Move::new(1, 1)]);
-        assert_eq!(evaluation, Evaluation::new(1.0));
+    fn test_solver_solves_with_node_budget() {
+        let root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        let mut solver = Solver::from_root(root.clone());
+        let result = solver.get_best_moves_and_evaluation(SolverMode::NodeBudget((22, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
+        let mut heuristic_solver = Solver::from_root(root.clone());
+        let heuristic_result = heuristic_solver.get_best_moves_and_evaluation(SolverMode::Heuristic((1, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
+        assert_eq!(result, heuristic_result);
+        assert_eq!(solver.get_root().count_nodes(), 22);
 
-        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("255\n1_2\n652".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
-        let mut solver = Solver::from_root(root);
-        let result = solver.get_best_moves_and_evaluation(SolverMode::BruteForce);
-        assert!(result.is_err());
+        // Even a tiny budget searches one move.
+        let mut solver = Solver::from_root(root.clone());
+        let (best_moves, _) = solver.get_best_moves_and_evaluation(SolverMode::NodeBudget((1, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
+        assert_eq!(best_moves.len(), 3);
 
-        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap(); // 40 before move.
-        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap(); // 28 before move.
-        // Player 2 has two moves:
-        // (1, 1) => 30
-        // (2, 2) => 44
-        // Player 1 has one move, (1, 1):
-        // Based on rolls, that means the score is:
-        // 1 => 43 => Player 2 wins
-        // 2 => 46 => Player 1 wins
-        // 3 => 43 => Player 2 wins iff Player 2 played (2, 2), else Player 1 wins
-        // 4 => 44 => Draws iff Player 2 played (2, 2), else wins
-        // 5 => 45 => Player 1 wins
-        // 6 => 46 => PLayer 1 wins
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
         let mut solver = Solver::from_root(root);
-        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
-        assert_eq!((best_moves, evaluation), (vec![Move::new(2, 2)], Evaluation::new((3.*1. + 1.*0. + 2.*(-1.))/6.)));
+        solver.get_best_moves_and_evaluation(SolverMode::NodeBudget((500, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
+        assert!(solver.get_root().count_nodes() <= 500);
+        assert!(solver.get_root().get_max_depth() > 3);
     }
 
     #[test]
-    fn test_solver_solves_heuristically() {
-        let player_1_board = Board::empty();
-        let player_2_board = Board::empty();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
-        let mut solver = Solver::from_root(root);
-        let result = solver
-            .get_best_moves_and_evaluation(
-                SolverMode::Heuristic((1, |x| Solver::difference_heuristic(x, 3.5))),
-            ).unwrap();
+    fn test_solver_solves_adaptively() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let opening = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Three));
+        assert_eq!(Solver::estimate_n_nodes(&opening, 0), 1.);
+        assert_eq!(Solver::estimate_n_nodes(&opening, 1), 19.);
+        assert_eq!(Solver::estimate_n_nodes(&opening, 2), 19. + 18. * 18.);
+
+        // The opening is too bushy to search very deep, and more nodes buy more depth.
+        let mut solver = Solver::from_root(opening.clone());
+        let depth = solver.get_adaptive_depth(10_000);
+        assert!((1..4).contains(&depth));
+        assert!(solver.get_adaptive_depth(1_000_000) > depth);
+        assert_eq!(solver.get_adaptive_depth(1), 1);
+        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Adaptive(2, (10_000, objective_function.clone()))).unwrap();
+        assert_eq!(solver.get_search_stats().get_depth(), depth);
+        assert!(!evaluation.is_expected_result());
+        assert!(!best_moves.is_empty());
+        assert!(best_moves.iter().all(|m| opening.get_legal_moves().unwrap().contains(m)));
         assert_eq!(
-            result,
-            (
-                vec![Move::new(0, 0), Move::new(0, 1), Move::new(0, 2)],
-                Evaluation::new(6.0)
-            )
+            Solver::from_root(opening.clone()).get_best_moves_and_evaluation(SolverMode::Heuristic((depth, objective_function.clone()))).unwrap(),
+            (best_moves, evaluation),
         );
+        let (_, tree_evaluation) = solver.get_evaluation_tree(SolverMode::Adaptive(2, (10_000, objective_function.clone()))).unwrap();
+        assert_eq!(tree_evaluation, evaluation);
+        assert_eq!(solver.get_search_stats().get_depth(), depth);
 
-        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap(); // 40 before move.
-        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap(); // 24 before move.
-        assert_eq!(player_1_board.sum(), 40);
-        assert_eq!(player_2_board.sum(), 24);
-        // Player 2 has two moves:
-        // (1, 1) => 30
-        // (2, 2) => 42
-        // Player 1 has one move, (1, 1):
-        // Based on rolls, that means the score is:
-        // 1 => 43 => Player 1 wins and diff = 1
-        // 2 => 46 + 2 (due to elimination of the 2) => Player 1 wins and diff = 6
-        // 3 => 43 => Player 1 wins.  diff = 1
-        // 4 => 44 => Player 1 wins.  Diff = 2.
-        // 5 => 45 => Player 1 wins.  Diff = 3.
-        // 6 => 46 + 24 (due to elimination of 2 6's) => Player 1 wins.  Diff = 28
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
-        let mut solver = Solver::from_root(root);
-        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Heuristic((5, |x| Solver::difference_heuristic(x, 3.5)))).unwrap();
-        assert_eq!(best_moves, vec![Move::new(2, 2)]);
-        assert!((evaluation.0 - (1. + 6. + 1. + 2. + 3. + 28.)/6.).abs() < 0.0001);
+        // Near the end it brute forces, like hybrid.
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let mut solver = Solver::from_root(endgame.clone());
+        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Adaptive(20, (1000, objective_function.clone()))).unwrap();
+        assert!(evaluation.is_expected_result());
+        assert_eq!(solver.get_search_stats().get_depth(), DEFAULT_BRUTE_FORCE_DEPTH);
+        assert!(best_moves.iter().all(|m| endgame.get_legal_moves().unwrap().contains(m)));
+        assert_eq!(Solver::from_root(endgame).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), (best_moves, evaluation));
+
+        assert!(matches!(solver.get_best_moves_and_evaluation(SolverMode::Adaptive(2, (0, objective_function))), Err(SolverError::InvalidMode(_))));
     }
 
     #[test]
@@ -240,10 +4512,11 @@ mod test_solver {
         let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
         let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
         let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        // Eliminations could stretch this to 7 plies, so it only brute forces with a threshold that high.
         let mut solver = Solver::from_root(root);
-        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Hybrid(5, (4, |x| Solver::difference_heuristic(x, 3.5)))).unwrap();
+        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Hybrid(7, (4, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
         assert_eq!(best_moves, vec![Move::new(1, 1)]);
-        assert_eq!(evaluation, Evaluation::new(1.0));
+        assert_eq!(evaluation, Evaluation::new(EvaluationKind::ExpectedResult, 1.0).unwrap());
 
 
         let player_1_board = Board::empty();
@@ -252,13 +4525,13 @@ mod test_solver {
         let mut solver = Solver::from_root(root);
         let result = solver
             .get_best_moves_and_evaluation(
-                SolverMode::Hybrid(5, (1, |x| Solver::difference_heuristic(x, 3.5))),
+                SolverMode::Hybrid(5, (1, Objective::new(|x| Solver::difference_heuristic(x, 3.5)))),
             ).unwrap();
         assert_eq!(
             result,
             (
                 vec![Move::new(0, 0), Move::new(0, 1), Move::new(0, 2)],
-                Evaluation::new(6.0)
+                Evaluation::new(EvaluationKind::Heuristic, 6.0).unwrap()
             )
         );
 
@@ -266,7 +4539,7 @@ mod test_solver {
         let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
         let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
         let mut solver = Solver::from_root(root);
-        let (evaluation_tree, evaluation) = solver.get_evaluation_tree(SolverMode::Hybrid(1, (2, |x| Solver::difference_heuristic(x, 3.5)))).unwrap();
+        let (evaluation_tree, _) = solver.get_evaluation_tree(SolverMode::Hybrid(1, (2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
         assert_eq!(evaluation_tree.clone().unwrap().get_max_depth(), 5);
         assert_eq!(evaluation_tree.unwrap().get_child_from_move(Move::new(1, 1)).unwrap().get_child_from_roll(Die::Six).unwrap().get_n_children(), 1);
 
@@ -275,10 +4548,429 @@ mod test_solver {
         let player_2_board = Board::from_string("356\n122\n62_".to_string()).unwrap();
         let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
         let mut solver = Solver::from_root(root);
-        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Hybrid(5, (4, |x| Solver::difference_heuristic(x, 3.5)))).unwrap();
-        assert!(evaluation >= Evaluation::new(-1.0));
-        assert!(evaluation <= Evaluation::new(1.0));
+        let (_, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Hybrid(12, (4, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
+        assert!(evaluation.is_expected_result());
+        assert!(evaluation >= Evaluation::new(EvaluationKind::ExpectedResult, -1.0).unwrap());
+        assert!(evaluation <= Evaluation::new(EvaluationKind::ExpectedResult, 1.0).unwrap());
+
+    }
+
+    #[test]
+    fn test_solver_ranks_all_moves() {
+        // Player 2's last square is in the third column, so the game ends with their next move.  Player 1 can place
+        // the 6 out of reach in the second column, or in the third, in either empty square.
+        //
+        // Player 2 has 9 + 6 + 9 = 24 with a 1 and 9 + 6 + 4 + r = 19 + r otherwise.
+        //
+        // In the second column, Player 1 has 11 + 13 + 3 = 27, or 24 if Player 2's roll is a 3 and takes out the 3,
+        // which wins every time: 1.
+        //
+        // In the third column, Player 1 has 11 + 7 + 9 = 27, 24 if the 3 is taken out and 21 if the 6 is, which only
+        // loses to a 6: (5 - 1) / 6 = 2 / 3.
+        let player_1_board = Board::from_string("123\n45_\n6__".to_string()).unwrap();
+        let player_2_board = Board::from_string("111\n111\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let ranked_moves = Solver::from_root(root.clone()).get_all_moves_ranked(SolverMode::BruteForce).unwrap();
+        assert_eq!(
+            ranked_moves.iter().map(|(m, _)| *m).collect::<Vec<Move>>(),
+            vec![Move::new(2, 1), Move::new(1, 2), Move::new(2, 2)]
+        );
+        assert_eq!(ranked_moves[0].1, Evaluation::new(EvaluationKind::ExpectedResult, 1.).unwrap());
+        assert!((ranked_moves[1].1.get_evaluation() - 2. / 3.).abs() < 0.0001);
+        assert_eq!(ranked_moves[2].1, ranked_moves[1].1);
+
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let cases = vec![
+            (opening.clone(), SolverMode::Heuristic((1, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (opening, SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+            (endgame.clone(), SolverMode::BruteForce),
+            (endgame, SolverMode::NodeBudget((200, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))),
+        ];
+        for (root, mode) in cases {
+            let ranked_moves = Solver::from_root(root.clone()).get_all_moves_ranked(mode.clone()).unwrap();
+            assert_eq!(ranked_moves.len(), root.get_legal_moves().unwrap().len());
+            let (best_moves, evaluation) = Solver::from_root(root).get_best_moves_and_evaluation(mode).unwrap();
+            assert_eq!(ranked_moves[0].1, evaluation);
+            assert!(best_moves.contains(&ranked_moves[0].0));
+        }
+
+        let finished = Node::new(Board::from_string("111\n111\n111".to_string()).unwrap(), Board::empty(), NodeType::Move(Player::Player2, Die::One));
+        assert_eq!(Solver::from_root(finished).get_all_moves_ranked(SolverMode::BruteForce), Err(SolverError::GameAlreadyOver));
+        assert!(Solver::from_root(Node::empty()).get_all_moves_ranked(SolverMode::BruteForce).is_err());
+    }
+
+    #[test]
+    fn test_solver_follows_config() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let mode = SolverMode::Heuristic((3, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        let expected = Solver::from_root(opening.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+
+        // Anything close enough to the best move is as good as it, so here every column is.
+        let config = SolverConfig::builder().tie_break_epsilon(f32::INFINITY).build().unwrap();
+        let (best_moves, evaluation) = Solver::from_root_with_config(opening.clone(), config).get_best_moves_and_evaluation(mode.clone()).unwrap();
+        assert_eq!(best_moves, opening.get_legal_moves_up_to_row_symmetry().unwrap());
+        assert_eq!(evaluation, expected.1);
+
+        let config = SolverConfig::builder().transposition_table_size(10).build().unwrap();
+        let mut solver = Solver::from_root_with_config(opening.clone(), config);
+        assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()).unwrap(), expected);
+        assert_eq!(solver.transposition_table.len(), 10);
+
+        let die_distribution = DieDistribution::from_weights([1., 1., 1., 1., 1., 5.]).unwrap();
+        let mut weighted_opening = opening.clone();
+        weighted_opening.set_die_distribution(die_distribution);
+        let config = SolverConfig::builder().die_distribution(die_distribution).build().unwrap();
+        assert_eq!(
+            Solver::from_root_with_config(opening, config).get_best_moves_and_evaluation(mode.clone()).unwrap(),
+            Solver::from_root(weighted_opening).get_best_moves_and_evaluation(mode).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_solver_takes_closure_objectives() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let empty_square_fill = 1.5;
+        let objective_function = Objective::new(move |x| Solver::difference_heuristic(x, empty_square_fill));
+        let mode = SolverMode::Heuristic((2, objective_function.clone()));
+        let (best_moves, evaluation) = Solver::from_root(root.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+        let (maybe_tree, tree_evaluation) = Solver::from_root(root.clone()).get_evaluation_tree(mode).unwrap();
+        assert_eq!(Solver::get_best_moves_from_evaluation_tree(maybe_tree).unwrap(), best_moves);
+        assert_eq!(tree_evaluation, evaluation);
+        let default_fill_mode = SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))));
+        assert_ne!(Solver::from_root(root.clone()).get_evaluation(default_fill_mode).unwrap(), evaluation);
+
+        // The objective can keep state, and clones share their cached evaluations.
+        let n_calls = Arc::new(AtomicUsize::new(0));
+        let counter = n_calls.clone();
+        let counting_objective = Objective::new(
+            move |x| {
+                counter.fetch_add(1, AtomicOrdering::Relaxed);
+                objective_function.evaluate(x)
+            }
+        );
+        let mut solver = Solver::from_root(root);
+        assert_eq!(solver.get_evaluation(SolverMode::Heuristic((2, counting_objective.clone()))).unwrap(), evaluation);
+        assert!(n_calls.swap(0, AtomicOrdering::Relaxed) > 0);
+        assert_eq!(solver.get_evaluation(SolverMode::Heuristic((2, counting_objective))).unwrap(), evaluation);
+        assert_eq!(n_calls.load(AtomicOrdering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_evaluation_total_order() {
+        // Kinds first, then values, then margins, with sampled values just before exact ones.
+        let mut evaluations = vec![
+            Evaluation::new(EvaluationKind::Heuristic, 2.).unwrap(),
+            Evaluation::new_with_margin(0.5, 3.).unwrap(),
+            Evaluation::new(EvaluationKind::ExpectedMargin, -4.).unwrap(),
+            Evaluation::new(EvaluationKind::Heuristic, -1.).unwrap().sampled(),
+            Evaluation::new(EvaluationKind::ExpectedResult, 0.5).unwrap(),
+            Evaluation::new_with_margin(0.5, -3.).unwrap(),
+            Evaluation::new(EvaluationKind::Heuristic, -1.).unwrap(),
+            Evaluation::new(EvaluationKind::ExpectedResult, -0.25).unwrap(),
+        ];
+        evaluations.sort();
+        assert_eq!(
+            evaluations,
+            vec![
+                Evaluation::new(EvaluationKind::ExpectedResult, -0.25).unwrap(),
+                Evaluation::new(EvaluationKind::ExpectedResult, 0.5).unwrap(),
+                Evaluation::new_with_margin(0.5, -3.).unwrap(),
+                Evaluation::new_with_margin(0.5, 3.).unwrap(),
+                Evaluation::new(EvaluationKind::ExpectedMargin, -4.).unwrap(),
+                Evaluation::new(EvaluationKind::Heuristic, -1.).unwrap().sampled(),
+                Evaluation::new(EvaluationKind::Heuristic, -1.).unwrap(),
+                Evaluation::new(EvaluationKind::Heuristic, 2.).unwrap(),
+            ],
+        );
+        assert_eq!(Evaluation::new(EvaluationKind::Heuristic, 0.).unwrap(), Evaluation::new(EvaluationKind::Heuristic, -0.).unwrap());
+        assert_ne!(Evaluation::new(EvaluationKind::Heuristic, 1.).unwrap(), Evaluation::new(EvaluationKind::Heuristic, 1.).unwrap().sampled());
+
+        // Equal evaluations keep their order, so moves with the same value stay in row-major order.
+        let mut moves = [(Move::new(2, 0), Evaluation::new(EvaluationKind::Heuristic, 1.).unwrap()), (Move::new(0, 0), Evaluation::new(EvaluationKind::Heuristic, 3.).unwrap()), (Move::new(1, 0), Evaluation::new(EvaluationKind::Heuristic, 1.).unwrap())];
+        moves.sort_by(|(_, a), (_, b)| b.cmp_for(a, Player::Player1));
+        assert_eq!(moves.iter().map(|(m, _)| *m).collect::<Vec<Move>>(), vec![Move::new(0, 0), Move::new(2, 0), Move::new(1, 0)]);
+        moves.sort_by(|(_, a), (_, b)| b.cmp_for(a, Player::Player2));
+        assert_eq!(moves.iter().map(|(m, _)| *m).collect::<Vec<Move>>(), vec![Move::new(2, 0), Move::new(1, 0), Move::new(0, 0)]);
+
+        let values = [Evaluation::new(EvaluationKind::ExpectedResult, 0.25).unwrap(), Evaluation::new(EvaluationKind::ExpectedResult, -0.5).unwrap(), Evaluation::new(EvaluationKind::ExpectedResult, 0.75).unwrap()];
+        assert_eq!(values.into_iter().max_by(Evaluation::better_for(Player::Player1)), Some(Evaluation::new(EvaluationKind::ExpectedResult, 0.75).unwrap()));
+        assert_eq!(values.into_iter().max_by(Evaluation::better_for(Player::Player2)), Some(Evaluation::new(EvaluationKind::ExpectedResult, -0.5).unwrap()));
+        assert_eq!(values.into_iter().min_by(Evaluation::better_for(Player::Player2)), Some(Evaluation::new(EvaluationKind::ExpectedResult, 0.75).unwrap()));
+
+        let heuristic = Evaluation::new(EvaluationKind::Heuristic, 2.5).unwrap();
+        assert_eq!((heuristic.get_kind(), heuristic.get_evaluation(), heuristic.get_margin()), (EvaluationKind::Heuristic, 2.5, None));
+        let margin = Evaluation::new(EvaluationKind::ExpectedMargin, -1.).unwrap();
+        assert_eq!((margin.get_kind(), margin.get_evaluation(), margin.get_margin()), (EvaluationKind::ExpectedMargin, -1., Some(-1.)));
+
+        // Every way of making an evaluation turns NaN away, so there's never one to break the order.
+        assert!(Evaluation::new(EvaluationKind::ExpectedResult, f32::NAN).is_err());
+        assert!(Evaluation::new(EvaluationKind::Heuristic, f32::NAN).is_err());
+        assert!(Evaluation::new_with_margin(f32::NAN, 3.).is_err());
+        assert!(Evaluation::new_with_margin(0.5, f32::NAN).is_err());
+        assert!(heuristic.with_evaluation(f32::NAN).is_err());
+        assert!(Evaluation::from_outcome(Outcome::Draw, f32::NAN).is_err());
+        assert_eq!(Evaluation::new_with_margin(0.5, 3.).unwrap().with_evaluation(0.25).unwrap().get_margin(), Some(3.));
+    }
+
+    #[test]
+    fn test_solver_rejects_nan_objective() {
+        // A NaN is passed up through the search rather than dropped by the comparisons, whoever's to act and however
+        // few of the leaves it's at, and the search fails instead of picking moves.
+        let opening = Node::from_kfen("3________ / _________ 2 r5").unwrap();
+        let nan_everywhere = Objective::new(|_| f32::NAN);
+        let nan_somewhere = Objective::new(|node: &Node| if node.get_score_difference().abs() > 5 { f32::NAN } else { node.get_score_difference() as f32 });
+        for root in [opening.clone(), opening.mirrored()] {
+            for objective_function in [nan_everywhere.clone(), nan_somewhere.clone()] {
+                for mode in [SolverMode::Heuristic((2, objective_function.clone())), SolverMode::NodeBudget((500, objective_function.clone()))] {
+                    let mut solver = Solver::from_root(root.clone());
+                    assert_eq!(solver.get_best_moves_and_evaluation(mode.clone()), Err(SolverError::NanEvaluation));
+                    assert_eq!(solver.get_all_moves_ranked(mode), Err(SolverError::NanEvaluation));
+                }
+            }
+        }
+        let mut solver = Solver::from_root(opening);
+        assert_eq!(solver.get_evaluation_tree(SolverMode::Heuristic((2, nan_somewhere))).map(|(_, evaluation)| evaluation), Err(SolverError::NanEvaluation));
+    }
+
+    #[test]
+    fn test_evaluation_kinds() {
+        let expected_result = Evaluation::new(EvaluationKind::ExpectedResult, 0.44).unwrap();
+        let heuristic = Evaluation::new(EvaluationKind::Heuristic, 6.5).unwrap();
+        assert_eq!(expected_result.to_string(), "+W 72%");
+        assert_eq!(expected_result.from_perspective(Player::Player2).to_string(), "-W 28%");
+        assert_eq!(heuristic.to_string(), "+6.5 (heuristic)");
+        assert_eq!(heuristic.from_perspective(Player::Player2), Evaluation::new(EvaluationKind::Heuristic, -6.5).unwrap());
+
+        assert!(expected_result > Evaluation::new(EvaluationKind::ExpectedResult, 0.).unwrap());
+        assert_eq!(expected_result.cmp(&heuristic), Ordering::Less);
+        assert_eq!(heuristic.compare_for(&Evaluation::new(EvaluationKind::Heuristic, 7.).unwrap(), Player::Player2), Ok(Comparison::Better));
+        assert!(expected_result.compare_for(&heuristic, Player::Player1).is_err());
+
+        assert_eq!(Evaluation::new(EvaluationKind::Heuristic, 0.).unwrap().as_win_probability(10.), 0.5);
+        assert!(Evaluation::new(EvaluationKind::Heuristic, 30.).unwrap().as_win_probability(10.) > 0.9);
+        assert!(Evaluation::new(EvaluationKind::Heuristic, -30.).unwrap().as_win_probability(10.) < 0.1);
+        assert!((heuristic.as_win_probability(10.) + heuristic.from_perspective(Player::Player2).as_win_probability(10.) - 1.).abs() < 1e-4);
+        assert_eq!(Evaluation::new(EvaluationKind::Heuristic, KNOWN_WIN_EVALUATION).unwrap().as_win_probability(1e6), 1.);
+        assert_eq!(Evaluation::new(EvaluationKind::Heuristic, -KNOWN_WIN_EVALUATION).unwrap().as_win_probability(1e6), 0.);
+        assert!((expected_result.as_win_probability(10.) - 0.72).abs() < 1e-4);
+        assert_eq!(Evaluation::new(EvaluationKind::Heuristic, 11.3).unwrap().to_win_probability_string(10.), "76% to win (+11.3 heuristic)");
+        assert_eq!(expected_result.to_win_probability_string(10.), expected_result.to_string());
+
+        // Sampled evaluations compare with the kind they approximate, but never know the winner.
+        let sampled = heuristic.sampled();
+        assert_eq!(sampled, Evaluation::new(EvaluationKind::Heuristic, 6.5).unwrap().sampled());
+        assert_eq!(sampled.to_string(), "+6.5 (heuristic) (sampled)");
+        assert_eq!(sampled.unsampled(), heuristic);
+        assert_eq!(sampled.from_perspective(Player::Player2), Evaluation::new(EvaluationKind::Heuristic, -6.5).unwrap().sampled());
+        assert!(sampled < Evaluation::new(EvaluationKind::Heuristic, 7.).unwrap());
+        assert_eq!(sampled.cmp(&expected_result), Ordering::Greater);
+        assert!(expected_result.sampled().is_expected_result());
+        assert_eq!(Evaluation::new(EvaluationKind::Heuristic, KNOWN_WIN_EVALUATION).unwrap().sampled().get_known_winner(), None);
+
+        // So do truncated ones, which only come first when the values are the same.
+        let truncated = expected_result.truncated();
+        assert_eq!(truncated.to_string(), "+W 72% (truncated)");
+        assert!(truncated.is_truncated() && !expected_result.is_truncated());
+        assert_eq!(truncated.from_perspective(Player::Player2), expected_result.from_perspective(Player::Player2).truncated());
+        assert!(truncated < expected_result);
+        assert!(truncated > Evaluation::new(EvaluationKind::ExpectedResult, 0.).unwrap());
+
+        // Hybrid says which of the two it fell back on.
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let (_, evaluation) = Solver::from_root(endgame.clone()).get_best_moves_and_evaluation(SolverMode::Hybrid(20, (2, objective_function.clone()))).unwrap();
+        assert!(evaluation.is_expected_result());
+        let (_, evaluation) = Solver::from_root(endgame).get_evaluation_tree(SolverMode::Hybrid(1, (2, objective_function))).unwrap();
+        assert!(!evaluation.is_expected_result());
+    }
+
+    #[test]
+    fn test_solver_plays_out_moves() {
+        // The position from test_solver_ranks_all_moves: the 6 in the second column wins every time, so every playout
+        // after it is a win.
+        let player_1_board = Board::from_string("123\n45_\n6__".to_string()).unwrap();
+        let player_2_board = Board::from_string("111\n111\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let (best_moves, evaluation) = Solver::from_root(root.clone()).get_best_moves_and_evaluation(SolverMode::MonteCarlo((200, Some(1)))).unwrap();
+        assert_eq!(best_moves, vec![Move::new(2, 1)]);
+        assert_eq!(evaluation, Evaluation::new(EvaluationKind::ExpectedResult, 1.).unwrap());
+        let ranked_moves = Solver::from_root(root.clone()).get_all_moves_ranked(SolverMode::MonteCarlo((200, Some(1)))).unwrap();
+        assert_eq!(ranked_moves[0], (Move::new(2, 1), Evaluation::new(EvaluationKind::ExpectedResult, 1.).unwrap()));
+        assert!(ranked_moves[1..].iter().all(|(_, evaluation)| evaluation.get_evaluation() < 1.));
+
+        // A seed plays out the same games every time.
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let mode = SolverMode::MonteCarlo((50, Some(42)));
+        assert_eq!(
+            Solver::from_root(opening.clone()).get_all_moves_ranked(mode.clone()).unwrap(),
+            Solver::from_root(opening.clone()).get_all_moves_ranked(mode.clone()).unwrap(),
+        );
+        assert_eq!(
+            Solver::from_root(opening.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap(),
+            Solver::from_root(opening.clone()).get_best_moves_and_evaluation(mode).unwrap(),
+        );
+
+        assert!(Solver::from_root(opening.clone()).get_best_moves_and_evaluation(SolverMode::MonteCarlo((0, None))).is_err());
+        assert!(Solver::from_root(opening).get_evaluation_tree(SolverMode::MonteCarlo((50, None))).is_err());
+    }
 
+    #[test]
+    fn test_solver_searches_mcts() {
+        // The forced win from test_solver_solves_hybrid.  Player 1 has only the one square, but the search still has
+        // to see the game through to back up a win.
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let mode = SolverMode::Mcts((5000, 1.4, Some(11)));
+        let (best_moves, evaluation) = Solver::from_root(root.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap();
+        assert_eq!(best_moves, vec![Move::new(1, 1)]);
+        assert!(evaluation.is_expected_result());
+        assert!(evaluation.get_evaluation() > 0.);
+        assert_eq!(Solver::from_root(root.clone()).get_best_moves_and_evaluation(mode.clone()).unwrap(), (best_moves, evaluation));
+        assert_eq!(Solver::from_root(root.clone()).get_all_moves_ranked(mode).unwrap()[0].0, Move::new(1, 1));
+
+        let visit_counts = Solver::from_root(root.clone()).get_mcts_visit_counts(5000, 1.4, Some(11)).unwrap();
+        assert_eq!(visit_counts.iter().map(|(_, visits)| visits).sum::<usize>(), 5000);
+        let (most_visited_move, _) = visit_counts.iter().max_by_key(|(_, visits)| *visits).unwrap();
+        assert_eq!(*most_visited_move, Move::new(1, 1));
+
+        assert!(Solver::from_root(root).get_best_moves_and_evaluation(SolverMode::Mcts((0, 1.4, None))).is_err());
+
+        // The position from test_solver_ranks_all_moves, where only one of three moves always wins.
+        let player_1_board = Board::from_string("123\n45_\n6__".to_string()).unwrap();
+        let player_2_board = Board::from_string("111\n111\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let (best_moves, _) = Solver::from_root(root.clone()).get_best_moves_and_evaluation(SolverMode::Mcts((2000, 1.4, Some(5)))).unwrap();
+        assert_eq!(best_moves, vec![Move::new(2, 1)]);
+        assert!(Solver::from_root(root).get_all_moves_ranked(SolverMode::Mcts((1, 1.4, None))).is_err());
+    }
+
+    #[test]
+    fn test_solver_probes_endgame_table() {
+        let player_1_board = Board::from_string("111\n22_\n33_".to_string()).unwrap();
+        let player_2_board = Board::from_string("444\n55_\n666".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let endgame_table = Arc::new(EndgameTable::build(std::slice::from_ref(&endgame), 3, |_, _| {}));
+        let expected = Solver::from_root(endgame.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+
+        let mut solver = Solver::from_root(endgame.clone());
+        solver.set_endgame_table(endgame_table.clone());
+        assert_eq!(solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), expected);
+        assert_eq!(solver.get_search_stats().get_n_hits(), 1);
+        assert_eq!(solver.get_search_stats().get_n_nodes(), 1);
+        assert_eq!(solver.get_best_moves_and_evaluation(SolverMode::Hybrid(20, (2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap(), expected);
+        assert_eq!(solver.get_search_stats().get_n_nodes(), 1);
+
+        assert_eq!(solver.get_best_moves_and_evaluation(SolverMode::Heuristic((2, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap(), expected);
+
+        // The same with Player 2 to act.
+        let endgame = endgame.mirrored();
+        let endgame_table = Arc::new(EndgameTable::build(std::slice::from_ref(&endgame), 3, |_, _| {}));
+        let expected = Solver::from_root(endgame.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        let mut solver = Solver::from_root(endgame);
+        solver.set_endgame_table(endgame_table.clone());
+        assert_eq!(solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap(), expected);
+        assert_eq!(solver.get_search_stats().get_n_hits(), 1);
+        assert_eq!(solver.get_search_stats().get_n_nodes(), 1);
+
+        // Positions the table doesn't cover are searched as usual.
+        let mut solver = Solver::from_root(Node::empty().with_roll(Die::Three).unwrap());
+        solver.set_endgame_table(endgame_table);
+        solver.get_best_moves_and_evaluation(SolverMode::Heuristic((1, Objective::new(|x| Solver::difference_heuristic(x, 3.5))))).unwrap();
+        assert!(solver.get_search_stats().get_n_nodes() > 1);
+    }
+
+    #[test]
+    fn test_solver_probes_opening_book() {
+        let config = SolverConfig::builder().strategy(SolverStrategy::Heuristic).heuristic_depth(2).build().unwrap();
+        let opening_book = Arc::new(OpeningBook::build(&config, 2, |_, _| {}).unwrap());
+        let opening = Node::empty().apply_sequence(&[(Die::Three, Move::new(2, 1))]).unwrap().with_roll(Die::Five).unwrap();
+        let expected = Solver::from_root_with_config(opening.clone(), config.clone()).get_best_moves_and_evaluation(config.get_solver_mode()).unwrap();
+
+        // Book moves are played without searching, whatever the mode.
+        let mut solver = Solver::from_root_with_config(opening.clone(), config.clone());
+        solver.set_opening_book(opening_book.clone());
+        for solver_mode in [config.get_solver_mode(), SolverMode::MonteCarlo((10, Some(1)))] {
+            assert_eq!(solver.get_best_moves_and_evaluation(solver_mode).unwrap(), expected);
+            assert_eq!(solver.get_search_stats().get_n_hits(), 1);
+            assert_eq!(solver.get_search_stats().get_n_nodes(), 0);
+        }
+
+        // Positions past the book are searched as usual.
+        let mut solver = Solver::from_root_with_config(opening.apply_sequence(&[(Die::Five, Move::new(2, 1))]).unwrap().with_roll(Die::Two).unwrap(), config.clone());
+        solver.set_opening_book(opening_book);
+        solver.get_best_moves_and_evaluation(config.get_solver_mode()).unwrap();
+        assert!(solver.get_search_stats().get_n_nodes() > 1);
+    }
+
+    #[test]
+    fn test_solver_evaluates_played_move() {
+        // Player 1's six takes one of Player 2's 6s in either column, but filling up the middle column throws most of their chances away.
+        let player_1_board = Board::from_string("25_\n21_\n3__".to_string()).unwrap();
+        let player_2_board = Board::from_string("_66\n134\n123".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let ranked_moves = Solver::from_root(endgame.clone()).get_all_moves_ranked(SolverMode::BruteForce).unwrap();
+
+        let mut solver = Solver::from_root(Node::empty().with_roll(Die::One).unwrap());
+        let best = solver.evaluate_played_move(&endgame, Move::new(0, 2), SolverMode::BruteForce).unwrap();
+        assert_eq!(best.get_classification(), MoveClassification::Best);
+        assert_eq!(best.get_delta(), 0.);
+        assert_eq!((best.get_played_evaluation(), best.get_best_evaluation()), (ranked_moves[0].1, ranked_moves[0].1));
+        // The solver's own root is left where it was.
+        assert!(solver.get_root().equals_up_to_children(&Node::empty().with_roll(Die::One).unwrap()));
+
+        let blunder = solver.evaluate_played_move(&endgame, Move::new(2, 1), SolverMode::BruteForce).unwrap();
+        assert_eq!(blunder.get_classification(), MoveClassification::Blunder);
+        assert_eq!(blunder.get_played_evaluation(), ranked_moves.last().unwrap().1);
+        assert!((blunder.get_delta() - (ranked_moves[0].1.get_evaluation() - ranked_moves.last().unwrap().1.get_evaluation())).abs() < 1e-6);
+
+        // The same move is only an inaccuracy with a higher bar for blunders, and the cost is from the mover's side.
+        let config = SolverConfig::builder().blunder_threshold(0.5).build().unwrap();
+        let as_player_2 = Solver::from_root_with_config(endgame.mirrored(), config)
+            .evaluate_played_move(&endgame.mirrored(), Move::new(2, 1), SolverMode::BruteForce)
+            .unwrap();
+        assert_eq!(as_player_2.get_classification(), MoveClassification::Inaccuracy);
+        assert!((as_player_2.get_delta() - blunder.get_delta()).abs() < 1e-6);
+
+        assert_eq!(solver.evaluate_played_move(&endgame, Move::new(0, 0), SolverMode::BruteForce), Err(SolverError::IllegalMove(Move::new(0, 0))));
+        assert_eq!(solver.evaluate_played_move(&Node::empty(), Move::new(0, 0), SolverMode::BruteForce), Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }));
+    }
+
+    #[test]
+    fn test_solver_errors() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let roll = Node::new(Board::empty(), Board::empty(), NodeType::Roll(Player::Player1));
+        let wrong_node_type = SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll };
+        assert_eq!(Solver::from_root(roll.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce), Err(wrong_node_type.clone()));
+        assert_eq!(Solver::from_root(roll.clone()).get_evaluation_tree(SolverMode::Heuristic((2, objective_function.clone()))).map(|(_, evaluation)| evaluation), Err(wrong_node_type.clone()));
+        assert_eq!(roll.get_evaluation_tree(&objective_function).map(|(_, evaluation)| evaluation), Err(wrong_node_type));
+
+        // A full board ends the game whatever the node type says.
+        let finished = Node::new(Board::from_string("111\n111\n111".to_string()).unwrap(), Board::empty(), NodeType::Move(Player::Player2, Die::One));
+        assert_eq!(Solver::from_root(finished.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce), Err(SolverError::GameAlreadyOver));
+        assert_eq!(Solver::from_root(finished.clone()).get_evaluation_tree(SolverMode::BruteForce).map(|(_, evaluation)| evaluation), Err(SolverError::GameAlreadyOver));
+        let almost_finished = Node::new(Board::from_string("111\n111\n11_".to_string()).unwrap(), Board::empty(), NodeType::Move(Player::Player1, Die::Two));
+        let finished = almost_finished.with_move_made(Move::new(2, 2)).unwrap();
+        assert_eq!(Solver::from_root(finished).get_best_moves_and_evaluation(SolverMode::MonteCarlo((10, None))), Err(SolverError::GameAlreadyOver));
+
+        let root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        assert_eq!(Solver::from_root(root.clone()).get_best_moves_and_evaluation(SolverMode::Heuristic((0, objective_function.clone()))), Err(SolverError::DepthZero));
+        assert_eq!(Solver::from_root(root.clone()).get_evaluation_tree(SolverMode::Hybrid(1, (0, objective_function))).map(|(_, evaluation)| evaluation), Err(SolverError::DepthZero));
+        assert!(matches!(Solver::from_root(root.clone()).get_evaluation_tree(SolverMode::MonteCarlo((10, None))), Err(SolverError::InvalidMode(_))));
+        assert!(matches!(Solver::from_root(root).get_best_moves_and_evaluation(SolverMode::Mcts((10, -1., None))), Err(SolverError::InvalidMode(_))));
     }
 
     #[test]
@@ -290,11 +4982,12 @@ mod test_solver {
         assert_eq!(Solver::difference_heuristic(&root, 0.), -22.0);
         assert_eq!(Solver::difference_heuristic(&root, 3.5), -22.0 + 3.5);
 
-        root.add_move(Move::new(2, 2));
+        root.add_move(Move::new(2, 2)).unwrap();
         let after_move = root.get_child_from_move(Move::new(2, 2)).unwrap();
         assert!(after_move.is_game_over());
-        assert_eq!(Solver::difference_heuristic(&after_move, 0.), 27.0 - 44.0);
-        assert_eq!(Solver::difference_heuristic(&after_move, 3.5), 27.0 - 44.0);
+        assert_eq!(after_move.get_score_difference(), 27 - 44);
+        assert_eq!(Objective::new(|x| Solver::difference_heuristic(x, 0.)).evaluate(after_move), -KNOWN_WIN_EVALUATION);
+        assert_eq!(Objective::new(|x| Solver::difference_heuristic(x, 3.5)).evaluate(after_move), -KNOWN_WIN_EVALUATION);
 
         let player_1_board = Board::from_string("111\n111\n11_".to_string()).unwrap(); // 22
         let player_2_board = Board::from_string("222\n222\n22_".to_string()).unwrap(); // 44
@@ -302,11 +4995,12 @@ mod test_solver {
         assert_eq!(Solver::difference_heuristic(&root, 0.), 22.0 - 44.0);
         assert_eq!(Solver::difference_heuristic(&root, 3.5), 22.0 - 44.0 - 3.5);
 
-        root.add_move(Move::new(2, 2));
+        root.add_move(Move::new(2, 2)).unwrap();
         let after_move = root.get_child_from_move(Move::new(2, 2)).unwrap();
         assert!(after_move.is_game_over());
-        assert_eq!(Solver::difference_heuristic(&after_move, 0.), 22.0 - 54.0);
-        assert_eq!(Solver::difference_heuristic(&after_move, 3.5), 22.0 - 54.0);
+        assert_eq!(after_move.get_score_difference(), 22 - 54);
+        assert_eq!(Objective::new(|x| Solver::difference_heuristic(x, 0.)).evaluate(after_move), -KNOWN_WIN_EVALUATION);
+        assert_eq!(Objective::new(|x| Solver::difference_heuristic(x, 3.5)).evaluate(after_move), -KNOWN_WIN_EVALUATION);
 
         let player_1_board = Board::empty();
         let player_2_board = Board::empty();
@@ -314,10 +5008,10 @@ mod test_solver {
         assert_eq!(Solver::difference_heuristic(&root, 0.), 0.0);
         assert_eq!(Solver::difference_heuristic(&root, 3.5), 3.5);
 
-        root.add_move(Move::new(0, 0));
+        root.add_move(Move::new(0, 0)).unwrap();
         let after_move = root.get_child_from_move(Move::new(0, 0)).unwrap();
-        assert_eq!(Solver::difference_heuristic(&after_move, 0.), 6.0);
-        assert_eq!(Solver::difference_heuristic(&after_move, 3.5), 6.0);
+        assert_eq!(Solver::difference_heuristic(after_move, 0.), 6.0);
+        assert_eq!(Solver::difference_heuristic(after_move, 3.5), 6.0);
 
         let player_1_board = Board::empty();
         let player_2_board = Board::empty();
@@ -325,10 +5019,10 @@ mod test_solver {
         assert_eq!(Solver::difference_heuristic(&root, 0.), 0.0);
         assert_eq!(Solver::difference_heuristic(&root, 3.5), 3.5);
 
-        root.add_move(Move::new(0, 0));
+        root.add_move(Move::new(0, 0)).unwrap();
         let after_move = root.get_child_from_move(Move::new(0, 0)).unwrap();
-        assert_eq!(Solver::difference_heuristic(&after_move, 0.), 1.0);
-        assert_eq!(Solver::difference_heuristic(&after_move, 3.5), 1.0);
+        assert_eq!(Solver::difference_heuristic(after_move, 0.), 1.0);
+        assert_eq!(Solver::difference_heuristic(after_move, 3.5), 1.0);
 
         let player_1_board = Board::from_string("__1\n___\n___".to_string()).unwrap();
         let player_2_board = Board::empty();
@@ -336,10 +5030,10 @@ mod test_solver {
         assert_eq!(Solver::difference_heuristic(&root, 0.), 1.0);
         assert_eq!(Solver::difference_heuristic(&root, 3.5), 1.0);
 
-        root.add_move(Move::new(0, 0));
+        root.add_move(Move::new(0, 0)).unwrap();
         let after_move = root.get_child_from_move(Move::new(0, 0)).unwrap();
-        assert_eq!(Solver::difference_heuristic(&after_move, 0.), -5.0);
-        assert_eq!(Solver::difference_heuristic(&after_move, 3.5), -1.5);
+        assert_eq!(Solver::difference_heuristic(after_move, 0.), -5.0);
+        assert_eq!(Solver::difference_heuristic(after_move, 3.5), -1.5);
 
         let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap(); // 40 before move.
         let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap(); // 24 before move.
@@ -352,8 +5046,150 @@ mod test_solver {
             .get_child_from_move(Move::new(2, 2)).unwrap()
             .get_child_from_roll(Die::Two).unwrap()
             .get_child_from_move(Move::new(2, 2)).unwrap();
-        assert_eq!(Solver::difference_heuristic(&after_final_move, 0.0), 6.0);
-        assert_eq!(Solver::difference_heuristic(&after_final_move, 3.5), 6.0);
+        assert_eq!(after_final_move.get_score_difference(), 6);
+        assert_eq!(Objective::new(|x| Solver::difference_heuristic(x, 0.0)).evaluate(after_final_move), KNOWN_WIN_EVALUATION);
+        assert_eq!(Objective::new(|x| Solver::difference_heuristic(x, 3.5)).evaluate(after_final_move), KNOWN_WIN_EVALUATION);
+    }
+
+    #[test]
+    fn test_column_synergy_heuristic() {
+        let weights = SynergyWeights::default();
+        let player_2_board = Board::from_string("___\n444\n555".to_string()).unwrap();
+
+        // An open pair of sixes is worth its score and then some, since a third six triples it.
+        let stacked = Node::new(Board::from_string("6__\n6__\n___".to_string()).unwrap(), player_2_board.clone(), NodeType::Roll(Player::Player2));
+        let spread = Node::new(Board::from_string("66_\n___\n___".to_string()).unwrap(), player_2_board.clone(), NodeType::Roll(Player::Player2));
+        let difference_gap = Solver::difference_heuristic(&stacked, 3.5) - Solver::difference_heuristic(&spread, 3.5);
+        let synergy_gap = Solver::column_synergy_heuristic(&stacked, &weights) - Solver::column_synergy_heuristic(&spread, &weights);
+        assert_eq!(difference_gap, 12.);
+        assert!(synergy_gap > difference_gap);
+
+        // With nothing to gain or lose from the next roll, it's the difference heuristic.
+        let no_synergy = SynergyWeights { completion: 0., exposure: 0., ..weights };
+        assert_eq!(Solver::column_synergy_heuristic(&stacked, &no_synergy), Solver::difference_heuristic(&stacked, 3.5));
+        let finished = Node::new(Board::from_string("666\n666\n666".to_string()).unwrap(), player_2_board, NodeType::Roll(Player::Player2));
+        assert_eq!(Objective::new(move |x| Solver::column_synergy_heuristic(x, &weights)).evaluate(&finished), KNOWN_WIN_EVALUATION);
+
+        // A position where a one move search by the difference heuristic passes up tripling its ones, which is what
+        // brute force plays.
+        let difference = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let synergy = Objective::new(move |x| Solver::column_synergy_heuristic(x, &weights));
+        let root = Node::from_kfen("_4514_166 / 61_3212__ 1 r1").unwrap();
+        let stacking_move = Move::new(0, 0);
+        let (difference_moves, _) = Solver::from_root(root.clone()).get_best_moves_and_evaluation(SolverMode::Heuristic((1, difference))).unwrap();
+        let (synergy_moves, _) = Solver::from_root(root.clone()).get_best_moves_and_evaluation(SolverMode::Heuristic((1, synergy))).unwrap();
+        let (brute_force_moves, _) = Solver::from_root(root).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+        assert!(!difference_moves.contains(&stacking_move));
+        assert_eq!(synergy_moves, vec![stacking_move]);
+        assert_eq!(brute_force_moves, vec![stacking_move]);
+    }
+
+    #[test]
+    fn test_elimination_threat_heuristic() {
+        let params = ThreatParams::default();
+        // Only the first column has room for one of Player 2's dice.
+        let player_2_board = Board::from_string("_12\n_34\n_56".to_string()).unwrap();
+        let exposed = Node::new(Board::from_string("6__\n6__\n___".to_string()).unwrap(), player_2_board.clone(), NodeType::Roll(Player::Player2));
+        let protected = Node::new(Board::from_string("_6_\n_6_\n___".to_string()).unwrap(), player_2_board.clone(), NodeType::Roll(Player::Player2));
+        assert_eq!(Solver::difference_heuristic(&exposed, 3.5), Solver::difference_heuristic(&protected, 3.5));
+        // Player 1 can take a one, three or five, or a two, four or six, either way.
+        assert!((Solver::elimination_threat(&protected, &params) - (9. + 12.) / 6.).abs() < 1e-4);
+        assert!((Solver::elimination_threat(&exposed, &params) - ((9. + 12.) / 6. - 24. / 6.)).abs() < 1e-4);
+        assert!(Solver::elimination_threat_heuristic(&exposed, &params) < Solver::elimination_threat_heuristic(&protected, &params));
+
+        // The same the other way around, where it's Player 1 whose threats count.
+        let swapped = |node: &Node| Node::new(node.get_player_2_board(), node.get_player_1_board(), NodeType::Roll(Player::Player1));
+        assert!((Solver::elimination_threat(&swapped(&exposed), &params) + Solver::elimination_threat(&exposed, &params)).abs() < 1e-4);
+        assert!(Solver::elimination_threat_heuristic(&swapped(&exposed), &params) > Solver::elimination_threat_heuristic(&swapped(&protected), &params));
+
+        let no_threat = ThreatParams { weight: 0., ..params };
+        assert_eq!(Solver::elimination_threat_heuristic(&exposed, &no_threat), Solver::difference_heuristic(&exposed, 3.5));
+        let finished = Node::new(Board::from_string("666\n666\n666".to_string()).unwrap(), player_2_board, NodeType::Roll(Player::Player2));
+        assert_eq!(Solver::elimination_threat(&finished, &params), 0.);
+        assert_eq!(Objective::new(move |x| Solver::elimination_threat_heuristic(x, &params)).evaluate(&finished), KNOWN_WIN_EVALUATION);
+    }
+
+    #[test]
+    fn test_solver_evaluates_by_roll() {
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let opening = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let endgame = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player2));
+        for (root, solver_mode, depth) in [
+            (opening, SolverMode::Heuristic((2, objective_function.clone())), 2),
+            (endgame, SolverMode::BruteForce, DEFAULT_BRUTE_FORCE_DEPTH),
+        ] {
+            // The transposition table can stand deeper searches in for shallower ones, which the tree never does,
+            // and which rolls searched with the one table can do for each other.
+            let mut solver = Solver::from_root(root.clone());
+            solver.set_use_transposition_table(false);
+            let evaluations = solver.get_evaluation_by_roll(solver_mode.clone()).unwrap();
+            assert_eq!(evaluations.iter().map(|(die, _, _)| *die).collect::<Vec<Die>>(), Die::all());
+            assert_eq!(solver.get_root(), &root);
+
+            // The roll node is worth the average over the rolls.
+            let objective = solver.get_objective_function(&solver_mode);
+            let mut tree = root.clone();
+            tree.build_n_moves_up_to_symmetry(depth);
+            let average = evaluations.iter().zip(root.get_roll_probabilities())
+                .map(|((_, evaluation, _), (_, probability))| probability * evaluation.get_evaluation())
+                .sum::<f32>();
+            assert!((average - tree.get_evaluation(&objective)).abs() < 1e-4);
+
+            for (die, evaluation, best_moves) in evaluations {
+                let mut child_solver = Solver::from_root(root.with_roll(die).unwrap());
+                child_solver.set_use_transposition_table(false);
+                assert_eq!(child_solver.get_best_moves_and_evaluation(solver_mode.clone()).unwrap(), (best_moves, evaluation));
+            }
+        }
+
+        let mut solver = Solver::from_root(Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six)));
+        assert_eq!(
+            solver.get_evaluation_by_roll(SolverMode::BruteForce),
+            Err(SolverError::WrongNodeType { expected: NodeKind::Roll, found: NodeKind::Move }),
+        );
+        let finished = Node::new(Board::from_string("111\n111\n111".to_string()).unwrap(), Board::empty(), NodeType::Roll(Player::Player2));
+        assert_eq!(Solver::from_root(finished).get_evaluation_by_roll(SolverMode::BruteForce), Err(SolverError::GameAlreadyOver));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_solver_evaluates_roll_roots() {
+        // After the best move, the roll that follows is worth what the move was, searched a move less deep.
+        let objective_function = Objective::new(|x| Solver::difference_heuristic(x, 3.5));
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let mut solver = Solver::from_root(root.clone());
+        solver.set_use_transposition_table(false);
+        let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::Heuristic((3, objective_function.clone()))).unwrap();
+        let roll_root = root.with_move_made(best_moves[0]).unwrap();
+        let mut solver = Solver::from_root(roll_root.clone());
+        solver.set_use_transposition_table(false);
+        let solver_mode = SolverMode::Heuristic((2, objective_function));
+        let expected_evaluation = solver.get_evaluation(solver_mode.clone()).unwrap();
+        assert!(!expected_evaluation.is_expected_result());
+        assert!((expected_evaluation.get_evaluation() - evaluation.get_evaluation()).abs() < 1e-4);
+
+        // The rolls are weighted by the die distribution.
+        let mut weighted_root = roll_root.clone();
+        weighted_root.set_die_distribution(DieDistribution::from_weights([5., 1., 1., 1., 1., 1.]).unwrap());
+        for root in [roll_root, weighted_root] {
+            let mut solver = Solver::from_root(root.clone());
+            solver.set_use_transposition_table(false);
+            let evaluations = solver.get_evaluation_by_roll(solver_mode.clone()).unwrap();
+            let expected_evaluation = solver.get_evaluation(solver_mode.clone()).unwrap();
+            assert!(!expected_evaluation.is_expected_result());
+            let average = evaluations.iter().zip(root.get_roll_probabilities())
+                .map(|((_, evaluation, _), (_, probability))| probability * evaluation.get_evaluation())
+                .sum::<f32>();
+            assert!((expected_evaluation.get_evaluation() - average).abs() < 1e-4);
+        }
+
+        let finished = Node::new(Board::from_string("111\n111\n111".to_string()).unwrap(), Board::empty(), NodeType::Roll(Player::Player2));
+        assert_eq!(Solver::from_root(finished).get_evaluation(SolverMode::BruteForce), Err(SolverError::GameAlreadyOver));
+    }
+
+}
@@ -0,0 +1,94 @@
+use crate::solver::config::{SolverConfig, SolverStrategy};
+use crate::solver::selfplay::{play_match, MatchResult};
+use crate::solver::solver::Solver;
+use crate::tree::tree::Objective;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SweepResult {
+    first_value: f32,
+    second_value: f32,
+    // For the first value, whichever side it played.
+    result: MatchResult,
+}
+
+impl SweepResult {
+
+    pub fn get_first_value(&self) -> f32 {
+        self.first_value
+    }
+
+    pub fn get_second_value(&self) -> f32 {
+        self.second_value
+    }
+
+    pub fn get_wins(&self) -> usize {
+        self.result.get_wins()
+    }
+
+    pub fn get_draws(&self) -> usize {
+        self.result.get_draws()
+    }
+
+    pub fn get_losses(&self) -> usize {
+        self.result.get_losses()
+    }
+
+    pub fn get_n_games(&self) -> usize {
+        self.result.get_n_games()
+    }
+
+    pub fn get_score(&self) -> f32 {
+        // The first value's share of the points, counting a draw as half a win.
+        self.result.get_score()
+    }
+}
+
+pub fn run_parameter_sweep(param_values: &[f32], games_per_pair: usize, depth: usize, seed: u64) -> Result<Vec<SweepResult>, String> {
+    // Plays a match between every pair of empty_square_fill values with difference_heuristic searched to depth.  Each
+    // pair gets its own rolls, and the same seed always plays the same games.
+    let mut results = vec![];
+    // Each match uses a roll seed for every two games from its seed on, so the next one starts after them.
+    let mut match_seed = seed;
+    for (i, first_value) in param_values.iter().enumerate() {
+        for second_value in param_values[i + 1..].iter() {
+            let result = play_match(
+                games_per_pair,
+                &get_sweep_config(*first_value, depth)?,
+                &get_sweep_config(*second_value, depth)?,
+                match_seed,
+            )?;
+            results.push(SweepResult { first_value: *first_value, second_value: *second_value, result });
+            match_seed = match_seed.wrapping_add(games_per_pair.div_ceil(2) as u64);
+        }
+    }
+    return Ok(results);
+}
+
+pub(crate) fn get_sweep_config(empty_square_fill: f32, depth: usize) -> Result<SolverConfig, String> {
+    // difference_heuristic with empty_square_fill, searched to depth.
+    return SolverConfig::builder()
+        .strategy(SolverStrategy::Heuristic)
+        .heuristic_depth(depth)
+        .objective(Objective::with_scores(move |x, scores| Solver::difference_heuristic_with_scores(x, scores, empty_square_fill)))
+        .build();
+}
+
+#[cfg(test)]
+mod test_sweep {
+    use super::*;
+
+    #[test]
+    fn test_parameter_sweep() {
+        let results = run_parameter_sweep(&[0., 3.5], 4, 1, 11).unwrap();
+        assert_eq!(results.len(), 1);
+        let result = results[0];
+        assert_eq!((result.get_first_value(), result.get_second_value()), (0., 3.5));
+        assert_eq!(result.get_n_games(), 4);
+        assert!((0. ..=1.).contains(&result.get_score()));
+        assert_eq!(run_parameter_sweep(&[0., 3.5], 4, 1, 11).unwrap(), results);
+
+        assert_eq!(run_parameter_sweep(&[1., 2., 3.], 2, 1, 11).unwrap().iter().map(|r| (r.get_first_value(), r.get_second_value())).collect::<Vec<_>>(), vec![(1., 2.), (1., 3.), (2., 3.)]);
+        assert!(run_parameter_sweep(&[3.5], 4, 1, 11).unwrap().is_empty());
+        assert!(run_parameter_sweep(&[0., 3.5], 4, 0, 11).is_err());
+    }
+}
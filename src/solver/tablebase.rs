@@ -0,0 +1,299 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::board::board::{DieDistribution, Move};
+use crate::solver::error::SolverError;
+use crate::solver::solver::{Solver, SolverMode};
+use crate::tree::tree::{Node, NodeType};
+
+pub const DEFAULT_MAX_EMPTY_SQUARES: usize = 4;
+
+// Identifies the file format, followed by a version byte.
+const FILE_MAGIC: &[u8; 4] = b"KBET";
+const FILE_VERSION: u8 = 2;
+// A u64 key, an f32 evaluation, a byte of best columns and a byte for whether the evaluation was truncated.
+const ENTRY_BYTES: usize = 14;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EndgameEntry {
+    evaluation: f32,
+    // Bit c is set if placing the die in column c is a best move.  Every empty square in a column gives the same
+    // position up to row symmetry, so the column is all there is to a move.
+    best_columns: u8,
+    // Whether brute force hit its depth before every line had finished.
+    is_truncated: bool,
+}
+
+impl EndgameEntry {
+
+    pub fn get_evaluation(&self) -> f32 {
+        self.evaluation
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.is_truncated
+    }
+
+    pub fn get_best_moves(&self, node: &Node) -> Result<Vec<Move>, SolverError> {
+        // The best moves in node up to row symmetry, in the usual row-major order, as brute forcing it would give.
+        let moves = node.get_legal_moves_up_to_row_symmetry()?;
+        return Ok(
+            node.get_legal_moves()?.into_iter()
+                .filter(|m| moves.contains(m) && self.best_columns & (1 << m.get_column()) != 0)
+                .collect()
+        );
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndgameTable {
+    max_empty_squares: usize,
+    // Brute force evaluations of Move nodes by symmetric position key, assuming a fair die.
+    entries: HashMap<u64, EndgameEntry>,
+}
+
+impl EndgameTable {
+
+    pub fn new(max_empty_squares: usize) -> Self {
+        EndgameTable { max_empty_squares, entries: HashMap::new() }
+    }
+
+    pub fn build(roots: &[Node], max_empty_squares: usize, progress: impl FnMut(usize, usize)) -> Self {
+        let mut table = Self::new(max_empty_squares);
+        table.add_positions_from(roots, progress);
+        return table;
+    }
+
+    pub fn add_positions_from(&mut self, roots: &[Node], mut progress: impl FnMut(usize, usize)) -> usize {
+        // Brute forces every position with at most max_empty_squares empty squares that the roots lead to and isn't in
+        // the table yet, calling progress with how many are done out of how many there are after each one.
+        // Every such position there could be is far too many to enumerate (nearly full boards alone make billions),
+        // so the table only covers the games the roots lead to, and is quick to build from roots near the end.
+        let positions = self.get_unsolved_positions(roots);
+        for (i, position) in positions.iter().enumerate() {
+            // A fresh solver each time, since entries a shared transposition table kept from searching deeper could
+            // change the result from what brute forcing the position on its own gives.
+            let (best_moves, evaluation) = Solver::from_root(position.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce)
+                .expect("Guaranteed to be a move node in a game that isn't over.");
+            let best_columns = best_moves.iter().fold(0, |columns, m| columns | (1 << m.get_column()));
+            self.entries.insert(position.symmetric_position_key(), EndgameEntry { evaluation: evaluation.get_evaluation(), best_columns, is_truncated: evaluation.is_truncated() });
+            progress(i + 1, positions.len());
+        }
+        return positions.len();
+    }
+
+    fn get_unsolved_positions(&self, roots: &[Node]) -> Vec<Node> {
+        // Only follows the games while they stay within the limit.  An elimination can take a game back over it on the
+        // way to other positions under it, but following those would mean searching most of the game again.
+        let mut seen = HashSet::new();
+        let mut stack = roots.iter().map(|root| root.clone_without_children()).collect::<Vec<Node>>();
+        let mut positions = Vec::new();
+        while let Some(node) = stack.pop() {
+            if node.is_terminal() || node.get_n_empty_squares() > self.max_empty_squares || !seen.insert(node.symmetric_position_key()) {
+                continue;
+            }
+            match node.get_node_type() {
+                NodeType::Move(_, _) => {
+                    if self.covers(&node) && !self.entries.contains_key(&node.symmetric_position_key()) {
+                        positions.push(node.clone());
+                    }
+                    for m in node.get_legal_moves_up_to_row_symmetry().expect("Guaranteed to be a move node.") {
+                        stack.push(node.with_move_made(m).expect("Won't error because we know the moves are legal."));
+                    }
+                },
+                NodeType::Roll(_) => {
+                    for (die, _) in node.get_roll_probabilities() {
+                        stack.push(node.with_roll(die).expect("Won't error because the game isn't over."));
+                    }
+                },
+            }
+        }
+        return positions;
+    }
+
+    pub fn covers(&self, node: &Node) -> bool {
+        // Whether the node is the kind of position the table holds, whether or not it's been added.
+        matches!(node.get_node_type(), NodeType::Move(_, _))
+            && !node.is_terminal()
+            && node.get_n_empty_squares() <= self.max_empty_squares
+            && node.get_die_distribution() == DieDistribution::uniform()
+    }
+
+    pub fn probe(&self, node: &Node) -> Option<EndgameEntry> {
+        if !self.covers(node) {
+            return None;
+        }
+        return self.entries.get(&node.symmetric_position_key()).copied();
+    }
+
+    pub fn get_max_empty_squares(&self) -> usize {
+        self.max_empty_squares
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, self.to_bytes())
+            .map_err(|e| format!("Couldn't write the endgame table to {}: {}", path.display(), e));
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Couldn't read the endgame table from {}: {}", path.display(), e))?;
+        return Self::from_bytes(&bytes);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // The magic and version, the max empty squares and number of entries as little endian u64s, then the entries
+        // in key order so the same table always makes the same file.
+        let mut bytes = Vec::with_capacity(FILE_MAGIC.len() + 17 + self.entries.len() * ENTRY_BYTES);
+        bytes.extend_from_slice(FILE_MAGIC);
+        bytes.push(FILE_VERSION);
+        bytes.extend_from_slice(&(self.max_empty_squares as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        let mut keys = self.entries.keys().copied().collect::<Vec<u64>>();
+        keys.sort();
+        for key in keys {
+            let entry = self.entries[&key];
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.extend_from_slice(&entry.evaluation.to_le_bytes());
+            bytes.push(entry.best_columns);
+            bytes.push(entry.is_truncated as u8);
+        }
+        return bytes;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let header_length = FILE_MAGIC.len() + 17;
+        if bytes.len() < header_length || &bytes[..FILE_MAGIC.len()] != FILE_MAGIC {
+            return Err("Not an endgame table.".to_string());
+        }
+        if bytes[FILE_MAGIC.len()] != FILE_VERSION {
+            return Err(format!("Unsupported endgame table version: {}", bytes[FILE_MAGIC.len()]));
+        }
+        let read_u64 = |start: usize| u64::from_le_bytes(bytes[start..start + 8].try_into().expect("Guaranteed to be 8 bytes."));
+        let max_empty_squares = read_u64(FILE_MAGIC.len() + 1) as usize;
+        let n_entries = read_u64(FILE_MAGIC.len() + 9) as usize;
+        if n_entries.checked_mul(ENTRY_BYTES).and_then(|n| n.checked_add(header_length)) != Some(bytes.len()) {
+            return Err(format!("Endgame table should have {} entries but is {} bytes long.", n_entries, bytes.len()));
+        }
+        let entries = bytes[header_length..].chunks_exact(ENTRY_BYTES)
+            .map(
+                |entry| {
+                    if entry[13] > 1 {
+                        return Err(format!("Invalid truncation flag in endgame table: {}", entry[13]));
+                    }
+                    Ok((
+                        u64::from_le_bytes(entry[..8].try_into().expect("Guaranteed to be 8 bytes.")),
+                        EndgameEntry {
+                            evaluation: f32::from_le_bytes(entry[8..12].try_into().expect("Guaranteed to be 4 bytes.")),
+                            best_columns: entry[12],
+                            is_truncated: entry[13] == 1,
+                        },
+                    ))
+                }
+            ).collect::<Result<HashMap<u64, EndgameEntry>, String>>()?;
+        return Ok(EndgameTable { max_empty_squares, entries });
+    }
+}
+
+#[cfg(test)]
+mod test_tablebase {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+
+    use crate::board::board::{Board, Die, Player};
+    use crate::tree::tree::NodeStatus;
+
+    use super::*;
+
+    fn get_endgame() -> Node {
+        let player_1_board = Board::from_string("111\n22_\n33_".to_string()).unwrap();
+        let player_2_board = Board::from_string("444\n55_\n666".to_string()).unwrap();
+        return Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+    }
+
+    #[test]
+    fn test_endgame_table_matches_brute_force() {
+        let endgame = get_endgame();
+        let mut n_calls = 0;
+        let table = EndgameTable::build(std::slice::from_ref(&endgame), 3, |done, total| {
+            n_calls += 1;
+            assert_eq!(done, n_calls);
+            assert!(done <= total);
+        });
+        assert_eq!(table.len(), n_calls);
+        assert!(table.probe(&endgame).is_some());
+
+        // Positions along random games from the endgame, looked up and brute forced afresh.
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut n_checked = 0;
+        for _ in 0..20 {
+            let mut node = endgame.clone();
+            loop {
+                node = match node.status() {
+                    NodeStatus::Terminal(_) => break,
+                    NodeStatus::Roll(_) => node.with_roll(Die::random_from(&mut rng)).unwrap(),
+                    NodeStatus::Move(_, _) => {
+                        if table.covers(&node) {
+                            let entry = table.probe(&node).expect("Reachable positions are all in the table.");
+                            let (best_moves, evaluation) = Solver::from_root(node.clone()).get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+                            assert_eq!(entry.get_evaluation(), evaluation.get_evaluation());
+                            assert_eq!(entry.is_truncated(), evaluation.is_truncated());
+                            assert_eq!(entry.get_best_moves(&node).unwrap(), best_moves);
+                            n_checked += 1;
+                        }
+                        let m = *node.get_legal_moves().unwrap().choose(&mut rng).unwrap();
+                        node.with_move_made(m).unwrap()
+                    },
+                };
+            }
+        }
+        assert!(n_checked >= 20);
+
+        // Adding the same positions again has nothing to do.
+        let mut extended = table.clone();
+        assert_eq!(extended.add_positions_from(&[endgame], |_, _| {}), 0);
+        assert_eq!(extended, table);
+    }
+
+    #[test]
+    fn test_endgame_table_covers_small_positions() {
+        let table = EndgameTable::new(2);
+        let endgame = get_endgame();
+        assert_eq!(endgame.get_n_empty_squares(), 3);
+        assert!(!table.covers(&endgame));
+        assert!(EndgameTable::new(3).covers(&endgame));
+        assert!(!EndgameTable::new(3).covers(&Node::new(endgame.get_player_1_board(), endgame.get_player_2_board(), NodeType::Roll(Player::Player2))));
+        let mut weighted = endgame.clone();
+        weighted.set_die_distribution(DieDistribution::from_weights([2., 1., 1., 1., 1., 1.]).unwrap());
+        assert!(!EndgameTable::new(3).covers(&weighted));
+    }
+
+    #[test]
+    fn test_endgame_table_saves_and_loads() {
+        let table = EndgameTable::build(&[get_endgame()], 3, |_, _| {});
+        assert_eq!(EndgameTable::from_bytes(&table.to_bytes()).unwrap(), table);
+        let path = std::env::temp_dir().join(format!("knucklebones-endgame-table-{}.bin", std::process::id()));
+        table.save(&path).unwrap();
+        assert_eq!(EndgameTable::load(&path).unwrap(), table);
+        fs::remove_file(&path).unwrap();
+
+        assert!(EndgameTable::from_bytes(b"not a table").is_err());
+        let mut truncated = table.to_bytes();
+        truncated.pop();
+        assert!(EndgameTable::from_bytes(&truncated).is_err());
+        let mut bad_flag = table.to_bytes();
+        *bad_flag.last_mut().unwrap() = 2;
+        assert!(EndgameTable::from_bytes(&bad_flag).is_err());
+        assert!(EndgameTable::load(&path).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt::Display;
+
+use crate::board::board::{Die, Move, Player};
+use crate::tree::tree::{MoveEffect, Node, NodeType};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PlacedMove {
+    player: Player,
+    die: Die,
+    placed_move: Move,
+}
+
+impl Display for PlacedMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} placed {} at {}", self.player.to_string(), self.die.to_string(), self.placed_move.to_string())
+    }
+}
+
+impl PlacedMove {
+
+    pub fn new(player: Player, die: Die, placed_move: Move) -> Self {
+        PlacedMove { player, die, placed_move }
+    }
+
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    pub fn get_die(&self) -> Die {
+        self.die
+    }
+
+    pub fn get_move(&self) -> Move {
+        self.placed_move
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameHistory {
+    current: Node,
+    entries: Vec<(Node, PlacedMove)>,
+}
+
+impl GameHistory {
+
+    pub fn new(start: Node) -> Self {
+        GameHistory {
+            current: start.clone_without_children(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn get_current(&self) -> &Node {
+        &self.current
+    }
+
+    pub fn roll(&mut self, die: Die) -> Result<(), String> {
+        // Rolls aren't recorded on their own; undoing a move goes back to the Move node with its roll.
+        self.current = self.current.with_roll(die)?;
+        return Ok(());
+    }
+
+    pub fn push_move(&mut self, m: Move) -> Result<MoveEffect, String> {
+        let (player, die) = match self.current.get_node_type() {
+            NodeType::Roll(_) => return Err(format!("Cannot make a move from a roll node: {}", self.current)),
+            NodeType::Move(player, die) => (player, die),
+        };
+        let (next, effect) = self.current.with_move_made_detailed(m)?;
+        let snapshot = std::mem::replace(&mut self.current, next);
+        self.entries.push((snapshot, PlacedMove::new(player, die, m)));
+        return Ok(effect);
+    }
+
+    pub fn undo(&mut self) -> Option<Node> {
+        // Restores the position from before the last move and returns it.
+        let (snapshot, _) = self.entries.pop()?;
+        self.current = snapshot;
+        return Some(self.current.clone());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PlacedMove> {
+        self.entries.iter().map(|(_, placed_move)| placed_move)
+    }
+
+    pub fn get_n_moves(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod test_history {
+    use super::*;
+    use crate::board::board::Board;
+
+    #[test]
+    fn test_history_records_moves() {
+        let mut history = GameHistory::new(Node::empty());
+        assert_eq!(history.get_n_moves(), 0);
+        assert!(history.push_move(Move::new(0, 0)).is_err());
+
+        history.roll(Die::Three).unwrap();
+        history.push_move(Move::new(0, 0)).unwrap();
+        history.roll(Die::Five).unwrap();
+        history.push_move(Move::new(1, 2)).unwrap();
+        assert_eq!(
+            history.iter().cloned().collect::<Vec<PlacedMove>>(),
+            vec![
+                PlacedMove::new(Player::Player1, Die::Three, Move::new(0, 0)),
+                PlacedMove::new(Player::Player2, Die::Five, Move::new(1, 2)),
+            ]
+        );
+        assert_eq!(history.get_current().get_node_type(), NodeType::Roll(Player::Player1));
+        assert_eq!(history.iter().next().unwrap().to_string(), "Player 1 placed 3 at (0, 0)".to_string());
+
+        history.roll(Die::One).unwrap();
+        assert!(history.push_move(Move::new(0, 0)).is_err());
+        assert_eq!(history.get_n_moves(), 2);
+    }
+
+    #[test]
+    fn test_history_undoes_eliminations() {
+        let player_1_board = Board::from_string("44_\n4__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("___\n_2_\n___".to_string()).unwrap();
+        let start = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Four));
+        let mut history = GameHistory::new(start.clone());
+
+        history.push_move(Move::new(0, 0)).unwrap();
+        assert_eq!(history.get_current().get_player_1_board(), Board::from_string("_4_\n___\n___".to_string()).unwrap());
+        history.roll(Die::Two).unwrap();
+        let before_second_move = history.get_current().clone();
+        history.push_move(Move::new(2, 1)).unwrap();
+        assert_eq!(history.get_current().get_player_2_board(), Board::from_string("4__\n___\n___".to_string()).unwrap());
+
+        assert_eq!(history.undo(), Some(before_second_move.clone()));
+        assert_eq!(*history.get_current(), before_second_move);
+        assert_eq!(history.undo(), Some(start.clone()));
+        assert_eq!(history.get_current().get_player_1_board(), player_1_board);
+        assert_eq!(history.get_current().get_player_2_board(), player_2_board);
+        assert_eq!(history.undo(), None);
+        assert_eq!(*history.get_current(), start);
+    }
+}
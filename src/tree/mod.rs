@@ -1 +1,5 @@
-pub mod tree;
\ No newline at end of file
+// The code here spells its returns out, and the tree module shares its directory's name.
+#[allow(clippy::module_inception, clippy::needless_return)]
+pub mod tree;
+#[allow(clippy::needless_return)]
+pub mod history;
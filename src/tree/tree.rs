@@ -1,18 +1,162 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
 use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::board::board::{Board, Move, Outcome, Player, Die, DieDistribution, Comparison, EliminationResult, are_tied, are_tied_within, compare_totally, max_keeping_nan};
+use crate::solver::config::DEFAULT_DRAW_WEIGHT;
+use crate::solver::error::{NodeKind, SolverError};
+use crate::solver::solver::{Evaluation, KNOWN_WIN_EVALUATION};
+
+// Where the next objective's key comes from, so no two objectives share one.
+static NEXT_OBJECTIVE_KEY: AtomicUsize = AtomicUsize::new(0);
+
+type ObjectiveFn = Arc<dyn Fn(&Node, Option<(u16, u16)>) -> f32 + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Objective {
+    // Identifies the function in the evaluation caches.  Clones keep the key, so they share cached evaluations.
+    key: usize,
+    // Takes both players' scores where the caller already has them, so functions reading them don't sum the boards.
+    function: ObjectiveFn,
+    // Whether the function scores a position the same whatever die is about to be placed.
+    ignores_roll: bool,
+    // Whether the function scores finished games itself.  Otherwise they get their known value instead.
+    exact: bool,
+}
 
-use crate::board::board::{Board, Move, Outcome, Player, Die, Comparison};
+impl Objective {
 
-#[derive(Clone, Debug, PartialEq)]
+    pub fn new(function: impl Fn(&Node) -> f32 + Send + Sync + 'static) -> Self {
+        Objective {
+            key: NEXT_OBJECTIVE_KEY.fetch_add(1, AtomicOrdering::Relaxed),
+            function: Arc::new(move |node, _| function(node)),
+            ignores_roll: false,
+            exact: false,
+        }
+    }
+
+    pub fn ignoring_roll(function: impl Fn(&Node) -> f32 + Send + Sync + 'static) -> Self {
+        // For functions that only look at the boards and who's to act, not the die about to be placed, which lets
+        // searches score the rolls they stop at once between them.
+        Objective { ignores_roll: true, ..Self::new(function) }
+    }
+
+    pub fn with_scores(function: impl Fn(&Node, (u16, u16)) -> f32 + Send + Sync + 'static) -> Self {
+        // For functions of both players' scores, which searches carry down to the positions they score.  Anywhere
+        // else they're summed from the boards.
+        Objective {
+            key: NEXT_OBJECTIVE_KEY.fetch_add(1, AtomicOrdering::Relaxed),
+            function: Arc::new(move |node, scores: Option<(u16, u16)>| function(node, scores.unwrap_or_else(|| node.get_scores()))),
+            ignores_roll: false,
+            exact: false,
+        }
+    }
+
+    pub fn ignoring_roll_with_scores(function: impl Fn(&Node, (u16, u16)) -> f32 + Send + Sync + 'static) -> Self {
+        Objective { ignores_roll: true, ..Self::with_scores(function) }
+    }
+
+    pub fn exact(self) -> Self {
+        // For functions that score finished games themselves, e.g. by the final margin, rather than by their known
+        // value.
+        Objective { exact: true, ..self }
+    }
+
+    pub fn ignores_roll(&self) -> bool {
+        self.ignores_roll
+    }
+
+    pub fn evaluate(&self, node: &Node) -> f32 {
+        // Finished games are scored by who won, so a heuristic never has to tell them from games in progress.
+        if !self.exact && node.is_terminal() {
+            return Self::evaluate_finished_game(node.get_scores());
+        }
+        (self.function)(node, None)
+    }
+
+    pub fn evaluate_with_scores(&self, node: &Node, scores: (u16, u16)) -> f32 {
+        // Scores node with both players' scores worked out by the caller, which have to be the boards' sums.
+        debug_assert_eq!(scores, node.get_scores(), "Scores carried to {} don't match its boards.", node);
+        if !self.exact && node.is_terminal() {
+            return Self::evaluate_finished_game(scores);
+        }
+        (self.function)(node, Some(scores))
+    }
+
+    pub fn evaluate_finished_game(scores: (u16, u16)) -> f32 {
+        // KNOWN_WIN_EVALUATION for whoever won, well beyond any lead, so a certain win is always preferred to a big
+        // one.
+        let (player_1_score, player_2_score) = scores;
+        return Evaluation::from_outcome(Outcome::from_scores(player_1_score, player_2_score), DEFAULT_DRAW_WEIGHT)
+            .expect("Outcome by score is never in progress.")
+            .get_evaluation() * KNOWN_WIN_EVALUATION;
+    }
+
+    pub fn get_key(&self) -> usize {
+        self.key
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
 pub struct Node {
     player_1_board: Board,
     player_2_board: Board,
     node_type: NodeType,
+    // Moves made since the start of the game.
+    ply: usize,
+    // How likely each roll is.  Not written out, so deserialized trees assume fair dice.
+    #[serde(skip)]
+    die_distribution: DieDistribution,
+    // Every move from the parent that leads here up to row symmetry, if the parent's children were merged.
+    #[serde(skip)]
+    equivalent_moves: Vec<Move>,
     children: Vec<Node>,
+    // The node's evaluation under the objective whose key is stored alongside it.
+    #[serde(skip)]
+    cached_evaluation: Cell<Option<(usize, f32)>>,
+    // The subtree's max depth and node count, worked out the first time either is asked for.
+    #[serde(skip)]
+    cached_size: Cell<Option<(usize, usize)>>,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        // The cache is derived from the rest, so it doesn't count.  Nor does the ply, since it's not part of the position.
+        self.equals_up_to_children(other) && self.children == other.children
+    }
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Written with its status so readers can tell finished games from ones waiting on a roll.  It's ignored when
+        // deserializing since it's determined by the rest.
+        let mut state = serializer.serialize_struct("Node", 6)?;
+        state.serialize_field("player_1_board", &self.player_1_board)?;
+        state.serialize_field("player_2_board", &self.player_2_board)?;
+        state.serialize_field("node_type", &self.node_type)?;
+        state.serialize_field("ply", &self.ply)?;
+        state.serialize_field("status", &self.status())?;
+        state.serialize_field("children", &self.children)?;
+        state.end()
+    }
 }
 
 impl Display for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Player 1:\n{}\nPlayer 2:\n{}\nType: {:?}\nN Children: {}", self.player_1_board, self.player_2_board, self.node_type, self.children.len())
+        // One line by default, so it reads well in logs and error messages.  {:#} gives the multi-line form.
+        if f.alternate() {
+            write!(f, "Player 1:\n{}\nPlayer 2:\n{}\nType: {:?}\nN Children: {}", self.player_1_board, self.player_2_board, self.node_type, self.children.len())
+        } else {
+            write!(f, "{}", self.to_compact_string())
+        }
     }
 }
 
@@ -23,17 +167,57 @@ impl Node {
             player_1_board: Board::empty(),
             player_2_board: Board::empty(),
             node_type: NodeType::Roll(Player::Player1),
+            ply: 0,
+            die_distribution: DieDistribution::uniform(),
+            equivalent_moves: Vec::new(),
             children: Vec::new(),
+            cached_evaluation: Cell::new(None),
+            cached_size: Cell::new(None),
         }
     }
 
     pub fn new(player_1_board: Board, player_2_board: Board, node_type: NodeType) -> Self {
+        // Without a history, assume no dice were eliminated, so the ply is the number of dice on the boards.  Use
+        // set_ply if that's wrong.
+        let ply = 18 - player_1_board.get_n_empty_squares() - player_2_board.get_n_empty_squares();
         Node {
             player_1_board,
             player_2_board,
             node_type,
+            ply,
+            die_distribution: DieDistribution::uniform(),
+            equivalent_moves: Vec::new(),
             children: Vec::new(),
+            cached_evaluation: Cell::new(None),
+            cached_size: Cell::new(None),
+        }
+    }
+
+    pub fn new_validated(player_1_board: Board, player_2_board: Board, node_type: NodeType) -> Result<Self, String> {
+        // Like new, but refuses positions no game could reach, e.g. ones typed in by hand.  Dice counts can't be held
+        // to strict parity since eliminations take dice off either board, but the die the opponent just placed can't
+        // have been eliminated yet.
+        let node = Node::new(player_1_board, player_2_board, node_type);
+        let player = node.get_active_player();
+        let player_board = node.get_player_board(player);
+        let opponent_board = node.get_player_board(player.opponent());
+        if let NodeType::Move(_, die) = node_type {
+            if player_board.is_full() {
+                return Err(format!("{}'s board is full, so there's nowhere to place the {}: {}", player.to_string(), die.to_string(), node));
+            }
+            if node.is_game_over() {
+                return Err(format!("The game is over, so there can't be a roll to place: {}", node));
+            }
         }
+        if opponent_board.get_n_empty_squares() == 9 && player_board.get_n_empty_squares() < 9 {
+            return Err(
+                format!(
+                    "{} has no dice, so can't have just moved, but {} has some: {}",
+                    player.opponent().to_string(), player.to_string(), node,
+                )
+            );
+        }
+        return Ok(node);
     }
 
     pub fn from_player_and_boards(active_player: Player, active_players_board: Board, opponents_board: Board, node_type: NodeType) -> Self {
@@ -43,12 +227,22 @@ impl Node {
         }
     }
 
+    pub fn from_active_player_boards(active_player: Player, active_players_board: Board, opponents_board: Board, roll: Die) -> Self {
+        // The position as whoever's next to act sees it, with them about to place the roll.
+        return Node::from_player_and_boards(active_player, active_players_board, opponents_board, NodeType::Move(active_player, roll));
+    }
+
     pub fn clone_without_children(&self) -> Self {
         Node {
             player_1_board: self.player_1_board.clone(),
             player_2_board: self.player_2_board.clone(),
-            node_type: self.node_type.clone(),
+            node_type: self.node_type,
+            ply: self.ply,
+            die_distribution: self.die_distribution,
+            equivalent_moves: self.equivalent_moves.clone(),
             children: Vec::new(),
+            cached_evaluation: Cell::new(None),
+            cached_size: Cell::new(None),
         }
     }
 
@@ -82,6 +276,47 @@ impl Node {
         }
     }
 
+    pub fn get_ply(&self) -> usize {
+        self.ply
+    }
+
+    pub fn set_ply(&mut self, ply: usize) {
+        self.ply = ply;
+    }
+
+    pub fn get_die_distribution(&self) -> DieDistribution {
+        self.die_distribution
+    }
+
+    pub fn set_die_distribution(&mut self, die_distribution: DieDistribution) {
+        // Children built from now on inherit it, but ones that already exist are left alone, so set it before
+        // building.
+        self.die_distribution = die_distribution;
+        self.cached_evaluation.set(None);
+    }
+
+    pub fn get_roll_probabilities(&self) -> Vec<(Die, f32)> {
+        // Empty unless we're waiting on a roll.
+        match self.status() {
+            NodeStatus::Roll(_) => Die::all().into_iter().map(|die| (die, self.die_distribution.get_probability(die))).collect(),
+            _ => vec![],
+        }
+    }
+
+    fn get_roll_child_weights(&self) -> Vec<f32> {
+        // Each Roll node child's share of the average, rescaled over the rolls that were built.
+        let probabilities = self.children.iter()
+            .map(|child| self.die_distribution.get_probability(child.get_die().expect("Children of a roll node are move nodes.")))
+            .collect::<Vec<f32>>();
+        let total = probabilities.iter().sum::<f32>();
+        return probabilities.into_iter().map(|probability| probability / total).collect();
+    }
+
+    pub fn get_turn_number(&self) -> usize {
+        // Turns are counted from 1, each made up of one move by each player.
+        self.ply / 2 + 1
+    }
+
     pub fn get_active_player(&self) -> Player {
         match self.node_type {
             NodeType::Roll(player) => player,
@@ -89,59 +324,185 @@ impl Node {
         }
     }
 
+    pub fn to_kfen(&self) -> String {
+        // "<player 1 board> / <player 2 board> <player to act> <r + pending roll, or - on a roll node>", with each
+        // board written row by row, e.g. "2________ / _35______ 1 r5".
+        let player = match self.get_active_player() {
+            Player::Player1 => "1",
+            Player::Player2 => "2",
+        };
+        let roll = match self.node_type {
+            NodeType::Roll(_) => "-".to_string(),
+            NodeType::Move(_, die) => format!("r{}", die.to_string()),
+        };
+        return format!(
+            "{} / {} {} {}",
+            self.player_1_board.to_string().replace('\n', ""),
+            self.player_2_board.to_string().replace('\n', ""),
+            player,
+            roll,
+        );
+    }
+
+    pub fn to_compact_string(&self) -> String {
+        // e.g. "[P1:2________|P2:_35______|Move P2 d5|ch:3]", with the boards written row by row as in KFEN.
+        let short_player = |player: Player| match player {
+            Player::Player1 => "P1",
+            Player::Player2 => "P2",
+        };
+        let node_type = match self.node_type {
+            NodeType::Roll(player) => format!("Roll {}", short_player(player)),
+            NodeType::Move(player, die) => format!("Move {} d{}", short_player(player), die.to_string()),
+        };
+        return format!(
+            "[P1:{}|P2:{}|{}|ch:{}]",
+            self.player_1_board.to_string().replace('\n', ""),
+            self.player_2_board.to_string().replace('\n', ""),
+            node_type,
+            self.children.len(),
+        );
+    }
+
+    pub fn from_kfen(kfen: &str) -> Result<Node, String> {
+        let fields = kfen.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() != 5 || fields[1] != "/" {
+            return Err(format!("Invalid KFEN, expected \"<board> / <board> <player> <roll>\": {}", kfen));
+        }
+        let player_1_board = Self::board_from_kfen(fields[0])?;
+        let player_2_board = Self::board_from_kfen(fields[2])?;
+        let player = match fields[3] {
+            "1" => Player::Player1,
+            "2" => Player::Player2,
+            p => return Err(format!("Invalid KFEN player, expected 1 or 2: {}", p)),
+        };
+        let node_type = match fields[4] {
+            "-" => NodeType::Roll(player),
+            roll => {
+                let die = match roll.strip_prefix('r').map(|value| value.chars().collect::<Vec<char>>()) {
+                    Some(chars) if chars.len() == 1 => Die::from_char(chars[0]),
+                    _ => Err(format!("Invalid KFEN roll, expected - or r1 to r6: {}", roll)),
+                }?;
+                NodeType::Move(player, die)
+            },
+        };
+        return Node::new_validated(player_1_board, player_2_board, node_type).map_err(|e| format!("Invalid KFEN position \"{}\": {}", kfen, e));
+    }
+
+    fn board_from_kfen(board: &str) -> Result<Board, String> {
+        let squares = board.chars().collect::<Vec<char>>();
+        if squares.len() != 9 {
+            return Err(format!("Invalid KFEN board, expected 9 squares: {}", board));
+        }
+        let rows = squares.chunks(3).map(|row| row.iter().collect::<String>()).collect::<Vec<String>>();
+        return Board::from_string(rows.join("\n"));
+    }
+
+    pub fn position_key(&self) -> u64 {
+        // Identifies the position: both boards, the die to place if any, and the player to act.  Children are excluded,
+        // so the same position reached by different move orders, or built to different depths, has the same key.
+        let die_value = match self.node_type {
+            NodeType::Roll(_) => 0,
+            NodeType::Move(_, die) => die.to_value() as u64,
+        };
+        let player_value = match self.get_active_player() {
+            Player::Player1 => 0,
+            Player::Player2 => 1,
+        };
+        return ((self.player_1_board.to_key() as u64) << 31)
+            | ((self.player_2_board.to_key() as u64) << 4)
+            | (die_value << 1)
+            | player_value;
+    }
+
+    pub fn symmetric_position_key(&self) -> u64 {
+        // As position_key, but equal for positions that only differ in the order of the dice within columns.
+        Node::new(
+            self.player_1_board.with_rows_sorted(),
+            self.player_2_board.with_rows_sorted(),
+            self.node_type,
+        ).position_key()
+    }
+
     pub fn to_string_from_perspective(&self, player: Player) -> String {
         let maybe_roll_string = match self.node_type {
             NodeType::Roll(_) => "".to_string(),
             NodeType::Move(_, die) => die.to_string() + "\n",
         };
-        match player {
-            Player::Player1 => format!("Player:\n{}\n\nOpponent:\n{}\n\nRoll: {}\n", self.player_1_board, self.player_2_board, maybe_roll_string),
-            Player::Player2 => format!("Player:\n{}\n\nOpponent:\n{}\n\nRoll: {}\n", self.player_2_board, self.player_1_board, maybe_roll_string),
-        }
+        let player_score = self.get_score(player);
+        let opponent_score = self.get_score(player.opponent());
+        let to_act = match self.status() {
+            NodeStatus::Terminal(_) => "Game Over",
+            _ if self.get_active_player() == player => "Player",
+            _ => "Opponent",
+        };
+        return format!(
+            "Turn: {}\n\nPlayer:\n{}\n\nOpponent:\n{}\n\nRoll: {}\nPlayer Score: {}\nOpponent Score: {}\nDifference: {:+}\nTo Act: {}\n",
+            self.get_turn_number(),
+            self.get_player_board(player),
+            self.get_player_board(player.opponent()),
+            maybe_roll_string,
+            player_score,
+            opponent_score,
+            player_score as i16 - opponent_score as i16,
+            to_act,
+        );
+    }
+
+    pub fn to_pretty_string(&self, objective_function: &Objective) -> String {        
+        self.to_pretty_string_with(&PrettyPrintOptions::default(), objective_function)
     }
 
-    pub fn to_pretty_string(&self, objective_function: fn(&Node) -> f32) -> String {        
-        self.to_pretty_vector(objective_function, 0, None).join("\n")
+    pub fn to_pretty_string_with(&self, options: &PrettyPrintOptions, objective_function: &Objective) -> String {
+        self.to_pretty_vector(options, objective_function, 0, None).join("\n")
     }
 
-    fn to_pretty_vector(&self, objective_function: fn(&Node) -> f32, depth: usize, last_move: Option<Move>) -> Vec<String> {
+    fn to_pretty_vector(&self, options: &PrettyPrintOptions, objective_function: &Objective, depth: usize, last_move: Option<Move>) -> Vec<String> {
         let mut to_return = Vec::new();
-        let mut indent = String::new();
-        for _ in 0..depth {
-            indent.push_str("  ");
-        }
-        let suffix = if self.get_n_children() > 0 {
-            " =>".to_string()
-        } else {
-            "".to_string()
-        };
-        let last_move_string = match last_move {
-            Some(m) => m.to_string(),
-            None => "Unknown Move".to_string(),
-        };
-        match self.get_node_type() {
-            NodeType::Roll(player) => {
-                if self.get_n_children() > 0 {
-                    to_return.push(format!("{}{}{}", indent, last_move_string, suffix));
-                    to_return.append(&mut self.children.iter().map(|child| child.to_pretty_vector(objective_function, depth + 1, None)).flatten().collect());
-                } else {
-                    to_return.push(format!("{}{}: {}", indent, last_move_string, objective_function(self)));
-                }
-                
+        let indent = options.indent.repeat(depth);
+        let (label, children) = match self.get_node_type() {
+            NodeType::Roll(_) => {
+                let last_move_string = match last_move {
+                    Some(m) => m.to_string(),
+                    None => "Unknown Move".to_string(),
+                };
+                (last_move_string, self.children.iter().map(|child| (None, child)).collect::<Vec<(Option<Move>, &Node)>>())
             },
-            NodeType::Move(player, die) => {
-                if self.get_n_children() > 0 {
-                    to_return.push(format!("{}{}{}", indent, die.to_string(), suffix));
-                    to_return.append(
-                        &mut self.get_moves()
-                        .expect("Must be a move node").iter().map(
-                            |m| self.get_child_from_move(*m).expect("Child is guaranteed to exist.")
-                                .to_pretty_vector(objective_function, depth + 1, Some(*m))
-                        ).flatten().collect());
-                } else {
-                    to_return.push(format!("{}{}: {}", indent, die.to_string(), objective_function(self)));
-                }
+            NodeType::Move(_, die) => {
+                let die_string = if options.unicode_dice { die.to_unicode() } else { die.to_string() };
+                let children = self.get_moves()
+                    .expect("Must be a move node").into_iter().map(
+                        |m| (Some(m), self.get_child_from_move(m).expect("Child is guaranteed to exist."))
+                    ).collect::<Vec<(Option<Move>, &Node)>>();
+                (die_string, children)
             },
+        };
+        // Finished games are marked so their exact values stand out from the heuristic ones at unexpanded leaves.
+        let terminal_marker = if self.is_terminal() { " (game over)" } else { "" };
+        if !children.is_empty() {
+            to_return.push(format!("{}{} =>", indent, label));
+        } else if options.show_evaluations {
+            to_return.push(format!("{}{}: {}{}", indent, label, self.evaluate_leaf(objective_function), terminal_marker));
+        } else {
+            to_return.push(format!("{}{}{}", indent, label, terminal_marker));
+        }
+        if options.show_boards {
+            let player_1_rows = self.player_1_board.to_string();
+            let player_2_rows = self.player_2_board.to_string();
+            for (player_1_row, player_2_row) in player_1_rows.split('\n').zip(player_2_rows.split('\n')) {
+                to_return.push(format!("{}| {} {}", indent, player_1_row, player_2_row));
+            }
+        }
+
+        let n_shown = match options.max_depth {
+            Some(max_depth) if depth >= max_depth => 0,
+            _ => options.max_children_per_node.unwrap_or(children.len()).min(children.len()),
+        };
+        for (m, child) in children.iter().take(n_shown) {
+            to_return.append(&mut child.to_pretty_vector(options, objective_function, depth + 1, *m));
+        }
+        let n_hidden = children.iter().skip(n_shown).map(|(_, child)| child.count_nodes()).sum::<usize>();
+        if n_hidden > 0 {
+            to_return.push(format!("{}{}... ({} hidden)", indent, options.indent, n_hidden));
         }
         return to_return
     }
@@ -149,23 +510,25 @@ impl Node {
     pub fn is_legal_move(&self, m: Move) -> bool {
         match self.node_type {
             NodeType::Roll(_) => false,
-            NodeType::Move(player, _) => self.get_legal_moves().map_or(false, |moves| moves.contains(&m)),
+            NodeType::Move(_, _) => self.get_legal_moves().is_ok_and(|moves| moves.contains(&m)),
         }
     }
 
-    pub fn get_legal_moves(&self) -> Result<Vec<Move>, String> {
+    pub fn get_legal_moves(&self) -> Result<Vec<Move>, SolverError> {
+        // Row-major, as are get_moves and get_legal_moves_up_to_row_symmetry, so children and best moves come out in
+        // a fixed order.
         match self.node_type {
-            NodeType::Roll(player) => {
-                return Err("Cannot get legal moves from a roll node".to_string());
+            NodeType::Roll(_) => {
+                return Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll });
             },
-            NodeType::Move(player, die) => {
+            NodeType::Move(player, _) => {
                 let board = self.get_player_board(player);
                 return Ok(board.get_empty_squares().iter().map(|square| Move::new(square.0, square.1)).collect());
             },
         }
     }
 
-    pub fn get_moves(&self) -> Result<Vec<Move>, String> {
+    pub fn get_moves(&self) -> Result<Vec<Move>, SolverError> {
         self.get_legal_moves().map(
             |moves| {
                 moves.into_iter()
@@ -176,12 +539,12 @@ impl Node {
         )
     }
 
-    pub fn get_legal_moves_up_to_row_symmetry(&self) -> Result<Vec<Move>, String> {
+    pub fn get_legal_moves_up_to_row_symmetry(&self) -> Result<Vec<Move>, SolverError> {
         match self.node_type {
-            NodeType::Roll(player) => {
-                return Err("Cannot get legal moves from a roll node".to_string());
+            NodeType::Roll(_) => {
+                return Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll });
             },
-            NodeType::Move(player, die) => {
+            NodeType::Move(player, _) => {
                 let board = self.get_player_board(player);
                 return Ok(board.get_empty_squares_up_to_row_symmetry().iter().map(|square| Move::new(square.0, square.1)).collect());
             },
@@ -206,6 +569,39 @@ impl Node {
         }
     }
 
+    pub fn get_moves_left_bounds(&self) -> (usize, usize) {
+        // Bounds on the plies left before a board fills and the game ends.
+        // Eliminations only ever empty squares, so the game can't end sooner than it would without any: that's the min.
+        // For the max, a square can be reopened whenever the opponent can still place a die in that column, and
+        // repeated eliminations can make the game arbitrarily long.  We cap it by assuming each square is reopened
+        // at most once, i.e. in every column the opponent can still play in, all three squares may need refilling.
+        if self.is_game_over() {
+            return (0, 0);
+        }
+        let min = self.get_moves_left_ignoring_elimination();
+        let player_1_reopenable = Self::get_n_reopenable_squares(&self.player_2_board);
+        let player_2_reopenable = Self::get_n_reopenable_squares(&self.player_1_board);
+        let max = self.get_plies_until_a_board_fills(
+            self.player_1_board.get_n_empty_squares() + player_1_reopenable,
+            self.player_2_board.get_n_empty_squares() + player_2_reopenable,
+        );
+        return (min, max);
+    }
+
+    fn get_n_reopenable_squares(opponent_board: &Board) -> usize {
+        (0..3).filter(|column| opponent_board.get_n_empty_squares_in_column(*column) > 0)
+            .count() * 3
+    }
+
+    fn get_plies_until_a_board_fills(&self, player_1_squares_left: usize, player_2_squares_left: usize) -> usize {
+        // The active player places on odd plies and their opponent on even ones.
+        let (active_squares_left, inactive_squares_left) = match self.get_active_player() {
+            Player::Player1 => (player_1_squares_left, player_2_squares_left),
+            Player::Player2 => (player_2_squares_left, player_1_squares_left),
+        };
+        return (2 * active_squares_left - 1).min(2 * inactive_squares_left);
+    }
+
     pub fn equals_up_to_children(&self, other: &Node) -> bool {
         self.player_1_board == other.player_1_board &&
         self.player_2_board == other.player_2_board &&
@@ -213,6 +609,9 @@ impl Node {
     }
 
     pub fn generate_children_up_to_symmetry(&mut self) {
+        if self.is_game_over() {
+            return;
+        }
         match self.node_type {
             NodeType::Roll(_) => {
                 self.add_rolls().expect("Won't error because we're in a Roll node type.");
@@ -226,660 +625,3215 @@ impl Node {
         }
     }
 
-    pub fn build_n_moves_up_to_symmetry(&mut self, n: usize) {
+    pub fn generate_children(&mut self) {
+        // One child for every legal move, without skipping symmetric ones.
         if self.is_game_over() {
             return;
-        };
+        }
         match self.node_type {
             NodeType::Roll(_) => {
-                self.generate_children_up_to_symmetry();                
-                for child in self.children.iter_mut() {
-                    child.build_n_moves_up_to_symmetry(n);
-                }
+                self.add_rolls().expect("Won't error because we're in a Roll node type.");
             },
             NodeType::Move(_, _) => {
-                if n == 0 {
-                    return;
-                };
-                self.generate_children_up_to_symmetry();
-                for child in self.children.iter_mut() {
-                    child.build_n_moves_up_to_symmetry(n - 1);
+                let legal_moves = self.get_legal_moves().expect("Won't error because we're in a Move node type.");
+                for m in legal_moves {
+                    self.add_move(m).expect("Won't error because we know the moves are legal.");
                 }
             },
         }
-        
     }
 
-    pub fn build_entire_tree_up_to_symmetry(&mut self) {
-        if self.is_game_over() {
+    pub fn generate_children_merged(&mut self) {
+        // Like generate_children, but with the children that are the same position merged.  The result has the same
+        // children as generate_children_up_to_symmetry, except each one knows all the moves that lead to it.
+        self.generate_children();
+        self.merge_equivalent_children();
+    }
+
+    pub fn merge_equivalent_children(&mut self) {
+        // Keeps the first of each Move node child that's the same position up to row symmetry, dropping the rest along
+        // with their subtrees.  Each child left records every legal move leading to it, so get_child_from_move and
+        // get_moves treat those moves as equivalent.
+        if !matches!(self.node_type, NodeType::Move(_, _)) || self.is_game_over() {
             return;
         }
-        self.generate_children_up_to_symmetry();
-        for child in self.children.iter_mut() {
-            child.build_entire_tree_up_to_symmetry();
+        let move_keys = self.get_legal_moves().expect("Guaranteed to be a move node.").into_iter()
+            .map(|m| (m, self.with_move_made(m).expect("Won't error because we know the moves are legal.").symmetric_position_key()))
+            .collect::<Vec<(Move, u64)>>();
+        let mut merged_children: Vec<Node> = Vec::new();
+        let mut merged_keys = Vec::new();
+        for mut child in self.children.drain(..) {
+            let key = child.symmetric_position_key();
+            if merged_keys.contains(&key) {
+                continue;
+            }
+            child.equivalent_moves = move_keys.iter()
+                .filter(|(_, move_key)| *move_key == key)
+                .map(|(m, _)| *m)
+                .collect();
+            merged_keys.push(key);
+            merged_children.push(child);
         }
+        self.children = merged_children;
+        self.invalidate_caches();
     }
 
-    pub fn get_evaluation_tree(&self, objective_function: fn(&Node) -> f32) -> Result<(Option<Node>, f32), String> {
-        let player = match self.node_type {
-            NodeType::Roll(_) => {
-                return Err("Cannot get next moves and evaluation from a roll node".to_string());
-            },
-            NodeType::Move(player, _) => player,
-        };
-        if self.is_leaf() {
-            return Ok((None, objective_function(self)));
-        }
+    pub fn get_canonical_column_order(&self) -> Vec<usize> {
+        // Which of this position's columns canonicalize_columns puts first, second and third.  Columns are compared
+        // with their dice sorted, so positions that only differ by the rows the dice are in get the same order.
+        let player = self.get_active_player();
+        let player_board = self.get_player_board(player).with_rows_sorted();
+        let opponent_board = self.get_player_board(player.opponent()).with_rows_sorted();
+        let mut order = vec![0, 1, 2];
+        order.sort_by_key(|column| (player_board.get_column(*column), opponent_board.get_column(*column)));
+        return order;
+    }
 
-        let legal_moves = self.get_legal_moves_up_to_row_symmetry()
-            .expect("Won't error because we're in a Move node type.");
-        let mut best_evaluation = match player {
-            Player::Player1 => f32::NEG_INFINITY,
-            Player::Player2 => f32::INFINITY,
+    pub fn get_equivalent_moves(&self) -> &Vec<Move> {
+        &self.equivalent_moves
+    }
+
+    pub fn canonicalize_columns(&self) -> Node {
+        // Permuting the columns of both boards together gives an equivalent position, since eliminations only
+        // ever pair a column with the opponent's column at the same index.  Children are dropped.
+        let order = self.get_canonical_column_order();
+        let mut canonical = Node::new(
+            self.player_1_board.with_columns_permuted(&order),
+            self.player_2_board.with_columns_permuted(&order),
+            self.node_type,
+        );
+        canonical.ply = self.ply;
+        canonical.die_distribution = self.die_distribution;
+        return canonical;
+    }
+
+    pub fn mirrored(&self) -> Node {
+        // The same position with the players' seats swapped: each board goes to the other player, and so does the
+        // turn.  Every evaluation of it should be the negation of this one's.  Children are dropped.
+        let node_type = match self.node_type {
+            NodeType::Roll(player) => NodeType::Roll(player.opponent()),
+            NodeType::Move(player, die) => NodeType::Move(player.opponent(), die),
         };
-        let mut evaluation_tree = self.clone_without_children();
-        for next_move in legal_moves {
-            let child_roll_node = self.get_child_from_move(next_move)
-                .expect("Won't error because we know the moves are legal.");
-            if child_roll_node.is_game_over() {
-                // It must be the case that we're making the only legal move so can just evaluate and return.
-                evaluation_tree.add_move(next_move).expect("Won't error because we know the moves are legal.");
-                return Ok((Some(evaluation_tree), objective_function(child_roll_node)));
-            }
-            let mut average_evaluation = 0.;
-            let average_denominator = child_roll_node.get_n_children() as f32;
-            let mut roll_node_evaluation_tree = child_roll_node.clone_without_children();
-            for child_move_node in child_roll_node.children.iter() {
-                let (child_evaluation_tree, child_evaluation) = child_move_node
-                    .get_evaluation_tree(objective_function)
-                    .expect("Won't error because we're in a Move node type.");
-                match child_evaluation_tree {
-                    Some(child_evaluation_tree) => {
-                        roll_node_evaluation_tree.children.push(child_evaluation_tree);
-                    },
-                    None => {
-                        // This is a leaf node, so we can just push the child move node on.
-                        // Note .clone() and .clone_without_children() should be equivalent here.
-                        roll_node_evaluation_tree.children.push(child_move_node.clone_without_children());
-                    },
-                }
-                average_evaluation += child_evaluation / average_denominator;
-            }
-            match player.compare_evaluation(average_evaluation, best_evaluation) {
-                Comparison::Equal => {
-                    evaluation_tree.children.push(roll_node_evaluation_tree);
-                },
-                Comparison::Better => {
-                    best_evaluation = average_evaluation;
-                    evaluation_tree = self.clone_without_children();
-                    evaluation_tree.children.push(roll_node_evaluation_tree);
-                },
-                Comparison::Worse => {},
-            }
-        }
-        return Ok((Some(evaluation_tree), best_evaluation));
+        let mut mirrored = Node::new(self.player_2_board.clone(), self.player_1_board.clone(), node_type);
+        mirrored.ply = self.ply;
+        mirrored.die_distribution = self.die_distribution;
+        return mirrored;
     }
 
-    pub fn with_move_made(&self, m: Move) -> Result<Node, String> {
+    pub fn generate_children_up_to_full_symmetry(&mut self) {
+        // Like generate_children_up_to_symmetry, but also skips moves whose children are column permutations
+        // of an earlier child's, with the dice in any rows.
+        if self.is_game_over() {
+            return;
+        }
         match self.node_type {
             NodeType::Roll(_) => {
-                return Err("Cannot make a move from a roll node".to_string());
+                self.add_rolls().expect("Won't error because we're in a Roll node type.");
             },
-            NodeType::Move(player, die) => {
-                let next_player = player.opponent();
-                let current_players_board = match self.get_player_board(player).with_move_made(die, m) {
-                    Ok(board) => board,
-                    Err(e) => return Err(e),
-                };
-                let next_players_board = self.get_player_board(next_player).eliminate(die, m.get_column());
-                return Ok(
-                    Node::from_player_and_boards(
-                        next_player,
-                        next_players_board,
-                        current_players_board,
-                        NodeType::Roll(next_player)
-                    )
-                );
+            NodeType::Move(_, _) => {
+                let legal_moves = self.get_legal_moves_up_to_row_symmetry().expect("Won't error because we're in a Move node type.");
+                let mut canonical_keys: Vec<u64> = Vec::new();
+                for m in legal_moves {
+                    let child = self.with_move_made(m).expect("Won't error because we know the moves are legal.");
+                    let canonical_key = child.canonicalize_columns().symmetric_position_key();
+                    if !canonical_keys.contains(&canonical_key) {
+                        canonical_keys.push(canonical_key);
+                        self.invalidate_caches();
+                        self.children.push(child);
+                    }
+                }
             },
         }
     }
 
-    pub fn with_rolls(&self, die: Die) -> Result<Node, String> {
-        match self.node_type {
-            NodeType::Roll(player) => {
-                let mut to_return = self.clone();
-                to_return.add_rolls();
-                return Ok(to_return);
-            },
-            NodeType::Move(player, _) => {
-                return Err("Cannot roll from a move node".to_string());
-            },
+    pub fn generate_children_ordered(&mut self, objective_function: impl Fn(&Node) -> f32) {
+        // Like generate_children_up_to_symmetry, but a Move node's children are sorted so the acting player's
+        // favourite by the objective comes first.  Roll node children stay in die order.
+        self.generate_children_up_to_symmetry();
+        if let NodeType::Move(player, _) = self.node_type {
+            let mut scored_children: Vec<(f32, Node)> = self.children.drain(..)
+                .map(|child| (objective_function(&child), child))
+                .collect();
+            scored_children.sort_by(|(a, _), (b, _)| player.order_best_first(*a, *b));
+            self.children = scored_children.into_iter().map(|(_, child)| child).collect();
         }
     }
 
-    pub fn get_scores(&self) -> (u16, u16) {
-        (self.player_1_board.sum(), self.player_2_board.sum())
+    pub fn build_n_moves_up_to_symmetry(&mut self, n: usize) {
+        self.build_n_moves_up_to_symmetry_with(n, &ChanceExpansion::Full);
     }
 
-    pub fn get_score(&self, player: Player) -> u16 {
-        match player {
-            Player::Player1 => self.player_1_board.sum(),
-            Player::Player2 => self.player_2_board.sum(),
-        }
+    pub fn build_n_moves_up_to_symmetry_with(&mut self, n: usize, chance_expansion: &ChanceExpansion) {
+        // Only the rolls chance_expansion picks are built at each Roll node, counting plies from this node.
+        self.build_n_moves_with(n, chance_expansion, self.ply, &Self::generate_children_up_to_symmetry);
+        self.debug_validate();
     }
 
-    pub fn get_score_difference(&self) -> i16 {
-        self.player_1_board.sum() as i16 - self.player_2_board.sum() as i16
+    pub fn build_n_moves_up_to_full_symmetry(&mut self, n: usize) {
+        self.build_n_moves_with(n, &ChanceExpansion::Full, self.ply, &Self::generate_children_up_to_full_symmetry);
+        self.debug_validate();
     }
 
-    pub fn get_outcome(&self) -> Outcome {
-        match self.is_game_over() {
-            true => {
-                let player_1_score = self.player_1_board.sum();
-                let player_2_score = self.player_2_board.sum();
-                if player_1_score > player_2_score {
-                    Outcome::Victory(Player::Player1)
-                } else if player_2_score > player_1_score {
-                    Outcome::Victory(Player::Player2)
-                } else {
-                    Outcome::Draw
-                }
-            },
-            false => Outcome::InProgress
-        }
+    pub fn build_n_moves_merged(&mut self, n: usize) {
+        self.build_n_moves_with(n, &ChanceExpansion::Full, self.ply, &Self::generate_children_merged);
+        self.debug_validate();
     }
 
-    pub fn is_game_over(&self) -> bool {
-        self.player_1_board.is_full() || self.player_2_board.is_full()
+    pub fn build_n_moves(&mut self, n: usize) {
+        // Every legal move, with no symmetry reduction, mainly to check the reduced trees against.
+        self.build_n_moves_with(n, &ChanceExpansion::Full, self.ply, &Self::generate_children);
+        self.debug_validate();
     }
 
-    pub fn add_move(&mut self, next_move: Move) -> Result<(), String> {
+    pub fn build_n_moves_ordered(&mut self, n: usize, objective_function: &Objective) {
+        self.build_n_moves_ordered_with(n, objective_function, &ChanceExpansion::Full);
+    }
+
+    pub fn build_n_moves_ordered_with(&mut self, n: usize, objective_function: &Objective, chance_expansion: &ChanceExpansion) {
+        self.build_n_moves_with(n, chance_expansion, self.ply, &|node: &mut Node| node.generate_children_ordered(|x| objective_function.evaluate(x)));
+        self.debug_validate();
+    }
+
+    fn build_n_moves_with(&mut self, n: usize, chance_expansion: &ChanceExpansion, root_ply: usize, generate_children: &impl Fn(&mut Node)) -> bool {
+        // Nodes that already have children keep them, so a tree that's been built before just gets deepened.  Returns
+        // whether the subtree grew, in which case the caches of every node down to where it grew are dropped.
+        if self.is_game_over() {
+            return false;
+        };
+        let was_leaf = self.is_leaf();
+        let mut has_grown = false;
         match self.node_type {
             NodeType::Roll(_) => {
-                return Err("Cannot add move to a roll node".to_string());
+                if was_leaf {
+                    let dice = chance_expansion.get_dice(self, self.ply.saturating_sub(root_ply));
+                    if dice.len() == Die::all().len() {
+                        generate_children(self);
+                    } else {
+                        self.add_rolls_of(&dice).expect("Won't error because the game isn't over.");
+                    }
+                }
+                for child in self.children.iter_mut() {
+                    has_grown |= child.build_n_moves_with(n, chance_expansion, root_ply, generate_children);
+                }
             },
             NodeType::Move(_, _) => {
-                match self.with_move_made(next_move) {
-                    Ok(node) => {
-                        self.children.push(node);
-                        return Ok(());
-                    },
-                    Err(e) => return Err(e),
+                if n == 0 {
+                    return false;
+                };
+                if was_leaf {
+                    generate_children(self);
                 }
-            }
+                for child in self.children.iter_mut() {
+                    has_grown |= child.build_n_moves_with(n - 1, chance_expansion, root_ply, generate_children);
+                }
+            },
+        }
+        has_grown |= was_leaf && !self.is_leaf();
+        if has_grown {
+            self.invalidate_caches();
         }
+        return has_grown;
     }
 
-    pub fn add_rolls(&mut self) -> Result<(), String> {
-        match self.node_type {
-            NodeType::Roll(player) => {
-                let opponent = player.opponent();
-                for die in Die::all() {
-                    let new_node = Node::from_player_and_boards(
-                        player,
-                        self.get_player_board(player),
+    pub fn expand_frontier_one_move(&mut self) -> usize {
+        // Grows every unfinished leaf by one move and the rolls after it, returning how many nodes were added.  Calling
+        // this k times on a fresh node builds the same tree as build_n_moves_up_to_symmetry(k).
+        if self.is_game_over() {
+            return 0;
+        }
+        let n_added = if !self.is_leaf() {
+            self.children.iter_mut().map(|child| child.expand_frontier_one_move()).sum()
+        } else {
+            self.generate_children_up_to_symmetry();
+            let n_children = self.get_n_children();
+            match self.node_type {
+                // A Roll leaf's children are Move leaves, which then get their move.
+                NodeType::Roll(_) => n_children + self.children.iter_mut().map(|child| child.expand_frontier_one_move()).sum::<usize>(),
+                NodeType::Move(_, _) => {
+                    for child in self.children.iter_mut() {
+                        child.generate_children_up_to_symmetry();
+                    }
+                    n_children + self.children.iter().map(|child| child.get_n_children()).sum::<usize>()
+                },
+            }
+        };
+        if n_added > 0 {
+            self.invalidate_caches();
+        }
+        return n_added;
+    }
+
+    pub fn build_until(&mut self, mut should_expand: impl FnMut(&Node, usize, usize) -> bool) {
+        // Expands leaves breadth first, so shallow plies fill out before deep ones, for as long as should_expand(leaf,
+        // depth below self, nodes in the tree so far) allows.  Nodes that already have children are left as they are.
+        let mut n_nodes = self.count_nodes();
+        let mut queue: VecDeque<(usize, Vec<usize>)> = VecDeque::from([(0, vec![])]);
+        while let Some((depth, path)) = queue.pop_front() {
+            let node = self.get_descendant_mut(&path);
+            if node.is_leaf() {
+                if node.is_game_over() || !should_expand(node, depth, n_nodes) {
+                    continue;
+                }
+                node.generate_children_up_to_symmetry();
+                n_nodes += node.get_n_children();
+            }
+            for index in 0..node.get_n_children() {
+                let mut child_path = path.clone();
+                child_path.push(index);
+                queue.push_back((depth + 1, child_path));
+            }
+        }
+        self.debug_validate();
+    }
+
+    pub fn build_with_node_budget(&mut self, max_nodes: usize) {
+        // Only expands a leaf if all of its children fit, so the tree never grows past max_nodes.
+        self.build_until(|node, _, n_nodes| n_nodes + node.get_n_children_up_to_symmetry() <= max_nodes);
+    }
+
+    fn get_n_children_up_to_symmetry(&self) -> usize {
+        // How many children generate_children_up_to_symmetry would add.
+        match self.status() {
+            NodeStatus::Terminal(_) => 0,
+            NodeStatus::Roll(_) => Die::all().len(),
+            NodeStatus::Move(_, _) => self.get_legal_moves_up_to_row_symmetry().map_or(0, |moves| moves.len()),
+        }
+    }
+
+    pub fn find_descendant(&self, player_1_board: &Board, player_2_board: &Board, node_type: NodeType) -> Option<&Node> {
+        // The shallowest matching node, searching breadth first from and including self.  Comparing position keys
+        // saves comparing the boards square by square.
+        let key = Node::new(player_1_board.clone(), player_2_board.clone(), node_type).position_key();
+        self.iter_bfs().find(|node| node.position_key() == key)
+    }
+
+    pub fn find_descendant_mut(&mut self, player_1_board: &Board, player_2_board: &Board, node_type: NodeType) -> Option<&mut Node> {
+        let key = Node::new(player_1_board.clone(), player_2_board.clone(), node_type).position_key();
+        let mut queue: VecDeque<(Vec<usize>, &Node)> = VecDeque::from([(vec![], &*self)]);
+        let mut maybe_path = None;
+        while let Some((path, node)) = queue.pop_front() {
+            if node.position_key() == key {
+                maybe_path = Some(path);
+                break;
+            }
+            for (index, child) in node.children.iter().enumerate() {
+                let mut child_path = path.clone();
+                child_path.push(index);
+                queue.push_back((child_path, child));
+            }
+        }
+        return maybe_path.map(|path| self.get_descendant_mut(&path));
+    }
+
+    fn get_descendant_mut(&mut self, path: &[usize]) -> &mut Node {
+        // The caller may grow the descendant, so the sizes and evaluations cached along the way are dropped.
+        let mut node = self;
+        node.invalidate_caches();
+        for index in path {
+            node = &mut node.children[*index];
+            node.invalidate_caches();
+        }
+        return node;
+    }
+
+    pub fn build_entire_tree_up_to_symmetry(&mut self) {
+        self.build_entire_subtree_up_to_symmetry();
+        self.debug_validate();
+    }
+
+    fn build_entire_subtree_up_to_symmetry(&mut self) {
+        if self.is_game_over() {
+            return;
+        }
+        self.generate_children_up_to_symmetry();
+        for child in self.children.iter_mut() {
+            child.build_entire_subtree_up_to_symmetry();
+        }
+        self.invalidate_caches();
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<InvariantViolation>> {
+        // Checks the whole tree is one that could have been built by making moves and rolls, collecting every problem
+        // found rather than stopping at the first.
+        let mut violations = Vec::new();
+        let mut stack: Vec<(Vec<usize>, &Node)> = vec![(vec![], self)];
+        while let Some((path, node)) = stack.pop() {
+            node.validate_children(&path, &mut violations);
+            for (index, child) in node.children.iter().enumerate().rev() {
+                let mut child_path = path.clone();
+                child_path.push(index);
+                stack.push((child_path, child));
+            }
+        }
+        if violations.is_empty() {
+            return Ok(());
+        }
+        return Err(violations);
+    }
+
+    fn validate_children(&self, path: &[usize], violations: &mut Vec<InvariantViolation>) {
+        let mut violation_at = |index: usize, description: String| {
+            let mut child_path = path.to_vec();
+            child_path.push(index);
+            violations.push(InvariantViolation::new(child_path, description));
+        };
+        if self.is_game_over() && !self.is_leaf() {
+            violations.push(InvariantViolation::new(path.to_vec(), format!("Finished game has children: {}", self)));
+            return;
+        }
+        match self.node_type {
+            NodeType::Roll(player) => {
+                let mut dice_seen = Vec::new();
+                for (index, child) in self.children.iter().enumerate() {
+                    match child.node_type {
+                        NodeType::Move(child_player, die) if child_player == player => {
+                            if child.get_boards() != self.get_boards() {
+                                violation_at(index, format!("Roll changed the boards: {} from {}", child, self));
+                            }
+                            if child.ply != self.ply {
+                                violation_at(index, format!("Roll changed the ply from {} to {}: {}", self.ply, child.ply, child));
+                            }
+                            if dice_seen.contains(&die) {
+                                violation_at(index, format!("Roll of {} appears twice: {}", die.to_string(), child));
+                            }
+                            dice_seen.push(die);
+                        },
+                        _ => violation_at(index, format!("Expected a Move node for {} after a roll: {}", player.to_string(), child)),
+                    }
+                }
+            },
+            NodeType::Move(player, _) => {
+                let successors = self.get_legal_moves().expect("Guaranteed to be a move node.").into_iter()
+                    .map(|m| self.with_move_made(m).expect("Won't error because we know the moves are legal."))
+                    .collect::<Vec<Node>>();
+                let n_dice = |node: &Node, player: Player| 9 - node.get_player_board(player).get_n_empty_squares();
+                for (index, child) in self.children.iter().enumerate() {
+                    if child.node_type != NodeType::Roll(player.opponent()) {
+                        violation_at(index, format!("Expected a Roll node for {} after a move: {}", player.opponent().to_string(), child));
+                    } else if n_dice(child, player) != n_dice(self, player) + 1 {
+                        violation_at(index, format!("Expected {} to place exactly one die: {} from {}", player.to_string(), child, self));
+                    } else if !successors.iter().any(|successor| successor.equals_up_to_children(child)) {
+                        violation_at(index, format!("Child doesn't come from a legal move: {} from {}", child, self));
+                    } else if child.ply != self.ply + 1 {
+                        violation_at(index, format!("Expected ply {} after a move, got {}: {}", self.ply + 1, child.ply, child));
+                    } else if self.children[..index].iter().any(|other| other.equals_up_to_children(child)) {
+                        violation_at(index, format!("Child appears twice: {}", child));
+                    }
+                }
+            },
+        }
+    }
+
+    fn debug_validate(&self) {
+        // Only checked in debug builds, since it walks the whole tree.
+        debug_assert!(
+            self.validate().is_ok(),
+            "Invalid tree: {}",
+            self.validate().err().unwrap_or_default().iter().map(|violation| violation.to_string()).collect::<Vec<String>>().join("; "),
+        );
+    }
+
+    pub fn get_evaluation_tree(&self, objective_function: &Objective) -> Result<(Option<Node>, f32), SolverError> {
+        let player = match self.node_type {
+            NodeType::Roll(_) => {
+                return Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll });
+            },
+            NodeType::Move(player, _) => player,
+        };
+        if self.is_leaf() {
+            return Ok((None, self.evaluate_leaf(objective_function)));
+        }
+
+        // Only the moves that were built, since symmetric ones may have been skipped.
+        let legal_moves = self.get_legal_moves_up_to_row_symmetry()
+            .expect("Won't error because we're in a Move node type.")
+            .into_iter()
+            .filter(|m| self.get_child_from_move(*m).is_ok());
+        // Compared from player's perspective, and kept in Player 1's like every other evaluation in the tree.
+        let mut best_evaluation = player.get_sign() * f32::NEG_INFINITY;
+        // A NaN from a broken objective is passed up rather than compared.
+        let mut is_nan = false;
+        let mut evaluation_tree = self.clone_without_children();
+        for next_move in legal_moves {
+            let child_roll_node = self.get_child_from_move(next_move)
+                .expect("Won't error because we know the moves are legal.");
+            let mut average_evaluation = 0.;
+            if child_roll_node.is_leaf() {
+                // A partially built tree can stop before the rolls, and a finished game has none, so it's scored like
+                // any other move.
+                average_evaluation = child_roll_node.evaluate_leaf(objective_function);
+            }
+            let weights = child_roll_node.get_roll_child_weights();
+            let mut roll_node_evaluation_tree = child_roll_node.clone_without_children();
+            for (child_move_node, weight) in child_roll_node.children.iter().zip(weights) {
+                let (child_evaluation_tree, child_evaluation) = child_move_node
+                    .get_evaluation_tree(objective_function)
+                    .expect("Won't error because we're in a Move node type.");
+                match child_evaluation_tree {
+                    Some(child_evaluation_tree) => {
+                        roll_node_evaluation_tree.children.push(child_evaluation_tree);
+                    },
+                    None => {
+                        // This is a leaf node, so we can just push the child move node on.
+                        // Note .clone() and .clone_without_children() should be equivalent here.
+                        let leaf = child_move_node.clone_without_children();
+                        leaf.set_cached_evaluation(objective_function, child_evaluation);
+                        roll_node_evaluation_tree.children.push(leaf);
+                    },
+                }
+                average_evaluation += child_evaluation * weight;
+            }
+            child_roll_node.set_cached_evaluation(objective_function, average_evaluation);
+            roll_node_evaluation_tree.set_cached_evaluation(objective_function, average_evaluation);
+            is_nan |= average_evaluation.is_nan();
+            let comparison = player.compare_evaluation(average_evaluation, best_evaluation);
+            if are_tied(average_evaluation, best_evaluation) {
+                // Keeps whichever of the two is really the best, as the search does.
+                evaluation_tree.children.push(roll_node_evaluation_tree);
+                if comparison == Comparison::Better {
+                    best_evaluation = average_evaluation;
+                }
+            } else if comparison == Comparison::Better {
+                best_evaluation = average_evaluation;
+                evaluation_tree = self.clone_without_children();
+                evaluation_tree.children.push(roll_node_evaluation_tree);
+            }
+        }
+        if is_nan {
+            best_evaluation = f32::NAN;
+        }
+        self.set_cached_evaluation(objective_function, best_evaluation);
+        evaluation_tree.set_cached_evaluation(objective_function, best_evaluation);
+        return Ok((Some(evaluation_tree), best_evaluation));
+    }
+
+    pub fn get_evaluation(&self, objective_function: &Objective) -> f32 {
+        // Expectimax value of the tree as built, scoring its leaves with the objective function.
+        if let Some(evaluation) = self.get_cached_evaluation(objective_function) {
+            return evaluation;
+        }
+        if self.is_leaf() {
+            return self.evaluate_leaf(objective_function);
+        }
+        let child_evaluations = self.children.iter().map(|child| child.get_evaluation(objective_function));
+        let evaluation = match self.node_type {
+            NodeType::Roll(_) => child_evaluations.zip(self.get_roll_child_weights()).map(|(evaluation, weight)| evaluation * weight).sum::<f32>(),
+            NodeType::Move(player, _) => player.get_sign() * child_evaluations.map(|evaluation| player.get_sign() * evaluation).fold(f32::NEG_INFINITY, max_keeping_nan),
+        };
+        self.set_cached_evaluation(objective_function, evaluation);
+        return evaluation;
+    }
+
+    fn get_cached_evaluation(&self, objective_function: &Objective) -> Option<f32> {
+        match self.cached_evaluation.get() {
+            Some((objective_key, evaluation)) if objective_key == objective_function.get_key() => Some(evaluation),
+            _ => None,
+        }
+    }
+
+    fn set_cached_evaluation(&self, objective_function: &Objective, evaluation: f32) {
+        self.cached_evaluation.set(Some((objective_function.get_key(), evaluation)));
+    }
+
+    fn invalidate_caches(&self) {
+        // For when the node's children change.  Its ancestors' caches are up to whoever got hold of it mutably.
+        self.cached_evaluation.set(None);
+        self.cached_size.set(None);
+    }
+
+    fn evaluate_leaf(&self, objective_function: &Objective) -> f32 {
+        if let Some(evaluation) = self.get_cached_evaluation(objective_function) {
+            return evaluation;
+        }
+        let evaluation = objective_function.evaluate(self);
+        self.set_cached_evaluation(objective_function, evaluation);
+        return evaluation;
+    }
+
+    pub fn clear_evaluations(&mut self) {
+        // Evaluations are cached as they're computed and dropped when a node's children change, but a node can't see
+        // its ancestors, so call this on the root after changing a tree some other way.
+        self.cached_evaluation.set(None);
+        for child in self.children.iter_mut() {
+            child.clear_evaluations();
+        }
+    }
+
+    pub fn get_move_evaluations(&self, objective_function: &Objective) -> Result<Vec<(Move, f32)>, SolverError> {
+        // The expectimax value of every legal move that was built, best first for the player to act, with ties in
+        // row-major order.  Moves left out by row symmetry get the value of the move they're equivalent to.
+        let player = match self.status() {
+            NodeStatus::Move(player, _) => player,
+            NodeStatus::Roll(_) => return Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }),
+            NodeStatus::Terminal(_) => return Err(SolverError::GameAlreadyOver),
+        };
+        let mut move_evaluations = Vec::new();
+        for m in self.get_legal_moves().expect("Guaranteed to be a move node.") {
+            let key = self.with_move_made(m).expect("Won't error because we know the moves are legal.").symmetric_position_key();
+            if let Some(child) = self.children.iter().find(|child| child.symmetric_position_key() == key) {
+                move_evaluations.push((m, child.get_evaluation(objective_function)));
+            }
+        }
+        move_evaluations.sort_by(|(_, a), (_, b)| player.order_best_first(*a, *b));
+        return Ok(move_evaluations);
+    }
+
+    pub fn get_principal_variation(&self, objective_function: &Objective) -> Vec<PvStep> {
+        // Follows the first best move at each Move node.  At Roll nodes we follow the roll whose line is closest to
+        // the expected value, i.e. the most representative one.
+        let mut principal_variation = Vec::new();
+        let mut current_node = self;
+        let mut roll_assumed = false;
+        while !current_node.is_leaf() {
+            let evaluation = current_node.get_evaluation(objective_function);
+            match current_node.node_type {
+                NodeType::Roll(_) => {
+                    current_node = current_node.children.iter()
+                        .min_by(
+                            |a, b| compare_totally(
+                                (a.get_evaluation(objective_function) - evaluation).abs(),
+                                (b.get_evaluation(objective_function) - evaluation).abs(),
+                            )
+                        ).expect("Roll node isn't a leaf, so it has children.");
+                    roll_assumed = true;
+                },
+                NodeType::Move(player, die) => {
+                    let mut best: Option<(Move, &Node, f32)> = None;
+                    for m in current_node.get_moves().expect("Guaranteed to be a move node.") {
+                        let child = current_node.get_child_from_move(m).expect("Move comes from the node's children.");
+                        let child_evaluation = child.get_evaluation(objective_function);
+                        let is_better = match best {
+                            Some((_, _, best_evaluation)) => player.compare_evaluation(child_evaluation, best_evaluation) == Comparison::Better,
+                            None => true,
+                        };
+                        if is_better {
+                            best = Some((m, child, child_evaluation));
+                        }
+                    }
+                    let (best_move, best_child, _) = best.expect("Move node isn't a leaf, so it has children.");
+                    principal_variation.push(PvStep { player, die, next_move: best_move, evaluation, roll_assumed });
+                    current_node = best_child;
+                },
+            }
+        }
+        return principal_variation;
+    }
+
+    pub fn prune_to_best_moves(&mut self, objective_function: &Objective, tie_break_epsilon: f32) {
+        // Drops every Move node child that isn't tied for best, keeping all Roll node children.  Ties are to within
+        // tie_break_epsilon, as in the solver, so the moves kept are the solver's best moves.
+        self.prune_children(None, objective_function, tie_break_epsilon);
+    }
+
+    pub fn prune_to_top_k(&mut self, k: usize, objective_function: &Objective) {
+        // Keeps the k best children of each Move node (ties broken by child order) and all Roll node children.
+        self.prune_children(Some(k.max(1)), objective_function, 0.);
+    }
+
+    fn prune_children(&mut self, maybe_k: Option<usize>, objective_function: &Objective, tie_break_epsilon: f32) -> f32 {
+        // Returns the node's evaluation, which pruning leaves unchanged since the best child is always kept.
+        if self.is_leaf() {
+            return self.evaluate_leaf(objective_function);
+        }
+        let child_evaluations: Vec<f32> = self.children.iter_mut()
+            .map(|child| child.prune_children(maybe_k, objective_function, tie_break_epsilon))
+            .collect();
+        let evaluation = match self.node_type {
+            NodeType::Roll(_) => child_evaluations.iter().zip(self.get_roll_child_weights()).map(|(evaluation, weight)| evaluation * weight).sum::<f32>(),
+            NodeType::Move(player, _) => {
+                let mut ranked_children: Vec<(usize, f32)> = child_evaluations.into_iter().enumerate().collect();
+                ranked_children.sort_by(|(_, a), (_, b)| player.order_best_first(*a, *b));
+                let best_evaluation = ranked_children[0].1;
+                let n_to_keep = match maybe_k {
+                    Some(k) => k.min(ranked_children.len()),
+                    None => ranked_children.iter().take_while(|(_, evaluation)| are_tied_within(*evaluation, best_evaluation, tie_break_epsilon)).count(),
+                };
+                let mut indices_to_keep: Vec<usize> = ranked_children[..n_to_keep].iter().map(|(i, _)| *i).collect();
+                indices_to_keep.sort();
+                let mut index = 0;
+                self.children.retain(|_| {
+                    let keep = indices_to_keep.contains(&index);
+                    index += 1;
+                    keep
+                });
+                best_evaluation
+            },
+        };
+        // Whatever was pruned below, this subtree's smaller now.
+        self.cached_size.set(None);
+        self.set_cached_evaluation(objective_function, evaluation);
+        return evaluation;
+    }
+
+    pub fn with_move_made(&self, m: Move) -> Result<Node, String> {
+        self.with_move_made_detailed(m).map(|(node, _)| node)
+    }
+
+    pub fn with_move_made_detailed(&self, m: Move) -> Result<(Node, MoveEffect), String> {
+        match self.node_type {
+            NodeType::Roll(_) => {
+                return Err(format!("Cannot make a move from a roll node: {}", self));
+            },
+            NodeType::Move(player, die) => {
+                let next_player = player.opponent();
+                let points_gained = self.get_player_board(player).score_delta_if_placed(die, m.get_column());
+                let current_players_board = self.get_player_board(player).with_move_made(die, m)?;
+                let (next_players_board, elimination) = self.get_player_board(next_player).eliminate_detailed(die, m.get_column());
+                let mut next_node = Node::from_player_and_boards(
+                    next_player,
+                    next_players_board,
+                    current_players_board,
+                    NodeType::Roll(next_player)
+                );
+                next_node.ply = self.ply + 1;
+                next_node.die_distribution = self.die_distribution;
+                return Ok((
+                    next_node,
+                    MoveEffect::new(player, die, m, points_gained, elimination),
+                ));
+            },
+        }
+    }
+
+    pub fn with_roll(&self, die: Die) -> Result<Node, String> {
+        if self.is_game_over() {
+            return Err(format!("Cannot roll in a finished game: {}", self));
+        }
+        match self.node_type {
+            NodeType::Roll(player) => {
+                let mut next_node = Node::new(self.get_player_1_board(), self.get_player_2_board(), NodeType::Move(player, die));
+                next_node.ply = self.ply;
+                next_node.die_distribution = self.die_distribution;
+                Ok(next_node)
+            },
+            NodeType::Move(_, _) => Err(format!("Cannot roll from a move node: {}", self)),
+        }
+    }
+
+    pub fn apply_sequence(&self, steps: &[(Die, Move)]) -> Result<Node, String> {
+        // Each step is a roll followed by placing that die.  If we're already on a Move node the first step's die must
+        // match the pending roll.
+        let mut node = self.clone_without_children();
+        for (index, (die, m)) in steps.iter().enumerate() {
+            if node.is_game_over() {
+                return Err(format!("Step {}: Game is already over", index));
+            }
+            node = match node.node_type {
+                NodeType::Roll(_) => node.with_roll(*die).expect("Guaranteed to be a roll node."),
+                NodeType::Move(_, pending_die) => {
+                    if pending_die != *die {
+                        return Err(format!("Step {}: Expected a roll of {}, got {}", index, pending_die.to_string(), die.to_string()));
+                    }
+                    node
+                },
+            };
+            node = node.with_move_made(*m).map_err(|e| format!("Step {}: {}", index, e))?;
+        }
+        return Ok(node);
+    }
+
+    pub fn with_rolls(&self, _die: Die) -> Result<Node, String> {
+        match self.node_type {
+            NodeType::Roll(_) => {
+                let mut to_return = self.clone();
+                to_return.add_rolls()?;
+                return Ok(to_return);
+            },
+            NodeType::Move(_, _) => {
+                return Err(format!("Cannot roll from a move node: {}", self));
+            },
+        }
+    }
+
+    pub fn random_playout(&self, rng: &mut impl Rng) -> Outcome {
+        self.random_playout_with_policy(
+            rng,
+            |node, rng| *node.get_legal_moves()
+                .expect("Policy is only asked on Move nodes.")
+                .choose(rng)
+                .expect("A game that isn't over has an empty square."),
+        )
+    }
+
+    pub fn random_playout_with_policy<R: Rng>(&self, rng: &mut R, mut policy: impl FnMut(&Node, &mut R) -> Move) -> Outcome {
+        // Plays the game out from here with uniform rolls and policy's moves, without building a tree.  Eliminations
+        // can reopen squares, but every move fills one, so random play finishes with probability 1.
+        let mut node = self.clone_without_children();
+        loop {
+            node = match node.status() {
+                NodeStatus::Terminal(outcome) => return outcome,
+                NodeStatus::Roll(_) => node.with_roll(Die::random_from(rng)).expect("Guaranteed to be a roll node."),
+                NodeStatus::Move(_, _) => {
+                    let m = policy(&node, rng);
+                    node.with_move_made(m).expect("Policy must pick a legal move.")
+                },
+            };
+        }
+    }
+
+    pub fn get_scores(&self) -> (u16, u16) {
+        (self.player_1_board.sum(), self.player_2_board.sum())
+    }
+
+    pub fn get_score(&self, player: Player) -> u16 {
+        let (player_1_score, player_2_score) = self.get_scores();
+        match player {
+            Player::Player1 => player_1_score,
+            Player::Player2 => player_2_score,
+        }
+    }
+
+    pub fn get_score_difference(&self) -> i16 {
+        let (player_1_score, player_2_score) = self.get_scores();
+        player_1_score as i16 - player_2_score as i16
+    }
+
+    pub fn get_outcome(&self) -> Outcome {
+        match self.is_game_over() {
+            true => self.get_outcome_by_score(),
+            false => Outcome::InProgress
+        }
+    }
+
+    pub fn get_outcome_by_score(&self) -> Outcome {
+        // The outcome if the game were to end right now.
+        let (player_1_score, player_2_score) = self.get_scores();
+        Outcome::from_scores(player_1_score, player_2_score)
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.player_1_board.is_full() || self.player_2_board.is_full()
+    }
+
+    pub fn status(&self) -> NodeStatus {
+        // A finished game keeps the Roll node type it was created with, so this is what to check before acting on it.
+        if self.is_game_over() {
+            return NodeStatus::Terminal(self.get_outcome());
+        }
+        match self.node_type {
+            NodeType::Roll(player) => NodeStatus::Roll(player),
+            NodeType::Move(player, die) => NodeStatus::Move(player, die),
+        }
+    }
+
+    pub fn add_move(&mut self, next_move: Move) -> Result<(), String> {
+        match self.node_type {
+            NodeType::Roll(_) => {
+                return Err(format!("Cannot add move to a roll node: {}", self));
+            },
+            NodeType::Move(_, _) => {
+                match self.with_move_made(next_move) {
+                    Ok(node) => {
+                        self.invalidate_caches();
+                        self.children.push(node);
+                        return Ok(());
+                    },
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    pub fn add_rolls(&mut self) -> Result<(), String> {
+        self.add_rolls_of(&Die::all())
+    }
+
+    pub fn add_rolls_of(&mut self, dice: &[Die]) -> Result<(), String> {
+        // A child for each of dice, which evaluations average over as if they were the only rolls there could be.
+        if self.is_game_over() {
+            return Err(format!("Cannot add rolls to a finished game: {}", self));
+        }
+        match self.node_type {
+            NodeType::Roll(player) => {
+                let opponent = player.opponent();
+                for die in dice.iter().copied() {
+                    let mut new_node = Node::from_player_and_boards(
+                        player,
+                        self.get_player_board(player),
                         self.get_player_board(opponent),
                         NodeType::Move(player, die)
                     );
+                    new_node.ply = self.ply;
+                    new_node.die_distribution = self.die_distribution;
+                    self.invalidate_caches();
                     self.children.push(new_node);
                 }
                 return Ok(())
             },
             NodeType::Move(_, _) => {
-                return Err("Cannot add rolls to a move node".to_string());
+                return Err(format!("Cannot add rolls to a move node: {}", self));
             }
         }
     }
 
-    pub fn is_leaf(&self) -> bool {
-        self.get_n_children() == 0
+    pub fn is_leaf(&self) -> bool {
+        // No children have been generated, either because the game's over or because nobody's expanded it yet.
+        self.get_n_children() == 0
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        // The game's over, so the node will never have children.
+        self.is_game_over()
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.get_expansion_state() == ExpansionState::Expanded
+    }
+
+    pub fn get_expansion_state(&self) -> ExpansionState {
+        if self.is_terminal() {
+            ExpansionState::Terminal
+        } else if self.is_leaf() {
+            ExpansionState::Unexpanded
+        } else {
+            ExpansionState::Expanded
+        }
+    }
+
+    pub fn get_n_children(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn get_max_depth(&self) -> usize {
+        self.get_cached_size().0
+    }
+
+    pub fn count_nodes(&self) -> usize {
+        self.get_cached_size().1
+    }
+
+    fn get_cached_size(&self) -> (usize, usize) {
+        // (max depth, node count), cached for the whole subtree on the first call so later ones are O(1).
+        if let Some(size) = self.cached_size.get() {
+            return size;
+        }
+        let mut max_depth = 1;
+        let mut n_nodes = 1;
+        for child in self.children.iter() {
+            let (child_max_depth, child_n_nodes) = child.get_cached_size();
+            max_depth = max_depth.max(1 + child_max_depth);
+            n_nodes += child_n_nodes;
+        }
+        self.cached_size.set(Some((max_depth, n_nodes)));
+        return (max_depth, n_nodes);
+    }
+
+    pub fn estimated_tree_size(&self, depth: usize) -> usize {
+        // Predicts how many nodes build_n_moves_up_to_symmetry(depth) would leave in the tree, without building it.
+        // Each player is assumed to keep the number of moves up to symmetry they have now, and games are assumed not
+        // to end, so deep estimates run high.  Saturates rather than overflowing.
+        if self.is_game_over() {
+            return 1;
+        }
+        let n_moves = |player: Player| self.get_player_board(player).get_empty_squares_up_to_row_symmetry().len();
+        let player = self.get_active_player();
+        // Nodes in the subtree of a Move node with moves_left moves still to build, working up from the leaves.
+        let mut move_subtree_size: usize = 1;
+        for moves_left in 1..=depth {
+            let mover = if (depth - moves_left).is_multiple_of(2) { player } else { player.opponent() };
+            let roll_subtree_size = Die::all().len().saturating_mul(move_subtree_size).saturating_add(1);
+            move_subtree_size = n_moves(mover).saturating_mul(roll_subtree_size).saturating_add(1);
+        }
+        return match self.node_type {
+            NodeType::Roll(_) => Die::all().len().saturating_mul(move_subtree_size).saturating_add(1),
+            NodeType::Move(_, _) => move_subtree_size,
+        };
+    }
+
+    pub fn actual_memory_footprint(&self) -> usize {
+        // Bytes used by the tree: the root itself, plus what every node has allocated for its boards and children.
+        std::mem::size_of::<Node>() + self.iter_dfs().map(
+            |node| node.player_1_board.get_heap_size()
+                + node.player_2_board.get_heap_size()
+                + node.children.capacity() * std::mem::size_of::<Node>()
+        ).sum::<usize>()
+    }
+
+    pub fn count_leaves(&self) -> usize {
+        if self.is_leaf() {
+            return 1;
+        }
+        self.children.iter().map(|child| child.count_leaves()).sum()
+    }
+
+    pub fn get_tree_stats(&self) -> TreeStats {
+        let mut stats = TreeStats::empty();
+        let mut expanded_nodes_per_ply = Vec::new();
+        let mut children_per_ply = Vec::new();
+        self.accumulate_tree_stats(0, &mut stats, &mut expanded_nodes_per_ply, &mut children_per_ply);
+        stats.branching_factors = expanded_nodes_per_ply.iter().zip(children_per_ply.iter())
+            .map(|(n_expanded, n_children)| *n_children as f32 / *n_expanded as f32)
+            .collect();
+        return stats;
+    }
+
+    fn accumulate_tree_stats(&self, depth: usize, stats: &mut TreeStats, expanded_nodes_per_ply: &mut Vec<usize>, children_per_ply: &mut Vec<usize>) {
+        stats.n_nodes += 1;
+        stats.max_depth = stats.max_depth.max(depth + 1);
+        match self.node_type {
+            NodeType::Roll(_) => stats.n_roll_nodes += 1,
+            NodeType::Move(_, _) => stats.n_move_nodes += 1,
+        }
+        if self.is_leaf() {
+            stats.n_leaves += 1;
+            return;
+        }
+        if expanded_nodes_per_ply.len() <= depth {
+            expanded_nodes_per_ply.push(0);
+            children_per_ply.push(0);
+        }
+        expanded_nodes_per_ply[depth] += 1;
+        children_per_ply[depth] += self.get_n_children();
+        for child in self.children.iter() {
+            child.accumulate_tree_stats(depth + 1, stats, expanded_nodes_per_ply, children_per_ply);
+        }
+    }
+
+    pub fn children_iter(&self) -> impl Iterator<Item = Node> + '_ {
+        // Builds each child as it's needed rather than storing it in self.children.
+        self.lazy_children(self.get_legal_moves())
+    }
+
+    pub fn children_iter_up_to_symmetry(&self) -> impl Iterator<Item = Node> + '_ {
+        self.lazy_children(self.get_legal_moves_up_to_row_symmetry())
+    }
+
+    fn lazy_children(&self, maybe_moves: Result<Vec<Move>, SolverError>) -> impl Iterator<Item = Node> + '_ {
+        // Roll nodes have no moves, Move nodes no rolls, so only one half of the chain yields anything.  Finished
+        // games have neither.
+        let rolls = match self.status() {
+            NodeStatus::Roll(_) => Die::all(),
+            _ => vec![],
+        };
+        let moves = match self.status() {
+            NodeStatus::Terminal(_) => vec![],
+            _ => maybe_moves.unwrap_or_default(),
+        };
+        rolls.into_iter()
+            .map(move |die| self.with_roll(die).expect("Won't error because we're in a Roll node type."))
+            .chain(moves.into_iter().map(move |m| self.with_move_made(m).expect("Won't error because we know the moves are legal.")))
+    }
+
+    pub fn iter_dfs(&self) -> impl Iterator<Item = &Node> {
+        self.iter_dfs_with_depth().map(|(_, node)| node)
+    }
+
+    pub fn iter_dfs_with_depth(&self) -> DepthFirstIter<'_> {
+        DepthFirstIter { stack: vec![(0, self)] }
+    }
+
+    pub fn iter_leaves(&self) -> impl Iterator<Item = &Node> {
+        self.iter_dfs().filter(|node| node.is_leaf())
+    }
+
+    pub fn leaf_depth_histogram(&self) -> Vec<usize> {
+        // Number of leaves at each depth below self, which is depth 0.
+        let mut histogram = Vec::new();
+        for (depth, node) in self.iter_dfs_with_depth() {
+            if node.is_leaf() {
+                if histogram.len() <= depth {
+                    histogram.resize(depth + 1, 0);
+                }
+                histogram[depth] += 1;
+            }
+        }
+        return histogram;
+    }
+
+    pub fn iter_bfs(&self) -> impl Iterator<Item = &Node> {
+        self.iter_bfs_with_depth().map(|(_, node)| node)
+    }
+
+    pub fn iter_bfs_with_depth(&self) -> BreadthFirstIter<'_> {
+        BreadthFirstIter { queue: VecDeque::from(vec![(0, self)]) }
+    }
+
+    pub fn path_to(&self, target: &Node) -> Option<Vec<Step>> {
+        // Path to the first node, in depth-first order, that's the same position as target.
+        let mut stack: Vec<(&Node, Vec<Step>)> = vec![(self, Vec::new())];
+        while let Some((node, path)) = stack.pop() {
+            if node.equals_up_to_children(target) {
+                return Some(path);
+            }
+            for child in node.children.iter().rev() {
+                let step = node.get_step_to_child(child).expect("Children are reachable in one step.");
+                let mut child_path = path.clone();
+                child_path.push(step);
+                stack.push((child, child_path));
+            }
+        }
+        return None;
+    }
+
+    fn get_step_to_child(&self, child: &Node) -> Option<Step> {
+        match self.node_type {
+            NodeType::Roll(_) => child.get_die().map(Step::Roll),
+            NodeType::Move(_, die) => self.get_legal_moves().ok()?.into_iter()
+                .find(|m| self.with_move_made(*m).is_ok_and(|node| node.equals_up_to_children(child)))
+                .map(|m| Step::Move(die, m)),
+        }
+    }
+
+    pub fn get_children(&self) -> &Vec<Node> {
+        &self.children
+    }
+
+    pub fn get_children_with_moves(&self) -> Result<Vec<(Move, &Node)>, String> {
+        // In the order the children were added.
+        match self.node_type {
+            NodeType::Roll(_) => Err(format!("Cannot get children with moves from a roll node: {}", self)),
+            NodeType::Move(_, _) => Ok(
+                self.children.iter().map(
+                    |child| match self.get_step_to_child(child) {
+                        Some(Step::Move(_, m)) => (m, child),
+                        _ => panic!("Children of a move node come from legal moves."),
+                    }
+                ).collect()
+            ),
+        }
+    }
+
+    pub fn get_children_with_rolls(&self) -> Result<Vec<(Die, &Node)>, String> {
+        match self.node_type {
+            NodeType::Roll(_) => Ok(
+                self.children.iter().map(
+                    |child| (child.get_die().expect("Children of a roll node are move nodes."), child)
+                ).collect()
+            ),
+            NodeType::Move(_, _) => Err(format!("Cannot get children with rolls from a move node: {}", self)),
+        }
+    }
+
+    pub fn get_child_from_move(&self, m: Move) -> Result<&Node, String> {
+        self.get_child(m.get_row(), m.get_column())
+    }
+
+    pub fn get_child_from_roll(&self, roll: Die) -> Result<&Node, String> {
+        self.get_child_index_from_roll(roll).map(|index| &self.children[index])
+    }
+
+    pub fn get_child_from_roll_mut(&mut self, roll: Die) -> Result<&mut Node, String> {
+        // The node's cached evaluation and size are dropped, since the caller may change the child.
+        let index = self.get_child_index_from_roll(roll)?;
+        self.invalidate_caches();
+        return Ok(&mut self.children[index]);
+    }
+
+    fn get_child_index_from_roll(&self, roll: Die) -> Result<usize, String> {
+        match self.node_type {
+            NodeType::Roll(_) => {
+                // Looked up by die rather than position, since a sampled node only has children for some rolls.
+                match self.children.iter().position(|child| child.get_die() == Some(roll)) {
+                    Some(index) => Ok(index),
+                    None => Err(format!("No child for a roll of {}: {}", roll.to_string(), self)),
+                }
+            },
+            NodeType::Move(_, _) => {
+                return Err(format!("Cannot get child from roll from a move node: {}", self));
+            }
+        }
+    }
+
+    pub fn detach_child_by_move(&mut self, m: Move) -> Option<Node> {
+        // Removes and returns the subtree after m, e.g. to make it the new root once m has been played.
+        let index = self.get_child_index(m.get_row(), m.get_column()).ok()?;
+        self.invalidate_caches();
+        return Some(self.children.remove(index));
+    }
+
+    pub fn detach_child_by_roll(&mut self, die: Die) -> Option<Node> {
+        let expected_node = self.with_roll(die).ok()?;
+        let index = self.children.iter().position(|child| child.equals_up_to_children(&expected_node))?;
+        self.invalidate_caches();
+        return Some(self.children.remove(index));
+    }
+
+    pub fn get_child(&self, row: usize, col: usize) -> Result<&Node, String> {
+        self.get_child_index(row, col).map(|index| &self.children[index])
+    }
+
+    pub fn get_child_mut(&mut self, row: usize, col: usize) -> Result<&mut Node, String> {
+        // As with get_child_from_roll_mut, the node's cached evaluation and size are dropped.
+        let index = self.get_child_index(row, col)?;
+        self.invalidate_caches();
+        return Ok(&mut self.children[index]);
+    }
+
+    fn get_child_index(&self, row: usize, col: usize) -> Result<usize, String> {
+        let m = Move::new(row, col);
+        let expected_node = self.with_move_made(m)?;
+        match self.children.iter()
+            .position(|child| child.equals_up_to_children(&expected_node) || child.equivalent_moves.contains(&m)) {
+                Some(index) => Ok(index),
+                None => Err(format!("No child at row {} and column {}", row, col)),
+        }
+    }
+
+    pub fn walk_mut(&mut self, path: &[Step]) -> Result<&mut Node, String> {
+        // Follows path down the tree as built, e.g. to expand one subtree in place.  Every node passed through drops
+        // its cached evaluation and size, since whatever's changed below would make them stale.
+        let mut node = self;
+        for (index, step) in path.iter().enumerate() {
+            node = match (node.node_type, step) {
+                (NodeType::Roll(_), Step::Roll(die)) => node.get_child_from_roll_mut(*die),
+                (NodeType::Move(_, pending_die), Step::Move(die, m)) => {
+                    if pending_die != *die {
+                        return Err(format!("Step {}: Expected a roll of {}, got {}", index, pending_die.to_string(), die.to_string()));
+                    }
+                    node.get_child_mut(m.get_row(), m.get_column())
+                },
+                (NodeType::Roll(_), Step::Move(_, _)) => Err(format!("Expected a roll, not a move: {}", node)),
+                (NodeType::Move(_, _), Step::Roll(_)) => Err(format!("Expected a move, not a roll: {}", node)),
+            }.map_err(|e| format!("Step {}: {}", index, e))?;
+        }
+        return Ok(node);
+    }
+
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeType {
+    Roll(Player),
+    Move(Player, Die),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExpansionState {
+    Terminal,
+    Unexpanded,
+    Expanded,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChanceExpansion {
+    // Every roll at every Roll node.
+    Full,
+    // n_rolls of the rolls, drawn without replacement, at Roll nodes more than full_plies moves below the root, which
+    // are averaged over in proportion to their probabilities.  The draw is seeded by the seed and the position, so a
+    // position always gets the same rolls however the search gets there.
+    Sampled { n_rolls: usize, full_plies: usize, seed: u64 },
+}
+
+impl ChanceExpansion {
+
+    pub fn get_dice(&self, node: &Node, plies_from_root: usize) -> Vec<Die> {
+        // The rolls to expand at a Roll node plies_from_root moves below the root, in die order.  Rolls that can't
+        // happen are never drawn.
+        match self {
+            ChanceExpansion::Sampled { n_rolls, full_plies, seed } if plies_from_root > *full_plies => {
+                let possible_dice = node.get_roll_probabilities().into_iter()
+                    .filter(|(_, probability)| *probability > 0.)
+                    .map(|(die, _)| die)
+                    .collect::<Vec<Die>>();
+                let mut rng = StdRng::seed_from_u64(seed ^ node.position_key());
+                let mut dice = possible_dice.choose_multiple(&mut rng, *n_rolls).copied().collect::<Vec<Die>>();
+                dice.sort();
+                dice
+            },
+            _ => Die::all(),
+        }
+    }
+
+    pub fn samples_within(&self, n_moves: usize) -> bool {
+        // Whether a search n_moves deep would leave out any rolls.
+        match self {
+            ChanceExpansion::Full => false,
+            ChanceExpansion::Sampled { n_rolls, full_plies, .. } => *n_rolls < Die::all().len() && n_moves > *full_plies,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeStatus {
+    Roll(Player),
+    Move(Player, Die),
+    Terminal(Outcome),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrettyPrintOptions {
+    pub max_depth: Option<usize>,
+    pub max_children_per_node: Option<usize>,
+    pub show_evaluations: bool,
+    pub show_boards: bool,
+    pub unicode_dice: bool,
+    pub indent: String,
+}
+
+impl Default for PrettyPrintOptions {
+    fn default() -> Self {
+        // Matches the original to_pretty_string output.
+        PrettyPrintOptions {
+            max_depth: None,
+            max_children_per_node: None,
+            show_evaluations: true,
+            show_boards: false,
+            unicode_dice: false,
+            indent: "  ".to_string(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PositionKey(u64);
+
+impl PositionKey {
+
+    pub fn from_node(node: &Node) -> Self {
+        PositionKey(node.position_key())
+    }
+
+    pub fn from_node_up_to_symmetry(node: &Node) -> Self {
+        PositionKey(node.symmetric_position_key())
+    }
+
+    pub fn get_key(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    Roll(Die),
+    Move(Die, Move),
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Roll(die) => write!(f, "roll {}", die.to_string()),
+            Step::Move(die, m) => write!(f, "{}→{}", die.to_string(), m.to_string()),
+        }
+    }
+}
+
+impl Step {
+
+    pub fn path_to_string(path: &[Step]) -> String {
+        path.iter().map(|step| step.to_string()).collect::<Vec<String>>().join(", ")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MoveEffect {
+    player: Player,
+    die: Die,
+    placed_move: Move,
+    points_gained: u16,
+    elimination: EliminationResult,
+}
+
+impl Display for MoveEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} placed {} at {} for {} points", self.player.to_string(), self.die.to_string(), self.placed_move.to_string(), self.points_gained)?;
+        if self.elimination.get_n_eliminated() > 0 {
+            write!(
+                f,
+                ", destroying {} of {}'s {}s ({} points)",
+                self.elimination.get_n_eliminated(),
+                self.player.opponent().to_string(),
+                self.die.to_string(),
+                self.elimination.get_points_lost(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl MoveEffect {
+
+    pub fn new(player: Player, die: Die, placed_move: Move, points_gained: u16, elimination: EliminationResult) -> Self {
+        MoveEffect { player, die, placed_move, points_gained, elimination }
+    }
+
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    pub fn get_die(&self) -> Die {
+        self.die
+    }
+
+    pub fn get_move(&self) -> Move {
+        self.placed_move
+    }
+
+    pub fn get_points_gained(&self) -> u16 {
+        self.points_gained
+    }
+
+    pub fn get_elimination(&self) -> EliminationResult {
+        self.elimination
+    }
+
+    pub fn get_scores_after(&self, scores: (u16, u16)) -> (u16, u16) {
+        // Both players' scores after the move, from what they were before it, without summing the boards again.
+        let (player_1_score, player_2_score) = scores;
+        match self.player {
+            Player::Player1 => (player_1_score + self.points_gained, player_2_score - self.elimination.get_points_lost()),
+            Player::Player2 => (player_1_score - self.elimination.get_points_lost(), player_2_score + self.points_gained),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PvStep {
+    player: Player,
+    die: Die,
+    next_move: Move,
+    evaluation: f32,
+    // Whether the die is the roll the line picked, rather than the one the position started with.
+    roll_assumed: bool,
+}
+
+impl Display for PvStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} rolls {} and plays {} ({:.2})", self.player.to_string(), self.die.to_string(), self.next_move.to_string(), self.evaluation)
+    }
+}
+
+impl PvStep {
+
+    pub fn new(player: Player, die: Die, next_move: Move, evaluation: f32) -> Self {
+        PvStep { player, die, next_move, evaluation, roll_assumed: false }
+    }
+
+    pub fn with_roll_assumed(mut self) -> Self {
+        self.roll_assumed = true;
+        self
+    }
+
+    pub fn to_line(principal_variation: &[PvStep]) -> String {
+        // e.g. "P1 6→(1, 1) | P2 r?4→(2, 2)", where r? marks a roll the line assumes.
+        return principal_variation.iter().map(|step| step.to_short_string()).collect::<Vec<String>>().join(" | ");
+    }
+
+    pub fn to_short_string(&self) -> String {
+        let player = match self.player {
+            Player::Player1 => "P1",
+            Player::Player2 => "P2",
+        };
+        let roll = if self.roll_assumed { "r?" } else { "" };
+        return format!("{} {}{}→{}", player, roll, self.die.to_string(), self.next_move.to_string());
+    }
+
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    pub fn get_die(&self) -> Die {
+        self.die
+    }
+
+    pub fn get_move(&self) -> Move {
+        self.next_move
+    }
+
+    pub fn get_evaluation(&self) -> f32 {
+        // Evaluation of the position before the move is made.
+        self.evaluation
+    }
+
+    pub fn is_roll_assumed(&self) -> bool {
+        self.roll_assumed
+    }
+
+    pub fn get_step(&self) -> Step {
+        Step::Move(self.die, self.next_move)
+    }
+}
+
+// Pre-order traversal yielding (depth, node), with the root at depth 0.  Uses an explicit stack so deep trees don't
+// overflow.
+pub struct DepthFirstIter<'a> {
+    stack: Vec<(usize, &'a Node)>,
+}
+
+impl<'a> Iterator for DepthFirstIter<'a> {
+    type Item = (usize, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node) = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev().map(|child| (depth + 1, child)));
+        Some((depth, node))
+    }
+}
+
+// Level-order traversal yielding (depth, node), with the root at depth 0.
+pub struct BreadthFirstIter<'a> {
+    queue: VecDeque<(usize, &'a Node)>,
+}
+
+impl<'a> Iterator for BreadthFirstIter<'a> {
+    type Item = (usize, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node) = self.queue.pop_front()?;
+        self.queue.extend(node.children.iter().map(|child| (depth + 1, child)));
+        Some((depth, node))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvariantViolation {
+    // Child indices from the root to the offending node.
+    path: Vec<usize>,
+    description: String,
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "At {:?}: {}", self.path, self.description)
+    }
+}
+
+impl InvariantViolation {
+
+    pub fn new(path: Vec<usize>, description: String) -> Self {
+        InvariantViolation { path, description }
+    }
+
+    pub fn get_path(&self) -> &Vec<usize> {
+        &self.path
+    }
+
+    pub fn get_description(&self) -> &String {
+        &self.description
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeStats {
+    n_nodes: usize,
+    n_leaves: usize,
+    max_depth: usize,
+    n_roll_nodes: usize,
+    n_move_nodes: usize,
+    branching_factors: Vec<f32>,
+}
+
+impl Display for TreeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let branching_factors = self.branching_factors.iter().map(|x| format!("{:.2}", x)).collect::<Vec<String>>().join(", ");
+        write!(
+            f,
+            "Nodes: {} (Roll: {}, Move: {})\nLeaves: {}\nMax Depth: {}\nBranching Factor by Ply: [{}]",
+            self.n_nodes, self.n_roll_nodes, self.n_move_nodes, self.n_leaves, self.max_depth, branching_factors
+        )
+    }
+}
+
+impl TreeStats {
+
+    fn empty() -> Self {
+        TreeStats {
+            n_nodes: 0,
+            n_leaves: 0,
+            max_depth: 0,
+            n_roll_nodes: 0,
+            n_move_nodes: 0,
+            branching_factors: Vec::new(),
+        }
+    }
+
+    pub fn get_n_nodes(&self) -> usize {
+        self.n_nodes
+    }
+
+    pub fn get_n_leaves(&self) -> usize {
+        self.n_leaves
+    }
+
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn get_n_roll_nodes(&self) -> usize {
+        self.n_roll_nodes
+    }
+
+    pub fn get_n_move_nodes(&self) -> usize {
+        self.n_move_nodes
+    }
+
+    pub fn get_branching_factors(&self) -> &Vec<f32> {
+        // Average number of children of the expanded nodes at each ply, starting from the root.
+        &self.branching_factors
+    }
+}
+
+#[cfg(test)]
+mod test_tree {
+    use super::*;
+
+    #[test]
+    fn test_tree_instantiates() {
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+
+        assert_eq!(root.get_player_1_board(), Board::empty());
+        assert_eq!(root.get_player_2_board(), Board::empty());
+        assert_eq!(root.get_die(), None);
+        assert_eq!(root.get_active_player(), Player::Player1);
+
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert_eq!(root.get_player_1_board().sum(), 2);
+        assert_eq!(root.get_player_2_board(), Board::empty());
+        assert_eq!(root.get_die(), Some(Die::Five));
+        assert_eq!(root.get_active_player(), Player::Player2);
+    }
+
+    #[test]
+    fn test_is_game_over() {
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert!(!root.is_game_over());
+
+        let player_1_board = Board::from_string("255\n122\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert!(root.is_game_over());
+    }
+
+    #[test]
+    fn test_evaluates_outcome() {
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert_eq!(root.get_outcome(), Outcome::InProgress);
+
+        let player_1_board = Board::from_string("255\n122\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert_eq!(root.get_scores(), (6 + 22 + 13, 7 + 10 + 3));
+        assert_eq!(root.get_outcome(), Outcome::Victory(Player::Player1));
+
+        let player_1_board = Board::from_string("111\n111\n111".to_string()).unwrap();
+        let player_2_board = Board::from_string("24_\n25_\n2__".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert_eq!(root.get_scores(), (27, 27));
+        assert_eq!(root.get_outcome(), Outcome::Draw);
+    }
+
+    #[test]
+    fn test_node_makes_move_and_add_rolls() {
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert_eq!(root.get_n_children(), 0);
+
+        let m = Move::new(0, 0);
+        let result = root.add_move(m).is_ok();
+        assert!(result);
+        assert_eq!(root.get_n_children(), 1);
+
+        let mut new_node = root.get_children()[0].clone();
+        let result = new_node.add_rolls();
+        assert!(result.is_ok());
+        assert_eq!(new_node.get_n_children(), 6);
+        assert!(new_node.get_children().iter().all(|x| x.get_die().is_some()));
+
+    }
+
+    #[test]
+    fn test_node_gets_child() {
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        root.add_move(Move::new(0, 0)).unwrap();
+        let actual_child = root.get_child(0, 0).unwrap();
+        
+        let expected_child = Node::new(
+            Board::from_string("2__\n___\n___".to_string()).unwrap(),
+            Board::from_string("5__\n___\n___".to_string()).unwrap(),
+            NodeType::Roll(Player::Player1),
+        );
+
+        assert_eq!(*actual_child, expected_child);
+
+        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        root.add_move(Move::new(1, 1)).unwrap();
+        let actual_child = root.get_child(1, 1).unwrap();
+        
+        let expected_child = Node::new(
+            Board::from_string("651\n142\n62_".to_string()).unwrap(),
+            Board::from_string("256\n162\n62_".to_string()).unwrap(),
+            NodeType::Roll(Player::Player1),
+        );
+
+        assert_eq!(*actual_child, expected_child);
+    }
+
+    #[test]
+    fn test_node_handles_elimination() {
+        let player_1_board = Board::from_string("2__\n__5\n2_3".to_string()).unwrap();
+        let player_2_board = Board::from_string("___\n___\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Two));
+
+        let result = root.add_move(Move::new(0, 0)).is_ok();
+        assert!(result);
+        
+        let new_node = root.get_children()[0].clone();
+        assert_eq!(
+            new_node.get_player_1_board(),
+            Board::from_string("___\n__5\n__3".to_string()).unwrap()
+        );
+        assert_eq!(
+            new_node.get_player_2_board(),
+            Board::from_string("2__\n___\n___".to_string()).unwrap()
+        );
+        
+    }
+
+    #[test]
+    fn test_node_gets_legal_moves() {
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert_eq!(
+            root.get_legal_moves(),
+            Ok(
+                vec![
+                    Move::new(0, 0), Move::new(0, 1), Move::new(0, 2),
+                    Move::new(1, 0), Move::new(1, 1), Move::new(1, 2),
+                    Move::new(2, 0), Move::new(2, 1), Move::new(2, 2),
+                ]
+            )
+        );
+    
+        let player_1_board = Board::from_string("2_5\n122\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Five));
+
+        assert_eq!(
+            root.get_legal_moves(),
+            Ok(vec![Move::new(0, 1),])
+        );
+
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+
+        assert!(root.get_legal_moves().is_err());
+
+
+    }
+
+    #[test]
+    fn test_node_with_move_made() {
+        let player_1_board = Board::from_string("2_5\n122\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("1__\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board.clone(), NodeType::Move(Player::Player1, Die::Five));
+
+        let m = Move::new(0, 1);
+        let new_node = root.with_move_made(m).unwrap();
+        assert_eq!(
+            new_node.get_player_1_board(),
+            Board::from_string("255\n122\n352".to_string()).unwrap()
+        );
+        assert_eq!(
+            new_node.get_player_2_board(),
+            player_2_board
+        );
+        assert_eq!(
+            new_node.node_type,
+            NodeType::Roll(Player::Player2)
+        );
+
+        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let actual_node = root.with_move_made(Move::new(1, 1)).unwrap();
+
+        let expected_player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let expected_player_2_board = Board::from_string("256\n162\n62_".to_string()).unwrap();
+        let expected_node = Node::new(expected_player_1_board, expected_player_2_board, NodeType::Roll(Player::Player1));
+
+        assert_eq!(actual_node, expected_node);
+
+        
+        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        root.generate_children_up_to_symmetry();
+
+        assert_eq!(root.with_move_made(Move::new(1, 1)).unwrap(), *root.get_child(1, 1).unwrap());
+        
+    }
+
+    #[test]
+    fn test_node_gets_legal_moves_up_to_row_symmetry() {
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+
+        assert_eq!(
+            root.get_legal_moves_up_to_row_symmetry(),
+            Ok(
+                vec![
+                    Move::new(0, 0), Move::new(0, 1), Move::new(0, 2),
+                ]
+            )
+        );
+        
+        let player_1_board = Board::from_string("235\n1_2\n3_2".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Five));
+
+        assert_eq!(
+            root.get_legal_moves_up_to_row_symmetry(),
+            Ok(vec![Move::new(1, 1),])
+        );
+
+        
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+
+        assert!(root.get_legal_moves_up_to_row_symmetry().is_err());
+    }
+
+    #[test]
+    fn test_node_orders_moves_row_major() {
+        let player_1_board = Board::from_string("35_\n1_2\n_4_".to_string()).unwrap();
+        let root = Node::new(player_1_board, Board::empty(), NodeType::Move(Player::Player1, Die::Four));
+        let legal_moves = root.get_legal_moves().unwrap();
+        assert_eq!(legal_moves, vec![Move::new(0, 2), Move::new(1, 1), Move::new(2, 0), Move::new(2, 2)]);
+        assert!(legal_moves.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(root.get_legal_moves_up_to_row_symmetry(), Ok(vec![Move::new(0, 2), Move::new(1, 1), Move::new(2, 0)]));
+
+        let mut root = root;
+        root.generate_children_up_to_symmetry();
+        let child_moves = root.get_children_with_moves().unwrap().into_iter().map(|(m, _)| m).collect::<Vec<Move>>();
+        assert_eq!(child_moves, vec![Move::new(0, 2), Move::new(1, 1), Move::new(2, 0)]);
+        assert_eq!(root.get_moves().unwrap(), child_moves);
+        assert_eq!(Move::all().windows(2).filter(|pair| pair[0] < pair[1]).count(), 8);
+    }
+
+    #[test]
+    fn test_node_gets_moves() {
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+        root.build_n_moves_up_to_symmetry(1);
+        assert!(root.get_moves().is_err());
+
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Two));
+        root.build_n_moves_up_to_symmetry(1);
+        assert_eq!(
+            root.get_moves(),
+            Ok(vec![Move::new(0, 0), Move::new(0, 1), Move::new(0, 2),])
+        )
+    }
+
+    #[test]
+    fn test_node_generates_children_up_to_row_symmetry() {
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        root.generate_children_up_to_symmetry();
+
+        assert_eq!(root.get_n_children(), 3);
+                
+        let player_1_board = Board::from_string("235\n1_2\n3_2".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Five));
+        root.generate_children_up_to_symmetry();
+
+        assert_eq!(root.get_n_children(), 1);
+
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+        root.generate_children_up_to_symmetry();
+
+        assert_eq!(root.get_n_children(), 6);
+    }
+
+    #[test]
+    fn test_node_makes_move_detailed() {
+        // Player 1 completes a triple of 4s in the first column while destroying Player 2's two 4s there.
+        let player_1_board = Board::from_string("4__\n4__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("4__\n42_\n1__".to_string()).unwrap();
+        let node = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let (child, effect) = node.with_move_made_detailed(Move::new(2, 0)).unwrap();
+        assert_eq!(child, node.with_move_made(Move::new(2, 0)).unwrap());
+        assert_eq!(child.get_player_1_board(), Board::from_string("4__\n4__\n4__".to_string()).unwrap());
+        assert_eq!(child.get_player_2_board(), Board::from_string("___\n_2_\n1__".to_string()).unwrap());
+        assert_eq!(effect.get_player(), Player::Player1);
+        assert_eq!(effect.get_die(), Die::Four);
+        assert_eq!(effect.get_move(), Move::new(2, 0));
+        assert_eq!(effect.get_points_gained(), 36 - 16);
+        assert_eq!(effect.get_elimination(), EliminationResult::new(Die::Four, 0, 2, 16));
+        assert_eq!(effect.to_string(), "Player 1 placed 4 at (2, 0) for 20 points, destroying 2 of Player 2's 4s (16 points)".to_string());
+
+        let (_, effect) = node.with_move_made_detailed(Move::new(0, 1)).unwrap();
+        assert_eq!(effect.get_points_gained(), 4);
+        assert_eq!(effect.get_elimination().get_n_eliminated(), 0);
+        assert_eq!(effect.to_string(), "Player 1 placed 4 at (0, 1) for 4 points".to_string());
+        assert!(node.with_move_made_detailed(Move::new(0, 0)).is_err());
+
+        // The scores carried over from the node's agree with summing the child's boards.
+        let (child, effect) = node.with_move_made_detailed(Move::new(2, 0)).unwrap();
+        assert_eq!(effect.get_scores_after(node.get_scores()), (36, 3));
+        assert_eq!(effect.get_scores_after(node.get_scores()), child.get_scores());
+        let (grandchild, effect) = child.with_roll(Die::Two).unwrap().with_move_made_detailed(Move::new(0, 1)).unwrap();
+        assert_eq!(effect.get_scores_after(child.get_scores()), (36, 9));
+        assert_eq!(effect.get_scores_after(child.get_scores()), grandchild.get_scores());
+    }
+
+    #[test]
+    fn test_node_carries_scores_through_random_playouts() {
+        use rand::SeedableRng;
+        // Scores carried a move at a time from the start of the game match the boards' sums all the way to the end,
+        // eliminations included.
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for _ in 0..200 {
+            let mut node = Node::empty();
+            let mut scores = node.get_scores();
+            while !node.is_terminal() {
+                node = match node.get_node_type() {
+                    NodeType::Roll(_) => node.with_roll(Die::random_from(&mut rng)).unwrap(),
+                    NodeType::Move(_, _) => {
+                        let m = *node.get_legal_moves().unwrap().choose(&mut rng).unwrap();
+                        let (child, effect) = node.with_move_made_detailed(m).unwrap();
+                        scores = effect.get_scores_after(scores);
+                        child
+                    },
+                };
+                assert_eq!(scores, node.get_scores());
+            }
+        }
+    }
+
+    #[test]
+    fn test_node_gets_children_with_moves_and_rolls() {
+        let player_1_board = Board::from_string("4__\n_5_\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("___\n4__\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Four));
+        root.build_n_moves_up_to_symmetry(1);
+
+        let children_with_moves = root.get_children_with_moves().unwrap();
+        assert_eq!(
+            children_with_moves.iter().map(|(m, _)| *m).collect::<Vec<Move>>(),
+            vec![Move::new(0, 0), Move::new(0, 1), Move::new(0, 2)]
+        );
+        for (m, child) in children_with_moves.iter() {
+            assert!(child.equals_up_to_children(&root.with_move_made(*m).unwrap()));
+        }
+        assert!(root.get_children_with_rolls().is_err());
+
+        let roll_node = children_with_moves[0].1;
+        assert_eq!(roll_node.get_player_1_board(), Board::from_string("___\n_5_\n___".to_string()).unwrap());
+        let children_with_rolls = roll_node.get_children_with_rolls().unwrap();
+        assert_eq!(children_with_rolls.iter().map(|(die, _)| *die).collect::<Vec<Die>>(), Die::all());
+        for (die, child) in children_with_rolls.iter() {
+            assert!(child.equals_up_to_children(&roll_node.with_roll(*die).unwrap()));
+        }
+        assert!(roll_node.get_children_with_moves().is_err());
+        assert_eq!(Node::empty().get_children_with_rolls().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_node_iterates_children_lazily() {
+        let nodes = vec![
+            Node::new(Board::from_string("2__\n___\n___".to_string()).unwrap(), Board::empty(), NodeType::Move(Player::Player2, Die::Five)),
+            Node::new(Board::from_string("235\n1_2\n3_2".to_string()).unwrap(), Board::from_string("15_\n333\n12_".to_string()).unwrap(), NodeType::Move(Player::Player1, Die::Three)),
+            Node::new(Board::from_string("255\n1_2\n352".to_string()).unwrap(), Board::from_string("15_\n333\n12_".to_string()).unwrap(), NodeType::Roll(Player::Player1)),
+            Node::empty(),
+        ];
+        for node in nodes {
+            let mut expected = node.clone();
+            expected.generate_children_up_to_symmetry();
+            assert_eq!(&node.children_iter_up_to_symmetry().collect::<Vec<Node>>(), expected.get_children());
+            assert!(node.is_leaf());
+
+            let legal_children = match node.get_legal_moves() {
+                Ok(moves) => moves.iter().map(|m| node.with_move_made(*m).unwrap()).collect::<Vec<Node>>(),
+                Err(_) => Die::all().iter().map(|die| node.with_roll(*die).unwrap()).collect::<Vec<Node>>(),
+            };
+            assert_eq!(node.children_iter().collect::<Vec<Node>>(), legal_children);
+        }
+    }
+
+    #[test]
+    fn test_tree_builds_entire_tree_up_to_symmetry() {
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_entire_tree_up_to_symmetry();
+        assert_eq!(root.get_n_children(), 1);
+        assert_eq!(root.get_max_depth(), 2);
+
+        let player_1_board = Board::from_string("235\n1_2\n3_2".to_string()).unwrap();
+        let player_2_board = Board::from_string("156\n333\n12_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player2));
+        let mut expected_root = root.clone();
+        expected_root.add_rolls().unwrap();
+        for child in expected_root.children.iter_mut() {
+            child.get_legal_moves_up_to_row_symmetry().unwrap().iter().for_each(
+                |&m| {
+                    child.add_move(m).unwrap();
+                }
+            );
+        }
+        root.build_entire_tree_up_to_symmetry();
+        assert_eq!(root.get_max_depth(), 3);
+        assert_eq!(root.get_n_children(), 6);
+        assert_eq!(root, expected_root);
+
+        let player_1_board = Board::from_string("251\n142\n32_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        root.build_entire_tree_up_to_symmetry();
+        assert_eq!(root.get_max_depth(), 4);
+        assert_eq!(root.get_n_children(), 2);
+        
+    }
+
+    #[test]
+    fn test_tree_builds_to_depth() {
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(1);
+
+        assert_eq!(root.get_max_depth(), 3);
+        assert_eq!(root.get_n_children(), 3);
+        assert!(root.get_children().iter().all(|c| c.get_n_children() == 6));
+        
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+        root.build_n_moves_up_to_symmetry(1);
+
+        assert_eq!(root.get_max_depth(), 4);
+        assert_eq!(root.get_n_children(), 6);
+        assert!(root.get_children().iter().all(|c| c.get_n_children() == 3));
+
+        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap(); // 40 before move.
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap(); // 24 before move.
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        let mut expected_root = root.clone();
+
+        root.build_n_moves_up_to_symmetry(5);
+        expected_root.build_entire_tree_up_to_symmetry();
+        assert_eq!(root, expected_root);
+
+    }
+
+    #[test]
+    fn test_node_gets_status() {
+        assert_eq!(Node::empty().status(), NodeStatus::Roll(Player::Player1));
+        let node = Node::empty().with_roll(Die::Four).unwrap();
+        assert_eq!(node.status(), NodeStatus::Move(Player::Player1, Die::Four));
+
+        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let node = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Three));
+        let finished = node.with_move_made(Move::new(2, 2)).unwrap();
+        assert_eq!(finished.get_node_type(), NodeType::Roll(Player::Player2));
+        assert_eq!(finished.status(), NodeStatus::Terminal(Outcome::Victory(Player::Player1)));
+        assert!(finished.with_roll(Die::One).is_err());
+        assert!(finished.clone().add_rolls().is_err());
+        assert_eq!(finished.children_iter().count(), 0);
+
+        let mut tree = finished.clone();
+        tree.build_entire_tree_up_to_symmetry();
+        assert_eq!(tree.count_nodes(), 1);
+        tree.build_n_moves_up_to_full_symmetry(3);
+        tree.generate_children_up_to_symmetry();
+        assert_eq!(tree.count_nodes(), 1);
+        assert_eq!(tree, finished);
+    }
+
+    #[test]
+    fn test_node_serializes_with_status() {
+        let node_type = NodeType::Move(Player::Player2, Die::Five);
+        assert_eq!(serde_json::from_str::<NodeType>(&serde_json::to_string(&node_type).unwrap()).unwrap(), node_type);
+
+        let player_1_board = Board::from_string("651\n142\n623".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let finished = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player2));
+        let value = serde_json::to_value(&finished).unwrap();
+        assert_eq!(value["status"], serde_json::json!({"Terminal": {"Victory": "Player1"}}));
+        assert_eq!(serde_json::from_value::<Node>(value).unwrap(), finished);
+
+        let mut tree = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Two));
+        tree.build_n_moves_up_to_symmetry(1);
+        let serialized = serde_json::to_string(&tree).unwrap();
+        assert_eq!(serde_json::from_str::<Node>(&serialized).unwrap(), tree);
     }
 
-    pub fn get_n_children(&self) -> usize {
-        self.children.len()
+    #[test]
+    fn test_node_round_trips_kfen() {
+        let node = Node::from_kfen("2________ / _35______ 1 r5").unwrap();
+        assert_eq!(node.get_player_1_board(), Board::from_string("2__\n___\n___".to_string()).unwrap());
+        assert_eq!(node.get_player_2_board(), Board::from_string("_35\n___\n___".to_string()).unwrap());
+        assert_eq!(node.get_node_type(), NodeType::Move(Player::Player1, Die::Five));
+        assert_eq!(node.to_kfen(), "2________ / _35______ 1 r5".to_string());
+
+        let nodes = vec![
+            Node::empty(),
+            Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player2, Die::One)),
+            Node::new(Board::from_string("651\n142\n62_".to_string()).unwrap(), Board::from_string("256\n1_2\n62_".to_string()).unwrap(), NodeType::Roll(Player::Player2)),
+            Node::new(Board::from_string("651\n142\n62_".to_string()).unwrap(), Board::from_string("256\n1_2\n62_".to_string()).unwrap(), NodeType::Move(Player::Player1, Die::Six)),
+            Node::new(Board::from_string("651\n142\n623".to_string()).unwrap(), Board::from_string("256\n1_2\n62_".to_string()).unwrap(), NodeType::Roll(Player::Player2)),
+        ];
+        for node in nodes {
+            assert_eq!(Node::from_kfen(&node.to_kfen()), Ok(node.clone()));
+        }
+        assert_eq!(Node::empty().to_kfen(), "_________ / _________ 1 -".to_string());
+        assert_eq!(Node::from_kfen("  _________   /  _________ 2   r3 "), Ok(Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player2, Die::Three))));
     }
 
-    pub fn get_max_depth(&self) -> usize {
-        if self.get_n_children() == 0 {
-            return 1;
-        } else {
-            return 1 + self.children.iter().map(|child| child.get_max_depth()).max().unwrap();
+    #[test]
+    fn test_node_new_validated() {
+        let full_board = Board::from_string("651\n142\n623".to_string()).unwrap();
+        let nearly_full_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+
+        let valid = vec![
+            (Board::empty(), Board::empty(), NodeType::Roll(Player::Player1)),
+            (Board::empty(), Board::empty(), NodeType::Move(Player::Player2, Die::Three)),
+            (nearly_full_board.clone(), full_board.clone(), NodeType::Roll(Player::Player1)),
+            // Player 1's dice could all have been eliminated.
+            (Board::empty(), nearly_full_board.clone(), NodeType::Move(Player::Player1, Die::Four)),
+        ];
+        for (player_1_board, player_2_board, node_type) in valid {
+            assert_eq!(
+                Node::new_validated(player_1_board.clone(), player_2_board.clone(), node_type),
+                Ok(Node::new(player_1_board, player_2_board, node_type)),
+            );
         }
+
+        assert_eq!(
+            Node::new_validated(full_board.clone(), nearly_full_board.clone(), NodeType::Move(Player::Player1, Die::Two)),
+            Err("Player 1's board is full, so there's nowhere to place the 2: [P1:651142623|P2:2561_262_|Move P1 d2|ch:0]".to_string()),
+        );
+        assert_eq!(
+            Node::new_validated(full_board.clone(), nearly_full_board.clone(), NodeType::Move(Player::Player2, Die::Two)),
+            Err("The game is over, so there can't be a roll to place: [P1:651142623|P2:2561_262_|Move P2 d2|ch:0]".to_string()),
+        );
+        assert_eq!(
+            Node::new_validated(nearly_full_board.clone(), Board::empty(), NodeType::Roll(Player::Player1)),
+            Err("Player 2 has no dice, so can't have just moved, but Player 1 has some: [P1:2561_262_|P2:_________|Roll P1|ch:0]".to_string()),
+        );
+        assert!(Node::new_validated(Board::empty(), nearly_full_board, NodeType::Move(Player::Player2, Die::One)).is_err());
     }
 
-    pub fn get_children(&self) -> &Vec<Node> {
-        &self.children
+    #[test]
+    fn test_node_rejects_invalid_kfen() {
+        let invalid = vec![
+            "",
+            "_________ _________ 1 -",
+            "_________ | _________ 1 -",
+            "_________ / _________ 1",
+            "_________ / _________ 1 - extra",
+            "________ / _________ 1 -",
+            "__________ / _________ 1 -",
+            "____7____ / _________ 1 -",
+            "_________ / ____x____ 1 -",
+            "_________ / _________ 3 -",
+            "_________ / _________ 1 5",
+            "_________ / _________ 1 r0",
+            "_________ / _________ 1 r7",
+            "_________ / _________ 1 r55",
+            "651142623 / _________ 2 r3",
+            "651142623 / 2561_262_ 1 r3",
+            "2________ / _________ 1 r3",
+        ];
+        for kfen in invalid {
+            assert!(Node::from_kfen(kfen).is_err(), "{}", kfen);
+        }
     }
 
-    pub fn get_child_from_move(&self, m: Move) -> Result<&Node, String> {
-        self.get_child(m.get_row(), m.get_column())
+    #[test]
+    fn test_node_gets_position_key() {
+        let root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Three));
+        let first_order = root
+            .with_move_made(Move::new(0, 0)).unwrap()
+            .with_roll(Die::Four).unwrap()
+            .with_move_made(Move::new(0, 1)).unwrap()
+            .with_roll(Die::Three).unwrap()
+            .with_move_made(Move::new(0, 2)).unwrap();
+        let second_order = root
+            .with_move_made(Move::new(0, 2)).unwrap()
+            .with_roll(Die::Four).unwrap()
+            .with_move_made(Move::new(0, 1)).unwrap()
+            .with_roll(Die::Three).unwrap()
+            .with_move_made(Move::new(0, 0)).unwrap();
+        assert_eq!(first_order.get_player_1_board(), second_order.get_player_1_board());
+        assert_eq!(first_order.position_key(), second_order.position_key());
+        assert_eq!(PositionKey::from_node(&first_order), PositionKey::from_node(&second_order));
+
+        let mut built = first_order.clone();
+        built.build_n_moves_up_to_symmetry(1);
+        assert_eq!(built.position_key(), first_order.position_key());
+
+        let with_three = first_order.with_roll(Die::Three).unwrap();
+        let with_six = first_order.with_roll(Die::Six).unwrap();
+        assert_ne!(with_three.position_key(), with_six.position_key());
+        assert_ne!(with_three.position_key(), first_order.position_key());
+        let other_player = Node::new(first_order.get_player_1_board(), first_order.get_player_2_board(), NodeType::Roll(Player::Player1));
+        assert_ne!(other_player.position_key(), first_order.position_key());
+
+        let top = Node::new(Board::from_string("6__\n___\n___".to_string()).unwrap(), Board::empty(), NodeType::Roll(Player::Player2));
+        let bottom = Node::new(Board::from_string("___\n___\n6__".to_string()).unwrap(), Board::empty(), NodeType::Roll(Player::Player2));
+        assert_ne!(top.position_key(), bottom.position_key());
+        assert_eq!(top.symmetric_position_key(), bottom.symmetric_position_key());
+        assert_eq!(PositionKey::from_node_up_to_symmetry(&top), PositionKey::from_node_up_to_symmetry(&bottom));
     }
 
-    pub fn get_child_from_roll(&self, roll: Die) -> Result<&Node, String> {
-        match self.node_type {
-            NodeType::Roll(_) => {
-                if self.get_n_children() != 6 {
-                    return Err("Roll node does not have children".to_string());
-                } else {
-                    return Ok(&self.children[(roll.to_value() - 1) as usize]);
-                }
-            },
-            NodeType::Move(_, _) => {
-                return Err("Cannot get child from roll from a move node".to_string());
+    #[test]
+    fn test_tree_expands_frontier_one_move() {
+        let roots = vec![
+            Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six)),
+            Node::empty(),
+            Node::new(Board::from_string("651\n142\n62_".to_string()).unwrap(), Board::from_string("256\n1_2\n62_".to_string()).unwrap(), NodeType::Move(Player::Player2, Die::Six)),
+        ];
+        for root in roots {
+            let mut expanded = root.clone();
+            for k in 1..=2 {
+                let n_before = expanded.count_nodes();
+                let n_added = expanded.expand_frontier_one_move();
+                let mut built = root.clone();
+                built.build_n_moves_up_to_symmetry(k);
+                assert_eq!(expanded, built);
+                assert_eq!(n_added, expanded.count_nodes() - n_before);
             }
         }
+
+        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        root.build_entire_tree_up_to_symmetry();
+        let complete = root.clone();
+        assert_eq!(root.expand_frontier_one_move(), 0);
+        assert_eq!(root.expand_frontier_one_move(), 0);
+        assert_eq!(root, complete);
     }
 
-    pub fn get_child(&self, row: usize, col: usize) -> Result<&Node, String> {
-        let expected_node = match self.with_move_made(Move::new(row, col)) {
-            Ok(node) => node,
-            Err(e) => return Err(e),
-        };
-        match self.children.iter()
-            .find(|child| child.equals_up_to_children(&expected_node)) {
-                Some(child) => Ok(child),
-                None => Err(format!("No child at row {} and column {}", row, col)),
+    #[test]
+    fn test_tree_generates_children_ordered() {
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32);
+        let player_1_board = Board::from_string("3_5\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("5__\n_1_\n__3".to_string()).unwrap();
+        for player in [Player::Player1, Player::Player2] {
+            let mut root = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(player, Die::Five));
+            root.generate_children_ordered(|x| objective_function.evaluate(x));
+            let scores = root.get_children().iter().map(|x| objective_function.evaluate(x)).collect::<Vec<f32>>();
+            assert_eq!(root.get_n_children(), 3);
+            assert!(scores.windows(2).all(|pair| player.compare_evaluation(pair[0], pair[1]) != Comparison::Worse));
+            assert_ne!(scores[0], scores[2]);
+
+            let mut ordered = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(player, Die::Five));
+            ordered.build_n_moves_ordered(2, &objective_function);
+            let mut unordered = ordered.clone_without_children();
+            unordered.build_n_moves_up_to_symmetry(2);
+            assert_ne!(ordered, unordered);
+            assert_eq!(ordered.count_nodes(), unordered.count_nodes());
+            assert_eq!(ordered.get_evaluation(&objective_function), unordered.get_evaluation(&objective_function));
+            assert_eq!(ordered.get_evaluation_tree(&objective_function).unwrap(), unordered.get_evaluation_tree(&objective_function).unwrap());
         }
+
+        let mut roll_node = Node::empty();
+        roll_node.generate_children_ordered(|x| objective_function.evaluate(x));
+        assert_eq!(roll_node.get_children_with_rolls().unwrap().iter().map(|(die, _)| *die).collect::<Vec<Die>>(), Die::all());
     }
 
-}
+    #[test]
+    fn test_tree_builds_with_node_budget() {
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32);
+        for max_nodes in [1, 3, 4, 10, 25, 100, 1000] {
+            let mut root = Node::new(Board::empty(), Board::from_string("2__\n___\n___".to_string()).unwrap(), NodeType::Move(Player::Player1, Die::Four));
+            root.build_with_node_budget(max_nodes);
+            assert!(root.count_nodes() <= max_nodes);
+            let evaluation = root.get_evaluation(&objective_function);
+            let (_, tree_evaluation) = root.get_evaluation_tree(&objective_function).unwrap();
+            assert!((evaluation - tree_evaluation).abs() < 1e-4, "{} {} {}", max_nodes, evaluation, tree_evaluation);
+        }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum NodeType {
-    Roll(Player),
-    Move(Player, Die),
-}
+        // Breadth first: the first move ply and its rolls are filled out before anything deeper.
+        let mut root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Four));
+        root.build_with_node_budget(30);
+        assert_eq!(root.count_nodes(), 1 + 3 + 18 + 3 + 3);
+        assert!(root.get_children().iter().all(|child| child.get_n_children() == 6));
 
-#[cfg(test)]
-mod test_tree {
-    use super::*;
+        let mut depths = Vec::new();
+        let mut root = Node::empty();
+        root.build_until(|_, depth, _| { depths.push(depth); depth < 2 });
+        assert_eq!(root.get_max_depth(), 3);
+        assert!(depths.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
 
     #[test]
-    fn test_tree_instantiates() {
-        let player_1_board = Board::empty();
-        let player_2_board = Board::empty();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
-
-        assert_eq!(root.get_player_1_board(), Board::empty());
-        assert_eq!(root.get_player_2_board(), Board::empty());
-        assert_eq!(root.get_die(), None);
-        assert_eq!(root.get_active_player(), Player::Player1);
+    fn test_node_canonicalizes_columns() {
+        let player_1_board = Board::from_string("__4\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("_1_\n___\n___".to_string()).unwrap();
+        let node = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Two));
+        let permuted = Node::new(
+            Board::from_string("4__\n___\n___".to_string()).unwrap(),
+            Board::from_string("__1\n___\n___".to_string()).unwrap(),
+            NodeType::Move(Player::Player1, Die::Two),
+        );
+        assert_eq!(node.canonicalize_columns(), permuted.canonicalize_columns());
+        assert_eq!(node.canonicalize_columns().canonicalize_columns(), node.canonicalize_columns());
+
+        // Moving a column on only one board isn't a symmetry.
+        let unpaired = Node::new(
+            Board::from_string("4__\n___\n___".to_string()).unwrap(),
+            Board::from_string("1__\n___\n___".to_string()).unwrap(),
+            NodeType::Move(Player::Player1, Die::Two),
+        );
+        assert_ne!(node.canonicalize_columns(), unpaired.canonicalize_columns());
 
-        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
-        let player_2_board = Board::empty();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        let mut root = Node::empty().with_roll(Die::Three).unwrap();
+        root.generate_children_up_to_full_symmetry();
+        assert_eq!(root.get_n_children(), 1);
 
-        assert_eq!(root.get_player_1_board().sum(), 2);
-        assert_eq!(root.get_player_2_board(), Board::empty());
-        assert_eq!(root.get_die(), Some(Die::Five));
-        assert_eq!(root.get_active_player(), Player::Player2);
+        // Nor do the rows the dice are in matter: a 2 with either 1 leaves one column of 1 and 2, and one of 1.
+        let player_1_board = Board::from_string("1__\n_1_\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, Board::empty(), NodeType::Move(Player::Player1, Die::Two));
+        let row_permuted = Node::new(Board::from_string("_1_\n1__\n___".to_string()).unwrap(), Board::empty(), NodeType::Move(Player::Player1, Die::Two));
+        assert_eq!(root.get_canonical_column_order(), row_permuted.get_canonical_column_order());
+        assert_eq!(root.canonicalize_columns().symmetric_position_key(), row_permuted.canonicalize_columns().symmetric_position_key());
+        root.generate_children_up_to_full_symmetry();
+        assert_eq!(root.get_n_children(), 2);
     }
 
     #[test]
-    fn test_is_game_over() {
-        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
-        let player_2_board = Board::empty();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
-
-        assert_eq!(root.is_game_over(), false);
+    fn test_tree_full_symmetry_preserves_evaluations() {
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32);
+        let positions = vec![
+            ("4_4\n___\n___", "_2_\n___\n___", NodeType::Move(Player::Player1, Die::Four)),
+            ("3_3\n___\n___", "_4_\n_4_\n___", NodeType::Move(Player::Player2, Die::Three)),
+            ("6__\n1__\n___", "3__\n1__\n___", NodeType::Roll(Player::Player1)),
+        ];
+        for (player_1_board, player_2_board, node_type) in positions {
+            let root = Node::new(
+                Board::from_string(player_1_board.to_string()).unwrap(),
+                Board::from_string(player_2_board.to_string()).unwrap(),
+                node_type,
+            );
+            let mut row_symmetric = root.clone();
+            row_symmetric.build_n_moves_up_to_symmetry(2);
+            let mut fully_symmetric = root.clone();
+            fully_symmetric.build_n_moves_up_to_full_symmetry(2);
+
+            assert!(fully_symmetric.count_nodes() < row_symmetric.count_nodes());
+            assert!((fully_symmetric.get_evaluation(&objective_function) - row_symmetric.get_evaluation(&objective_function)).abs() < 1e-4);
+            if let NodeType::Move(_, _) = node_type {
+                assert!((fully_symmetric.get_evaluation_tree(&objective_function).unwrap().1 - row_symmetric.get_evaluation_tree(&objective_function).unwrap().1).abs() < 1e-4);
+            }
+        }
+    }
 
-        let player_1_board = Board::from_string("255\n122\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+    #[test]
+    fn test_get_moves_left_bounds() {
+        let node = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        assert_eq!(node.get_moves_left_bounds(), (17, 35));
 
-        assert_eq!(root.is_game_over(), true);
+        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Six));
+        assert_eq!(node.get_moves_left_bounds(), (1, 10));
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Two));
+        assert_eq!(node.get_moves_left_bounds(), (2, 9));
+
+        // Eliminating the 2 reopens a square in Player 1's last column.
+        let eliminated = node.with_move_made(Move::new(2, 2)).unwrap();
+        assert_eq!(eliminated.get_player_1_board(), Board::from_string("651\n14_\n62_".to_string()).unwrap());
+        assert_eq!(eliminated.get_moves_left_bounds(), (2, 8));
+
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Five));
+        let not_eliminated = node.with_move_made(Move::new(2, 2)).unwrap();
+        assert_eq!(not_eliminated.get_player_1_board(), player_1_board);
+        assert_eq!(not_eliminated.get_moves_left_bounds(), (1, 7));
+
+        let game_over = not_eliminated.with_roll(Die::Three).unwrap().with_move_made(Move::new(2, 2)).unwrap();
+        assert_eq!(game_over.get_moves_left_bounds(), (0, 0));
     }
 
     #[test]
-    fn test_evaluates_outcome() {
-        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
-        let player_2_board = Board::empty();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
-
-        assert_eq!(root.get_outcome(), Outcome::InProgress);
+    fn test_get_moves_left_ignoring_elimination() {
 
-        let player_1_board = Board::from_string("255\n122\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Six));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 17);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Six));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 17);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 17);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player2));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 17);
 
-        assert_eq!(root.get_scores(), (6 + 22 + 13, 7 + 10 + 3));
-        assert_eq!(root.get_outcome(), Outcome::Victory(Player::Player1));
+        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
+        let node = Node::new(player_1_board.clone().clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Six));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 1);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 1);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Six));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 2);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player2));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 2);
+        
 
-        let player_1_board = Board::from_string("111\n111\n111".to_string()).unwrap();
-        let player_2_board = Board::from_string("24_\n25_\n2__".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        let player_1_board = Board::from_string("651\n_42\n62_".to_string()).unwrap();
+        let player_2_board = Board::from_string("___\n1_2\n62_".to_string()).unwrap();
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Six));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 3);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 3);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Six));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 4);
+        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player2));
+        assert_eq!(node.get_moves_left_ignoring_elimination(), 4);
 
-        assert_eq!(root.get_scores(), (27, 27));
-        assert_eq!(root.get_outcome(), Outcome::Draw);
     }
 
     #[test]
-    fn test_node_makes_move_and_add_rolls() {
-        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+    fn test_tree_counts_nodes_and_leaves() {
+        let player_1_board = Board::empty();
         let player_2_board = Board::empty();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        assert_eq!(root.count_nodes(), 1);
+        assert_eq!(root.count_leaves(), 1);
 
-        assert_eq!(root.get_n_children(), 0);
+        root.build_n_moves_up_to_symmetry(1);
+        assert_eq!(root.count_nodes(), 1 + 3 + 18);
+        assert_eq!(root.count_leaves(), 18);
 
-        let m = Move::new(0, 0);
-        let mut result = root.add_move(m).is_ok();
-        assert!(result);
-        assert_eq!(root.get_n_children(), 1);
+        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
+        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_entire_tree_up_to_symmetry();
+        assert_eq!(root.count_nodes(), 2);
+        assert_eq!(root.count_leaves(), 1);
+    }
 
-        let mut new_node = root.get_children()[0].clone();
-        let result = new_node.add_rolls();
-        assert!(result.is_ok());
-        assert_eq!(new_node.get_n_children(), 6);
-        assert!(new_node.get_children().iter().all(|x| x.get_die().is_some()));
+    #[test]
+    fn test_tree_estimates_size() {
+        let player_1_board = Board::from_string("35_\n1__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n6__".to_string()).unwrap();
+        for node_type in [NodeType::Move(Player::Player1, Die::Four), NodeType::Roll(Player::Player2)] {
+            let root = Node::new(player_1_board.clone(), player_2_board.clone(), node_type);
+            let mut tree = root.clone();
+            tree.build_n_moves_up_to_symmetry(0);
+            assert_eq!(root.estimated_tree_size(0), tree.count_nodes());
+            let mut tree = root.clone();
+            tree.build_n_moves_up_to_symmetry(1);
+            assert_eq!(root.estimated_tree_size(1), tree.count_nodes());
+            let mut tree = root.clone();
+            tree.build_n_moves_up_to_symmetry(2);
+            // Eliminations can open up squares the estimate doesn't know about.
+            let error = (root.estimated_tree_size(2) as f32 - tree.count_nodes() as f32).abs() / tree.count_nodes() as f32;
+            assert!(error < 0.1);
+        }
+        assert_eq!(Node::empty().estimated_tree_size(100), usize::MAX);
 
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        let leaf_footprint = root.actual_memory_footprint();
+        assert!(leaf_footprint > std::mem::size_of::<Node>());
+        root.build_n_moves_up_to_symmetry(2);
+        assert!(root.actual_memory_footprint() >= root.count_nodes() * leaf_footprint);
     }
 
     #[test]
-    fn test_node_gets_child() {
-        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+    fn test_tree_stats() {
+        let player_1_board = Board::empty();
         let player_2_board = Board::empty();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
-        root.add_move(Move::new(0, 0)).unwrap();
-        let actual_child = root.get_child(0, 0).unwrap();
-        
-        let expected_child = Node::new(
-            Board::from_string("2__\n___\n___".to_string()).unwrap(),
-            Board::from_string("5__\n___\n___".to_string()).unwrap(),
-            NodeType::Roll(Player::Player1),
-        );
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        let stats = root.get_tree_stats();
+        assert_eq!(stats.get_n_nodes(), 1);
+        assert_eq!(stats.get_n_leaves(), 1);
+        assert_eq!(stats.get_max_depth(), 1);
+        assert_eq!(stats.get_n_roll_nodes(), 0);
+        assert_eq!(stats.get_n_move_nodes(), 1);
+        assert!(stats.get_branching_factors().is_empty());
 
-        assert_eq!(*actual_child, expected_child);
+        root.build_n_moves_up_to_symmetry(1);
+        let stats = root.get_tree_stats();
+        assert_eq!(stats.get_n_nodes(), root.count_nodes());
+        assert_eq!(stats.get_n_leaves(), root.count_leaves());
+        assert_eq!(stats.get_max_depth(), root.get_max_depth());
+        assert_eq!(stats.get_n_roll_nodes(), 3);
+        assert_eq!(stats.get_n_move_nodes(), 19);
+        assert_eq!(stats.get_branching_factors(), &vec![3.0, 6.0]);
+        assert_eq!(
+            stats.to_string(),
+            "Nodes: 22 (Roll: 3, Move: 19)\nLeaves: 18\nMax Depth: 3\nBranching Factor by Ply: [3.00, 6.00]".to_string()
+        );
 
-        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
+        let player_1_board = Board::from_string("251\n142\n32_".to_string()).unwrap();
         let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
         let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
-        root.add_move(Move::new(1, 1)).unwrap();
-        let actual_child = root.get_child(1, 1).unwrap();
-        
-        let expected_child = Node::new(
-            Board::from_string("651\n142\n62_".to_string()).unwrap(),
-            Board::from_string("256\n162\n62_".to_string()).unwrap(),
-            NodeType::Roll(Player::Player1),
-        );
-
-        assert_eq!(*actual_child, expected_child);
+        root.build_n_moves_up_to_symmetry(1);
+        let stats = root.get_tree_stats();
+        assert_eq!(stats.get_n_nodes(), 1 + 2 + 12);
+        assert_eq!(stats.get_n_roll_nodes(), 2);
+        assert_eq!(stats.get_n_leaves(), 12);
+        assert_eq!(stats.get_branching_factors(), &vec![2.0, 6.0]);
     }
 
     #[test]
-    fn test_node_handles_elimination() {
-        let player_1_board = Board::from_string("2__\n__5\n2_3".to_string()).unwrap();
-        let player_2_board = Board::from_string("___\n___\n___".to_string()).unwrap();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Two));
+    fn test_tree_caches_size() {
+        fn uncached_size(node: &Node) -> (usize, usize) {
+            let child_sizes = node.get_children().iter().map(uncached_size).collect::<Vec<(usize, usize)>>();
+            let max_depth = 1 + child_sizes.iter().map(|(depth, _)| *depth).max().unwrap_or(0);
+            let n_nodes = 1 + child_sizes.iter().map(|(_, n)| *n).sum::<usize>();
+            return (max_depth, n_nodes);
+        }
+        let cached_size = |node: &Node| (node.get_max_depth(), node.count_nodes());
 
-        let result = root.add_move(Move::new(0, 0)).is_ok();
-        assert!(result);
-        
-        let mut new_node = root.get_children()[0].clone();
-        assert_eq!(
-            new_node.get_player_1_board(),
-            Board::from_string("___\n__5\n__3".to_string()).unwrap()
-        );
-        assert_eq!(
-            new_node.get_player_2_board(),
-            Board::from_string("2__\n___\n___".to_string()).unwrap()
-        );
-        
-    }
+        let mut root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        assert_eq!(cached_size(&root), (1, 1));
 
-    #[test]
-    fn test_node_gets_legal_moves() {
-        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
-        let player_2_board = Board::empty();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        root.add_move(Move::new(0, 0)).unwrap();
+        assert_eq!(cached_size(&root), (2, 2));
 
-        assert_eq!(
-            root.get_legal_moves(),
-            Ok(
-                vec![
-                    Move::new(0, 0), Move::new(0, 1), Move::new(0, 2),
-                    Move::new(1, 0), Move::new(1, 1), Move::new(1, 2),
-                    Move::new(2, 0), Move::new(2, 1), Move::new(2, 2),
-                ]
-            )
-        );
-    
-        let player_1_board = Board::from_string("2_5\n122\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Five));
+        root.get_child_mut(0, 0).unwrap().add_rolls().unwrap();
+        assert_eq!(cached_size(&root), (3, 8));
 
-        assert_eq!(
-            root.get_legal_moves(),
-            Ok(vec![Move::new(0, 1),])
-        );
+        root.walk_mut(&[Step::Move(Die::Six, Move::new(0, 0)), Step::Roll(Die::One)]).unwrap().generate_children_up_to_symmetry();
+        assert_eq!(cached_size(&root), (4, 11));
 
-        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+        root.build_n_moves_up_to_symmetry(2);
+        assert_eq!(cached_size(&root), uncached_size(&root));
 
-        assert!(root.get_legal_moves().is_err());
+        let player_1_board = Board::from_string("6__\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("1__\n___\n___".to_string()).unwrap();
+        let leaf = root.find_descendant_mut(&player_1_board, &player_2_board, NodeType::Move(Player::Player1, Die::Three)).unwrap();
+        assert!(leaf.is_leaf());
+        leaf.generate_children_up_to_symmetry();
+        assert_eq!(cached_size(&root), uncached_size(&root));
 
+        root.prune_to_top_k(1, &Objective::new(|x| x.get_score_difference() as f32));
+        assert_eq!(cached_size(&root), uncached_size(&root));
 
+        root.detach_child_by_move(Move::new(0, 0)).unwrap();
+        assert_eq!(cached_size(&root), (1, 1));
     }
 
     #[test]
-    fn test_node_with_move_made() {
-        let player_1_board = Board::from_string("2_5\n122\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("1__\n333\n12_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board.clone(), NodeType::Move(Player::Player1, Die::Five));
-
-        let m = Move::new(0, 1);
-        let mut new_node = root.with_move_made(m).unwrap();
-        assert_eq!(
-            new_node.get_player_1_board(),
-            Board::from_string("255\n122\n352".to_string()).unwrap()
-        );
+    fn test_tree_iterates_depth_first() {
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(1);
+
+        let visited = root.iter_dfs_with_depth().collect::<Vec<(usize, &Node)>>();
+        assert_eq!(visited.len(), root.count_nodes());
+        assert_eq!(*visited[0].1, root);
+        let mut expected_depths = vec![0];
+        for _ in 0..3 {
+            expected_depths.push(1);
+            expected_depths.append(&mut vec![2; 6]);
+        }
+        assert_eq!(visited.iter().map(|(depth, _)| *depth).collect::<Vec<usize>>(), expected_depths);
+        assert_eq!(*visited[1].1, *root.get_child_from_move(Move::new(0, 0)).unwrap());
+        assert_eq!(*visited[8].1, *root.get_child_from_move(Move::new(0, 1)).unwrap());
         assert_eq!(
-            new_node.get_player_2_board(),
-            player_2_board
+            visited[2..8].iter().map(|(_, node)| node.get_die().unwrap()).collect::<Vec<Die>>(),
+            Die::all()
         );
+    }
+
+    #[test]
+    fn test_tree_iterates_breadth_first() {
+        let player_1_board = Board::empty();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(1);
+
+        let visited = root.iter_bfs_with_depth().collect::<Vec<(usize, &Node)>>();
+        assert_eq!(visited.len(), root.count_nodes());
+        let mut expected_depths = vec![0, 1, 1, 1];
+        expected_depths.append(&mut vec![2; 18]);
+        assert_eq!(visited.iter().map(|(depth, _)| *depth).collect::<Vec<usize>>(), expected_depths);
         assert_eq!(
-            new_node.node_type,
-            NodeType::Roll(Player::Player2)
+            visited[1..4].iter().map(|(_, node)| (*node).clone()).collect::<Vec<Node>>(),
+            *root.get_children()
         );
 
-        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
-        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
-        let actual_node = root.with_move_made(Move::new(1, 1)).unwrap();
+        let dfs_nodes = root.iter_dfs().collect::<Vec<&Node>>();
+        let bfs_nodes = root.iter_bfs().collect::<Vec<&Node>>();
+        assert_eq!(dfs_nodes.len(), bfs_nodes.len());
+        assert!(dfs_nodes.iter().all(|x| bfs_nodes.iter().any(|y| std::ptr::eq(*x, *y))));
+    }
 
-        let expected_player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
-        let expected_player_2_board = Board::from_string("256\n162\n62_".to_string()).unwrap();
-        let expected_node = Node::new(expected_player_1_board, expected_player_2_board, NodeType::Roll(Player::Player1));
+    #[test]
+    fn test_tree_iterators_find_leaves() {
+        let player_1_board = Board::from_string("6__\n6__\n___".to_string()).unwrap();
+        let player_2_board = Board::empty();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(1);
 
-        assert_eq!(actual_node, expected_node);
+        // The only way for Player 1 to score 54 with three dice is a tripled six.
+        let tripled_six_leaves = root.iter_dfs()
+            .filter(|node| node.is_leaf() && node.get_player_1_board().sum() == 54)
+            .collect::<Vec<&Node>>();
+        assert_eq!(tripled_six_leaves.len(), 6);
+        assert!(tripled_six_leaves.iter().all(|node| node.get_player_1_board() == Board::from_string("6__\n6__\n6__".to_string()).unwrap()));
+    }
 
-        
+    #[test]
+    fn test_tree_finds_descendant() {
+        let mut root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(2);
+        let target = root.apply_sequence(&[(Die::Six, Move::new(0, 1)), (Die::Three, Move::new(0, 1))]).unwrap()
+            .with_roll(Die::Two).unwrap();
+
+        let (player_1_board, player_2_board) = target.get_boards();
+        let found = root.find_descendant(&player_1_board, &player_2_board, target.get_node_type()).unwrap();
+        assert!(found.equals_up_to_children(&target));
+        assert_eq!(found.get_n_children(), 0);
+        assert_eq!(root.find_descendant(&Board::empty(), &Board::empty(), NodeType::Move(Player::Player1, Die::Six)), Some(&root));
+
+        let roll_node = root.with_move_made(Move::new(0, 2)).unwrap();
+        let found = root.find_descendant(&roll_node.get_player_1_board(), &roll_node.get_player_2_board(), roll_node.get_node_type()).unwrap();
+        assert_eq!(found.get_n_children(), 6);
+
+        // Wrong player to act, wrong roll, and a move the symmetry reduction never generates.
+        assert_eq!(root.find_descendant(&player_1_board, &player_2_board, NodeType::Roll(Player::Player2)), None);
+        assert_eq!(root.find_descendant(&Board::empty(), &Board::empty(), NodeType::Move(Player::Player1, Die::Five)), None);
+        let unreachable = Board::from_string("___\n6__\n___".to_string()).unwrap();
+        assert_eq!(root.find_descendant(&unreachable, &Board::empty(), NodeType::Roll(Player::Player2)), None);
+
+        let found = root.find_descendant_mut(&player_1_board, &player_2_board, target.get_node_type()).unwrap();
+        found.generate_children_up_to_symmetry();
+        assert_eq!(root.find_descendant(&player_1_board, &player_2_board, target.get_node_type()).unwrap().get_n_children(), 3);
+        assert!(root.find_descendant_mut(&unreachable, &Board::empty(), NodeType::Roll(Player::Player2)).is_none());
+    }
+
+    #[test]
+    fn test_tree_gets_leaves() {
+        let root = Node::empty();
+        assert_eq!(root.iter_leaves().collect::<Vec<&Node>>(), vec![&root]);
+        assert_eq!(root.leaf_depth_histogram(), vec![1]);
+
+        let mut root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        root.build_with_node_budget(10);
+        assert_eq!(root.leaf_depth_histogram(), vec![0, 2, 6]);
+        assert_eq!(root.iter_leaves().count(), 8);
+        assert_eq!(root.iter_leaves().filter(|leaf| leaf.get_node_type() == NodeType::Move(Player::Player2, Die::One)).count(), 1);
+
+        let mut root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(2);
+        assert_eq!(root.leaf_depth_histogram(), vec![0, 0, 0, 0, 324]);
+        assert!(root.iter_leaves().all(|leaf| matches!(leaf.get_node_type(), NodeType::Move(Player::Player1, _))));
+
+        // Every line ends with Player 1 filling their board.
         let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
         let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
         let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
-        root.generate_children_up_to_symmetry();
-
-        assert_eq!(root.with_move_made(Move::new(1, 1)).unwrap(), *root.get_child(1, 1).unwrap());
-        
+        root.build_entire_tree_up_to_symmetry();
+        assert_eq!(root.leaf_depth_histogram(), vec![0, 0, 0, 12]);
+        assert!(root.iter_leaves().all(|leaf| leaf.is_game_over()));
     }
 
     #[test]
-    fn test_node_gets_legal_moves_up_to_row_symmetry() {
-        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
-        let player_2_board = Board::empty();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+    fn test_tree_gets_principal_variation() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(2);
+        let objective_function = Objective::new(|x: &Node| x.get_score_difference() as f32);
+        let (_, expected_evaluation) = root.get_evaluation_tree(&objective_function).unwrap();
+
+        let principal_variation = root.get_principal_variation(&objective_function);
+        assert_eq!(principal_variation.len(), 2);
+        assert_eq!(principal_variation[0].get_player(), Player::Player1);
+        assert_eq!(principal_variation[0].get_die(), Die::Six);
+        assert_eq!(principal_variation[0].get_evaluation(), expected_evaluation);
+        assert_eq!(root.get_evaluation(&objective_function), expected_evaluation);
+        assert_eq!(principal_variation[1].get_player(), Player::Player2);
+        assert!(!principal_variation[0].is_roll_assumed());
+        assert!(principal_variation[1].is_roll_assumed());
+
+        let after_first_move = root.get_child_from_move(principal_variation[0].get_move()).unwrap();
+        let second_move_node = after_first_move.get_child_from_roll(principal_variation[1].get_die()).unwrap();
+        assert!(second_move_node.is_legal_move(principal_variation[1].get_move()));
+
+        let leaf = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        assert!(leaf.get_principal_variation(&objective_function).is_empty());
+    }
 
+    #[test]
+    fn test_tree_prunes_to_best_moves() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(2);
+        let objective_function = Objective::new(|x: &Node| x.get_score_difference() as f32);
+        let evaluation = root.get_evaluation(&objective_function);
+
+        let mut pruned = root.clone();
+        pruned.prune_to_best_moves(&objective_function, 0.);
+        assert_eq!(pruned.get_evaluation(&objective_function), evaluation);
+        assert!(pruned.count_nodes() < root.count_nodes());
+        assert!(pruned.get_children().iter().all(|roll_node| roll_node.get_n_children() == 6));
         assert_eq!(
-            root.get_legal_moves_up_to_row_symmetry(),
-            Ok(
-                vec![
-                    Move::new(0, 0), Move::new(0, 1), Move::new(0, 2),
-                ]
-            )
+            pruned.get_moves().unwrap(),
+            root.get_evaluation_tree(&objective_function).unwrap().0.unwrap().get_moves().unwrap()
         );
-        
-        let player_1_board = Board::from_string("235\n1_2\n3_2".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Five));
 
+        // Moves within tie_break_epsilon of the best are kept, as they're among the solver's best moves.
+        let move_evaluations: Vec<(Move, f32)> = root.get_children_with_moves().unwrap().into_iter()
+            .map(|(m, child)| (m, child.get_evaluation(&objective_function)))
+            .collect();
+        let mut within_epsilon = root.clone();
+        within_epsilon.prune_to_best_moves(&objective_function, 4.);
+        assert_eq!(within_epsilon.get_evaluation(&objective_function), evaluation);
         assert_eq!(
-            root.get_legal_moves_up_to_row_symmetry(),
-            Ok(vec![Move::new(1, 1),])
+            within_epsilon.get_moves().unwrap(),
+            move_evaluations.iter().filter(|(_, move_evaluation)| (move_evaluation - evaluation).abs() <= 4.).map(|(m, _)| *m).collect::<Vec<Move>>()
         );
-
-        
-        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
-
-        assert!(root.get_legal_moves_up_to_row_symmetry().is_err());
+        assert!(within_epsilon.count_nodes() > pruned.count_nodes());
+        let mut unpruned = root.clone();
+        unpruned.prune_to_best_moves(&objective_function, f32::INFINITY);
+        assert_eq!(unpruned, root);
+
+        // Nor are moves dropped over rounding.
+        let rounding_objective = Objective::new(|x: &Node| x.get_score_difference() as f32 + 1e-6 * x.get_player_1_board().get_n_empty_squares_in_column(0) as f32);
+        let mut rounded = root.clone();
+        rounded.prune_to_best_moves(&rounding_objective, 0.);
+        assert_eq!(rounded, pruned);
+
+        let mut top_2 = root.clone();
+        top_2.prune_to_top_k(2, &objective_function);
+        assert_eq!(top_2.get_evaluation(&objective_function), evaluation);
+        assert!(top_2.iter_dfs().all(|node| node.get_die().is_none() || node.get_n_children() <= 2));
+        assert_eq!(top_2.get_n_children(), 2);
+        assert!(top_2.count_nodes() < root.count_nodes());
+
+        let mut top_10 = root.clone();
+        top_10.prune_to_top_k(10, &objective_function);
+        assert_eq!(top_10, root);
     }
 
     #[test]
-    fn test_node_gets_moves() {
-        let player_1_board = Board::empty();
-        let player_2_board = Board::empty();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
-        root.build_n_moves_up_to_symmetry(1);
-        assert!(root.get_moves().is_err());
+    fn test_tree_finds_path_to_node() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(2);
+
+        assert_eq!(root.path_to(&root), Some(vec![]));
+        assert_eq!(root.path_to(&Node::empty()), None);
+
+        let target = root.iter_dfs().filter(|node| node.is_leaf()).last().unwrap();
+        let path = root.path_to(target).unwrap();
+        assert_eq!(path.len(), 4);
+        let moves = path.iter().filter_map(
+            |step| match step {
+                Step::Move(die, m) => Some((*die, *m)),
+                Step::Roll(_) => None,
+            }
+        ).collect::<Vec<(Die, Move)>>();
+        let replayed = root.apply_sequence(&moves).unwrap().with_roll(target.get_die().unwrap()).unwrap();
+        assert_eq!(path.last(), Some(&Step::Roll(target.get_die().unwrap())));
+        assert!(replayed.equals_up_to_children(target));
+        assert_eq!(Step::path_to_string(&path), "6→(1, 1), roll 6, 6→(2, 2), roll 6".to_string());
+    }
 
-        let player_1_board = Board::empty();
-        let player_2_board = Board::empty();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Two));
-        root.build_n_moves_up_to_symmetry(1);
+    #[test]
+    fn test_node_applies_sequence() {
+        let mut steps = Vec::new();
+        for (i, m) in Move::all().into_iter().enumerate() {
+            steps.push((Die::One, m));
+            if i < 8 {
+                steps.push((Die::Two, m));
+            }
+        }
+        let game = Node::empty().apply_sequence(&steps).unwrap();
+        assert_eq!(game.get_player_1_board(), Board::from_string("111\n111\n111".to_string()).unwrap());
+        assert_eq!(game.get_player_2_board(), Board::from_string("222\n222\n22_".to_string()).unwrap());
+        assert!(game.is_game_over());
+        assert_eq!(game.get_outcome(), Outcome::Victory(Player::Player2));
+        assert!(game.apply_sequence(&[(Die::Two, Move::new(2, 2))]).unwrap_err().contains("Game is already over"));
+
+        let game = Node::empty().apply_sequence(&[(Die::Four, Move::new(0, 0)), (Die::Four, Move::new(1, 0))]).unwrap();
+        assert_eq!(game.get_player_1_board(), Board::empty());
+        assert_eq!(game.get_player_2_board(), Board::from_string("___\n4__\n___".to_string()).unwrap());
+        assert_eq!(game.get_node_type(), NodeType::Roll(Player::Player1));
+
+        let result = Node::empty().apply_sequence(&[(Die::One, Move::new(0, 0)), (Die::Two, Move::new(0, 0)), (Die::Three, Move::new(0, 0))]);
+        assert_eq!(result, Err("Step 2: Square already set: Move { row: 0, column: 0 }".to_string()));
+
+        let root = Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player2, Die::Five));
         assert_eq!(
-            root.get_moves(),
-            Ok(vec![Move::new(0, 0), Move::new(0, 1), Move::new(0, 2),])
-        )
+            root.apply_sequence(&[(Die::Five, Move::new(1, 1))]).unwrap().get_player_2_board(),
+            Board::from_string("___\n_5_\n___".to_string()).unwrap()
+        );
+        assert_eq!(root.apply_sequence(&[(Die::Six, Move::new(1, 1))]), Err("Step 0: Expected a roll of 5, got 6".to_string()));
     }
 
     #[test]
-    fn test_node_generates_children_up_to_row_symmetry() {
-        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
-        let player_2_board = Board::empty();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
-        root.generate_children_up_to_symmetry();
+    fn test_node_plays_out_randomly() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let player_1_board = Board::from_string("665\n665\n66_".to_string()).unwrap();
+        let player_2_board = Board::from_string("1__\n2__\n3__".to_string()).unwrap();
+        let roll_node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player2));
+        let move_node = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
+        for root in [roll_node, move_node] {
+            let n_player_1_wins = (0..1000)
+                .filter(|_| root.random_playout(&mut rng) == Outcome::Victory(Player::Player1))
+                .count();
+            assert!(n_player_1_wins > 950);
+        }
 
-        assert_eq!(root.get_n_children(), 3);
-                
-        let player_1_board = Board::from_string("235\n1_2\n3_2".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Five));
-        root.generate_children_up_to_symmetry();
+        // Always playing the first legal move still finishes.
+        let outcome = Node::empty().random_playout_with_policy(&mut rng, |node, _| node.get_legal_moves().unwrap()[0]);
+        assert_ne!(outcome, Outcome::InProgress);
 
-        assert_eq!(root.get_n_children(), 1);
+        let player_1_board = Board::from_string("111\n111\n111".to_string()).unwrap();
+        let finished = Node::new(player_1_board, Board::empty(), NodeType::Roll(Player::Player2));
+        assert_eq!(finished.random_playout(&mut rng), Outcome::Victory(Player::Player1));
+    }
 
-        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
-        root.generate_children_up_to_symmetry();
+    #[test]
+    fn test_tree_detaches_children() {
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32);
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        root.build_n_moves_up_to_symmetry(3);
+        root.get_evaluation_tree(&objective_function).unwrap();
+        let n_children = root.get_n_children();
+
+        let expected = root.with_move_made(Move::new(0, 2)).unwrap().with_roll(Die::Four).unwrap();
+        let mut roll_node = root.detach_child_by_move(Move::new(0, 2)).unwrap();
+        assert_eq!(root.get_n_children(), n_children - 1);
+        assert!(root.get_child_from_move(Move::new(0, 2)).is_err());
+        let mut detached = roll_node.detach_child_by_roll(Die::Four).unwrap();
+        assert_eq!(roll_node.get_n_children(), 5);
+        assert!(detached.equals_up_to_children(&expected));
+
+        let mut fresh = expected.clone();
+        fresh.build_n_moves_up_to_symmetry(2);
+        assert_eq!(detached, fresh);
+        detached.clear_evaluations();
+        assert_eq!(detached.get_evaluation_tree(&objective_function).unwrap(), fresh.get_evaluation_tree(&objective_function).unwrap());
+
+        // Deepening the detached tree gives the same tree as building the fresh one deeper.
+        detached.build_n_moves_up_to_symmetry(3);
+        let mut fresh = expected.clone();
+        fresh.build_n_moves_up_to_symmetry(3);
+        assert_eq!(detached, fresh);
+
+        assert_eq!(root.detach_child_by_move(Move::new(0, 2)), None);
+        assert_eq!(root.detach_child_by_move(Move::new(0, 0)), None);
+        assert_eq!(root.detach_child_by_roll(Die::Four), None);
+        assert_eq!(Node::empty().detach_child_by_roll(Die::Four), None);
+    }
 
-        assert_eq!(root.get_n_children(), 6);
+    #[test]
+    fn test_node_counts_plies() {
+        let root = Node::empty();
+        assert_eq!(root.get_ply(), 0);
+        assert_eq!(root.get_turn_number(), 1);
+        let steps = [
+            (Die::Four, Move::new(0, 0)),
+            (Die::Two, Move::new(0, 1)),
+            (Die::One, Move::new(1, 1)),
+            (Die::Two, Move::new(0, 0)),
+            (Die::Six, Move::new(2, 2)),
+            (Die::Two, Move::new(0, 2)),
+        ];
+        let game = root.apply_sequence(&steps).unwrap();
+        assert_eq!(game.get_ply(), 6);
+        assert_eq!(game.get_turn_number(), 4);
+        assert_eq!(game.with_roll(Die::Three).unwrap().get_ply(), 6);
+        assert!(game.to_string_from_perspective(Player::Player1).starts_with("Turn: 4\n"));
+
+        // Nothing was eliminated, so building the position from its boards gets the same ply.
+        let player_1_board = Board::from_string("4__\n_1_\n__6".to_string()).unwrap();
+        let player_2_board = Board::from_string("222\n___\n___".to_string()).unwrap();
+        assert_eq!(game.get_boards(), (player_1_board.clone(), player_2_board.clone()));
+        let mut position = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
+        assert_eq!(position.get_ply(), 6);
+        position.set_ply(7);
+        assert_eq!(position.get_ply(), 7);
+
+        let player_1_board = Board::from_string("35_\n1__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n6_1".to_string()).unwrap();
+        let mut position = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        assert_eq!(position.get_ply(), 7);
+        assert_eq!(position.get_turn_number(), 4);
+        position.generate_children_up_to_full_symmetry();
+        assert!(position.get_children().iter().all(|child| child.get_ply() == 8));
+        let from_kfen = Node::from_kfen("35_1_____ / __4__46_1 2 r5").unwrap();
+        assert_eq!(from_kfen.get_ply(), 7);
     }
 
     #[test]
-    fn test_tree_builds_entire_tree_up_to_symmetry() {
-        let player_1_board = Board::from_string("255\n1_2\n352".to_string()).unwrap();
-        let player_2_board = Board::from_string("15_\n333\n12_".to_string()).unwrap();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
-        root.build_entire_tree_up_to_symmetry();
-        assert_eq!(root.get_n_children(), 1);
-        assert_eq!(root.get_max_depth(), 2);
+    fn test_tree_weights_rolls_by_probability() {
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32);
+        let player_1_board = Board::from_string("35_\n1__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n6__".to_string()).unwrap();
+        let mut roll_node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
+        assert_eq!(roll_node.get_roll_probabilities(), Die::all().into_iter().map(|die| (die, 1. / 6.)).collect::<Vec<(Die, f32)>>());
+        assert_eq!(roll_node.with_roll(Die::One).unwrap().get_roll_probabilities(), vec![]);
+        roll_node.build_n_moves_up_to_symmetry(1);
+        let child_evaluations = roll_node.get_children().iter().map(|child| child.get_evaluation(&objective_function)).collect::<Vec<f32>>();
+        let mean = child_evaluations.iter().sum::<f32>() / 6.;
+        assert!((roll_node.get_evaluation(&objective_function) - mean).abs() < 0.0001);
+
+        let loaded = DieDistribution::from_weights([0., 0., 0., 0., 0., 1.]).unwrap();
+        let mut loaded_roll_node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
+        loaded_roll_node.set_die_distribution(loaded);
+        assert_eq!(loaded_roll_node.get_roll_probabilities()[5], (Die::Six, 1.));
+        loaded_roll_node.build_n_moves_up_to_symmetry(1);
+        assert_eq!(loaded_roll_node.get_evaluation(&objective_function), child_evaluations[5]);
+        assert!(child_evaluations[5] > mean);
+
+        // The evaluation tree weights its rolls the same way, and the distribution is passed down as the tree grows.
+        let mut evaluations = Vec::new();
+        for die_distribution in [DieDistribution::uniform(), loaded] {
+            let mut root = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Two));
+            root.set_die_distribution(die_distribution);
+            root.build_n_moves_up_to_symmetry(2);
+            assert!(root.iter_dfs().all(|node| node.get_die_distribution() == die_distribution));
+            let (_, evaluation) = root.get_evaluation_tree(&objective_function).unwrap();
+            assert_eq!(evaluation, root.get_evaluation(&objective_function));
+            let roll_node = root.get_child_from_move(Move::new(2, 2)).unwrap();
+            let expected_evaluation = roll_node.get_children_with_rolls().unwrap().iter()
+                .map(|(die, child)| die_distribution.get_probability(*die) * child.get_evaluation(&objective_function))
+                .sum::<f32>();
+            assert!((roll_node.get_evaluation(&objective_function) - expected_evaluation).abs() < 0.0001);
+            evaluations.push(evaluation);
+        }
+        assert!(evaluations[1] > evaluations[0]);
+    }
 
-        let player_1_board = Board::from_string("235\n1_2\n3_2".to_string()).unwrap();
-        let player_2_board = Board::from_string("156\n333\n12_".to_string()).unwrap();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player2));
-        let mut expected_root = root.clone();
-        expected_root.add_rolls();
-        for child in expected_root.children.iter_mut() {
-            child.get_legal_moves_up_to_row_symmetry().unwrap().iter().for_each(
-                |&m| {
-                    child.add_move(m);
-                }
-            );
+    #[test]
+    fn test_tree_samples_rolls() {
+        let player_1_board = Board::from_string("35_\n1__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n6__".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Two));
+        let chance_expansion = ChanceExpansion::Sampled { n_rolls: 2, full_plies: 1, seed: 3 };
+        let mut sampled = root.clone();
+        sampled.build_n_moves_up_to_symmetry_with(3, &chance_expansion);
+        assert!(sampled.validate().is_ok());
+        // Every roll after the first move, and two of them after that, the same two for the same position.
+        for node in sampled.iter_dfs().filter(|node| matches!(node.get_node_type(), NodeType::Roll(_)) && !node.is_leaf()) {
+            let dice = node.get_children().iter().map(|child| child.get_die().unwrap()).collect::<Vec<Die>>();
+            if node.get_ply() == root.get_ply() + 1 {
+                assert_eq!(dice, Die::all());
+            } else {
+                assert_eq!(dice.len(), 2);
+                assert_eq!(dice, chance_expansion.get_dice(node, node.get_ply() - root.get_ply()));
+            }
+        }
+        let mut full = root.clone();
+        full.build_n_moves_up_to_symmetry(3);
+        assert!(sampled.count_nodes() < full.count_nodes());
+        assert!(chance_expansion.samples_within(2) && !chance_expansion.samples_within(1));
+
+        // Sampled rolls are found by their die, and the rest aren't there.
+        let (first_move, first_roll) = sampled.get_children_with_moves().unwrap()[0];
+        let (second_move, second_roll) = first_roll.get_child_from_roll(Die::One).unwrap().get_children_with_moves().unwrap()[0];
+        let second_roll = second_roll.clone();
+        let dice = chance_expansion.get_dice(&second_roll, second_roll.get_ply() - root.get_ply());
+        for die in Die::all() {
+            let path = [Step::Move(Die::Two, first_move), Step::Roll(Die::One), Step::Move(Die::One, second_move), Step::Roll(die)];
+            if dice.contains(&die) {
+                assert_eq!(second_roll.get_child_from_roll(die).unwrap().get_die(), Some(die));
+                assert_eq!(sampled.walk_mut(&path).unwrap().get_die(), Some(die));
+            } else {
+                assert!(second_roll.get_child_from_roll(die).is_err());
+                assert!(sampled.walk_mut(&path).is_err());
+            }
         }
-        root.build_entire_tree_up_to_symmetry();
-        assert_eq!(root.get_max_depth(), 3);
-        assert_eq!(root.get_n_children(), 6);
-        assert_eq!(root, expected_root);
 
-        let player_1_board = Board::from_string("251\n142\n32_".to_string()).unwrap();
-        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
-        root.build_entire_tree_up_to_symmetry();
-        assert_eq!(root.get_max_depth(), 4);
-        assert_eq!(root.get_n_children(), 2);
-        
+        // Sampling all six is no sampling at all.
+        let mut full = root.clone();
+        full.build_n_moves_up_to_symmetry(2);
+        let mut sampled_six = root.clone();
+        sampled_six.build_n_moves_up_to_symmetry_with(2, &ChanceExpansion::Sampled { n_rolls: 6, full_plies: 0, seed: 3 });
+        assert_eq!(sampled_six, full);
+        assert!(!ChanceExpansion::Sampled { n_rolls: 6, full_plies: 0, seed: 3 }.samples_within(2));
+
+        // Rolls that can't happen aren't drawn.
+        let mut loaded = root.clone();
+        loaded.set_die_distribution(DieDistribution::from_weights([0., 0., 0., 0., 1., 1.]).unwrap());
+        loaded.build_n_moves_up_to_symmetry_with(2, &ChanceExpansion::Sampled { n_rolls: 3, full_plies: 0, seed: 3 });
+        assert!(loaded.iter_dfs().filter_map(|node| node.get_die()).all(|die| die == Die::Five || die == Die::Six || die == Die::Two));
     }
 
     #[test]
-    fn test_tree_builds_to_depth() {
-        let player_1_board = Board::empty();
-        let player_2_board = Board::empty();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
-        root.build_n_moves_up_to_symmetry(1);
-
-        assert_eq!(root.get_max_depth(), 3);
+    fn test_tree_merges_equivalent_children() {
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32);
+        // Player 1's 4 can go in any row of the last column, and those are all the same position up to row order.
+        let player_1_board = Board::from_string("35_\n1__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n6__".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        root.generate_children_merged();
         assert_eq!(root.get_n_children(), 3);
-        assert!(root.get_children().iter().all(|c| c.get_n_children() == 6));
-        
-        let player_1_board = Board::empty();
-        let player_2_board = Board::empty();
-        let mut root = Node::new(player_1_board, player_2_board, NodeType::Roll(Player::Player1));
-        root.build_n_moves_up_to_symmetry(1);
-
-        assert_eq!(root.get_max_depth(), 4);
-        assert_eq!(root.get_n_children(), 6);
-        assert!(root.get_children().iter().all(|c| c.get_n_children() == 3));
+        assert_eq!(root.get_child_from_move(Move::new(0, 2)), root.get_child_from_move(Move::new(2, 2)));
+        assert_eq!(
+            root.get_child_from_move(Move::new(2, 2)).unwrap().get_equivalent_moves(),
+            &vec![Move::new(0, 2), Move::new(1, 2), Move::new(2, 2)]
+        );
+        assert_eq!(root.get_moves().unwrap(), root.get_legal_moves().unwrap());
+        let mut unmerged = root.clone_without_children();
+        unmerged.generate_children_up_to_symmetry();
+        assert!(root.get_children().iter().zip(unmerged.get_children()).all(|(a, b)| a.equals_up_to_children(b)));
+
+        let mut merged = root.clone_without_children();
+        merged.build_n_moves_merged(2);
+        let mut full = root.clone_without_children();
+        full.build_n_moves(2);
+        let mut up_to_symmetry = root.clone_without_children();
+        up_to_symmetry.build_n_moves_up_to_symmetry(2);
+        assert!(merged.count_nodes() < full.count_nodes());
+        assert_eq!(merged.count_nodes(), up_to_symmetry.count_nodes());
+        assert_eq!(merged.get_evaluation(&objective_function), full.get_evaluation(&objective_function));
+        let (merged_tree, merged_evaluation) = merged.get_evaluation_tree(&objective_function).unwrap();
+        let (full_tree, full_evaluation) = full.get_evaluation_tree(&objective_function).unwrap();
+        assert_eq!(merged_evaluation, full_evaluation);
+        // The best moves from the merged tree include the equivalent ones.
+        let merged_best_moves = merged_tree.unwrap().get_moves().unwrap();
+        assert_eq!(merged_best_moves.len(), 3);
+        assert!(full_tree.unwrap().get_moves().unwrap().iter().all(|m| merged_best_moves.contains(m)));
+
+        let mut detached = merged.detach_child_by_move(Move::new(1, 2)).unwrap();
+        assert_eq!(detached.detach_child_by_roll(Die::One).unwrap().get_n_children(), 3);
+    }
 
-        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap(); // 40 before move.
+    #[test]
+    fn test_tree_gets_move_evaluations() {
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32).exact();
+        let player_1_board = Board::from_string("661\n142\n62_".to_string()).unwrap(); // 40 before move.
         let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap(); // 24 before move.
         let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Six));
-        let mut expected_root = root.clone();
+        assert!(root.get_move_evaluations(&objective_function).unwrap().is_empty());
+        root.build_n_moves_up_to_symmetry(2);
+        let move_evaluations = root.get_move_evaluations(&objective_function).unwrap();
+        // Player 2 prefers the lower evaluation, so (2, 2) comes first.
+        assert_eq!(move_evaluations.iter().map(|(m, _)| *m).collect::<Vec<Move>>(), vec![Move::new(2, 2), Move::new(1, 1)]);
+        // After (2, 2) => 42, Player 1 fills their board with whatever they roll, winning by 1, 6, 1, 2, 3 or 28.
+        let (_, evaluation) = move_evaluations[0];
+        assert!((evaluation - (1. + 6. + 1. + 2. + 3. + 28.) / 6.).abs() < 0.0001);
+        assert_eq!(evaluation, root.get_evaluation(&objective_function));
+        assert!(move_evaluations[1].1 > evaluation);
+
+        // Moves in the same column are expanded back out from the one that was built.
+        let mut root = Node::new(Board::empty(), Board::from_string("___\n___\n3__".to_string()).unwrap(), NodeType::Move(Player::Player1, Die::Three));
+        root.build_n_moves_up_to_symmetry(1);
+        let move_evaluations = root.get_move_evaluations(&objective_function).unwrap();
+        assert_eq!(move_evaluations.len(), 9);
+        assert_eq!(move_evaluations[..3].iter().map(|(m, _)| *m).collect::<Vec<Move>>(), vec![Move::new(0, 0), Move::new(1, 0), Move::new(2, 0)]);
+        assert!(move_evaluations.windows(2).all(|pair| pair[0].1 >= pair[1].1));
 
-        root.build_n_moves_up_to_symmetry(5);
-        expected_root.build_entire_tree_up_to_symmetry();
-        assert_eq!(root, expected_root);
+        assert!(Node::empty().get_move_evaluations(&objective_function).is_err());
+    }
+
+    #[test]
+    fn test_node_to_string_from_perspective() {
+        let player_1_board = Board::from_string("35_\n1__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n6__".to_string()).unwrap();
+        let root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player2, Die::Five));
+        assert_eq!(
+            root.to_string_from_perspective(Player::Player1),
+            "Turn: 4\n\nPlayer:\n35_\n1__\n___\n\nOpponent:\n__4\n__4\n6__\n\nRoll: 5\n\nPlayer Score: 9\nOpponent Score: 22\nDifference: -13\nTo Act: Opponent\n"
+        );
+        assert_eq!(
+            root.to_string_from_perspective(Player::Player2),
+            "Turn: 4\n\nPlayer:\n__4\n__4\n6__\n\nOpponent:\n35_\n1__\n___\n\nRoll: 5\n\nPlayer Score: 22\nOpponent Score: 9\nDifference: +13\nTo Act: Player\n"
+        );
 
+        let player_1_board = Board::from_string("111\n111\n111".to_string()).unwrap();
+        let finished = Node::new(player_1_board, Board::empty(), NodeType::Roll(Player::Player2));
+        assert!(finished.to_string_from_perspective(Player::Player2).ends_with("Roll: \nPlayer Score: 0\nOpponent Score: 27\nDifference: -27\nTo Act: Game Over\n"));
     }
 
     #[test]
-    fn test_get_moves_left_ignoring_elimination() {
+    fn test_node_to_compact_string() {
+        let mut root = Node::from_kfen("2________ / _35______ 2 r5").unwrap();
+        root.generate_children_up_to_symmetry();
+        assert_eq!(root.to_compact_string(), "[P1:2________|P2:_35______|Move P2 d5|ch:3]");
+        assert_eq!(format!("{}", root), root.to_compact_string());
+        assert_eq!(format!("{:#}", root), "Player 1:\n2__\n___\n___\nPlayer 2:\n_35\n___\n___\nType: Move(Player2, Five)\nN Children: 3");
 
-        let player_1_board = Board::empty();
-        let player_2_board = Board::empty();
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Six));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 17);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Six));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 17);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 17);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player2));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 17);
+        let child = root.get_child_from_move(Move::new(0, 0)).unwrap();
+        assert_eq!(child.to_compact_string(), "[P1:2________|P2:535______|Roll P1|ch:0]");
+        assert_eq!(child.get_legal_moves(), Err(SolverError::WrongNodeType { expected: NodeKind::Move, found: NodeKind::Roll }));
+    }
 
-        let player_1_board = Board::from_string("651\n142\n62_".to_string()).unwrap();
-        let player_2_board = Board::from_string("256\n1_2\n62_".to_string()).unwrap();
-        let node = Node::new(player_1_board.clone().clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Six));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 1);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 1);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Six));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 2);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player2));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 2);
-        
+    #[test]
+    fn test_tree_walks_mutably() {
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32);
+        let player_1_board = Board::from_string("35_\n1__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n6__".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        root.build_n_moves_up_to_symmetry(1);
+        root.get_evaluation(&objective_function);
+        let original = root.clone();
+
+        let path = [Step::Move(Die::Four, Move::new(1, 1)), Step::Roll(Die::Two)];
+        let grandchild = root.walk_mut(&path).unwrap();
+        assert!(grandchild.is_leaf());
+        grandchild.build_n_moves_up_to_symmetry(1);
+        let n_added = grandchild.count_nodes() - 1;
+        assert!(n_added > 0);
+        assert_eq!(root.count_nodes(), original.count_nodes() + n_added);
+
+        // The siblings at every level are untouched.
+        for m in [Move::new(0, 2), Move::new(2, 0)] {
+            assert_eq!(root.get_child_from_move(m), original.get_child_from_move(m));
+        }
+        let roll_node = root.get_child_from_move(Move::new(1, 1)).unwrap();
+        let original_roll_node = original.get_child_from_move(Move::new(1, 1)).unwrap();
+        for die in Die::all().into_iter().filter(|die| *die != Die::Two) {
+            assert_eq!(roll_node.get_child_from_roll(die), original_roll_node.get_child_from_roll(die));
+        }
+        // Cached evaluations along the path were dropped, so the root sees the deeper line.
+        let mut expected = original.clone();
+        expected.get_child_mut(1, 1).unwrap().get_child_from_roll_mut(Die::Two).unwrap().build_n_moves_up_to_symmetry(1);
+        assert_eq!(root, expected);
+        expected.clear_evaluations();
+        assert_eq!(root.get_evaluation(&objective_function), expected.get_evaluation(&objective_function));
+
+        assert_eq!(root.walk_mut(&[]).unwrap().get_node_type(), NodeType::Move(Player::Player1, Die::Four));
+        assert_eq!(root.walk_mut(&[Step::Move(Die::Three, Move::new(1, 1))]).unwrap_err(), "Step 0: Expected a roll of 4, got 3".to_string());
+        assert!(root.walk_mut(&[Step::Roll(Die::Two)]).unwrap_err().starts_with("Step 0: Expected a move, not a roll"));
+        assert!(root.walk_mut(&[Step::Move(Die::Four, Move::new(0, 0))]).is_err());
+        assert_eq!(
+            root.walk_mut(&[Step::Move(Die::Four, Move::new(0, 2)), Step::Roll(Die::Two), Step::Move(Die::Two, Move::new(0, 0))]).unwrap_err(),
+            "Step 2: No child at row 0 and column 0".to_string()
+        );
+    }
 
-        let player_1_board = Board::from_string("651\n_42\n62_".to_string()).unwrap();
-        let player_2_board = Board::from_string("___\n1_2\n62_".to_string()).unwrap();
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player1, Die::Six));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 3);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player1));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 3);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Move(Player::Player2, Die::Six));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 4);
-        let node = Node::new(player_1_board.clone(), player_2_board.clone(), NodeType::Roll(Player::Player2));
-        assert_eq!(node.get_moves_left_ignoring_elimination(), 4);
+    #[test]
+    fn test_tree_validates() {
+        let player_1_board = Board::from_string("35_\n1__\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n6__".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        root.build_n_moves_up_to_symmetry(2);
+        assert_eq!(root.validate(), Ok(()));
+        let mut merged = root.clone_without_children();
+        merged.build_n_moves_merged(2);
+        assert_eq!(merged.validate(), Ok(()));
+
+        // A move that wasn't legal, and a Move node where a Roll node should be.
+        let mut corrupted = root.clone();
+        let illegal_child = Node::new(Board::empty(), Board::empty(), NodeType::Roll(Player::Player2));
+        corrupted.children.push(illegal_child);
+        corrupted.children[0].children[1].children.push(Node::new(Board::empty(), Board::empty(), NodeType::Move(Player::Player1, Die::One)));
+        let violations = corrupted.validate().unwrap_err();
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].get_path(), &vec![3]);
+        assert!(violations[0].get_description().starts_with("Expected Player 1 to place exactly one die"));
+        assert_eq!(violations[1].get_path(), &vec![0, 1, 3]);
+        assert!(violations[1].get_description().starts_with("Expected a Roll node for Player 1 after a move"));
+
+        // A roll that changes the boards, a repeated roll, and a child pushed twice.
+        let mut corrupted = root.clone();
+        let roll_node = &mut corrupted.children[2];
+        roll_node.children[0] = roll_node.children[0].clone_without_children();
+        roll_node.children[0].player_1_board = Board::empty();
+        let repeated = roll_node.children[5].clone();
+        roll_node.children.push(repeated);
+        let repeated = corrupted.children[1].clone();
+        corrupted.children.push(repeated);
+        let violations = corrupted.validate().unwrap_err();
+        assert_eq!(
+            violations.iter().map(|violation| violation.get_path().clone()).collect::<Vec<Vec<usize>>>(),
+            vec![vec![3], vec![2, 0], vec![2, 6]],
+        );
+        assert!(violations[0].get_description().starts_with("Child appears twice"));
+        assert!(violations[1].to_string().starts_with("At [2, 0]: Roll changed the boards"));
+        assert!(violations[2].get_description().starts_with("Roll of 6 appears twice"));
+
+        let mut corrupted = root.clone();
+        corrupted.children[0].ply = 0;
+        assert!(corrupted.validate().unwrap_err()[0].get_description().starts_with("Expected ply 7 after a move, got 0"));
+    }
+
+    static N_OBJECTIVE_CALLS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
 
+    fn counting_objective(node: &Node) -> f32 {
+        N_OBJECTIVE_CALLS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        node.get_score_difference() as f32
+    }
+
+    fn take_objective_calls() -> usize {
+        N_OBJECTIVE_CALLS.swap(0, std::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_tree_caches_evaluations() {
+        let player_1_board = Board::from_string("35_\n___\n___".to_string()).unwrap();
+        let player_2_board = Board::from_string("__4\n__4\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Four));
+        root.build_n_moves_up_to_symmetry(1);
+        take_objective_calls();
+        let counting_objective = Objective::new(counting_objective);
+
+        let (evaluation_tree, evaluation) = root.get_evaluation_tree(&counting_objective).unwrap();
+        assert_eq!(take_objective_calls(), root.count_leaves());
+
+        assert_eq!(root.get_evaluation_tree(&counting_objective).unwrap(), (evaluation_tree.clone(), evaluation));
+        assert_eq!(root.get_evaluation(&counting_objective), evaluation);
+        let pretty = root.to_pretty_string(&counting_objective);
+        let evaluation_tree = evaluation_tree.unwrap();
+        assert_eq!(evaluation_tree.get_evaluation(&counting_objective), evaluation);
+        assert!(!evaluation_tree.get_principal_variation(&counting_objective).is_empty());
+        let mut pruned = root.clone();
+        pruned.prune_to_best_moves(&counting_objective, 0.);
+        assert_eq!(take_objective_calls(), 0);
+
+        // A different objective isn't served the cached values.
+        assert_ne!(root.get_evaluation(&Objective::new(|node| -(node.get_score_difference() as f32))), evaluation);
+
+        root.clear_evaluations();
+        assert_eq!(root.to_pretty_string(&counting_objective), pretty);
+        assert_eq!(take_objective_calls(), root.count_leaves());
+
+        // Adding children drops the node's cached value.
+        let mut leaf = root.get_children()[0].get_children()[0].clone();
+        assert_eq!(leaf.get_evaluation(&counting_objective), leaf.get_score_difference() as f32);
+        leaf.build_n_moves_up_to_symmetry(1);
+        take_objective_calls();
+        leaf.get_evaluation(&counting_objective);
+        assert_eq!(take_objective_calls(), leaf.count_leaves());
+
+        // So does deepening the tree below it, however it's deepened.
+        let objective_function = Objective::new(|node: &Node| node.get_score_difference() as f32);
+        let shallow = Node::new(root.get_player_1_board(), root.get_player_2_board(), root.get_node_type());
+        let mut deep = shallow.clone();
+        deep.build_n_moves_up_to_symmetry(3);
+        let deep_evaluation = deep.get_evaluation(&objective_function);
+        let mut deepened = shallow.clone();
+        deepened.build_n_moves_up_to_symmetry(1);
+        assert_ne!(deepened.get_evaluation(&objective_function), deep_evaluation);
+        deepened.build_n_moves_up_to_symmetry(3);
+        assert_eq!(deepened.get_evaluation(&objective_function), deep_evaluation);
+        let mut deepened = shallow;
+        deepened.build_n_moves_up_to_symmetry(1);
+        deepened.get_evaluation(&objective_function);
+        deepened.build_until(|_, depth, _| depth < 6);
+        assert_eq!(deepened.get_evaluation(&objective_function), deep_evaluation);
     }
 
     #[test]
@@ -887,18 +3841,111 @@ mod test_tree {
         let player_1_board = Board::empty();
         let player_2_board = Board::empty();
         let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::Six));
-        let s = root.to_pretty_string(|n| n.get_n_children() as f32);
+        let s = root.to_pretty_string(&Objective::new(|n| n.get_n_children() as f32));
         assert_eq!(
             s,
             "6: 0".to_string()
         );
 
         root.build_n_moves_up_to_symmetry(1);
-        let s = root.to_pretty_string(|n| n.get_n_children() as f32);
+        let s = root.to_pretty_string(&Objective::new(|n| n.get_n_children() as f32));
         assert_eq!(
             s,
             "6 =>\n  (0, 0) =>\n    1: 0\n    2: 0\n    3: 0\n    4: 0\n    5: 0\n    6: 0\n  (0, 1) =>\n    1: 0\n    2: 0\n    3: 0\n    4: 0\n    5: 0\n    6: 0\n  (0, 2) =>\n    1: 0\n    2: 0\n    3: 0\n    4: 0\n    5: 0\n    6: 0".to_string()
         )
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_tree_pretty_prints_with_options() {
+        let player_1_board = Board::from_string("2__\n___\n___".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, Board::empty(), NodeType::Move(Player::Player1, Die::Six));
+        root.build_n_moves_up_to_symmetry(1);
+
+        let options = PrettyPrintOptions { max_depth: Some(1), ..PrettyPrintOptions::default() };
+        assert_eq!(
+            root.to_pretty_string_with(&options, &Objective::new(|n| n.get_n_children() as f32)),
+            "6 =>\n  (0, 1) =>\n    ... (6 hidden)\n  (0, 2) =>\n    ... (6 hidden)\n  (1, 0) =>\n    ... (6 hidden)".to_string()
+        );
+
+        let options = PrettyPrintOptions {
+            max_children_per_node: Some(1),
+            show_evaluations: false,
+            show_boards: true,
+            unicode_dice: true,
+            indent: "--".to_string(),
+            ..PrettyPrintOptions::default()
+        };
+        assert_eq!(
+            root.to_pretty_string_with(&options, &Objective::new(|n| n.get_n_children() as f32)),
+            "⚅ =>\n| 2__ ___\n| ___ ___\n| ___ ___\n--(0, 1) =>\n--| 26_ ___\n--| ___ ___\n--| ___ ___\n----⚀\n----| 26_ ___\n----| ___ ___\n----| ___ ___\n----... (5 hidden)\n--... (14 hidden)".to_string()
+        );
+    }
+
+    #[test]
+    fn test_tree_distinguishes_terminal_from_leaf() {
+        let player_1_board = Board::from_string("111\n111\n11_".to_string()).unwrap();
+        let player_2_board = Board::from_string("222\n222\n22_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::One));
+        assert!(root.is_leaf());
+        assert!(!root.is_terminal());
+        assert!(!root.is_expanded());
+        assert_eq!(root.get_expansion_state(), ExpansionState::Unexpanded);
+
+        root.build_n_moves_up_to_symmetry(1);
+        assert!(root.is_expanded());
+        assert_eq!(root.get_expansion_state(), ExpansionState::Expanded);
+
+        let after_move = root.get_child_from_move(Move::new(2, 2)).unwrap();
+        assert!(after_move.is_leaf());
+        assert!(after_move.is_terminal());
+        assert!(!after_move.is_expanded());
+        assert_eq!(after_move.get_expansion_state(), ExpansionState::Terminal);
+
+        assert_eq!(root.to_pretty_string(&Objective::new(|n| n.get_score_difference() as f32).exact()), "1 =>\n  (2, 2): -17 (game over)".to_string());
+        let options = PrettyPrintOptions { show_evaluations: false, ..PrettyPrintOptions::default() };
+        assert_eq!(root.to_pretty_string_with(&options, &Objective::new(|n| n.get_score_difference() as f32).exact()), "1 =>\n  (2, 2) (game over)".to_string());
+
+        // Finished games are scored by who won, whatever the objective makes of them, unless it's exact.
+        let heuristic = Objective::new(|_| 0.);
+        assert_eq!(heuristic.evaluate(after_move), -KNOWN_WIN_EVALUATION);
+        assert_eq!(Objective::with_scores(|_, _| 0.).evaluate_with_scores(after_move, after_move.get_scores()), -KNOWN_WIN_EVALUATION);
+        assert_eq!(root.get_evaluation(&heuristic), -KNOWN_WIN_EVALUATION);
+        assert_eq!(heuristic.evaluate(&root), 0.);
+        assert_eq!(Objective::new(|_| 0.).exact().evaluate(after_move), 0.);
+    }
+
+    #[test]
+    fn test_evaluation_tree_scores_game_ending_moves() {
+        // A game-ending move is scored and compared like any other move rather than returned straight away.
+        let player_1_board = Board::from_string("111\n111\n11_".to_string()).unwrap();
+        let player_2_board = Board::from_string("222\n222\n22_".to_string()).unwrap();
+        let mut root = Node::new(player_1_board, player_2_board, NodeType::Move(Player::Player1, Die::One));
+        root.build_n_moves_up_to_symmetry(1);
+        let objective_function = Objective::new(|n| n.get_score_difference() as f32).exact();
+        let (evaluation_tree, evaluation) = root.get_evaluation_tree(&objective_function).unwrap();
+        assert_eq!(evaluation, -17.);
+        assert_eq!(evaluation_tree.unwrap().get_moves().unwrap(), vec![Move::new(2, 2)]);
+
+        // Placing a die only ever fills the mover's own board, so a game-ending move fills their last square and is
+        // the only legal move: there's never a better alternative to it.
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        for _ in 0..50 {
+            let mut node = Node::empty();
+            loop {
+                node = match node.status() {
+                    NodeStatus::Terminal(_) => break,
+                    NodeStatus::Roll(_) => node.with_roll(Die::random_from(&mut rng)).unwrap(),
+                    NodeStatus::Move(_, _) => {
+                        let moves = node.get_legal_moves().unwrap();
+                        if moves.iter().any(|m| node.with_move_made(*m).unwrap().is_terminal()) {
+                            assert_eq!(moves.len(), 1);
+                        }
+                        node.with_move_made(*moves.choose(&mut rng).unwrap()).unwrap()
+                    },
+                };
+            }
+        }
+    }
+
+}
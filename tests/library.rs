@@ -0,0 +1,41 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use knucklebones_solver::prelude::*;
+use knucklebones_solver::solver::config::HeuristicFunction;
+use knucklebones_solver::tree::tree::NodeStatus;
+
+#[test]
+fn test_library_solves_position() {
+    let position = Node::from_kfen("1125545_6 / 435623_4_ 2 r6").unwrap();
+    let mut solver = Solver::from_root(position.clone());
+    let (best_moves, evaluation) = solver.get_best_moves_and_evaluation(SolverMode::BruteForce).unwrap();
+    assert!(!best_moves.is_empty());
+    assert!(best_moves.iter().all(|m| position.with_move_made(*m).is_ok()));
+    assert!(evaluation.is_expected_result());
+}
+
+#[test]
+fn test_library_plays_game() {
+    // A whole game of depth 1 searches against seeded rolls, driven through the library alone.
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut node = Node::new_validated(Board::empty(), Board::empty(), NodeType::Roll(Player::Player1)).unwrap();
+    let mut n_moves = 0;
+    let outcome = loop {
+        node = match node.status() {
+            NodeStatus::Terminal(outcome) => break outcome,
+            NodeStatus::Roll(_) => node.with_roll(Die::random_from(&mut rng)).unwrap(),
+            NodeStatus::Move(_, _) => {
+                let mut solver = Solver::from_root(node.clone());
+                let (best_moves, _) = solver.get_best_moves_and_evaluation(
+                    SolverMode::Heuristic((1, HeuristicFunction::Difference.to_objective()))
+                ).unwrap();
+                n_moves += 1;
+                node.with_move_made(best_moves[0]).unwrap()
+            },
+        };
+    };
+    assert_ne!(outcome, Outcome::InProgress);
+    assert!(n_moves >= 9);
+    assert_eq!(node.get_outcome(), outcome);
+}
@@ -0,0 +1,121 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn play(args: &[&str], input: &str) -> String {
+    // Everything play prints for the game, with input as what's typed in.
+    let mut child = Command::new(env!("CARGO_BIN_EXE_knucklebones-solver"))
+        .arg("play")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn first_roll(transcript: &str) -> char {
+    // The die in the first position shown, which comes from the seed.
+    transcript.split("Roll: ").nth(1).unwrap().chars().next().unwrap()
+}
+
+#[test]
+fn test_play_side() {
+    let transcript = play(&["--side", "first", "--seed", "1", "-d", "1"], "");
+    assert!(transcript.starts_with("You're Player 1, so you move first."));
+    assert!(transcript.contains("Enter move"));
+    assert!(!transcript.contains("Solver rolls"));
+
+    // The human's move, then the solver's reply to it, before the human is asked again.
+    let transcript = play(&["--side", "player1", "--seed", "1", "-d", "1"], "0 0\n");
+    let first_prompt = transcript.find("Enter move").unwrap();
+    let solver_move = transcript.find("Solver rolls a ").unwrap();
+    assert!(first_prompt < solver_move);
+    assert!(transcript[solver_move..].contains("Enter move"));
+
+    let transcript = play(&["--side", "second", "--seed", "1", "-d", "1"], "");
+    assert!(transcript.starts_with("You're Player 2, so the solver moves first."));
+    assert!(transcript.find("Solver rolls a ").unwrap() < transcript.find("Enter move").unwrap());
+
+    assert_eq!(play(&["--side", "middle"], "").trim(), "Invalid side, expected first, second, player1, player2 or random: middle");
+}
+
+#[test]
+fn test_play_hint() {
+    let transcript = play(&["--side", "first", "--seed", "1", "-d", "1"], "hint\nhint full\n1 1\n");
+    let hint = transcript.find("Hint: ").unwrap();
+    let full_hint = transcript.find("Hint:\n1. ").unwrap();
+    assert!(hint < full_hint);
+    assert!(transcript[full_hint..].contains("\n3. "));
+    assert!(!transcript[full_hint..].contains("\n4. "));
+    // The hints leave the position alone, so the move after them is still the first, with the first roll.
+    let solver_move = transcript.find("Solver rolls a ").unwrap();
+    assert!(full_hint < solver_move);
+    assert_eq!(transcript[..solver_move].matches("Current Position").count(), 1);
+    assert!(transcript[solver_move..].contains(&format!("_{}_", first_roll(&transcript))));
+}
+
+#[test]
+fn test_play_undo() {
+    let transcript = play(&["--side", "first", "--seed", "1", "-d", "1"], "undo\n0 0\n0 1\nundo\nundo\n");
+    assert!(transcript.contains("Nothing to undo!"));
+    assert_eq!(transcript.matches("Took back your last move").count(), 2);
+    let positions = transcript.split("Current Position:").skip(1)
+        .map(|position| position.split("Enter move").next().unwrap())
+        .collect::<Vec<&str>>();
+    // Each undo takes back the solver's reply along with the move before it, back to the same roll.
+    assert_eq!(positions.len(), 5);
+    assert_eq!(positions[3], positions[1]);
+    assert_eq!(positions[4], positions[0]);
+    assert_ne!(positions[1], positions[0]);
+    assert!(positions[1].contains("Turn: 2"));
+}
+
+#[test]
+fn test_play_resumes_position() {
+    // Two empty squares, one on each board, so the game is over after one move each.
+    let transcript = play(&["--side", "second", "--position", "1125545_6 / 435623_4_ 2 r6", "--seed", "1", "-d", "1"], "2 0\n");
+    assert!(transcript.starts_with("You're Player 2, so you move first."));
+    assert_eq!(transcript.matches("Enter move").count(), 1);
+    assert!(transcript.contains("Solver rolls a "));
+    assert!(transcript.contains("Game Over!"));
+
+    // Without a roll it rolls first, and the side can be the player who isn't to act.
+    let transcript = play(&["--side", "first", "--position", "1125545_6 / 435623_4_ 2 -", "--seed", "1", "-d", "1"], "");
+    assert!(transcript.starts_with("You're Player 1, so the solver moves first."));
+    assert!(transcript.contains("Solver rolls a "));
+
+    let transcript = play(&["--position", "1________ / _________ 1 r3"], "");
+    assert!(transcript.starts_with("Invalid KFEN position"));
+    assert!(transcript.contains("Player 2 has no dice"));
+}
+
+#[test]
+fn test_play_seed() {
+    // A square at a time in row-major order, three times over so there's a move for every turn the game could last.
+    let input = "0 0\n0 1\n0 2\n1 0\n1 1\n1 2\n2 0\n2 1\n2 2\n".repeat(3);
+    let transcript = play(&["--seed", "7", "-d", "1"], &input);
+    assert_eq!(transcript, include_str!("snapshots/play_seed_7.txt"));
+    assert_eq!(play(&["--seed", "7", "-d", "1"], &input), transcript);
+    assert_ne!(play(&["--seed", "8", "-d", "1"], &input), transcript);
+    assert_eq!(play(&["--seed", "x"], "").trim(), "Invalid seed: x");
+}
+
+#[test]
+fn test_play_show_eval() {
+    // After the human's move and the solver's reply, before the human's next move.
+    let transcript = play(&["--side", "first", "--seed", "1", "-d", "1", "--show-eval"], "0 0\n");
+    assert_eq!(transcript.matches("\nEval: ").count(), 2);
+    let solver_move = transcript.find("Solver rolls a ").unwrap();
+    assert!(transcript[..solver_move].contains("\nEval: "));
+    assert!(transcript[solver_move..transcript.rfind("Enter move").unwrap()].contains("\nEval: "));
+
+    // The solver's move ends the game, so only the human's is evaluated, and listed at the end.
+    let transcript = play(&["--side", "second", "--position", "1125545_6 / 435623_4_ 2 r6", "--seed", "1", "-d", "1", "--show-eval", "--eval-depth", "1"], "2 0\n");
+    assert_eq!(transcript.matches("\nEval: ").count(), 1);
+    let evaluations = transcript.split("Evaluations (for you):\n").nth(1).unwrap();
+    assert_eq!(evaluations.lines().count(), 1);
+    assert!(evaluations.starts_with("Player 2 placed 6 at (2, 0): "));
+}